@@ -3,10 +3,13 @@ use std::sync::Arc;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::collections::HashMap;
-use rustls::ServerConfig;
+use rustls::crypto::CryptoProvider;
+use rustls::{ProtocolVersion, ServerConfig, SupportedProtocolVersion};
 use rustls::pki_types::CertificateDer;
 use serde::{Deserialize, Serialize};
 
+use crate::parser::{LogDecoder, LogFormat};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AgentConfig {
@@ -14,11 +17,39 @@ pub struct AgentConfig {
     pub tls_cert_path: String,
     pub tls_key_path: String,
     pub tls_ca_path: String,
+    pub tls: TlsPolicyConfig,
     pub docker_socket: String,
     pub max_concurrent_streams: usize,
     pub audit_log_path: Option<String>,
     pub multiline: MultilineConfig,
+    pub stream_qos: StreamQosConfig,
+    pub adaptive_detection: AdaptiveDetectionConfig,
+    pub format_lock: FormatLockConfig,
+    pub docker_reconnect: DockerReconnectConfig,
+    pub stream_buffers: StreamBufferConfig,
+    pub reverse: ReverseConfig,
+    pub redaction: RedactionConfig,
     pub inventory_sync_interval_secs: u64,
+    /// Re-detect a container's log format when its log was reset (restart, or
+    /// rotation that discarded the oldest lines) since the last stream opened
+    pub redetect_on_log_reset: bool,
+    /// Encoding for BOM-less log lines that aren't valid UTF-8 (e.g. "windows-1252")
+    pub fallback_encoding: Option<String>,
+    /// Formats that take part in auto-detection (json, logfmt, syslog, http_log).
+    /// Plain text is always the fallback.
+    pub enabled_formats: Vec<String>,
+    /// Allow FreezeInspect, which briefly pauses a container to capture a
+    /// debugging snapshot. Off by default; operators opt in per agent.
+    pub allow_freeze_inspect: bool,
+    /// Longest a FreezeInspect capture may keep a container paused before
+    /// it is abandoned and the container unpaused
+    pub freeze_capture_timeout_ms: u64,
+    /// Allow UpdateContainerResources, which changes a running container's
+    /// CPU and memory limits. Off by default; operators opt in per agent.
+    pub allow_resource_updates: bool,
+    /// JSON Schema that parsed JSON log lines are checked against when a
+    /// stream asks for validation (optional)
+    pub log_schema_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,9 +59,171 @@ pub struct MultilineConfig {
     pub timeout_ms: u64,
     pub max_lines: usize,
     pub require_error_anchor: bool,
+    /// Built-in grouping profile, normally selected per container via the
+    /// `docktail.multiline` label
+    pub profile: Option<MultilineProfile>,
+    /// Reassemble pretty-printed JSON objects that span several lines
+    pub json: bool,
+    /// Lines a JSON object may span before its lines are released as plain text
+    pub json_max_lines: usize,
+    /// Bytes a JSON object may span before its lines are released as plain text
+    pub json_max_bytes: usize,
     pub container_overrides: HashMap<String, ContainerMultilineConfig>,
 }
 
+/// Built-in multiline rules for common runtimes' stack trace layouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MultilineProfile {
+    Java,
+    Python,
+    Go,
+}
+
+impl MultilineProfile {
+    pub const ALL: [MultilineProfile; 3] = [Self::Java, Self::Python, Self::Go];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Java => "java",
+            Self::Python => "python",
+            Self::Go => "go",
+        }
+    }
+}
+
+impl std::str::FromStr for MultilineProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        Self::ALL
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(value))
+            .ok_or_else(|| {
+                let known: Vec<&str> = Self::ALL.iter().map(|p| p.as_str()).collect();
+                format!("unknown multiline profile '{}' (expected one of: {})", value, known.join(", "))
+            })
+    }
+}
+
+/// Stream admission thresholds per priority class, as a percentage of
+/// `max_concurrent_streams`. High-priority streams may always use the full limit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamQosConfig {
+    pub low_limit_pct: u8,
+    pub normal_limit_pct: u8,
+    /// Agent resident memory (MiB) at which low-priority streams are refused
+    /// and running ones shed lines below WARN (0 = off)
+    pub memory_pressure_mb: u64,
+}
+
+/// Adaptive format detection: containers whose lines keep failing to parse
+/// are re-detected from a larger sample, while containers that parse cleanly
+/// shrink back to a minimal sample.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveDetectionConfig {
+    pub enabled: bool,
+    /// Lines sampled for a container that parses well
+    pub min_sample: usize,
+    /// Upper bound on the sample; growth stops here
+    pub max_sample: usize,
+    /// Lines per evaluation window
+    pub window: u64,
+    /// Fit rate (percent) below which the sample grows and detection re-runs
+    pub low_success_pct: u8,
+    /// Fit rate (percent) at or above which the sample shrinks
+    pub high_success_pct: u8,
+}
+
+/// Format lock: once a container's detected format has parsed cleanly for
+/// enough lines, stop tracking it and skip re-detection until the log is
+/// reset or the format is invalidated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatLockConfig {
+    pub enabled: bool,
+    /// Lines parsed with the detected format before it can lock
+    pub min_lines: u64,
+    /// Parse success rate (percent) required over those lines
+    pub min_success_pct: u8,
+}
+
+/// Masking secrets in log content before it leaves the agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    /// Redact the values of container environment variables whose name
+    /// contains one of these (case-insensitive)
+    pub env_name_keywords: Vec<String>,
+    /// Shorter env values are left alone; redacting "true" or "1" would
+    /// mangle ordinary log text
+    pub min_secret_length: usize,
+    /// Regular expressions whose matches are redacted in every container's logs
+    pub patterns: Vec<String>,
+}
+
+/// Protocol versions and cipher suites the gRPC listener accepts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsPolicyConfig {
+    /// Lowest TLS version accepted: "1.2" or "1.3"
+    pub min_version: String,
+    /// Allowed cipher suites by IANA name (e.g. "TLS13_AES_256_GCM_SHA384").
+    /// Empty allows rustls' defaults, which are all AEAD with forward secrecy.
+    pub cipher_suites: Vec<String>,
+}
+
+/// Re-establishing the Docker client after the daemon goes away
+/// (e.g. a daemon restart leaves the old connection stale)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DockerReconnectConfig {
+    pub enabled: bool,
+    /// Consecutive failed Docker calls before reconnecting (and before
+    /// health reports the agent Degraded)
+    pub failure_threshold: u32,
+    /// Delay before the first reconnect retry; doubles on each further failure
+    pub initial_backoff_ms: u64,
+    /// Upper bound on the delay between reconnect attempts
+    pub max_backoff_ms: u64,
+}
+
+/// How far a log stream works ahead of its client, trading latency and
+/// memory for throughput
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamBufferConfig {
+    /// Entries a stream reads, parses and queues ahead of what the cluster
+    /// has taken (0 = none: each entry is read once the previous was sent).
+    /// Larger keeps busy streams flowing when the link stalls briefly, at
+    /// up to this many entries held in memory per stream.
+    pub emit_buffer_entries: usize,
+    /// Lines a stream scans per poll before yielding to other tasks when its
+    /// filter drops them. Larger drains filtered-out lines faster; smaller
+    /// keeps other streams responsive.
+    pub scan_budget_lines: usize,
+}
+
+/// Dialing the cluster instead of waiting to be dialed, for agents the
+/// cluster can't reach (NAT, firewalls)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReverseConfig {
+    pub enabled: bool,
+    /// Cluster's `agents.reverse_listen_address` ("host:port")
+    pub cluster_address: String,
+    /// ID this agent has in the cluster's `static_agents`
+    pub agent_id: String,
+    /// Delay before redialing after a failure; doubles on each further failure
+    pub initial_backoff_ms: u64,
+    /// Upper bound on the delay between dial attempts
+    pub max_backoff_ms: u64,
+}
+
 /// Per-container multiline override
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContainerMultilineConfig {
@@ -98,6 +291,7 @@ impl AgentConfig {
                 .unwrap_or_else(|_| "certs/agent.key".to_string()),
             tls_ca_path: std::env::var("AGENT_TLS_CA")
                 .unwrap_or_else(|_| "certs/ca.crt".to_string()),
+            tls: TlsPolicyConfig::from_env(),
             docker_socket: std::env::var("DOCKER_SOCKET")
                 .unwrap_or_else(|_| "".to_string()),
             max_concurrent_streams: std::env::var("AGENT_MAX_STREAMS")
@@ -106,16 +300,58 @@ impl AgentConfig {
                 .unwrap_or(100),
             audit_log_path: std::env::var("AGENT_AUDIT_LOG").ok(),
             multiline: MultilineConfig::from_env(),
+            stream_qos: StreamQosConfig::from_env(),
+            adaptive_detection: AdaptiveDetectionConfig::from_env(),
+            format_lock: FormatLockConfig::from_env(),
+            docker_reconnect: DockerReconnectConfig::from_env(),
+            stream_buffers: StreamBufferConfig::from_env(),
+            reverse: ReverseConfig::from_env(),
+            redaction: RedactionConfig::from_env(),
             inventory_sync_interval_secs: std::env::var("AGENT_INVENTORY_SYNC_INTERVAL")
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(2),
+            redetect_on_log_reset: std::env::var("AGENT_REDETECT_ON_LOG_RESET")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
+            fallback_encoding: std::env::var("AGENT_FALLBACK_ENCODING").ok(),
+            enabled_formats: std::env::var("AGENT_ENABLED_FORMATS")
+                .map(|s| s.split(',').map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect())
+                .unwrap_or_else(|_| default_enabled_formats()),
+            allow_freeze_inspect: std::env::var("AGENT_ALLOW_FREEZE_INSPECT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            freeze_capture_timeout_ms: std::env::var("AGENT_FREEZE_CAPTURE_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(2000),
+            allow_resource_updates: std::env::var("AGENT_ALLOW_RESOURCE_UPDATES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            log_schema_path: std::env::var("AGENT_LOG_SCHEMA_PATH").ok(),
         }
     }
 
     /// Validate that all required files exist and configuration values are sane
     pub fn validate(&self) -> Result<(), String> {
         // Validate configuration values first (fast, no I/O)
+        self.validate_values()?;
+
+        // Validate file existence (I/O)
+        self.validate_file(&self.tls_cert_path, "TLS certificate")?;
+        self.validate_file(&self.tls_key_path, "TLS key")?;
+        self.validate_file(&self.tls_ca_path, "CA certificate")?;
+        if let Some(path) = &self.log_schema_path {
+            crate::parser::schema::load_schema(path).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Check setting values without touching the filesystem
+    pub fn validate_values(&self) -> Result<(), String> {
         if self.bind_address.is_empty() {
             return Err("bind_address must not be empty".to_string());
         }
@@ -125,15 +361,43 @@ impl AgentConfig {
         if self.inventory_sync_interval_secs == 0 {
             return Err("inventory_sync_interval_secs must be > 0".to_string());
         }
+        if self.freeze_capture_timeout_ms == 0 {
+            return Err("freeze_capture_timeout_ms must be > 0".to_string());
+        }
         self.multiline.validate()?;
-
-        // Validate file existence (I/O)
-        self.validate_file(&self.tls_cert_path, "TLS certificate")?;
-        self.validate_file(&self.tls_key_path, "TLS key")?;
-        self.validate_file(&self.tls_ca_path, "CA certificate")?;
+        self.stream_qos.validate()?;
+        self.adaptive_detection.validate()?;
+        self.format_lock.validate()?;
+        self.docker_reconnect.validate()?;
+        self.stream_buffers.validate()?;
+        self.reverse.validate()?;
+        self.redaction.validate()?;
+        self.tls.validate()?;
+        if let Some(label) = &self.fallback_encoding {
+            if LogDecoder::fallback_from_label(label).is_none() {
+                return Err(format!("fallback_encoding '{}' is not a known encoding label", label));
+            }
+        }
+        for name in &self.enabled_formats {
+            if LogFormat::from_name(name).is_none() {
+                let known: Vec<&str> = LogFormat::DETECTABLE.iter().map(|f| f.as_str()).collect();
+                return Err(format!(
+                    "enabled_formats: unknown format '{}' (expected one of: {})",
+                    name, known.join(", ")
+                ));
+            }
+        }
         Ok(())
     }
 
+    /// Parsed `enabled_formats`. Unknown names are rejected by `validate()`.
+    pub fn enabled_log_formats(&self) -> Vec<LogFormat> {
+        self.enabled_formats
+            .iter()
+            .filter_map(|name| LogFormat::from_name(name))
+            .collect()
+    }
+
     fn validate_file(&self, path: &str, name: &str) -> Result<(), String> {
         if path.is_empty() {
             return Err(format!("{} path is not configured (empty string)", name));
@@ -170,12 +434,14 @@ impl AgentConfig {
             root_store.add(cert)?;
         }
         
-        let client_verifier = rustls::server::WebPkiClientVerifier::builder(
-            Arc::new(root_store)
+        let client_verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(
+            Arc::new(root_store),
+            Arc::new(self.tls.crypto_provider()?),
         ).build()?;
 
-        // Build server config with mTLS
-        let mut config = ServerConfig::builder()
+        // Build server config with mTLS, restricted to the TLS policy
+        let mut config = ServerConfig::builder_with_provider(Arc::new(self.tls.crypto_provider()?))
+            .with_protocol_versions(&self.tls.protocol_versions()?)?
             .with_client_cert_verifier(client_verifier)
             .with_single_cert(certs, key)?;
 
@@ -186,6 +452,10 @@ impl AgentConfig {
     }
 }
 
+fn default_enabled_formats() -> Vec<String> {
+    LogFormat::DETECTABLE.iter().map(|f| f.as_str().to_string()).collect()
+}
+
 impl Default for AgentConfig {
     fn default() -> Self {
         Self {
@@ -193,11 +463,26 @@ impl Default for AgentConfig {
             tls_cert_path: "certs/agent.crt".to_string(),
             tls_key_path: "certs/agent.key".to_string(),
             tls_ca_path: "certs/ca.crt".to_string(),
+            tls: TlsPolicyConfig::default(),
             docker_socket: "".to_string(),
             max_concurrent_streams: 100,
             audit_log_path: None,
             multiline: MultilineConfig::default(),
+            stream_qos: StreamQosConfig::default(),
+            adaptive_detection: AdaptiveDetectionConfig::default(),
+            format_lock: FormatLockConfig::default(),
+            docker_reconnect: DockerReconnectConfig::default(),
+            stream_buffers: StreamBufferConfig::default(),
+            reverse: ReverseConfig::default(),
+            redaction: RedactionConfig::default(),
             inventory_sync_interval_secs: 2,
+            redetect_on_log_reset: true,
+            fallback_encoding: None,
+            enabled_formats: default_enabled_formats(),
+            allow_freeze_inspect: false,
+            freeze_capture_timeout_ms: 2000,
+            allow_resource_updates: false,
+            log_schema_path: None,
         }
     }
 }
@@ -222,6 +507,19 @@ impl MultilineConfig {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(true),
+            profile: None,
+            json: std::env::var("AGENT_MULTILINE_JSON")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
+            json_max_lines: std::env::var("AGENT_MULTILINE_JSON_MAX_LINES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(500),
+            json_max_bytes: std::env::var("AGENT_MULTILINE_JSON_MAX_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(256 * 1024),
             container_overrides: HashMap::new(),
         }
     }
@@ -274,6 +572,22 @@ impl MultilineConfig {
             }
         }
 
+        if let Some(json_str) = labels.get("docktail.multiline.json") {
+            if let Ok(json) = json_str.parse::<bool>() {
+                config.json = json;
+            }
+        }
+
+        if let Some(profile_str) = labels.get("docktail.multiline") {
+            match profile_str.parse::<MultilineProfile>() {
+                Ok(profile) => config.profile = Some(profile),
+                Err(e) => tracing::warn!(
+                    container = %container_name,
+                    "Ignoring docktail.multiline label: {}", e
+                ),
+            }
+        }
+
         config
     }
 }
@@ -288,6 +602,9 @@ impl MultilineConfig {
             if self.max_lines == 0 {
                 return Err("multiline.max_lines must be > 0 when multiline is enabled".to_string());
             }
+            if self.json && (self.json_max_lines == 0 || self.json_max_bytes == 0) {
+                return Err("multiline.json_max_lines and json_max_bytes must be > 0 when multiline JSON is enabled".to_string());
+            }
         }
         Ok(())
     }
@@ -300,11 +617,426 @@ impl Default for MultilineConfig {
             timeout_ms: 300,
             max_lines: 50,
             require_error_anchor: true,
+            profile: None,
+            json: true,
+            json_max_lines: 500,
+            json_max_bytes: 256 * 1024,
             container_overrides: HashMap::new(),
         }
     }
 }
 
+impl StreamQosConfig {
+    /// Load stream QoS thresholds from environment variables
+    pub fn from_env() -> Self {
+        Self {
+            low_limit_pct: std::env::var("AGENT_QOS_LOW_LIMIT_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(70),
+            normal_limit_pct: std::env::var("AGENT_QOS_NORMAL_LIMIT_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(90),
+            memory_pressure_mb: std::env::var("AGENT_QOS_MEMORY_PRESSURE_MB")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+        }
+    }
+
+    /// Validate that thresholds are ordered 1 <= low <= normal <= 100
+    pub fn validate(&self) -> Result<(), String> {
+        if self.normal_limit_pct == 0 || self.normal_limit_pct > 100 {
+            return Err("stream_qos.normal_limit_pct must be between 1 and 100".to_string());
+        }
+        if self.low_limit_pct == 0 {
+            return Err("stream_qos.low_limit_pct must be at least 1".to_string());
+        }
+        if self.low_limit_pct > self.normal_limit_pct {
+            return Err("stream_qos.low_limit_pct must be <= stream_qos.normal_limit_pct".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for StreamQosConfig {
+    fn default() -> Self {
+        Self {
+            low_limit_pct: 70,
+            normal_limit_pct: 90,
+            memory_pressure_mb: 0,
+        }
+    }
+}
+
+impl AdaptiveDetectionConfig {
+    /// Load adaptive detection settings from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("AGENT_ADAPTIVE_DETECTION")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.enabled),
+            min_sample: std::env::var("AGENT_ADAPTIVE_DETECTION_MIN_SAMPLE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.min_sample),
+            max_sample: std::env::var("AGENT_ADAPTIVE_DETECTION_MAX_SAMPLE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_sample),
+            window: std::env::var("AGENT_ADAPTIVE_DETECTION_WINDOW")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.window),
+            low_success_pct: std::env::var("AGENT_ADAPTIVE_DETECTION_LOW_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.low_success_pct),
+            high_success_pct: std::env::var("AGENT_ADAPTIVE_DETECTION_HIGH_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.high_success_pct),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_sample == 0 {
+            return Err("adaptive_detection.min_sample must be > 0".to_string());
+        }
+        if self.max_sample < self.min_sample {
+            return Err("adaptive_detection.max_sample must be >= adaptive_detection.min_sample".to_string());
+        }
+        if self.window == 0 {
+            return Err("adaptive_detection.window must be > 0".to_string());
+        }
+        if self.high_success_pct > 100 || self.low_success_pct >= self.high_success_pct {
+            return Err("adaptive_detection thresholds must satisfy low_success_pct < high_success_pct <= 100".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for AdaptiveDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_sample: 1,
+            max_sample: 32,
+            window: 200,
+            low_success_pct: 50,
+            high_success_pct: 95,
+        }
+    }
+}
+
+impl FormatLockConfig {
+    /// Load format lock settings from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("AGENT_FORMAT_LOCK")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.enabled),
+            min_lines: std::env::var("AGENT_FORMAT_LOCK_MIN_LINES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.min_lines),
+            min_success_pct: std::env::var("AGENT_FORMAT_LOCK_MIN_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.min_success_pct),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.min_lines == 0 {
+            return Err("format_lock.min_lines must be > 0 when enabled".to_string());
+        }
+        if self.min_success_pct > 100 {
+            return Err("format_lock.min_success_pct must be <= 100".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for FormatLockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_lines: 1000,
+            min_success_pct: 99,
+        }
+    }
+}
+
+impl DockerReconnectConfig {
+    /// Load Docker reconnect settings from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("AGENT_DOCKER_RECONNECT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.enabled),
+            failure_threshold: std::env::var("AGENT_DOCKER_RECONNECT_AFTER")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.failure_threshold),
+            initial_backoff_ms: std::env::var("AGENT_DOCKER_RECONNECT_BACKOFF_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.initial_backoff_ms),
+            max_backoff_ms: std::env::var("AGENT_DOCKER_RECONNECT_MAX_BACKOFF_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_backoff_ms),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.failure_threshold == 0 {
+            return Err("docker_reconnect.failure_threshold must be > 0 when enabled".to_string());
+        }
+        if self.max_backoff_ms < self.initial_backoff_ms {
+            return Err("docker_reconnect.max_backoff_ms must be >= docker_reconnect.initial_backoff_ms".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for DockerReconnectConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: 3,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+impl StreamBufferConfig {
+    pub const MAX_EMIT_BUFFER_ENTRIES: usize = 65_536;
+    pub const MAX_SCAN_BUDGET_LINES: usize = 65_536;
+
+    /// Load stream buffer settings from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            emit_buffer_entries: std::env::var("AGENT_STREAM_EMIT_BUFFER")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.emit_buffer_entries),
+            scan_budget_lines: std::env::var("AGENT_STREAM_SCAN_BUDGET")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.scan_budget_lines),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.emit_buffer_entries > Self::MAX_EMIT_BUFFER_ENTRIES {
+            return Err(format!(
+                "stream_buffers.emit_buffer_entries must be at most {}",
+                Self::MAX_EMIT_BUFFER_ENTRIES
+            ));
+        }
+        if !(1..=Self::MAX_SCAN_BUDGET_LINES).contains(&self.scan_budget_lines) {
+            return Err(format!(
+                "stream_buffers.scan_budget_lines must be between 1 and {}",
+                Self::MAX_SCAN_BUDGET_LINES
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for StreamBufferConfig {
+    fn default() -> Self {
+        Self {
+            emit_buffer_entries: 0,
+            scan_budget_lines: 1024,
+        }
+    }
+}
+
+impl ReverseConfig {
+    /// Load reverse mode settings from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("AGENT_REVERSE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.enabled),
+            cluster_address: std::env::var("AGENT_REVERSE_CLUSTER").unwrap_or(defaults.cluster_address),
+            agent_id: std::env::var("AGENT_REVERSE_ID").unwrap_or(defaults.agent_id),
+            initial_backoff_ms: std::env::var("AGENT_REVERSE_BACKOFF_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.initial_backoff_ms),
+            max_backoff_ms: std::env::var("AGENT_REVERSE_MAX_BACKOFF_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_backoff_ms),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.cluster_address.is_empty() {
+            return Err("reverse.cluster_address must be set when enabled".to_string());
+        }
+        if self.agent_id.is_empty() || self.agent_id.contains(char::is_whitespace) {
+            return Err("reverse.agent_id must be set, without whitespace, when enabled".to_string());
+        }
+        if self.initial_backoff_ms == 0 {
+            return Err("reverse.initial_backoff_ms must be > 0".to_string());
+        }
+        if self.max_backoff_ms < self.initial_backoff_ms {
+            return Err("reverse.max_backoff_ms must be >= reverse.initial_backoff_ms".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for ReverseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cluster_address: String::new(),
+            agent_id: String::new(),
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+impl RedactionConfig {
+    /// Load redaction settings from environment variables. Patterns are
+    /// file-only: regular expressions don't survive a comma-separated list.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("AGENT_REDACTION")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.enabled),
+            env_name_keywords: std::env::var("AGENT_REDACTION_ENV_KEYWORDS")
+                .map(|s| s.split(',').map(|k| k.trim().to_string()).filter(|k| !k.is_empty()).collect())
+                .unwrap_or(defaults.env_name_keywords),
+            min_secret_length: std::env::var("AGENT_REDACTION_MIN_LENGTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.min_secret_length),
+            patterns: defaults.patterns,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_secret_length == 0 {
+            return Err("redaction.min_secret_length must be > 0".to_string());
+        }
+        for pattern in &self.patterns {
+            regex::bytes::Regex::new(pattern)
+                .map_err(|e| format!("redaction.patterns: invalid pattern '{}': {}", pattern, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl TlsPolicyConfig {
+    /// Load the TLS policy from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            min_version: std::env::var("AGENT_TLS_MIN_VERSION").unwrap_or(defaults.min_version),
+            cipher_suites: std::env::var("AGENT_TLS_CIPHER_SUITES")
+                .map(|s| s.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect())
+                .unwrap_or(defaults.cipher_suites),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.protocol_versions()?;
+        let provider = self.crypto_provider()?;
+        let min = self.min_version_tag()?;
+        if !provider.cipher_suites.iter().any(|suite| u16::from(suite.version().version) >= u16::from(min)) {
+            return Err(format!(
+                "tls.cipher_suites: none of the allowed suites work with TLS {} or later",
+                self.min_version
+            ));
+        }
+        Ok(())
+    }
+
+    /// Versions from `min_version` up
+    pub fn protocol_versions(&self) -> Result<Vec<&'static SupportedProtocolVersion>, String> {
+        let min = self.min_version_tag()?;
+        Ok(rustls::ALL_VERSIONS.iter().copied().filter(|v| u16::from(v.version) >= u16::from(min)).collect())
+    }
+
+    /// The default crypto provider, limited to `cipher_suites` when set
+    pub fn crypto_provider(&self) -> Result<CryptoProvider, String> {
+        let mut provider = rustls::crypto::aws_lc_rs::default_provider();
+        if self.cipher_suites.is_empty() {
+            return Ok(provider);
+        }
+        let mut allowed = Vec::with_capacity(self.cipher_suites.len());
+        for name in &self.cipher_suites {
+            let suite = rustls::crypto::aws_lc_rs::ALL_CIPHER_SUITES
+                .iter()
+                .find(|s| s.suite().as_str() == Some(name.as_str()))
+                .ok_or_else(|| {
+                    let known: Vec<&str> = rustls::crypto::aws_lc_rs::ALL_CIPHER_SUITES
+                        .iter()
+                        .filter_map(|s| s.suite().as_str())
+                        .collect();
+                    format!("tls.cipher_suites: unknown suite '{}' (expected one of: {})", name, known.join(", "))
+                })?;
+            allowed.push(*suite);
+        }
+        provider.cipher_suites = allowed;
+        Ok(provider)
+    }
+
+    fn min_version_tag(&self) -> Result<ProtocolVersion, String> {
+        match self.min_version.as_str() {
+            "1.2" => Ok(ProtocolVersion::TLSv1_2),
+            "1.3" => Ok(ProtocolVersion::TLSv1_3),
+            other => Err(format!("tls.min_version must be \"1.2\" or \"1.3\", got '{}'", other)),
+        }
+    }
+}
+
+impl Default for TlsPolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_version: "1.2".to_string(),
+            cipher_suites: Vec::new(),
+        }
+    }
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            env_name_keywords: ["PASSWORD", "PASSWD", "SECRET", "TOKEN", "API_KEY", "PRIVATE_KEY", "CREDENTIAL"]
+                .map(String::from)
+                .to_vec(),
+            min_secret_length: 6,
+            patterns: Vec::new(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -348,6 +1080,13 @@ mod tests {
         assert!(result.unwrap_err().contains("inventory_sync_interval"));
     }
 
+    #[test]
+    fn test_validate_zero_freeze_capture_timeout() {
+        let mut config = valid_config();
+        config.freeze_capture_timeout_ms = 0;
+        assert!(config.validate().unwrap_err().contains("freeze_capture_timeout_ms"));
+    }
+
     // ── MultilineConfig validation ──────────────────────────────
 
     #[test]
@@ -387,6 +1126,144 @@ mod tests {
         assert!(config.validate().is_ok());
     }
 
+    #[test]
+    fn test_validate_multiline_json_limits() {
+        let config = MultilineConfig {
+            json_max_bytes: 0,
+            ..MultilineConfig::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = MultilineConfig {
+            json: false,
+            json_max_bytes: 0,
+            ..MultilineConfig::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    // ── StreamQosConfig validation ──────────────────────────────
+
+    #[test]
+    fn test_validate_stream_qos_defaults_ok() {
+        assert!(StreamQosConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_stream_qos_low_above_normal() {
+        let mut config = valid_config();
+        config.stream_qos.low_limit_pct = 95;
+        let result = config.validate();
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("low_limit_pct"));
+    }
+
+    #[test]
+    fn test_validate_stream_qos_low_zero() {
+        let mut config = valid_config();
+        config.stream_qos.low_limit_pct = 0;
+        assert!(config.validate().unwrap_err().contains("low_limit_pct"));
+    }
+
+    #[test]
+    fn test_validate_stream_qos_normal_out_of_range() {
+        let mut config = valid_config();
+        config.stream_qos.normal_limit_pct = 101;
+        assert!(config.validate().unwrap_err().contains("normal_limit_pct"));
+    }
+
+    // ── AdaptiveDetectionConfig validation ──────────────────────
+
+    #[test]
+    fn test_validate_adaptive_detection() {
+        assert!(AdaptiveDetectionConfig::default().validate().is_ok());
+
+        let mut config = valid_config();
+        config.adaptive_detection.max_sample = 0;
+        assert!(config.validate().unwrap_err().contains("max_sample"));
+
+        let mut config = valid_config();
+        config.adaptive_detection.low_success_pct = 95;
+        assert!(config.validate().unwrap_err().contains("low_success_pct"));
+    }
+
+    #[test]
+    fn test_validate_format_lock() {
+        assert!(FormatLockConfig::default().validate().is_ok());
+
+        let mut config = valid_config();
+        config.format_lock.min_lines = 0;
+        assert!(config.validate().unwrap_err().contains("min_lines"));
+
+        config.format_lock.enabled = false;
+        assert!(config.format_lock.validate().is_ok());
+
+        let mut config = valid_config();
+        config.format_lock.min_success_pct = 101;
+        assert!(config.validate().unwrap_err().contains("min_success_pct"));
+    }
+
+    #[test]
+    fn test_validate_docker_reconnect() {
+        assert!(DockerReconnectConfig::default().validate().is_ok());
+
+        let mut config = valid_config();
+        config.docker_reconnect.failure_threshold = 0;
+        assert!(config.validate().unwrap_err().contains("failure_threshold"));
+
+        let mut config = valid_config();
+        config.docker_reconnect.max_backoff_ms = 10;
+        assert!(config.validate().unwrap_err().contains("max_backoff_ms"));
+    }
+
+    #[test]
+    fn test_validate_stream_buffers() {
+        assert!(StreamBufferConfig::default().validate().is_ok());
+
+        let mut config = valid_config();
+        config.stream_buffers.emit_buffer_entries = StreamBufferConfig::MAX_EMIT_BUFFER_ENTRIES + 1;
+        assert!(config.validate().unwrap_err().contains("emit_buffer_entries"));
+
+        let mut config = valid_config();
+        config.stream_buffers.scan_budget_lines = 0;
+        assert!(config.validate().unwrap_err().contains("scan_budget_lines"));
+    }
+
+    #[test]
+    fn test_validate_redaction() {
+        assert!(RedactionConfig::default().validate().is_ok());
+
+        let mut config = valid_config();
+        config.redaction.patterns = vec!["AKIA[0-9A-Z]{16}".to_string(), "(unclosed".to_string()];
+        assert!(config.validate().unwrap_err().contains("(unclosed"));
+
+        let mut config = valid_config();
+        config.redaction.min_secret_length = 0;
+        assert!(config.validate().unwrap_err().contains("min_secret_length"));
+    }
+
+    #[test]
+    fn test_validate_fallback_encoding() {
+        let mut config = valid_config();
+        config.fallback_encoding = Some("klingon".to_string());
+        assert!(config.validate().unwrap_err().contains("fallback_encoding"));
+
+        // A known label passes this check and validation moves on to the TLS files
+        config.fallback_encoding = Some("latin1".to_string());
+        assert!(config.validate().unwrap_err().contains("TLS certificate"));
+    }
+
+    #[test]
+    fn test_validate_enabled_formats() {
+        let mut config = valid_config();
+        config.enabled_formats = vec!["json".to_string(), "yaml".to_string()];
+        let err = config.validate().unwrap_err();
+        assert!(err.contains("enabled_formats") && err.contains("yaml"));
+
+        config.enabled_formats = vec!["json".to_string(), " Logfmt ".to_string()];
+        assert_eq!(config.enabled_log_formats(), vec![LogFormat::Json, LogFormat::Logfmt]);
+    }
+
     // ── for_container override priority ─────────────────────────
 
     #[test]
@@ -440,6 +1317,27 @@ mod tests {
         assert_eq!(result.timeout_ms, 300); // Unchanged, invalid label ignored
     }
 
+    #[test]
+    fn test_for_container_profile_label() {
+        let base = MultilineConfig::default();
+        let mut labels = HashMap::new();
+        labels.insert("docktail.multiline".to_string(), "Java".to_string());
+
+        let result = base.for_container("any", &labels);
+        assert_eq!(result.profile, Some(MultilineProfile::Java));
+    }
+
+    #[test]
+    fn test_for_container_unknown_profile_label_ignored() {
+        let base = MultilineConfig::default();
+        let mut labels = HashMap::new();
+        labels.insert("docktail.multiline".to_string(), "cobol".to_string());
+
+        let result = base.for_container("any", &labels);
+        assert_eq!(result.profile, None);
+        assert!("cobol".parse::<MultilineProfile>().unwrap_err().contains("java, python, go"));
+    }
+
     // ── Default values ──────────────────────────────────────────
 
     #[test]
@@ -449,5 +1347,187 @@ mod tests {
         assert_eq!(config.max_concurrent_streams, 100);
         assert_eq!(config.inventory_sync_interval_secs, 2);
         assert!(config.multiline.enabled);
+        assert!(!config.allow_freeze_inspect);
+        assert!(!config.allow_resource_updates);
+    }
+
+    // ── TLS policy ──────────────────────────────────────────────
+
+    #[test]
+    fn test_validate_tls_policy() {
+        assert!(TlsPolicyConfig::default().validate().is_ok());
+
+        let mut config = valid_config();
+        config.tls.min_version = "1.1".to_string();
+        assert!(config.validate().unwrap_err().contains("tls.min_version"));
+
+        let mut config = valid_config();
+        config.tls.cipher_suites = vec!["TLS_RSA_WITH_RC4_128_SHA".to_string()];
+        assert!(config.validate().unwrap_err().contains("unknown suite"));
+
+        // Pinning 1.3 while allowing only 1.2 suites leaves nothing to negotiate
+        let mut config = valid_config();
+        config.tls.min_version = "1.3".to_string();
+        config.tls.cipher_suites = vec!["TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384".to_string()];
+        assert!(config.validate().unwrap_err().contains("none of the allowed suites"));
     }
-}
\ No newline at end of file
+
+    mod handshake {
+        use super::*;
+        use rcgen::{BasicConstraints, CertificateParams, IsCa, Issuer, KeyPair};
+        use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+        use rustls::{CipherSuite, ClientConfig, ClientConnection, Connection, ServerConnection};
+
+        struct Pki {
+            dir: std::path::PathBuf,
+            ca: CertificateDer<'static>,
+            client_cert: CertificateDer<'static>,
+            client_key: PrivatePkcs8KeyDer<'static>,
+        }
+
+        /// A CA, an agent certificate for "localhost" and a client
+        /// certificate, with the agent's files written to a temp dir
+        fn pki(name: &str) -> Pki {
+            let dir = std::env::temp_dir().join(format!("docktail-tls-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let ca_key = KeyPair::generate().unwrap();
+            let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = ca_params.self_signed(&ca_key).unwrap();
+            let issuer = Issuer::from_params(&ca_params, &ca_key);
+
+            let agent_key = KeyPair::generate().unwrap();
+            let agent = CertificateParams::new(vec!["localhost".to_string()])
+                .unwrap()
+                .signed_by(&agent_key, &issuer)
+                .unwrap();
+            let client_key = KeyPair::generate().unwrap();
+            let client = CertificateParams::new(vec!["docktail-cluster".to_string()])
+                .unwrap()
+                .signed_by(&client_key, &issuer)
+                .unwrap();
+
+            std::fs::write(dir.join("ca.crt"), ca.pem()).unwrap();
+            std::fs::write(dir.join("agent.crt"), agent.pem()).unwrap();
+            std::fs::write(dir.join("agent.key"), agent_key.serialize_pem()).unwrap();
+            Pki {
+                dir,
+                ca: ca.der().clone(),
+                client_cert: client.der().clone(),
+                client_key: PrivatePkcs8KeyDer::from(client_key.serialize_der()),
+            }
+        }
+
+        impl Pki {
+            fn server(&self, policy: TlsPolicyConfig) -> Arc<ServerConfig> {
+                let config = AgentConfig {
+                    tls_cert_path: self.dir.join("agent.crt").display().to_string(),
+                    tls_key_path: self.dir.join("agent.key").display().to_string(),
+                    tls_ca_path: self.dir.join("ca.crt").display().to_string(),
+                    tls: policy,
+                    ..AgentConfig::default()
+                };
+                config.build_rustls_config().unwrap()
+            }
+
+            fn client(
+                &self,
+                versions: &[&'static SupportedProtocolVersion],
+                suites: &[CipherSuite],
+            ) -> Arc<ClientConfig> {
+                let mut provider = rustls::crypto::aws_lc_rs::default_provider();
+                if !suites.is_empty() {
+                    provider.cipher_suites.retain(|s| suites.contains(&s.suite()));
+                }
+                let mut roots = rustls::RootCertStore::empty();
+                roots.add(self.ca.clone()).unwrap();
+                let config = ClientConfig::builder_with_provider(Arc::new(provider))
+                    .with_protocol_versions(versions)
+                    .unwrap()
+                    .with_root_certificates(roots)
+                    .with_client_auth_cert(
+                        vec![self.client_cert.clone()],
+                        PrivateKeyDer::Pkcs8(self.client_key.clone_key()),
+                    )
+                    .unwrap();
+                Arc::new(config)
+            }
+        }
+
+        impl Drop for Pki {
+            fn drop(&mut self) {
+                let _ = std::fs::remove_dir_all(&self.dir);
+            }
+        }
+
+        fn pump(from: &mut Connection, to: &mut Connection) -> Result<(), rustls::Error> {
+            let mut buf = Vec::new();
+            while from.wants_write() {
+                from.write_tls(&mut buf).unwrap();
+            }
+            let mut pending = &buf[..];
+            while !pending.is_empty() {
+                to.read_tls(&mut pending).unwrap();
+            }
+            to.process_new_packets().map(|_| ())
+        }
+
+        /// Run a handshake in memory; the server's connection on success
+        fn handshake(server: Arc<ServerConfig>, client: Arc<ClientConfig>) -> Result<Connection, rustls::Error> {
+            let name = ServerName::try_from("localhost").unwrap();
+            let mut client = Connection::from(ClientConnection::new(client, name)?);
+            let mut server = Connection::from(ServerConnection::new(server)?);
+            for _ in 0..10 {
+                if !client.is_handshaking() && !server.is_handshaking() {
+                    return Ok(server);
+                }
+                pump(&mut client, &mut server)?;
+                pump(&mut server, &mut client)?;
+            }
+            panic!("handshake did not finish");
+        }
+
+        fn tls13_only() -> TlsPolicyConfig {
+            TlsPolicyConfig {
+                min_version: "1.3".to_string(),
+                cipher_suites: vec!["TLS13_AES_256_GCM_SHA384".to_string()],
+            }
+        }
+
+        #[test]
+        fn test_tls12_rejected_when_tls13_pinned() {
+            let pki = pki("pinned");
+            let server = pki.server(tls13_only());
+            let client = pki.client(&[&rustls::version::TLS12], &[]);
+
+            let err = handshake(server, client).unwrap_err();
+            assert!(matches!(err, rustls::Error::PeerIncompatible(_)), "{:?}", err);
+
+            // The default policy still serves TLS 1.2 clients
+            let server = pki.server(TlsPolicyConfig::default());
+            let client = pki.client(&[&rustls::version::TLS12], &[]);
+            let conn = handshake(server, client).unwrap();
+            assert_eq!(conn.protocol_version(), Some(ProtocolVersion::TLSv1_2));
+        }
+
+        #[test]
+        fn test_allowed_suite_negotiates() {
+            let pki = pki("suites");
+            let versions = [&rustls::version::TLS13, &rustls::version::TLS12];
+
+            // The client prefers another suite, but only the allowed one is accepted
+            let client = pki.client(&versions, &[]);
+            let conn = handshake(pki.server(tls13_only()), client).unwrap();
+            assert_eq!(conn.protocol_version(), Some(ProtocolVersion::TLSv1_3));
+            assert_eq!(
+                conn.negotiated_cipher_suite().map(|s| s.suite()),
+                Some(CipherSuite::TLS13_AES_256_GCM_SHA384)
+            );
+
+            // A client with no allowed suite in common is turned away
+            let client = pki.client(&versions, &[CipherSuite::TLS13_AES_128_GCM_SHA256]);
+            assert!(handshake(pki.server(tls13_only()), client).is_err());
+        }
+    }
+}
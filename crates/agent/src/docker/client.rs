@@ -1,14 +1,19 @@
+use crate::config::DockerReconnectConfig;
+use crate::docker::interleave::interleave_by_timestamp;
 use crate::docker::inventory::ContainerInfo;
 use crate::docker::stream::{LogStream, LogStreamRequest, LogLine, LogLevel};
-use crate::filter::engine::FilterEngine;
+use crate::filter::live::LiveFilter;
 use bollard::Docker;
 use bollard::container::{LogOutput};
-use bollard::models::ContainerInspectResponse;
-use bollard::query_parameters::{ListContainersOptions, LogsOptions};
+use bollard::models::{ContainerInspectResponse, ContainerTopResponse, ContainerUpdateBody};
+use bollard::query_parameters::{ListContainersOptions, LogsOptions, TopOptions};
 use thiserror::Error;
 use futures_util::stream::StreamExt;
 use bytes::Bytes;
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 #[derive(Error, Debug)]
 pub enum DockerError {
@@ -22,8 +27,37 @@ pub enum DockerError {
     StreamClosed,
     #[error("Unsupported log driver: {0}")]
     UnsupportedLogDriver(String),
+    #[error("Docker API rate limited (retry after {}s): {message}", retry_after.as_secs())]
+    RateLimited { message: String, retry_after: Duration },
     #[error("Bollard error: {0}")]
-    BollardError(#[from] bollard::errors::Error),
+    BollardError(bollard::errors::Error),
+}
+
+impl From<bollard::errors::Error> for DockerError {
+    fn from(e: bollard::errors::Error) -> Self {
+        match e {
+            bollard::errors::Error::DockerResponseServerError { status_code: 429, message } => {
+                let retry_after = retry_after_hint(&message).unwrap_or(DEFAULT_RETRY_AFTER);
+                DockerError::RateLimited { message, retry_after }
+            }
+            e => DockerError::BollardError(e),
+        }
+    }
+}
+
+/// Wait assumed when a 429 doesn't say how long to back off
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// bollard drops response headers, so `Retry-After` is only available when
+/// the daemon or registry repeats it in the message ("retry after 30s")
+fn retry_after_hint(message: &str) -> Option<Duration> {
+    let lower = message.to_ascii_lowercase();
+    let start = ["retry after", "retry-after"]
+        .iter()
+        .find_map(|key| lower.find(key).map(|i| i + key.len()))?;
+    let rest = lower[start..].trim_start_matches([':', ' ']);
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    rest[..digits].parse().ok().map(Duration::from_secs)
 }
 
 // use for time-travel (since/until parameters)
@@ -31,28 +65,71 @@ const SUPPORTED_LOG_DRIVERS: &[&str] = &["json-file", "journald", "local"];
 
 #[derive(Debug)]
 pub struct DockerClient {
-    client: Docker,
+    /// Swapped out by `reconnect`; callers clone the handle (cheap, Arc inside)
+    client: RwLock<Docker>,
+    socket_path: String,
+    throttle: RateLimitGate,
+    /// Successful `reconnect` calls since startup
+    reconnects: AtomicU64,
 }
 
 impl DockerClient {
     pub fn new(socket_path: &str) -> Result<Self, DockerError> {
-        let connection = if socket_path.is_empty() {
+        Ok(DockerClient {
+            client: RwLock::new(Self::connect(socket_path)?),
+            socket_path: socket_path.to_string(),
+            throttle: RateLimitGate::default(),
+            reconnects: AtomicU64::new(0),
+        })
+    }
+
+    fn connect(socket_path: &str) -> Result<Docker, DockerError> {
+        if socket_path.is_empty() {
             Docker::connect_with_defaults()
-                .map_err(|e| DockerError::ConnectionFailed(e.to_string()))?
+                .map_err(|e| DockerError::ConnectionFailed(e.to_string()))
         } else {
             let clean_path = socket_path.trim_start_matches("unix://");
             Docker::connect_with_socket(clean_path, 120, &bollard::API_DEFAULT_VERSION)
-                .map_err(|e| DockerError::ConnectionFailed(e.to_string()))?
-        };
+                .map_err(|e| DockerError::ConnectionFailed(e.to_string()))
+        }
+    }
+
+    fn docker(&self) -> Docker {
+        self.client.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
 
-        Ok(DockerClient { client: connection })
+    /// Run a Docker API call unless the daemon has told us to back off.
+    /// While throttled, calls fail fast with `RateLimited` instead of adding
+    /// to the load.
+    async fn call<T>(
+        &self,
+        request: impl Future<Output = Result<T, bollard::errors::Error>>,
+    ) -> Result<T, DockerError> {
+        self.throttle.check(Instant::now())?;
+        request.await.map_err(|e| self.throttle.observe(e.into(), Instant::now()))
+    }
+
+    /// Replace the client with a fresh connection, dropping pooled
+    /// connections to a daemon that may have restarted. In-flight streams
+    /// keep their old handle and end on their own.
+    pub fn reconnect(&self) -> Result<(), DockerError> {
+        let fresh = Self::connect(&self.socket_path)?;
+        *self.client.write().unwrap_or_else(|e| e.into_inner()) = fresh;
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+        Ok(())
     }
+
+    /// Times the client has been rebuilt by `reconnect`
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
     pub async fn list_containers(&self) -> Result<Vec<ContainerInfo>, DockerError> {
         let options = Some(ListContainersOptions {
             all: true,  // Include stopped containers
             ..Default::default()
         });
-        let containers = self.client.list_containers(options).await?;
+        let containers = self.call(self.docker().list_containers(options)).await?;
         Ok(containers
             .into_iter()
             .map(|c| c.into())
@@ -61,8 +138,10 @@ impl DockerClient {
     pub async fn stream_logs(
         &self,
         request: LogStreamRequest,
-        filter: Option<Arc<FilterEngine>>,
+        filter: Arc<LiveFilter>,
     ) -> Result<LogStream, DockerError> {
+        self.throttle.check(Instant::now())?;
+
         // Validate time-travel support if since/until is requested
         if request.since.is_some() || request.until.is_some() {
             let container = self.inspect_container(&request.container_id).await?;
@@ -104,7 +183,7 @@ impl DockerClient {
         };
 
         
-        let bollard_stream = self.client.logs(&request.container_id, Some(options));
+        let bollard_stream = self.docker().logs(&request.container_id, Some(options));
         
         let log_stream = bollard_stream.map(move |result| {
             match result {
@@ -113,22 +192,23 @@ impl DockerClient {
             }
         });
 
-        Ok(LogStream::new(
-            request.container_id,
-            log_stream,
-            filter,
-        ))
+        // Reordered before sequence numbers are assigned, so they follow
+        // emission order too
+        Ok(match request.interleave_window {
+            Some(window) => LogStream::new(request.container_id, interleave_by_timestamp(log_stream, window), filter),
+            None => LogStream::new(request.container_id, log_stream, filter),
+        })
     }
     
     pub async fn inspect_container(&self, id: &str) -> Result<ContainerInfo, DockerError> {
-        let details: ContainerInspectResponse = self.client.inspect_container(id, None).await?;
+        let details: ContainerInspectResponse = self.call(self.docker().inspect_container(id, None)).await?;
         Ok(ContainerInfo::from(details))
     }
 
     /// Returns the full `ContainerInspectResponse` from Docker for a container.
     /// Use this when you need details beyond `ContainerInfo` (ports, mounts, etc.).
     pub async fn inspect_container_raw(&self, id: &str) -> Result<ContainerInspectResponse, DockerError> {
-        let details: ContainerInspectResponse = self.client.inspect_container(id, None).await?;
+        let details: ContainerInspectResponse = self.call(self.docker().inspect_container(id, None)).await?;
         Ok(details)
     }
 
@@ -143,8 +223,171 @@ impl DockerClient {
             stream,
             ..Default::default()
         });
+        self.throttle.check(Instant::now())?;
+
+        Ok(self.docker().stats(container_id, options))
+    }
+
+    /// A single stats sample, taken without waiting for a second one to
+    /// compute CPU usage (`precpu_stats` is left empty)
+    pub async fn stats_snapshot(&self, container_id: &str) -> Result<Option<bollard::models::ContainerStatsResponse>, DockerError> {
+        use bollard::query_parameters::StatsOptions;
+
+        let options = Some(StatsOptions {
+            stream: false,
+            one_shot: true,
+        });
+        let mut stats = self.docker().stats(container_id, options);
+        self.call(async move { stats.next().await.transpose() }).await
+    }
+
+    pub async fn pause_container(&self, id: &str) -> Result<(), DockerError> {
+        self.call(self.docker().pause_container(id)).await?;
+        Ok(())
+    }
+
+    pub async fn unpause_container(&self, id: &str) -> Result<(), DockerError> {
+        self.call(self.docker().unpause_container(id)).await?;
+        Ok(())
+    }
+
+    /// Change a container's CPU (nano CPUs) and memory (bytes) limits in
+    /// place; `None` leaves a limit unchanged
+    pub async fn update_container_resources(
+        &self,
+        id: &str,
+        nano_cpus: Option<i64>,
+        memory: Option<i64>,
+    ) -> Result<(), DockerError> {
+        let body = ContainerUpdateBody { nano_cpus, memory, ..Default::default() };
+        self.call(self.docker().update_container(id, body)).await?;
+        Ok(())
+    }
+
+    /// Process list for a container (`docker top`, `ps -ef` columns)
+    pub async fn top_processes(&self, id: &str) -> Result<ContainerTopResponse, DockerError> {
+        let top = self.call(self.docker().top_processes(id, Some(TopOptions::default()))).await?;
+        Ok(top)
+    }
+
+    /// Timestamp (Unix nanos) of the oldest log line Docker still retains;
+    /// `None` when the log is empty
+    pub async fn first_log_timestamp(&self, id: &str) -> Result<Option<i64>, DockerError> {
+        self.throttle.check(Instant::now())?;
+        let options = LogsOptions {
+            stdout: true,
+            stderr: true,
+            timestamps: true,
+            tail: "all".to_string(),
+            ..Default::default()
+        };
+
+        // Read only the first frame; dropping the stream ends the request
+        let mut stream = self.docker().logs(id, Some(options));
+        match stream.next().await {
+            Some(output) => Ok(Some(convert_bollard_log(output?)?.timestamp)),
+            None => Ok(None),
+        }
+    }
+
+    /// The last `tail` log lines of a container (stdout and stderr interleaved),
+    /// decoded lossily. Does not follow.
+    pub async fn recent_logs(&self, id: &str, tail: u32) -> Result<Vec<String>, DockerError> {
+        let options = LogsOptions {
+            stdout: true,
+            stderr: true,
+            tail: tail.to_string(),
+            ..Default::default()
+        };
+
+        let mut stream = self.docker().logs(id, Some(options));
+        let mut lines = Vec::new();
+        while let Some(output) = stream.next().await {
+            let line = String::from_utf8_lossy(&output?.into_bytes()).trim_end().to_string();
+            lines.push(line);
+        }
+        Ok(lines)
+    }
+}
+
+/// Remembers a rate limit reported by the daemon and refuses calls until
+/// its retry-after has passed
+#[derive(Debug, Default)]
+struct RateLimitGate {
+    until: Mutex<Option<Instant>>,
+}
+
+impl RateLimitGate {
+    fn check(&self, now: Instant) -> Result<(), DockerError> {
+        let until = *self.until.lock().unwrap_or_else(|e| e.into_inner());
+        match until {
+            Some(until) if now < until => Err(DockerError::RateLimited {
+                message: "backing off after a rate limit from the Docker daemon".to_string(),
+                retry_after: until - now,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Start backing off if `error` is a rate limit; returns it unchanged
+    fn observe(&self, error: DockerError, now: Instant) -> DockerError {
+        if let DockerError::RateLimited { retry_after, .. } = &error {
+            tracing::warn!("Docker API rate limited; backing off for {:?}", retry_after);
+            *self.until.lock().unwrap_or_else(|e| e.into_inner()) = Some(now + *retry_after);
+        }
+        error
+    }
+}
+
+/// Decides when a failing Docker connection should be re-established.
+///
+/// Fed with the outcome of periodic Docker calls (the inventory sync). After
+/// `failure_threshold` consecutive failures a reconnect is due; further
+/// attempts back off exponentially until a call succeeds.
+#[derive(Debug)]
+pub struct ReconnectPolicy {
+    config: DockerReconnectConfig,
+    consecutive_failures: u32,
+    backoff: Duration,
+    next_attempt: Option<Instant>,
+}
+
+impl ReconnectPolicy {
+    pub fn new(config: DockerReconnectConfig) -> Self {
+        Self {
+            backoff: Duration::from_millis(config.initial_backoff_ms),
+            config,
+            consecutive_failures: 0,
+            next_attempt: None,
+        }
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Record a successful call. Returns `true` if this ends an outage.
+    pub fn record_success(&mut self) -> bool {
+        let recovered = self.consecutive_failures >= self.config.failure_threshold;
+        self.consecutive_failures = 0;
+        self.backoff = Duration::from_millis(self.config.initial_backoff_ms);
+        self.next_attempt = None;
+        recovered
+    }
+
+    /// Record a failed call. Returns `true` if a reconnect should be attempted now.
+    pub fn record_failure(&mut self, now: Instant) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if !self.config.enabled || self.consecutive_failures < self.config.failure_threshold {
+            return false;
+        }
+        if self.next_attempt.is_some_and(|at| now < at) {
+            return false;
+        }
 
-        Ok(self.client.stats(container_id, options))
+        self.next_attempt = Some(now + self.backoff);
+        self.backoff = (self.backoff * 2).min(Duration::from_millis(self.config.max_backoff_ms));
+        true
     }
 }
 
@@ -430,4 +673,104 @@ mod tests {
         // Should handle gracefully (returns None)
         assert!(dt_invalid.is_some() || dt_invalid.is_none());
     }
+
+    fn rate_limit(message: &str) -> bollard::errors::Error {
+        bollard::errors::Error::DockerResponseServerError { status_code: 429, message: message.to_string() }
+    }
+
+    #[test]
+    fn test_429_is_rate_limited_with_retry_hint() {
+        let err = DockerError::from(rate_limit("toomanyrequests: slow down, retry after 30s"));
+        assert!(matches!(err, DockerError::RateLimited { retry_after, .. } if retry_after == Duration::from_secs(30)));
+
+        let err = DockerError::from(rate_limit("Retry-After: 7"));
+        assert!(matches!(err, DockerError::RateLimited { retry_after, .. } if retry_after == Duration::from_secs(7)));
+
+        let err = DockerError::from(rate_limit("toomanyrequests"));
+        assert!(matches!(err, DockerError::RateLimited { retry_after, .. } if retry_after == DEFAULT_RETRY_AFTER));
+
+        let err = DockerError::from(bollard::errors::Error::DockerResponseServerError {
+            status_code: 500,
+            message: "retry after 3s".to_string(),
+        });
+        assert!(matches!(err, DockerError::BollardError(_)));
+    }
+
+    #[test]
+    fn test_gate_refuses_calls_until_retry_after() {
+        let gate = RateLimitGate::default();
+        let now = Instant::now();
+        assert!(gate.check(now).is_ok());
+
+        gate.observe(rate_limit("retry after 10s").into(), now);
+        match gate.check(now + Duration::from_secs(4)) {
+            Err(DockerError::RateLimited { retry_after, .. }) => assert_eq!(retry_after, Duration::from_secs(6)),
+            other => panic!("expected RateLimited, got {:?}", other),
+        }
+        assert!(gate.check(now + Duration::from_secs(10)).is_ok());
+
+        // Other errors don't throttle
+        gate.observe(DockerError::StreamClosed, now + Duration::from_secs(10));
+        assert!(gate.check(now + Duration::from_secs(10)).is_ok());
+    }
+
+    fn reconnect_config() -> DockerReconnectConfig {
+        DockerReconnectConfig {
+            enabled: true,
+            failure_threshold: 3,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 4000,
+        }
+    }
+
+    #[test]
+    fn test_reconnect_after_threshold_with_backoff() {
+        let mut policy = ReconnectPolicy::new(reconnect_config());
+        let start = Instant::now();
+
+        assert!(!policy.record_failure(start));
+        assert!(!policy.record_failure(start));
+        assert!(policy.record_failure(start), "third failure reconnects");
+
+        // Backing off: 1s, then 2s, then capped at 4s
+        assert!(!policy.record_failure(start + Duration::from_millis(500)));
+        assert!(policy.record_failure(start + Duration::from_secs(1)));
+        assert!(!policy.record_failure(start + Duration::from_secs(2)));
+        assert!(policy.record_failure(start + Duration::from_secs(3)));
+        assert!(policy.record_failure(start + Duration::from_secs(7)));
+        assert!(!policy.record_failure(start + Duration::from_secs(10)));
+        assert!(policy.record_failure(start + Duration::from_secs(11)));
+        assert_eq!(policy.consecutive_failures(), 10);
+    }
+
+    #[test]
+    fn test_reconnect_success_resets() {
+        let mut policy = ReconnectPolicy::new(reconnect_config());
+        let now = Instant::now();
+
+        assert!(!policy.record_failure(now));
+        assert!(!policy.record_success(), "a blip below the threshold is not an outage");
+
+        for _ in 0..3 {
+            policy.record_failure(now);
+        }
+        assert!(policy.record_success());
+        assert_eq!(policy.consecutive_failures(), 0);
+
+        // Backoff starts over
+        policy.record_failure(now);
+        policy.record_failure(now);
+        assert!(policy.record_failure(now));
+    }
+
+    #[test]
+    fn test_reconnect_disabled() {
+        let mut policy = ReconnectPolicy::new(DockerReconnectConfig {
+            enabled: false,
+            ..reconnect_config()
+        });
+        let now = Instant::now();
+        assert!((0..10).all(|_| !policy.record_failure(now)));
+        assert_eq!(policy.consecutive_failures(), 10);
+    }
 }
@@ -0,0 +1,232 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_stream::{Stream, StreamExt};
+
+use super::client::DockerError;
+use super::stream::LogLine;
+
+/// Longest interleave window a client may ask for; lines are held this long
+pub const MAX_INTERLEAVE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Puts stdout and stderr lines back in emission order.
+///
+/// Docker multiplexes the two streams as separate frames, and a frame of
+/// one can arrive after a later frame of the other. Each line is held for
+/// `window` after it arrives; once a line is due it goes out together with
+/// every held line timestamped no later than it, earliest first. A line
+/// arriving more than `window` late goes out as soon as it's seen. Equal
+/// timestamps keep arrival order.
+pub struct TimestampReorderer {
+    window: Duration,
+    held: BinaryHeap<Reverse<Held>>,
+    /// Arrival time and timestamp of held lines, in arrival order (released
+    /// lines linger until they are due, harmlessly)
+    arrivals: VecDeque<(Instant, i64)>,
+    /// Latest timestamp of a due line; anything up to it can be emitted
+    released_through: Option<i64>,
+    arrived: u64,
+}
+
+struct Held {
+    timestamp: i64,
+    arrival: u64,
+    line: LogLine,
+}
+
+impl Held {
+    fn key(&self) -> (i64, u64) {
+        (self.timestamp, self.arrival)
+    }
+}
+
+impl PartialEq for Held {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Held {}
+
+impl PartialOrd for Held {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Held {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl TimestampReorderer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            held: BinaryHeap::new(),
+            arrivals: VecDeque::new(),
+            released_through: None,
+            arrived: 0,
+        }
+    }
+
+    /// Hold a line; returns the lines that are now ready, in timestamp order
+    pub fn process(&mut self, line: LogLine, now: Instant) -> Vec<LogLine> {
+        self.arrivals.push_back((now, line.timestamp));
+        self.held.push(Reverse(Held { timestamp: line.timestamp, arrival: self.arrived, line }));
+        self.arrived += 1;
+        self.expired(now)
+    }
+
+    /// Lines whose window has closed, with everything timestamped before them
+    pub fn expired(&mut self, now: Instant) -> Vec<LogLine> {
+        while let Some(&(arrived_at, timestamp)) = self.arrivals.front() {
+            if now < arrived_at + self.window {
+                break;
+            }
+            self.arrivals.pop_front();
+            self.released_through = Some(self.released_through.map_or(timestamp, |t| t.max(timestamp)));
+        }
+
+        let mut out = Vec::new();
+        while let Some(Reverse(next)) = self.held.peek() {
+            if self.released_through.is_none_or(|t| next.timestamp > t) {
+                break;
+            }
+            out.extend(self.held.pop().map(|Reverse(held)| held.line));
+        }
+        out
+    }
+
+    /// When the earliest-arrived held line is due
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.arrivals.front().map(|(arrived_at, _)| *arrived_at + self.window)
+    }
+
+    /// Everything still held, in timestamp order (at stream end or before an error)
+    pub fn flush(&mut self) -> Vec<LogLine> {
+        self.arrivals.clear();
+        std::iter::from_fn(|| self.held.pop().map(|Reverse(held)| held.line)).collect()
+    }
+}
+
+/// Wrap a container's Docker log lines so stdout and stderr come out in
+/// timestamp order. Errors are passed through after flushing.
+pub fn interleave_by_timestamp<S>(
+    inner: S,
+    window: Duration,
+) -> impl Stream<Item = Result<LogLine, DockerError>>
+where
+    S: Stream<Item = Result<LogLine, DockerError>>,
+{
+    async_stream::stream! {
+        let mut inner = std::pin::pin!(inner);
+        let mut reorderer = TimestampReorderer::new(window);
+
+        loop {
+            let deadline = reorderer.next_deadline();
+            tokio::select! {
+                item = inner.next() => match item {
+                    Some(Ok(line)) => {
+                        for ready in reorderer.process(line, Instant::now()) {
+                            yield Ok(ready);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        for held in reorderer.flush() {
+                            yield Ok(held);
+                        }
+                        yield Err(e);
+                    }
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    for ready in reorderer.expired(Instant::now()) {
+                        yield Ok(ready);
+                    }
+                }
+            }
+        }
+
+        for held in reorderer.flush() {
+            yield Ok(held);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::stream::LogLevel;
+
+    const WINDOW: Duration = Duration::from_millis(50);
+
+    fn line(timestamp: i64, stream_type: LogLevel, content: &'static str) -> LogLine {
+        LogLine { timestamp, stream_type, content: bytes::Bytes::from_static(content.as_bytes()) }
+    }
+
+    fn contents(lines: &[LogLine]) -> Vec<&str> {
+        lines.iter().map(|l| std::str::from_utf8(&l.content).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_frames_come_out_in_timestamp_order() {
+        // stderr frames delivered after later stdout frames
+        let frames = vec![
+            Ok(line(1_000, LogLevel::Stdout, "starting")),
+            Ok(line(3_000, LogLevel::Stdout, "retrying")),
+            Ok(line(2_000, LogLevel::Stderr, "connection refused")),
+            Ok(line(5_000, LogLevel::Stdout, "connected")),
+            Ok(line(4_000, LogLevel::Stderr, "retry 1 failed")),
+            Ok(line(4_000, LogLevel::Stdout, "retry 1 done")),
+        ];
+        let out: Vec<LogLine> = interleave_by_timestamp(tokio_stream::iter(frames), WINDOW)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(
+            contents(&out),
+            ["starting", "connection refused", "retrying", "retry 1 failed", "retry 1 done", "connected"]
+        );
+        assert_eq!(out[1].stream_type, LogLevel::Stderr);
+    }
+
+    #[test]
+    fn test_lines_held_for_the_window() {
+        let mut reorderer = TimestampReorderer::new(WINDOW);
+        let start = Instant::now();
+
+        assert!(reorderer.process(line(2_000, LogLevel::Stdout, "b"), start).is_empty());
+        assert!(reorderer.process(line(1_000, LogLevel::Stderr, "a"), start + Duration::from_millis(20)).is_empty());
+        assert!(reorderer.expired(start + Duration::from_millis(49)).is_empty());
+
+        // "b" is due, and "a" came before it
+        let ready = reorderer.expired(start + WINDOW);
+        assert_eq!(contents(&ready), ["a", "b"]);
+        assert_eq!(reorderer.next_deadline(), Some(start + Duration::from_millis(70)));
+        assert!(reorderer.expired(start + Duration::from_millis(70)).is_empty());
+        assert_eq!(reorderer.next_deadline(), None);
+
+        // Too late to be put in place: sent right away
+        let ready = reorderer.process(line(500, LogLevel::Stderr, "late"), start + Duration::from_millis(80));
+        assert_eq!(contents(&ready), ["late"]);
+    }
+
+    #[tokio::test]
+    async fn test_error_flushes_held_lines_first() {
+        let frames = vec![
+            Ok(line(2_000, LogLevel::Stdout, "b")),
+            Ok(line(1_000, LogLevel::Stderr, "a")),
+            Err(DockerError::StreamClosed),
+        ];
+        let out: Vec<_> = interleave_by_timestamp(tokio_stream::iter(frames), WINDOW).collect().await;
+
+        assert_eq!(out.len(), 3);
+        assert_eq!(&out[0].as_ref().unwrap().content[..], b"a");
+        assert_eq!(&out[1].as_ref().unwrap().content[..], b"b");
+        assert!(out[2].is_err());
+    }
+}
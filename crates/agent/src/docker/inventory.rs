@@ -1,5 +1,6 @@
-use bollard::models::{ContainerSummary, ContainerInspectResponse};
+use bollard::models::{ContainerConfig, ContainerSummary, ContainerInspectResponse};
 use chrono::DateTime;
+use std::path::Path;
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct PortMapping {
@@ -19,6 +20,14 @@ pub struct ContainerStateInfo {
     pub restart_count: i32,
 }
 
+/// What a container runs, from its inspect config
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ContainerCommand {
+    pub entrypoint: Vec<String>,
+    pub cmd: Vec<String>,
+    pub working_dir: Option<String>,
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct ContainerInfo {
     pub id: String,         // Full container ID 64-char hash
@@ -27,10 +36,14 @@ pub struct ContainerInfo {
     pub state: String,       // "running", "paused", "exited"
     pub status: String,      // "Up 2 hours"
     pub log_driver: Option<String>,  // Note: Critical for checking "Time-Travel" support
+    pub log_options: std::collections::HashMap<String, String>,  // Driver options, e.g. max-size/max-file (from inspect)
     pub labels: std::collections::HashMap<String, String>,
     pub created_at: i64,     // Unix timestamp (better for gRPC)
     pub ports: Vec<PortMapping>,  // Structured port mappings
     pub state_info: Option<ContainerStateInfo>,  // Detailed state from inspect
+    pub log_path: Option<String>,      // Host path of the json-file log (from inspect)
+    pub log_size_bytes: Option<u64>,   // Log file + rotated segments on disk
+    pub command: Option<ContainerCommand>,  // Entrypoint/Cmd/WorkingDir (from inspect)
 }
 
 impl From<ContainerSummary> for ContainerInfo {
@@ -65,10 +78,14 @@ impl From<ContainerSummary> for ContainerInfo {
                 .unwrap_or_else(|| "unknown".into()),
             status: s.status.unwrap_or_default(),
             log_driver: None, // Not available in list API
+            log_options: Default::default(),
             labels: s.labels.unwrap_or_default(),
             created_at: s.created.unwrap_or_default(),
             ports,
             state_info: None, // Not available in list API
+            log_path: None,   // Not available in list API
+            log_size_bytes: None,
+            command: None,    // List API only has a flattened string
         }
     }
 }
@@ -80,6 +97,14 @@ impl From<ContainerInspectResponse> for ContainerInfo {
             .as_ref()
             .and_then(|hc| hc.log_config.as_ref())
             .and_then(|lc| lc.typ.clone());
+        let log_options = details.host_config
+            .as_ref()
+            .and_then(|hc| hc.log_config.as_ref())
+            .and_then(|lc| lc.config.clone())
+            .unwrap_or_default();
+        let log_path = details.log_path.clone().filter(|p| !p.is_empty());
+        let log_size_bytes = log_size_on_disk(log_driver.as_deref(), log_path.as_deref());
+        let command = details.config.as_ref().and_then(ContainerCommand::from_config);
 
         // Parse "Created" time (RFC3339 string format)
         // Inspect returns a String (RFC3339)
@@ -156,13 +181,259 @@ impl From<ContainerInspectResponse> for ContainerInfo {
                 .unwrap_or_default(),
             
             log_driver, // Note: Critical for time-travel support validation
-            
+            log_options,
+
             labels: details.config
                 .and_then(|c| c.labels)
                 .unwrap_or_default(),
             created_at,
             ports,
             state_info,
+            log_path,
+            log_size_bytes,
+            command,
+        }
+    }
+}
+
+impl ContainerCommand {
+    /// `None` when the image sets no entrypoint, command or working directory
+    pub fn from_config(config: &ContainerConfig) -> Option<Self> {
+        let command = Self {
+            entrypoint: config.entrypoint.clone().unwrap_or_default(),
+            cmd: config.cmd.clone().unwrap_or_default(),
+            working_dir: config.working_dir.clone().filter(|d| !d.is_empty()),
+        };
+        if command.entrypoint.is_empty() && command.cmd.is_empty() && command.working_dir.is_none() {
+            return None;
+        }
+        Some(command)
+    }
+
+    /// Whether the effective command runs through a shell (`/bin/sh -c ...`).
+    /// A shell-form entrypoint ignores `Cmd`; with no entrypoint `Cmd` itself
+    /// may be in shell form.
+    pub fn is_shell_form(&self) -> bool {
+        self.shell_script().is_some()
+    }
+
+    /// The command line as it would be typed: the shell script for shell
+    /// form, otherwise entrypoint and cmd with arguments quoted as needed.
+    pub fn command_line(&self) -> String {
+        if let Some(script) = self.shell_script() {
+            return script.to_string();
+        }
+        self.entrypoint
+            .iter()
+            .chain(&self.cmd)
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn shell_script(&self) -> Option<&str> {
+        if self.entrypoint.is_empty() {
+            shell_form_script(&self.cmd)
+        } else {
+            shell_form_script(&self.entrypoint)
+        }
+    }
+}
+
+/// The script of a shell-form argv as Docker writes it (`/bin/sh -c <script>`,
+/// or `cmd /S /C <script>` for Windows images)
+fn shell_form_script(argv: &[String]) -> Option<&str> {
+    match argv {
+        [shell, flag, script, ..]
+            if matches!(shell.as_str(), "/bin/sh" | "sh" | "/bin/bash" | "bash") && flag == "-c" =>
+        {
+            Some(script)
+        }
+        [shell, s, c, script, ..]
+            if shell.eq_ignore_ascii_case("cmd") && s.eq_ignore_ascii_case("/S") && c.eq_ignore_ascii_case("/C") =>
+        {
+            Some(script)
+        }
+        _ => None,
+    }
+}
+
+fn shell_quote(arg: &str) -> std::borrow::Cow<'_, str> {
+    let plain = !arg.is_empty()
+        && arg.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_./:=@%+,".contains(&b));
+    if plain {
+        arg.into()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''")).into()
+    }
+}
+
+/// Whether a container's log can grow without bound on the host.
+///
+/// Only json-file keeps every line until `max-size` is set; the `local`
+/// driver rotates by default and other drivers don't keep the log on disk.
+/// Daemon-wide `log-opts` are copied into each container at create time, so
+/// the container's own options are the whole story.
+pub fn log_rotation_unbounded(log_driver: Option<&str>, log_options: &std::collections::HashMap<String, String>) -> bool {
+    log_driver == Some("json-file")
+        && log_options.get("max-size").is_none_or(|size| size.is_empty() || size == "-1")
+}
+
+/// Bytes used by a container's json-file log: the live file plus rotated
+/// segments next to it (`<id>-json.log.1`, `<id>-json.log.2.gz`, ...).
+///
+/// `None` for other drivers, which keep no file at `LogPath`, and when the
+/// file can't be read (e.g. the Docker data root isn't mounted into the agent).
+pub fn log_size_on_disk(log_driver: Option<&str>, log_path: Option<&str>) -> Option<u64> {
+    if log_driver != Some("json-file") {
+        return None;
+    }
+    let path = Path::new(log_path?);
+    let mut total = std::fs::metadata(path).ok()?.len();
+
+    let (Some(dir), Some(base)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+        return Some(total);
+    };
+    let rotated_prefix = format!("{}.", base);
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_str().is_some_and(|n| n.starts_with(&rotated_prefix)) {
+                total += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
         }
     }
-}
\ No newline at end of file
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_size_includes_rotated_segments() {
+        let dir = std::env::temp_dir().join(format!("docktail-logsize-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("abc-json.log");
+        std::fs::write(&log, vec![b'x'; 1000]).unwrap();
+        std::fs::write(dir.join("abc-json.log.1"), vec![b'x'; 300]).unwrap();
+        std::fs::write(dir.join("abc-json.log.2.gz"), vec![b'x'; 20]).unwrap();
+        // Another file in the directory isn't part of the log
+        std::fs::write(dir.join("config.v2.json"), vec![b'x'; 7]).unwrap();
+
+        let path = log.to_str();
+        assert_eq!(log_size_on_disk(Some("json-file"), path), Some(1320));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(log_size_on_disk(Some("json-file"), path), None);
+    }
+
+    #[test]
+    fn test_log_size_unsupported_drivers() {
+        assert_eq!(log_size_on_disk(Some("journald"), Some("/etc/hostname")), None);
+        assert_eq!(log_size_on_disk(Some("local"), None), None);
+        assert_eq!(log_size_on_disk(None, None), None);
+        assert_eq!(log_size_on_disk(Some("json-file"), None), None);
+    }
+
+    fn inspect_with_config(config: ContainerConfig) -> ContainerInspectResponse {
+        ContainerInspectResponse {
+            id: Some("abc".to_string()),
+            config: Some(config),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_command_with_custom_entrypoint() {
+        let info = ContainerInfo::from(inspect_with_config(ContainerConfig {
+            entrypoint: Some(vec!["/docker-entrypoint.sh".to_string()]),
+            cmd: Some(["nginx", "-g", "daemon off;"].map(String::from).to_vec()),
+            working_dir: Some("/usr/share/nginx".to_string()),
+            ..Default::default()
+        }));
+
+        let command = info.command.expect("command from inspect");
+        assert_eq!(command.entrypoint, vec!["/docker-entrypoint.sh"]);
+        assert_eq!(command.cmd, vec!["nginx", "-g", "daemon off;"]);
+        assert_eq!(command.working_dir.as_deref(), Some("/usr/share/nginx"));
+        assert!(!command.is_shell_form());
+        assert_eq!(command.command_line(), "/docker-entrypoint.sh nginx -g 'daemon off;'");
+    }
+
+    #[test]
+    fn test_command_shell_form() {
+        // ENTRYPOINT java -jar app.jar: the shell ignores CMD
+        let command = ContainerCommand {
+            entrypoint: ["/bin/sh", "-c", "exec java -jar app.jar"].map(String::from).to_vec(),
+            cmd: vec!["--port=80".to_string()],
+            working_dir: None,
+        };
+        assert!(command.is_shell_form());
+        assert_eq!(command.command_line(), "exec java -jar app.jar");
+
+        // CMD in shell form with no entrypoint
+        let command = ContainerCommand {
+            entrypoint: vec![],
+            cmd: ["/bin/sh", "-c", "npm start"].map(String::from).to_vec(),
+            working_dir: None,
+        };
+        assert!(command.is_shell_form());
+        assert_eq!(command.command_line(), "npm start");
+
+        // An exec-form entrypoint wrapping a shell-form cmd is exec form
+        let command = ContainerCommand {
+            entrypoint: ["tini", "--"].map(String::from).to_vec(),
+            cmd: ["/bin/sh", "-c", "it's up"].map(String::from).to_vec(),
+            working_dir: None,
+        };
+        assert!(!command.is_shell_form());
+        assert_eq!(command.command_line(), r"tini -- /bin/sh -c 'it'\''s up'");
+    }
+
+    #[test]
+    fn test_command_absent() {
+        let info = ContainerInfo::from(inspect_with_config(ContainerConfig::default()));
+        assert!(info.command.is_none());
+    }
+
+    fn inspect_with_log_config(driver: &str, options: &[(&str, &str)]) -> ContainerInspectResponse {
+        ContainerInspectResponse {
+            id: Some("abc".to_string()),
+            host_config: Some(bollard::models::HostConfig {
+                log_config: Some(bollard::models::HostConfigLogConfig {
+                    typ: Some(driver.to_string()),
+                    config: Some(options.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_log_rotation_options() {
+        let info = ContainerInfo::from(inspect_with_log_config("json-file", &[("max-size", "10m"), ("max-file", "3")]));
+        assert_eq!(info.log_options.get("max-size").map(String::as_str), Some("10m"));
+        assert_eq!(info.log_options.get("max-file").map(String::as_str), Some("3"));
+        assert!(!log_rotation_unbounded(info.log_driver.as_deref(), &info.log_options));
+    }
+
+    #[test]
+    fn test_unbounded_log_flagged() {
+        let info = ContainerInfo::from(inspect_with_log_config("json-file", &[]));
+        assert!(info.log_options.is_empty());
+        assert!(log_rotation_unbounded(info.log_driver.as_deref(), &info.log_options));
+
+        // max-file alone doesn't cap anything; -1 is explicitly unlimited
+        let info = ContainerInfo::from(inspect_with_log_config("json-file", &[("max-file", "5")]));
+        assert!(log_rotation_unbounded(info.log_driver.as_deref(), &info.log_options));
+        let info = ContainerInfo::from(inspect_with_log_config("json-file", &[("max-size", "-1")]));
+        assert!(log_rotation_unbounded(info.log_driver.as_deref(), &info.log_options));
+
+        // local rotates by default; journald keeps nothing in the container dir
+        let info = ContainerInfo::from(inspect_with_log_config("local", &[]));
+        assert!(!log_rotation_unbounded(info.log_driver.as_deref(), &info.log_options));
+        assert!(!log_rotation_unbounded(Some("journald"), &Default::default()));
+        assert!(!log_rotation_unbounded(None, &Default::default()));
+    }
+}
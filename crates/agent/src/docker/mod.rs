@@ -1,3 +1,4 @@
 pub(crate) mod stream;
 pub(crate) mod client;
-pub(crate) mod inventory;
\ No newline at end of file
+pub(crate) mod inventory;
+pub(crate) mod interleave;
@@ -4,10 +4,11 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::task::{Context, Poll};
 use tokio_stream::Stream;
 use crate::docker::client::DockerError;
-use crate::filter::engine::{FilterEngine, FilterMode};
+use crate::filter::engine::FilterMode;
+use crate::filter::live::LiveFilter;
 
 // prevents executor starvation during heavy filtering.
-const POLL_BUDGET: usize = 1024;
+const DEFAULT_POLL_BUDGET: usize = 1024;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogLevel {
@@ -23,6 +24,7 @@ pub struct LogStreamRequest {
     pub filter_pattern: Option<String>,  // Regex pattern for ripgrep
     pub filter_mode: FilterMode,         // Include/Exclude/None
     pub tail_lines: Option<u32>,         // Like "docker logs --tail 100"
+    pub interleave_window: Option<std::time::Duration>, // Reorder stdout/stderr by timestamp
 }
 
 pub struct LogStreamResponse {
@@ -41,30 +43,39 @@ pub struct LogLine {
 pub struct LogStream {
     pub container_id: Arc<str>,  
     pub inner_stream: Pin<Box<dyn Stream<Item = Result<LogLine, DockerError>> + Send>>,
-    pub filter: Option<Arc<FilterEngine>>,
+    pub filter: Arc<LiveFilter>,
     pub sequence_counter: AtomicU64,  
+    /// Lines scanned per poll before yielding
+    pub poll_budget: usize,
 }
 
 impl LogStream {
     pub fn new(
         container_id: String,
         inner_stream: impl Stream<Item = Result<LogLine, DockerError>> + Send + 'static,
-        filter: Option<Arc<FilterEngine>>,
+        filter: Arc<LiveFilter>,
     ) -> Self {
         Self {
             container_id: container_id.into(),  
             inner_stream: Box::pin(inner_stream),
             filter,
             sequence_counter: AtomicU64::new(0),
+            poll_budget: DEFAULT_POLL_BUDGET,
         }
     }
+
+    /// Scan at most `lines` lines per poll (at least one)
+    pub fn with_poll_budget(mut self, lines: usize) -> Self {
+        self.poll_budget = lines.max(1);
+        self
+    }
 }
 
 impl Stream for LogStream {
     type Item = Result<LogStreamResponse, DockerError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut budget = POLL_BUDGET;
+        let mut budget = self.poll_budget;
 
         loop {
             // Yield to executor if budget exhausted to prevent starvation
@@ -82,10 +93,8 @@ impl Stream for LogStream {
                     match result {
                         Ok(line) => {
                             // Apply filter - skip non-matching lines
-                            if let Some(filter) = &this.filter {
-                                if !filter.should_include(&line.content) {
-                                    continue;  // Stack-safe: loop iteration, not recursion
-                                }
+                            if !this.filter.should_include(&line.content) {
+                                continue;  // Stack-safe: loop iteration, not recursion
                             }
 
                             // Generate sequence number for this matching line
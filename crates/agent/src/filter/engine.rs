@@ -45,10 +45,27 @@ impl FilterEngine {
 
     #[inline]
     pub fn should_include(&self, line: &[u8]) -> bool {
-        self.stats.lines_scanned.fetch_add(1, Ordering::Relaxed);
         self.stats.bytes_processed.fetch_add(line.len() as u64, Ordering::Relaxed);
-
         let matches = self.matcher.is_match(line).unwrap_or(false);
+        self.decide(matches)
+    }
+
+    /// Judge several lines as one entry (a multiline group): it matches when
+    /// any of its lines does, so it's kept or dropped whole
+    pub fn should_include_any<'a>(&self, lines: impl IntoIterator<Item = &'a [u8]>) -> bool {
+        let mut matches = false;
+        for line in lines {
+            self.stats.bytes_processed.fetch_add(line.len() as u64, Ordering::Relaxed);
+            if self.matcher.is_match(line).unwrap_or(false) {
+                matches = true;
+                break;
+            }
+        }
+        self.decide(matches)
+    }
+
+    fn decide(&self, matches: bool) -> bool {
+        self.stats.lines_scanned.fetch_add(1, Ordering::Relaxed);
 
         let include = match self.mode {
             FilterMode::Include => matches,
@@ -130,6 +147,18 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_group_judged_by_any_line() {
+        let trace: [&[u8]; 3] = [b"ERROR request failed", b"    at OrderRepository.save", b"    at Main.run"];
+
+        let include = FilterEngine::new("orderrepository", false, FilterMode::Include).unwrap();
+        assert!(include.should_include_any(trace));
+        assert!(!include.should_include_any([&b"INFO ok"[..]]));
+
+        let exclude = FilterEngine::new("OrderRepository", true, FilterMode::Exclude).unwrap();
+        assert!(!exclude.should_include_any(trace));
+    }
+
     #[test]
     fn test_stats_tracking() {
         let filter = FilterEngine::new("test", false, FilterMode::Include)
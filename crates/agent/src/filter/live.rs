@@ -0,0 +1,132 @@
+//! Filters that can be replaced while their stream runs.
+//!
+//! A stream opened with a `filter_token` registers its filter here, and
+//! `UpdateStreamFilter` swaps in a new one. The new filter applies to lines
+//! read after the swap; lines already sent are not revisited.
+
+use std::sync::{Arc, RwLock};
+use dashmap::DashMap;
+
+use super::engine::FilterEngine;
+
+/// A stream's current filter; `None` lets every line through
+#[derive(Default)]
+pub struct LiveFilter {
+    current: RwLock<Option<Arc<FilterEngine>>>,
+}
+
+impl LiveFilter {
+    pub fn new(filter: Option<FilterEngine>) -> Self {
+        Self { current: RwLock::new(filter.map(Arc::new)) }
+    }
+
+    #[inline]
+    pub fn should_include(&self, line: &[u8]) -> bool {
+        match self.current.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            Some(filter) => filter.should_include(line),
+            None => true,
+        }
+    }
+
+    /// Judge a multiline group as one entry; see `FilterEngine::should_include_any`
+    pub fn should_include_any<'a>(&self, lines: impl IntoIterator<Item = &'a [u8]>) -> bool {
+        match self.current.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            Some(filter) => filter.should_include_any(lines),
+            None => true,
+        }
+    }
+
+    pub fn replace(&self, filter: Option<Arc<FilterEngine>>) {
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = filter;
+    }
+}
+
+/// Live filters of the open streams, by token. Streams of one subscription
+/// (several containers on this agent) share a token.
+#[derive(Default)]
+pub struct LiveFilters {
+    streams: Arc<DashMap<String, Vec<Arc<LiveFilter>>>>,
+}
+
+/// Keeps a stream's filter registered until the stream is dropped
+pub struct LiveFilterRegistration {
+    streams: Arc<DashMap<String, Vec<Arc<LiveFilter>>>>,
+    token: String,
+    filter: Arc<LiveFilter>,
+}
+
+impl Drop for LiveFilterRegistration {
+    fn drop(&mut self) {
+        self.streams.remove_if_mut(&self.token, |_, filters| {
+            filters.retain(|f| !Arc::ptr_eq(f, &self.filter));
+            filters.is_empty()
+        });
+    }
+}
+
+impl LiveFilters {
+    pub fn register(&self, token: &str, filter: Arc<LiveFilter>) -> LiveFilterRegistration {
+        self.streams.entry(token.to_string()).or_default().push(Arc::clone(&filter));
+        LiveFilterRegistration {
+            streams: Arc::clone(&self.streams),
+            token: token.to_string(),
+            filter,
+        }
+    }
+
+    /// Swap the filter of every stream holding `token`; returns how many
+    pub fn replace(&self, token: &str, filter: Option<FilterEngine>) -> usize {
+        let Some(filters) = self.streams.get(token) else {
+            return 0;
+        };
+        let filter = filter.map(Arc::new);
+        for live in filters.iter() {
+            live.replace(filter.clone());
+        }
+        filters.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::client::DockerError;
+    use crate::docker::stream::{LogLevel, LogLine, LogStream};
+    use crate::filter::engine::FilterMode;
+    use tokio_stream::StreamExt;
+
+    fn line(content: &'static str) -> Result<LogLine, DockerError> {
+        Ok(LogLine { timestamp: 0, stream_type: LogLevel::Stdout, content: bytes::Bytes::from(content) })
+    }
+
+    #[tokio::test]
+    async fn test_filter_replaced_mid_stream() {
+        let filters = LiveFilters::default();
+        let initial = FilterEngine::new("error", false, FilterMode::Include).unwrap();
+        let live = Arc::new(LiveFilter::new(Some(initial)));
+        let registration = filters.register("search-1", Arc::clone(&live));
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut stream = LogStream::new("web".to_string(), tokio_stream::wrappers::UnboundedReceiverStream::new(rx), live);
+
+        tx.send(line("GET /orders 200")).unwrap();
+        tx.send(line("error: payment declined")).unwrap();
+        assert_eq!(&stream.next().await.unwrap().unwrap().content[..], b"error: payment declined");
+
+        // Refined search: later lines go through the new filter
+        let refined = FilterEngine::new("timeout", false, FilterMode::Include).unwrap();
+        assert_eq!(filters.replace("search-1", Some(refined)), 1);
+        tx.send(line("error: payment declined")).unwrap();
+        tx.send(line("upstream timeout after 30s")).unwrap();
+        assert_eq!(&stream.next().await.unwrap().unwrap().content[..], b"upstream timeout after 30s");
+
+        // Cleared: everything passes
+        assert_eq!(filters.replace("search-1", None), 1);
+        tx.send(line("GET /orders 200")).unwrap();
+        assert_eq!(&stream.next().await.unwrap().unwrap().content[..], b"GET /orders 200");
+
+        // Unknown once the stream is gone
+        drop(registration);
+        assert_eq!(filters.replace("search-1", None), 0);
+    }
+}
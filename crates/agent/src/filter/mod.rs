@@ -1 +1,4 @@
-pub mod engine;
\ No newline at end of file
+pub mod engine;
+pub mod severity;
+pub mod redact;
+pub mod live;
@@ -0,0 +1,101 @@
+/// Normalized log severity, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Severity {
+    /// Normalize a parsed level string. Accepts common spellings
+    /// (`WARNING`, `err`, `crit`, ...) and pino/bunyan numeric levels.
+    pub fn parse(level: &str) -> Option<Self> {
+        let level = level.trim();
+        if let Ok(n) = level.parse::<u32>() {
+            return Self::from_numeric(n);
+        }
+
+        match level.to_ascii_lowercase().as_str() {
+            "trace" | "trc" | "verbose" => Some(Self::Trace),
+            "debug" | "dbg" => Some(Self::Debug),
+            "info" | "inf" | "information" | "notice" => Some(Self::Info),
+            "warn" | "wrn" | "warning" => Some(Self::Warn),
+            "error" | "err" | "eror" => Some(Self::Error),
+            "fatal" | "ftl" | "critical" | "crit" | "panic" | "alert" | "emerg" | "emergency" => Some(Self::Fatal),
+            _ => None,
+        }
+    }
+
+    /// pino/bunyan levels: 10 trace, 20 debug, 30 info, 40 warn, 50 error, 60 fatal
+    fn from_numeric(n: u32) -> Option<Self> {
+        match n {
+            10 => Some(Self::Trace),
+            20 => Some(Self::Debug),
+            30 => Some(Self::Info),
+            40 => Some(Self::Warn),
+            50 => Some(Self::Error),
+            60 => Some(Self::Fatal),
+            _ => None,
+        }
+    }
+}
+
+/// Drops entries whose parsed level is below a minimum severity.
+///
+/// Entries without a recognizable level (plain text, parsing disabled, or an
+/// unknown level string) are kept or dropped according to `drop_unleveled`.
+#[derive(Debug, Clone, Copy)]
+pub struct SeverityFloor {
+    pub min: Severity,
+    pub drop_unleveled: bool,
+}
+
+impl SeverityFloor {
+    pub fn allows(&self, level: Option<&str>) -> bool {
+        match level.and_then(Severity::parse) {
+            Some(severity) => severity >= self.min,
+            None => !self.drop_unleveled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aliases() {
+        assert_eq!(Severity::parse("WARNING"), Some(Severity::Warn));
+        assert_eq!(Severity::parse("err"), Some(Severity::Error));
+        assert_eq!(Severity::parse(" Info "), Some(Severity::Info));
+        assert_eq!(Severity::parse("crit"), Some(Severity::Fatal));
+        assert_eq!(Severity::parse("40"), Some(Severity::Warn));
+        assert_eq!(Severity::parse("loud"), None);
+        assert_eq!(Severity::parse("42"), None);
+    }
+
+    #[test]
+    fn test_floor_keeps_warn_and_above() {
+        let floor = SeverityFloor { min: Severity::Warn, drop_unleveled: false };
+        let kept: Vec<_> = ["debug", "info", "warn", "error", "fatal"]
+            .into_iter()
+            .filter(|l| floor.allows(Some(l)))
+            .collect();
+        assert_eq!(kept, vec!["warn", "error", "fatal"]);
+    }
+
+    #[test]
+    fn test_unleveled_policy() {
+        let pass = SeverityFloor { min: Severity::Warn, drop_unleveled: false };
+        assert!(pass.allows(None));
+        assert!(pass.allows(Some("???")));
+
+        let drop = SeverityFloor { min: Severity::Warn, drop_unleveled: true };
+        assert!(!drop.allows(None));
+        assert!(!drop.allows(Some("???")));
+        assert!(drop.allows(Some("ERROR")));
+    }
+}
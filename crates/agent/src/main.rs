@@ -10,6 +10,7 @@ use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio_rustls::TlsAcceptor;
 use tokio_stream::wrappers::TcpListenerStream;
 use tokio_stream::StreamExt;
+use tonic::codec::CompressionEncoding;
 use tonic::transport::Server;
 use tracing::{info, error, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -20,6 +21,7 @@ mod filter;
 mod config;
 mod state;
 mod parser;
+mod reverse;
 
 use config::AgentConfig;
 use docker::client::DockerClient;
@@ -29,8 +31,13 @@ use service::{
     LogServiceServer, InventoryServiceServer, HealthServiceServer, StatsServiceServer,
 };
 
-/// Wrapper for TlsStream that implements tonic's Connected trait
-struct TlsStreamWrapper(tokio_rustls::server::TlsStream<TcpStream>);
+/// Wrapper for TlsStream that implements tonic's Connected trait. A
+/// connection the agent dialed in reverse mode carries its dial guard.
+struct TlsStreamWrapper(
+    tokio_rustls::server::TlsStream<TcpStream>,
+    #[allow(dead_code)] // Only held: dropping it tells reverse mode to redial
+    Option<reverse::DialGuard>,
+);
 
 impl tonic::transport::server::Connected for TlsStreamWrapper {
     type ConnectInfo = TlsConnectInfo;
@@ -107,7 +114,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Successfully connected to Docker daemon");
 
     // Create shared application state
-    let state = Arc::new(AgentState::new(docker_client, config.clone()));
+    let mut state = AgentState::new(docker_client, config.clone());
+    if let Some(path) = &config.log_schema_path {
+        state.log_schema = Some(Arc::new(parser::schema::load_schema(path)?));
+        info!("Loaded log schema from {}", path);
+    }
+    let state = Arc::new(state);
     info!("Initialized shared application state");
 
     // Start background inventory sync task
@@ -118,10 +130,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         sync_interval,
     ));
 
+    // Reload stream settings on SIGHUP
+    tokio::spawn(service::reload::reload_on_sighup(Arc::clone(&state)));
+
     // Create service implementations
     let log_service = LogServiceImpl::new(Arc::clone(&state));
     let inventory_service = InventoryServiceImpl::new(Arc::clone(&state));
-    let health_service = HealthServiceImpl::new(Arc::clone(&state.metrics));
+    let health_service = HealthServiceImpl::new(
+        Arc::clone(&state.metrics),
+        Arc::clone(&state.parser_cache),
+        config.docker_reconnect.failure_threshold,
+    );
     let stats_service = StatsServiceImpl::new(Arc::clone(&state));
 
     let addr: SocketAddr = config.bind_address.parse()
@@ -151,6 +170,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
 
     let tls_acceptor = TlsAcceptor::from(rustls_config);
+    let reverse_tls_acceptor = tls_acceptor.clone();
     
     let listener = TcpListener::bind(addr).await?;
     
@@ -162,6 +182,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("========================================");
     info!("Docktail Agent is ready!");
     info!("Listening on: {} (mTLS enabled)", addr);
+    if config.reverse.enabled {
+        info!("Reverse mode: dialing cluster at {} as '{}'", config.reverse.cluster_address, config.reverse.agent_id);
+    }
     info!("Max concurrent streams: {}", config.max_concurrent_streams);
     info!("Press Ctrl+C to shutdown gracefully");
     info!("========================================");
@@ -176,7 +199,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     Ok(stream) => {
                         match tls_acceptor.accept(stream).await {
                             Ok(tls_stream) => {
-                                Some(Ok::<_, std::io::Error>(TlsStreamWrapper(tls_stream)))
+                                Some(Ok::<_, std::io::Error>(TlsStreamWrapper(tls_stream, None)))
                             }
                             Err(e) => {
                                 warn!("TLS handshake failed: {}", e);
@@ -193,10 +216,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         })
         .filter_map(|x| x);
 
+    // Reverse mode: connections the agent dialed to the cluster, which is
+    // the TLS client on them as on accepted ones
+    let dialed = reverse::connections(config.reverse.clone())
+        .then(move |(stream, guard)| {
+            let tls_acceptor = reverse_tls_acceptor.clone();
+            async move {
+                match tls_acceptor.accept(stream).await {
+                    Ok(tls_stream) => Some(Ok(TlsStreamWrapper(tls_stream, Some(guard)))),
+                    Err(e) => {
+                        warn!("TLS handshake with cluster failed on reverse connection: {}", e);
+                        None
+                    }
+                }
+            }
+        })
+        .filter_map(|x| x);
+    let incoming = incoming.merge(dialed);
+
     Server::builder()
         .initial_stream_window_size(1 << 20) // 1 MiB
         .concurrency_limit_per_connection(config.max_concurrent_streams)
-        .add_service(LogServiceServer::new(log_service))
+        // Log frames are only gzipped for clients that advertise support
+        .add_service(
+            LogServiceServer::new(log_service)
+                .accept_compressed(CompressionEncoding::Gzip)
+                .send_compressed(CompressionEncoding::Gzip),
+        )
         .add_service(InventoryServiceServer::new(inventory_service))
         .add_service(HealthServiceServer::new(health_service))
         .add_service(StatsServiceServer::new(stats_service))
@@ -1,10 +1,86 @@
 use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use super::LogFormat;
+use crate::config::FormatLockConfig;
 
 #[derive(Debug, Clone, Copy)]
 pub struct ContainerState {
     pub format: LogFormat,
     pub is_enabled: bool,
+    /// Format is trusted; lines are no longer tracked
+    pub locked: bool,
+}
+
+impl ContainerState {
+    fn new(format: LogFormat) -> Self {
+        Self {
+            format,
+            is_enabled: true,
+            locked: false,
+        }
+    }
+}
+
+/// Where a container's log currently starts. A later observation that
+/// differs means the log was reset and may be written by a different app
+/// version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogEpoch {
+    /// Start time of the process writing the log (nanos); a restart changes it
+    pub started_at: i64,
+    /// Timestamp of the oldest line Docker retains (nanos), `None` when the
+    /// log is empty or it couldn't be read. Rotation that discards old lines
+    /// moves it forward.
+    pub first_line: Option<i64>,
+}
+
+impl LogEpoch {
+    /// Whether the log was reset between `previous` and this observation.
+    /// An unknown first line is not taken as a rotation.
+    fn resets(&self, previous: &LogEpoch) -> bool {
+        if self.started_at != previous.started_at {
+            return true;
+        }
+        matches!((self.first_line, previous.first_line), (Some(now), Some(before)) if now != before)
+    }
+}
+
+/// Lines a stream has parsed with its current format, toward the lock.
+///
+/// Once `min_lines` lines have been seen with at least `min_success_pct`
+/// succeeding, the window passes; a window that falls short starts over.
+#[derive(Debug, Clone)]
+pub struct FormatLockWindow {
+    lock: FormatLockConfig,
+    lines: u64,
+    successes: u64,
+}
+
+impl FormatLockWindow {
+    /// Count a line. Returns `true` when this line completed a passing window.
+    pub fn record(&mut self, success: bool) -> bool {
+        if !self.lock.enabled {
+            return false;
+        }
+
+        self.lines += 1;
+        if success {
+            self.successes += 1;
+        }
+        if self.lines < self.lock.min_lines {
+            return false;
+        }
+
+        let passed = self.successes * 100 >= self.lines * self.lock.min_success_pct as u64;
+        self.reset();
+        passed
+    }
+
+    /// Start over, e.g. after the format changed
+    pub fn reset(&mut self) {
+        self.lines = 0;
+        self.successes = 0;
+    }
 }
 
 /// Per-container parser cache
@@ -16,17 +92,34 @@ pub struct ParserCache {
     /// Single Map: container_id → State
     /// Merging them ensures atomic updates and single-lookup efficiency
     state: DashMap<String, ContainerState>,
+    /// container_id → last observed log epoch
+    log_epochs: DashMap<String, LogEpoch>,
+    lock: FormatLockConfig,
+    /// Format lookups answered from / missing in the cache
+    hits: AtomicU64,
+    misses: AtomicU64,
+    /// Manual evictions so far; running streams compare it to notice one
+    evictions: AtomicU64,
 }
 
 impl ParserCache {
     pub fn new() -> Self {
+        Self::with_format_lock(FormatLockConfig::default())
+    }
+
+    pub fn with_format_lock(lock: FormatLockConfig) -> Self {
         Self {
             state: DashMap::new(),
+            log_epochs: DashMap::new(),
+            lock,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
         }
     }
  
     pub fn get_format(&self, container_id: &str) -> Option<LogFormat> {
-        self.state.get(container_id).and_then(|r| {
+        let format = self.state.get(container_id).and_then(|r| {
             if r.is_enabled {
                 Some(r.format)
             } else {
@@ -35,7 +128,10 @@ impl ParserCache {
                 // Usually returning None forces the caller to fallback.
                 None 
             }
-        })
+        });
+        let counter = if format.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        format
     }
 
     /// Returns `true` if the container is cached but parsing is disabled.
@@ -49,35 +145,114 @@ impl ParserCache {
         self.state.entry(container_id).and_modify(|s| {
             // Stay disabled on re-detection to avoid enable-fail loop.
             if s.format != format {
-                s.format = format;
-                s.is_enabled = true; 
+                *s = ContainerState::new(format);
             }
-        }).or_insert(ContainerState {
-            format,
-            is_enabled: true,
-        });
+        }).or_insert(ContainerState::new(format));
     }
 
     /// fallbacks to plain text
     pub fn disable_parsing(&self, container_id: &str) {
         if let Some(mut entry) = self.state.get_mut(container_id) {
             entry.is_enabled = false;
+            entry.locked = false;
+        }
+    }
+
+    /// Returns `true` if the container's format is locked, so callers can skip
+    /// per-line tracking and re-detection.
+    pub fn is_locked(&self, container_id: &str) -> bool {
+        self.state.get(container_id).map(|r| r.locked).unwrap_or(false)
+    }
+
+    /// A window for one stream to count lines toward the format lock.
+    /// Lines are counted in the stream; the cache is only touched by
+    /// [`lock_format`](Self::lock_format) once a window passes.
+    pub fn lock_window(&self) -> FormatLockWindow {
+        FormatLockWindow {
+            lock: self.lock.clone(),
+            lines: 0,
+            successes: 0,
         }
     }
 
+    /// Lock the container's format if it is still `format` and parsing is
+    /// enabled. Returns `true` when the format is locked.
+    pub fn lock_format(&self, container_id: &str, format: LogFormat) -> bool {
+        let Some(mut entry) = self.state.get_mut(container_id) else {
+            return false;
+        };
+        if entry.format != format || !entry.is_enabled {
+            return false;
+        }
+        entry.locked = true;
+        true
+    }
+
     pub fn enable_parsing(&self, container_id: &str) {
         if let Some(mut entry) = self.state.get_mut(container_id) {
             entry.is_enabled = true;
         }
     }
 
+    /// Record the log epoch observed for a container.
+    ///
+    /// If the log was reset since the last observation (restart or rotation),
+    /// it may now be written by a different app version, so the cached format
+    /// is dropped and the next line is re-sampled. Returns `true` if the
+    /// format was invalidated.
+    pub fn observe_log_epoch(&self, container_id: &str, epoch: LogEpoch) -> bool {
+        let mut reset = false;
+        self.log_epochs
+            .entry(container_id.to_string())
+            .and_modify(|previous| {
+                reset = epoch.resets(previous);
+                // An unknown first line keeps the last known one to compare with
+                let first_line = if epoch.started_at == previous.started_at {
+                    epoch.first_line.or(previous.first_line)
+                } else {
+                    epoch.first_line
+                };
+                *previous = LogEpoch { first_line, ..epoch };
+            })
+            .or_insert(epoch);
+        reset && self.invalidate_format(container_id)
+    }
+
+    /// Drop the cached format so the next line triggers re-detection.
+    /// Containers with parsing disabled stay disabled.
+    pub fn invalidate_format(&self, container_id: &str) -> bool {
+        self.state.remove_if(container_id, |_, s| s.is_enabled).is_some()
+    }
+
+    /// Manually drop a container's cached state, disabled or locked
+    /// included, so its next line is detected again. Returns `true` if
+    /// the container was cached.
+    pub fn evict(&self, container_id: &str) -> bool {
+        let evicted = self.state.remove(container_id).is_some();
+        if evicted {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        evicted
+    }
+
+    /// Manual evictions so far, of any container
+    pub fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+
+    pub fn contains(&self, container_id: &str) -> bool {
+        self.state.contains_key(container_id)
+    }
+
     /// Remove a container from the cache
     pub fn remove(&self, container_id: &str) {
         self.state.remove(container_id);
+        self.log_epochs.remove(container_id);
     }
 
     pub fn clear(&self) {
         self.state.clear();
+        self.log_epochs.clear();
     }
 
     pub fn len(&self) -> usize {
@@ -93,12 +268,15 @@ impl ParserCache {
             total_containers: 0,
             enabled_containers: 0,
             disabled_containers: 0,
+            locked_containers: 0,
             json_containers: 0,
             logfmt_containers: 0,
             syslog_containers: 0,
             httplog_containers: 0,
             plain_containers: 0,
             unknown_containers: 0,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
         };
 
         // Single pass iteration O(N)
@@ -111,6 +289,9 @@ impl ParserCache {
             } else {
                 stats.disabled_containers += 1;
             }
+            if state.locked {
+                stats.locked_containers += 1;
+            }
 
             match state.format {
                 LogFormat::Json => stats.json_containers += 1,
@@ -137,12 +318,29 @@ pub struct CacheStats {
     pub total_containers: usize,
     pub enabled_containers: usize,
     pub disabled_containers: usize,
+    pub locked_containers: usize,
     pub json_containers: usize,
     pub logfmt_containers: usize,
     pub syslog_containers: usize,
     pub httplog_containers: usize,
     pub plain_containers: usize,
     pub unknown_containers: usize,
+    /// Format lookups answered from the cache
+    pub hits: u64,
+    /// Format lookups that had to detect
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Share of format lookups answered from the cache (0 before any lookup)
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
 }
 
 #[cfg(test)]
@@ -209,4 +407,191 @@ mod tests {
         assert_eq!(stats.enabled_containers, 0);
         assert_eq!(cache.get_format("c1"), None);
     }
+
+    fn started(started_at: i64) -> LogEpoch {
+        LogEpoch { started_at, first_line: Some(0) }
+    }
+
+    #[test]
+    fn test_log_epoch_change_invalidates_format() {
+        let cache = ParserCache::new();
+        assert!(!cache.observe_log_epoch("c1", started(100)), "First observation is not a reset");
+        cache.set_format("c1".to_string(), LogFormat::Json);
+
+        assert!(!cache.observe_log_epoch("c1", started(100)), "Same epoch keeps the cached format");
+        assert_eq!(cache.get_format("c1"), Some(LogFormat::Json));
+
+        // Rotation/restart: new epoch drops the cached detection
+        assert!(cache.observe_log_epoch("c1", started(200)));
+        assert_eq!(cache.get_format("c1"), None);
+
+        cache.set_format("c1".to_string(), LogFormat::Logfmt);
+        assert_eq!(cache.get_format("c1"), Some(LogFormat::Logfmt));
+    }
+
+    #[test]
+    fn test_rotation_without_restart_invalidates_format() {
+        let cache = ParserCache::new();
+        let epoch = LogEpoch { started_at: 100, first_line: Some(1_000) };
+        cache.observe_log_epoch("c1", epoch);
+        cache.set_format("c1".to_string(), LogFormat::Json);
+
+        // Oldest line unknown this time: not mistaken for a rotation
+        assert!(!cache.observe_log_epoch("c1", LogEpoch { first_line: None, ..epoch }));
+        assert_eq!(cache.get_format("c1"), Some(LogFormat::Json));
+
+        // Rotation discarded the oldest lines while the process kept running
+        assert!(cache.observe_log_epoch("c1", LogEpoch { first_line: Some(5_000), ..epoch }));
+        assert_eq!(cache.get_format("c1"), None);
+    }
+
+    #[test]
+    fn test_log_epoch_change_keeps_disabled_state() {
+        let cache = ParserCache::new();
+        cache.observe_log_epoch("c1", started(100));
+        cache.set_format("c1".to_string(), LogFormat::Json);
+        cache.disable_parsing("c1");
+
+        assert!(!cache.observe_log_epoch("c1", started(200)));
+        assert!(cache.is_disabled("c1"));
+    }
+
+    fn lock_after(min_lines: u64) -> ParserCache {
+        ParserCache::with_format_lock(FormatLockConfig {
+            enabled: true,
+            min_lines,
+            min_success_pct: 99,
+        })
+    }
+
+    /// Count `lines` clean lines the way a stream does, locking on a pass
+    fn parse_clean(cache: &ParserCache, container_id: &str, format: LogFormat, lines: u64) {
+        let mut window = cache.lock_window();
+        for _ in 0..lines {
+            if window.record(true) {
+                cache.lock_format(container_id, format);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tracking_stops_after_lock() {
+        let cache = lock_after(1000);
+        cache.set_format("c1".to_string(), LogFormat::Json);
+
+        // Mirrors the log stream: lines are counted locally while unlocked,
+        // and the cache is touched once, when the window passes
+        let mut window = cache.lock_window();
+        let mut tracked = 0;
+        let mut locks = 0;
+        let mut locked = false;
+        for _ in 0..100_000 {
+            if !locked {
+                tracked += 1;
+                if window.record(true) {
+                    locks += 1;
+                    locked = cache.lock_format("c1", LogFormat::Json);
+                }
+            }
+        }
+
+        assert_eq!((tracked, locks), (1000, 1), "no detection work after the lock threshold");
+        assert!(cache.is_locked("c1"));
+        assert_eq!(cache.stats().locked_containers, 1);
+        assert_eq!(cache.get_format("c1"), Some(LogFormat::Json));
+    }
+
+    #[test]
+    fn test_low_success_rate_does_not_lock() {
+        let cache = lock_after(100);
+        let mut window = cache.lock_window();
+
+        assert!(!(0..1000).any(|i| window.record(i % 10 != 0)));
+
+        // A clean window after a bad one still passes
+        assert!((0..100).any(|_| window.record(true)));
+    }
+
+    #[test]
+    fn test_lock_needs_the_current_format() {
+        let cache = lock_after(10);
+        assert!(!cache.lock_format("c1", LogFormat::Json), "nothing cached");
+
+        // Re-detected while the stream counted its window
+        cache.set_format("c1".to_string(), LogFormat::Logfmt);
+        assert!(!cache.lock_format("c1", LogFormat::Json));
+        assert!(!cache.is_locked("c1"));
+
+        cache.disable_parsing("c1");
+        assert!(!cache.lock_format("c1", LogFormat::Logfmt));
+    }
+
+    #[test]
+    fn test_redetection_triggers_unlock() {
+        let cache = lock_after(10);
+        cache.observe_log_epoch("c1", started(100));
+        cache.set_format("c1".to_string(), LogFormat::Json);
+        parse_clean(&cache, "c1", LogFormat::Json, 10);
+        assert!(cache.is_locked("c1"));
+
+        // Same format re-detected keeps the lock
+        cache.set_format("c1".to_string(), LogFormat::Json);
+        assert!(cache.is_locked("c1"));
+
+        // Log reset drops the format and its lock
+        assert!(cache.observe_log_epoch("c1", started(200)));
+        assert!(!cache.is_locked("c1"));
+
+        cache.set_format("c1".to_string(), LogFormat::Logfmt);
+        assert!(!cache.is_locked("c1"));
+    }
+
+    #[test]
+    fn test_lock_disabled() {
+        let cache = ParserCache::with_format_lock(FormatLockConfig {
+            enabled: false,
+            ..FormatLockConfig::default()
+        });
+        let mut window = cache.lock_window();
+        assert!((0..10_000).all(|_| !window.record(true)));
+    }
+
+    #[test]
+    fn test_stats_reflect_lookups_and_locks() {
+        let cache = lock_after(10);
+        assert_eq!(cache.stats().hit_rate(), 0.0);
+
+        assert_eq!(cache.get_format("c1"), None);
+        cache.set_format("c1".to_string(), LogFormat::Json);
+        cache.set_format("c2".to_string(), LogFormat::Logfmt);
+        (0..3).for_each(|_| { cache.get_format("c1"); });
+        parse_clean(&cache, "c1", LogFormat::Json, 10);
+
+        let stats = cache.stats();
+        assert_eq!(stats.total_containers, 2);
+        assert_eq!(stats.locked_containers, 1);
+        assert_eq!((stats.hits, stats.misses), (3, 1));
+        assert_eq!(stats.hit_rate(), 0.75);
+    }
+
+    #[test]
+    fn test_evict_forces_redetection() {
+        let cache = lock_after(10);
+        cache.set_format("c1".to_string(), LogFormat::Json);
+        parse_clean(&cache, "c1", LogFormat::Json, 10);
+        cache.set_format("c2".to_string(), LogFormat::Logfmt);
+        cache.disable_parsing("c2");
+
+        assert!(cache.evict("c1"));
+        assert_eq!(cache.get_format("c1"), None, "next line detects again");
+        assert!(!cache.is_locked("c1"));
+
+        // Unlike a log reset, eviction also clears a disabled container
+        assert!(cache.evict("c2"));
+        assert!(!cache.is_disabled("c2"));
+
+        assert!(!cache.evict("c3"));
+        assert_eq!(cache.evictions(), 2);
+        assert_eq!(cache.stats().total_containers, 0);
+    }
 }
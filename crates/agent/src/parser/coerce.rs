@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+
+/// A field value recognized as something more specific than a string
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Timestamp(DateTime<Utc>),
+}
+
+/// Infer the type of a structured field value. Conservative: anything that
+/// could reasonably be meant as text stays a string (`None`), such as
+/// zero-padded codes (`"007"`), `"yes"`, exponents or bare dates.
+pub fn infer_type(value: &str) -> Option<TypedValue> {
+    match value {
+        "true" => return Some(TypedValue::Bool(true)),
+        "false" => return Some(TypedValue::Bool(false)),
+        _ => {}
+    }
+    if let Some(number) = infer_number(value) {
+        return Some(number);
+    }
+    // RFC 3339 requires a date, time and offset, so plain numbers never match
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| TypedValue::Timestamp(dt.with_timezone(&Utc)))
+}
+
+/// Decimal integers and fractions only: optional `-`, digits, at most one
+/// `.` with digits on both sides, no leading zero unless it is the only
+/// integer digit
+fn infer_number(value: &str) -> Option<TypedValue> {
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    let (int_part, frac_part) = match unsigned.split_once('.') {
+        Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+        None => (unsigned, None),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(int_part) || (int_part.len() > 1 && int_part.starts_with('0')) {
+        return None;
+    }
+    match frac_part {
+        None => value.parse().ok().map(TypedValue::Int),
+        Some(frac) if all_digits(frac) => value.parse().ok().map(TypedValue::Float),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_typed_values_are_recognized() {
+        assert_eq!(infer_type("500"), Some(TypedValue::Int(500)));
+        assert_eq!(infer_type("-3"), Some(TypedValue::Int(-3)));
+        assert_eq!(infer_type("0"), Some(TypedValue::Int(0)));
+        assert_eq!(infer_type("12.75"), Some(TypedValue::Float(12.75)));
+        assert_eq!(infer_type("true"), Some(TypedValue::Bool(true)));
+        assert_eq!(infer_type("false"), Some(TypedValue::Bool(false)));
+        assert_eq!(
+            infer_type("2024-03-01T14:02:17.250+01:00"),
+            Some(TypedValue::Timestamp(Utc.with_ymd_and_hms(2024, 3, 1, 13, 2, 17).unwrap() + chrono::Duration::milliseconds(250)))
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_values_stay_strings() {
+        for value in [
+            "007",                   // zero-padded code
+            "01.5",
+            "1e5",                   // exponent
+            "1.",
+            ".5",
+            "1.2.3",                 // version
+            "+1",
+            "-",
+            "",
+            "True",                  // only the exact JSON spellings
+            "yes",
+            "2024-03-01",            // date without time
+            "14:02:17",
+            "99999999999999999999",  // overflows i64
+            "NaN",
+            "inf",
+            "500 ms",
+        ] {
+            assert_eq!(infer_type(value), None, "{:?}", value);
+        }
+    }
+}
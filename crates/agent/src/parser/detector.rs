@@ -23,6 +23,16 @@ impl FormatDetectorOrchestrator {
         Self { detectors }
     }
 
+    /// Build an orchestrator that only runs detectors for `enabled` formats.
+    /// The plain-text fallback always participates.
+    pub fn with_formats(enabled: &[LogFormat]) -> Self {
+        let mut orchestrator = Self::new();
+        orchestrator.detectors.retain(|d| {
+            d.format() == LogFormat::PlainText || enabled.contains(&d.format())
+        });
+        orchestrator
+    }
+
     pub fn detect_single(&self, sample: &[u8]) -> DetectionResult {
         self.run_detectors(sample)
     }
@@ -250,4 +260,23 @@ mod tests {
         
         let result = orchestrator.detect_single(sample);
         assert_eq!(result.format, LogFormat::HttpLog);
-    }}
+    }
+    #[test]
+    fn test_disabled_format_never_detected() {
+        let orchestrator = FormatDetectorOrchestrator::with_formats(&[LogFormat::Logfmt]);
+        let json = br#"{"level":"info","msg":"hello","ts":"2026-01-01T00:00:00Z"}"#;
+
+        let result = orchestrator.detect_single(json);
+        assert_ne!(result.format, LogFormat::Json);
+
+        let result = orchestrator.detect_multi(&[json, json, json]);
+        assert_ne!(result.format, LogFormat::Json);
+    }
+
+    #[test]
+    fn test_plain_text_fallback_always_enabled() {
+        let orchestrator = FormatDetectorOrchestrator::with_formats(&[]);
+        let result = orchestrator.detect_single(b"level=info msg=hello");
+        assert_eq!(result.format, LogFormat::PlainText);
+    }
+}
@@ -0,0 +1,200 @@
+use encoding_rs::{Encoding, UTF_16LE, UTF_8};
+use std::borrow::Cow;
+
+/// Per-stream log decoder that transcodes non-UTF-8 container output to UTF-8
+/// before parsing.
+///
+/// Windows containers commonly write UTF-16 or BOM-prefixed UTF-8. A byte-order
+/// mark on the first line of the stream locks the encoding for the rest of it.
+/// A stream that starts mid-file (a tail) has no BOM, so any line whose NUL
+/// bytes look like UTF-16LE text locks UTF-16LE too. Other lines that aren't
+/// valid UTF-8 are decoded with the configured fallback encoding (e.g.
+/// `windows-1252`), if any; otherwise bytes pass through untouched, as before.
+pub struct LogDecoder {
+    /// Encoding announced by a BOM on the first line, or recognised from a
+    /// line's content
+    encoding: Option<&'static Encoding>,
+    fallback: Option<&'static Encoding>,
+    first_line: bool,
+}
+
+impl LogDecoder {
+    pub fn new(fallback: Option<&'static Encoding>) -> Self {
+        Self {
+            encoding: None,
+            fallback,
+            first_line: true,
+        }
+    }
+
+    /// Resolve a fallback encoding from a WHATWG label like `latin1` or `windows-1252`
+    pub fn fallback_from_label(label: &str) -> Option<&'static Encoding> {
+        Encoding::for_label(label.trim().as_bytes())
+    }
+
+    /// Decode one log line to UTF-8. Valid UTF-8 input is returned borrowed.
+    pub fn decode<'a>(&mut self, line: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut line = line;
+
+        if self.first_line {
+            self.first_line = false;
+            if let Some((encoding, bom_len)) = Encoding::for_bom(line) {
+                self.encoding = Some(encoding);
+                line = &line[bom_len..];
+            }
+        }
+        if self.encoding.is_none() && looks_like_utf16le(line) {
+            self.encoding = Some(UTF_16LE);
+        }
+        if self.encoding == Some(UTF_16LE) && line.first() == Some(&0) {
+            // Docker splits on the 0x0A byte, which leaves the high byte of a
+            // UTF-16LE newline at the start of the following line
+            line = &line[1..];
+        }
+
+        match self.encoding {
+            Some(encoding) if encoding != UTF_8 => Cow::Owned(decode_utf16(encoding, line)),
+            _ => {
+                if std::str::from_utf8(line).is_ok() {
+                    return Cow::Borrowed(line);
+                }
+                match self.fallback {
+                    Some(fallback) => {
+                        let (text, _) = fallback.decode_without_bom_handling(line);
+                        Cow::Owned(text.into_owned().into_bytes())
+                    }
+                    None => Cow::Borrowed(line),
+                }
+            }
+        }
+    }
+}
+
+/// Whether a BOM-less line looks like UTF-16LE. Mostly-ASCII text has a zero
+/// high byte in nearly every code unit, so NULs fill one byte parity and
+/// not the other; which one depends on whether the line starts with the
+/// newline byte Docker's split left over. UTF-16BE isn't guessed: without a
+/// BOM its ASCII lines are indistinguishable from these.
+fn looks_like_utf16le(line: &[u8]) -> bool {
+    let body = line.strip_suffix(b"\n").unwrap_or(line);
+    if body.len() < 8 || !body.contains(&0) {
+        return false;
+    }
+    let zeros = |start: usize| body.iter().skip(start).step_by(2).filter(|&&b| b == 0).count();
+    let (even, odd) = (zeros(0), zeros(1));
+    let units = body.len() / 2;
+    even.max(odd) * 4 >= units * 3 && even.min(odd) * 4 < units
+}
+
+/// Decode a UTF-16 line, keeping a dangling single-byte `\n` left by Docker's
+/// line splitting as a newline rather than a replacement character.
+fn decode_utf16(encoding: &'static Encoding, line: &[u8]) -> Vec<u8> {
+    let (body, newline) = match line.split_last() {
+        Some((b'\n', rest)) if line.len() % 2 == 1 => (rest, true),
+        _ => (line, false),
+    };
+
+    let (text, _) = encoding.decode_without_bom_handling(body);
+    let mut out = text.into_owned().into_bytes();
+    if newline {
+        out.push(b'\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+    }
+
+    /// Split a byte stream on 0x0A the way Docker frames log lines
+    fn docker_lines(stream: &[u8]) -> Vec<Vec<u8>> {
+        stream
+            .split_inclusive(|&b| b == b'\n')
+            .map(|l| l.to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_utf16le_with_bom() {
+        let mut stream = vec![0xFF, 0xFE];
+        stream.extend(utf16le("Starting service\nERROR Fehler: Größe\n"));
+
+        let mut decoder = LogDecoder::new(None);
+        let decoded: Vec<String> = docker_lines(&stream)
+            .iter()
+            .map(|l| String::from_utf8(decoder.decode(l).into_owned()).unwrap())
+            .collect();
+
+        assert_eq!(decoded[0], "Starting service\n");
+        assert_eq!(decoded[1], "ERROR Fehler: Größe\n");
+    }
+
+    #[test]
+    fn test_utf16le_tail_without_bom() {
+        let mut stream = vec![0xFF, 0xFE];
+        stream.extend(utf16le("Starting service\nListening on :8080\nERROR Fehler: Größe\nWARN retrying\n"));
+
+        // A tail starts after the BOM, at a line left with the previous
+        // newline's high byte (the last split is only the final one's)
+        let lines = docker_lines(&stream);
+        let mut decoder = LogDecoder::new(None);
+        let decoded: Vec<String> = lines[1..lines.len() - 1]
+            .iter()
+            .map(|l| String::from_utf8(decoder.decode(l).into_owned()).unwrap())
+            .collect();
+
+        assert_eq!(decoded, ["Listening on :8080\n", "ERROR Fehler: Größe\n", "WARN retrying\n"]);
+    }
+
+    #[test]
+    fn test_stray_nul_is_not_utf16() {
+        let mut decoder = LogDecoder::new(None);
+        let line = b"payload=\0\0 truncated record for id=42\n";
+        assert!(matches!(decoder.decode(line), Cow::Borrowed(_)));
+        assert!(matches!(decoder.decode(b"next line\n"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_utf8_bom_stripped() {
+        let mut decoder = LogDecoder::new(None);
+        let first = decoder.decode(b"\xEF\xBB\xBF{\"level\":\"info\"}");
+        assert_eq!(first.as_ref(), b"{\"level\":\"info\"}");
+
+        // Later lines are borrowed unchanged
+        let second = decoder.decode(b"{\"level\":\"warn\"}");
+        assert!(matches!(second, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_bomless_latin1_uses_fallback() {
+        let fallback = LogDecoder::fallback_from_label("latin1");
+        assert!(fallback.is_some());
+
+        let mut decoder = LogDecoder::new(fallback);
+        let decoded = decoder.decode(b"caf\xE9 ouvert");
+        assert_eq!(std::str::from_utf8(&decoded).unwrap(), "café ouvert");
+    }
+
+    #[test]
+    fn test_bomless_invalid_utf8_without_fallback_passes_through() {
+        let mut decoder = LogDecoder::new(None);
+        let decoded = decoder.decode(b"caf\xE9");
+        assert_eq!(decoded.as_ref(), b"caf\xE9");
+    }
+
+    #[test]
+    fn test_valid_utf8_ignores_fallback() {
+        let mut decoder = LogDecoder::new(LogDecoder::fallback_from_label("windows-1252"));
+        let decoded = decoder.decode("café".as_bytes());
+        assert!(matches!(decoded, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_unknown_fallback_label() {
+        assert!(LogDecoder::fallback_from_label("klingon").is_none());
+    }
+}
@@ -4,11 +4,17 @@ use bytes::Bytes;
 use serde_json::Value;
 
 const DEFAULT_MAX_DETECTION_SIZE: usize = 1024;
+/// Most segments in a flattened key; deeper values stay JSON
+pub const MAX_FLATTEN_DEPTH: usize = 8;
+/// Arrays longer than this stay JSON instead of one key per element
+pub const MAX_FLATTEN_ARRAY_LEN: usize = 64;
 
 #[derive(Debug, Clone)]
 pub struct JsonParserConfig {
     pub max_event_size: usize,
     pub max_detection_size: usize,
+    /// Report nested objects and arrays as dotted keys (`user.id`,
+    /// `roles[0]`) instead of one JSON-encoded field per top-level key
     pub flatten_nested: bool,
 }
 
@@ -165,6 +171,7 @@ impl LogParser for JsonParser {
             error,
             fields,
             raw_content: Bytes::copy_from_slice(raw),
+            document: Some(value),
         })
     }
 
@@ -449,6 +456,7 @@ fn extract_additional_fields(
 
             Value::Object(_) | Value::Array(_) => {
                 if flatten_nested {
+                    flatten_value(key.clone(), value, 1, &mut fields);
                     continue;
                 } else {
                     // This preserves full data fidelity while staying human-readable
@@ -470,6 +478,27 @@ fn extract_additional_fields(
     fields
 }
 
+/// Push `value` as dotted keys under `key` (`key.child`, `key[0]`). Past
+/// `MAX_FLATTEN_DEPTH`, and for empty or over-long containers, the value is
+/// kept as JSON under the key reached so far.
+fn flatten_value(key: String, value: &Value, depth: usize, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) if depth < MAX_FLATTEN_DEPTH && !map.is_empty() => {
+            for (child, value) in map {
+                flatten_value(format!("{}.{}", key, child), value, depth + 1, fields);
+            }
+        }
+        Value::Array(items) if depth < MAX_FLATTEN_DEPTH && !items.is_empty() && items.len() <= MAX_FLATTEN_ARRAY_LEN => {
+            for (i, value) in items.iter().enumerate() {
+                flatten_value(format!("{}[{}]", key, i), value, depth + 1, fields);
+            }
+        }
+        Value::String(s) => fields.push((key, s.clone())),
+        Value::Object(_) | Value::Array(_) => fields.push((key, value.to_string())),
+        other => fields.push((key, other.to_string())),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -644,6 +673,41 @@ mod tests {
 
         let user_field = parsed.fields.iter().find(|(k, _)| k == "user");
         assert!(user_field.is_none(), "Nested user field should be skipped when flatten_nested=true");
+        assert!(parsed.fields.contains(&("user.id".to_string(), "123".to_string())));
+        assert!(parsed.fields.contains(&("user.name".to_string(), "Alice".to_string())));
+    }
+
+    #[test]
+    fn test_flatten_nested_keys() {
+        let parser = JsonParser::with_config(JsonParserConfig { flatten_nested: true, ..Default::default() });
+
+        let sample = br#"{"msg":"ok","a":{"b":1},"req":{"headers":{"host":"api"},"tags":["x",{"k":true}],"empty":[]}}"#;
+        let parsed = parser.parse(sample).unwrap();
+        let field = |key: &str| parsed.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+        assert_eq!(field("a.b"), Some("1"));
+        assert_eq!(field("req.headers.host"), Some("api"));
+        assert_eq!(field("req.tags[0]"), Some("x"));
+        assert_eq!(field("req.tags[1].k"), Some("true"));
+        assert_eq!(field("req.empty"), Some("[]"));
+        assert_eq!(field("a"), None);
+        assert_eq!(field("req"), None);
+    }
+
+    #[test]
+    fn test_flatten_depth_and_array_bounds() {
+        let parser = JsonParser::with_config(JsonParserConfig { flatten_nested: true, ..Default::default() });
+
+        let levels = MAX_FLATTEN_DEPTH + 1;
+        let deep = format!(r#"{{"msg":"ok","d":{}0{}}}"#, r#"{"n":"#.repeat(levels), "}".repeat(levels));
+        let parsed = parser.parse(deep.as_bytes()).unwrap();
+        let key = format!("d{}", ".n".repeat(MAX_FLATTEN_DEPTH - 1));
+        assert_eq!(parsed.fields, vec![(key, r#"{"n":{"n":0}}"#.to_string())]);
+
+        let long: Vec<usize> = (0..=MAX_FLATTEN_ARRAY_LEN).collect();
+        let sample = serde_json::json!({"msg": "ok", "ids": long}).to_string();
+        let parsed = parser.parse(sample.as_bytes()).unwrap();
+        assert_eq!(parsed.fields, vec![("ids".to_string(), serde_json::to_string(&long).unwrap())]);
     }
 
     #[test]
@@ -191,6 +191,7 @@ impl LogParser for LogfmtParser {
             error,
             fields,
             raw_content: Bytes::copy_from_slice(raw),
+            document: None,
         })
     }
 
@@ -320,6 +321,7 @@ fn parse_tracing_format(text: &str) -> Option<ParsedLog> {
         error: None,
         fields: Vec::new(),
         raw_content: Bytes::copy_from_slice(text.as_bytes()),
+        document: None,
     })
 }
 
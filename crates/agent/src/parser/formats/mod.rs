@@ -5,7 +5,7 @@ pub mod syslog;
 pub mod http_log;
 
 
-pub use json::{JsonDetector, JsonParser};
+pub use json::{JsonDetector, JsonParser, JsonParserConfig};
 pub use logfmt::{LogfmtDetector, LogfmtParser};
 pub use plain::{PlainTextDetector, PlainTextParser};
 pub use syslog::SyslogDetector;
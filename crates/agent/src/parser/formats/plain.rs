@@ -35,6 +35,7 @@ impl LogParser for PlainTextParser {
             error: None,
             fields: Vec::new(),
             raw_content: Bytes::copy_from_slice(raw),
+            document: None,
         })
     }
 
@@ -1,6 +1,10 @@
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, AtomicI64, Ordering};
+use dashmap::DashMap;
 use serde::Serialize;
 
+use crate::config::AdaptiveDetectionConfig;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MetricErrorType {
     /// Parse operation exceeded time limit
@@ -15,6 +19,19 @@ pub enum MetricErrorType {
     Other,
 }
 
+impl From<&super::model::ParseError> for MetricErrorType {
+    fn from(error: &super::model::ParseError) -> Self {
+        use super::model::ParseError;
+        match error {
+            ParseError::Timeout(_) => MetricErrorType::Timeout,
+            ParseError::ParserPanic(_) => MetricErrorType::Panic,
+            ParseError::LineTooLarge(..) => MetricErrorType::TooLarge,
+            ParseError::NonUtf8 => MetricErrorType::NonUtf8,
+            ParseError::InvalidFormat(_) | ParseError::ParseFailed(_) => MetricErrorType::Other,
+        }
+    }
+}
+
 /// wrapper that forces the wrapped data onto its own cache line(s).
 #[repr(align(64))]
 #[derive(Debug, Default)]
@@ -264,6 +281,150 @@ impl MetricsSnapshot {
     }
 }
 
+/// What the tuner keeps for one container while it has open streams
+#[derive(Debug, Clone, Copy)]
+struct TunedContainer {
+    sample_size: usize,
+    streams: usize,
+}
+
+/// Tunes the format detection sample size per container from how well its
+/// lines fit the detected format.
+///
+/// Each stream counts lines through its [`StreamTuning`]; after every
+/// `window` lines the fit rate is evaluated: a low rate doubles the
+/// container's sample (up to `max_sample`) and asks the stream to re-detect;
+/// a consistently high rate halves it back toward `min_sample`. Once at
+/// `max_sample`, a container that still fits badly is left alone rather
+/// than re-sampled forever. A container's entry is dropped with its last
+/// stream.
+#[derive(Debug)]
+pub struct DetectionTuner {
+    config: AdaptiveDetectionConfig,
+    containers: Arc<DashMap<String, TunedContainer>>,
+}
+
+impl DetectionTuner {
+    pub fn new(config: AdaptiveDetectionConfig) -> Self {
+        Self {
+            config,
+            containers: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Number of lines to sample when detecting this container's format
+    pub fn sample_size(&self, container_id: &str) -> usize {
+        if !self.config.enabled {
+            return 1;
+        }
+        self.containers
+            .get(container_id)
+            .map(|c| c.sample_size)
+            .unwrap_or(self.config.min_sample)
+    }
+
+    /// Start tuning for a stream of this container. The container's entry
+    /// lives until the last of its streams drops its handle.
+    pub fn track(&self, container_id: &str) -> StreamTuning {
+        if self.config.enabled {
+            self.containers
+                .entry(container_id.to_string())
+                .or_insert(TunedContainer { sample_size: self.config.min_sample, streams: 0 })
+                .streams += 1;
+        }
+        StreamTuning {
+            config: self.config.clone(),
+            containers: Arc::clone(&self.containers),
+            container_id: container_id.to_string(),
+            lines: 0,
+            fits: 0,
+        }
+    }
+
+    /// Containers with open streams being tuned
+    pub fn len(&self) -> usize {
+        self.containers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+}
+
+/// One stream's view of the tuner. Lines are counted locally; the shared
+/// entry is only touched when a window closes.
+#[derive(Debug)]
+pub struct StreamTuning {
+    config: AdaptiveDetectionConfig,
+    containers: Arc<DashMap<String, TunedContainer>>,
+    container_id: String,
+    lines: u64,
+    fits: u64,
+}
+
+impl StreamTuning {
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Number of lines to sample when detecting this container's format
+    pub fn sample_size(&self) -> usize {
+        if !self.config.enabled {
+            return 1;
+        }
+        self.containers
+            .get(&self.container_id)
+            .map(|c| c.sample_size)
+            .unwrap_or(self.config.min_sample)
+    }
+
+    /// Record whether a line fit the detected format. Returns the new
+    /// (larger) sample size when the window closed with a low fit rate and
+    /// detection should re-run.
+    pub fn record(&mut self, fits: bool) -> Option<usize> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        self.lines += 1;
+        if fits {
+            self.fits += 1;
+        }
+        if self.lines < self.config.window {
+            return None;
+        }
+
+        let fit_pct = self.fits * 100 / self.lines;
+        self.lines = 0;
+        self.fits = 0;
+
+        let mut container = self.containers.get_mut(&self.container_id)?;
+        if fit_pct < self.config.low_success_pct as u64 {
+            if container.sample_size >= self.config.max_sample {
+                return None;
+            }
+            container.sample_size = (container.sample_size * 2).min(self.config.max_sample);
+            Some(container.sample_size)
+        } else {
+            if fit_pct >= self.config.high_success_pct as u64 {
+                container.sample_size = (container.sample_size / 2).max(self.config.min_sample);
+            }
+            None
+        }
+    }
+}
+
+impl Drop for StreamTuning {
+    fn drop(&mut self) {
+        if self.config.enabled {
+            self.containers.remove_if_mut(&self.container_id, |_, c| {
+                c.streams -= 1;
+                c.streams == 0
+            });
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -357,4 +518,87 @@ mod tests {
         assert_eq!(snap.http_parsed, 1);
         assert_eq!(snap.plain_parsed, 2); // PlainText + Unknown
     }
-}
\ No newline at end of file
+
+    fn tuner() -> DetectionTuner {
+        DetectionTuner::new(AdaptiveDetectionConfig {
+            enabled: true,
+            min_sample: 1,
+            max_sample: 8,
+            window: 10,
+            low_success_pct: 50,
+            high_success_pct: 95,
+        })
+    }
+
+    #[test]
+    fn test_low_fit_triggers_larger_resample() {
+        let tuner = tuner();
+        let mut stream = tuner.track("c1");
+        assert_eq!(stream.sample_size(), 1);
+
+        // 2/10 fit: the closing line asks for a re-sample with a bigger sample
+        let mut resample = None;
+        for i in 0..10 {
+            resample = stream.record(i < 2);
+            if i < 9 {
+                assert!(resample.is_none(), "no decision before the window closes");
+            }
+        }
+        assert_eq!(resample, Some(2));
+        assert_eq!(stream.sample_size(), 2);
+        assert_eq!(tuner.sample_size("c1"), 2);
+
+        // Other containers are unaffected
+        assert_eq!(tuner.sample_size("c2"), 1);
+    }
+
+    #[test]
+    fn test_adaptation_is_bounded_and_shrinks_back() {
+        let tuner = tuner();
+        let mut stream = tuner.track("c1");
+        let mut sizes = Vec::new();
+        for _ in 0..5 {
+            for _ in 0..10 {
+                if let Some(size) = stream.record(false) {
+                    sizes.push(size);
+                }
+            }
+        }
+        // Doubles up to max_sample, then stops asking
+        assert_eq!(sizes, vec![2, 4, 8]);
+
+        // A clean window halves the sample again
+        for _ in 0..10 {
+            assert!(stream.record(true).is_none());
+        }
+        assert_eq!(tuner.sample_size("c1"), 4);
+    }
+
+    #[test]
+    fn test_entry_dropped_with_last_stream() {
+        let tuner = tuner();
+        let mut first = tuner.track("c1");
+        let second = tuner.track("c1");
+        (0..10).for_each(|_| { first.record(false); });
+        assert_eq!(tuner.sample_size("c1"), 2);
+
+        drop(first);
+        assert_eq!(tuner.len(), 1, "another stream still tunes c1");
+        assert_eq!(second.sample_size(), 2);
+
+        drop(second);
+        assert!(tuner.is_empty());
+        assert_eq!(tuner.sample_size("c1"), 1);
+    }
+
+    #[test]
+    fn test_disabled_tuner_is_inert() {
+        let tuner = DetectionTuner::new(AdaptiveDetectionConfig::default());
+        let mut stream = tuner.track("c1");
+        for _ in 0..1000 {
+            assert!(stream.record(false).is_none());
+        }
+        assert_eq!(tuner.sample_size("c1"), 1);
+        assert!(tuner.is_empty());
+    }
+}
@@ -1,15 +1,19 @@
 pub mod traits;
 pub mod detector;
 pub mod cache;
+pub mod coerce;
 pub mod metrics;
 pub mod formats;
 pub mod model;
+pub mod schema;
 mod ansi;
+mod encoding;
 mod serde_utils;
 
 pub use traits::LogParser;
 pub use model::LogFormat;
 pub use ansi::strip_ansi_codes;
+pub use encoding::LogDecoder;
 
 pub const MAX_LINE_SIZE: usize = 1_048_576; // 1MB
 pub const DETECTION_SAMPLE_SIZE: usize = 5; // Lines to sample for detection
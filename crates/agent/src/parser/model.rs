@@ -23,6 +23,23 @@ pub enum LogFormat {
 }
 
 impl LogFormat {
+    /// Formats that have a detector and can be toggled via `enabled_formats`.
+    /// Plain text is the fallback and is always available.
+    pub const DETECTABLE: [LogFormat; 4] = [
+        LogFormat::Json,
+        LogFormat::Logfmt,
+        LogFormat::Syslog,
+        LogFormat::HttpLog,
+    ];
+
+    /// Look up a format by its `as_str` name
+    pub fn from_name(name: &str) -> Option<LogFormat> {
+        Self::DETECTABLE
+            .into_iter()
+            .chain([LogFormat::PlainText])
+            .find(|f| f.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             LogFormat::Json => "json",
@@ -133,6 +150,11 @@ pub struct ParsedLog {
     /// Skipped during serialization to save bandwidth - raw logs stored separately
     #[serde(skip)]
     pub raw_content: bytes::Bytes,
+
+    /// The decoded JSON object (JSON lines only), kept so schema checks
+    /// don't decode the line a second time
+    #[serde(skip)]
+    pub document: Option<serde_json::Value>,
 }
 
 impl ParsedLog {
@@ -147,6 +169,7 @@ impl ParsedLog {
             error: None,
             fields: Vec::new(),
             raw_content: raw,
+            document: None,
         }
     }
 
@@ -161,6 +184,7 @@ impl ParsedLog {
             error: None,
             fields: Vec::new(),
             raw_content: raw,
+            document: None,
         }
     }
 }
@@ -0,0 +1,141 @@
+//! JSON Schema checks for structured-log contracts.
+//!
+//! Validation is done by the `jsonschema` crate, so every keyword of the
+//! schema's draft applies (`$schema` picks the draft, 2020-12 otherwise),
+//! including `$ref`, `allOf`/`anyOf`/`oneOf`/`not`, `if`/`then`/`else` and
+//! `patternProperties`. `format` is checked rather than treated as an
+//! annotation. References are resolved within the schema only: one that
+//! needs a file or a URL is rejected when the schema is loaded.
+
+use serde_json::Value;
+use std::path::Path;
+
+/// Errors reported per entry are capped; the first few say enough
+const MAX_ERRORS: usize = 10;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error("Failed to read log schema {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Log schema is not valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid log schema at {path}: {message}")]
+    Invalid { path: String, message: String },
+}
+
+/// Read a schema file and check that it compiles
+pub fn load_schema(path: &str) -> Result<Value, SchemaError> {
+    let text = std::fs::read_to_string(Path::new(path)).map_err(|e| SchemaError::Io(path.to_string(), e))?;
+    let schema: Value = serde_json::from_str(&text)?;
+    CompiledSchema::compile(&schema)?;
+    Ok(schema)
+}
+
+/// A schema prepared for repeated validation
+#[derive(Debug)]
+pub struct CompiledSchema {
+    validator: jsonschema::Validator,
+}
+
+impl CompiledSchema {
+    pub fn compile(schema: &Value) -> Result<Self, SchemaError> {
+        let validator = jsonschema::options()
+            .should_validate_formats(true)
+            .build(schema)
+            .map_err(|e| SchemaError::Invalid {
+                path: pointer(&e.instance_path().to_string()),
+                message: e.to_string(),
+            })?;
+        Ok(Self { validator })
+    }
+
+    /// Validation errors for a document, as `<json pointer>: <message>`.
+    /// Empty when the document conforms.
+    pub fn validate(&self, document: &Value) -> Vec<String> {
+        self.validator
+            .iter_errors(document)
+            .take(MAX_ERRORS)
+            .map(|e| format!("{}: {}", pointer(&e.instance_path().to_string()), e))
+            .collect()
+    }
+}
+
+/// The root is reported as `/` rather than an empty pointer
+fn pointer(path: &str) -> String {
+    if path.is_empty() { "/".to_string() } else { path.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn contract() -> CompiledSchema {
+        CompiledSchema::compile(&json!({
+            "type": "object",
+            "required": ["level", "msg", "service"],
+            "properties": {
+                "level": { "enum": ["debug", "info", "warn", "error"] },
+                "msg": { "type": "string", "minLength": 1 },
+                "service": { "type": "string", "pattern": "^[a-z-]+$" },
+                "status": { "type": "integer", "minimum": 100, "maximum": 599 },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "additionalProperties": false
+        }))
+        .unwrap()
+    }
+
+    fn paths(errors: &[String]) -> Vec<&str> {
+        let mut paths: Vec<&str> = errors.iter().map(|e| e.split(": ").next().unwrap()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_conforming_document() {
+        let doc = json!({"level": "info", "msg": "ok", "service": "payments", "status": 200, "tags": ["a"]});
+        assert!(contract().validate(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_violations_reported_with_paths() {
+        let doc = json!({"level": "loud", "msg": "ok", "service": "Payments", "status": 700, "tags": [1]});
+        let errors = contract().validate(&doc);
+        assert_eq!(paths(&errors), ["/level", "/service", "/status", "/tags/0"], "{:?}", errors);
+
+        let errors = contract().validate(&json!({"level": "info", "service": "api", "extra": true}));
+        assert_eq!(paths(&errors), ["/", "/"], "{:?}", errors);
+        assert!(errors.iter().any(|e| e.contains("\"msg\" is a required property")), "{:?}", errors);
+        assert!(errors.iter().any(|e| e.contains("extra")), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_combinators_and_refs_are_enforced() {
+        let schema = CompiledSchema::compile(&json!({
+            "$defs": { "level": { "enum": ["info", "error"] } },
+            "type": "object",
+            "properties": { "level": { "$ref": "#/$defs/level" } },
+            "anyOf": [{ "required": ["msg"] }, { "required": ["message"] }],
+            "patternProperties": { "^ts_": { "type": "string", "format": "date-time" } }
+        }))
+        .unwrap();
+
+        assert!(schema.validate(&json!({"level": "info", "msg": "ok", "ts_start": "2024-03-01T14:02:17Z"})).is_empty());
+        // Each line breaks one keyword a hand-rolled subset would skip
+        assert_eq!(schema.validate(&json!({"level": "loud", "msg": "ok"})).len(), 1);
+        assert_eq!(schema.validate(&json!({"level": "info"})).len(), 1);
+        assert_eq!(schema.validate(&json!({"msg": "ok", "ts_start": "yesterday"})).len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_schema_rejected() {
+        assert!(CompiledSchema::compile(&json!({"type": "text"})).is_err());
+        assert!(CompiledSchema::compile(&json!({"anyOf": "not a list"})).is_err());
+        assert!(CompiledSchema::compile(&json!({"properties": {"a": {"pattern": "("}}})).is_err());
+        assert!(CompiledSchema::compile(&json!("object")).is_err());
+        assert!(CompiledSchema::compile(&json!(true)).is_ok());
+        // References outside the schema aren't fetched
+        assert!(CompiledSchema::compile(&json!({"$ref": "https://example.com/log.json"})).is_err());
+    }
+}
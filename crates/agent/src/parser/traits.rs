@@ -12,3 +12,16 @@ pub trait LogParser: Send + Sync {
     fn parse(&self, raw: &[u8]) -> Result<ParsedLog, ParseError>;    
     fn format(&self) -> LogFormat;
 }
+
+/// Run `parser` on one line, turning a panic into `ParseError::ParserPanic`
+/// so a parser bug costs that line its structure, not the whole stream
+pub fn parse_guarded(parser: &dyn LogParser, raw: &[u8]) -> Result<ParsedLog, ParseError> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| parser.parse(raw))).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(ParseError::ParserPanic(message))
+    })
+}
@@ -0,0 +1,224 @@
+//! Reverse mode: the agent dials the cluster instead of waiting to be
+//! dialed, for networks where the cluster can't reach it (NAT, firewalls).
+//!
+//! The agent sends `DOCKTAIL-REVERSE/1 <agent id>\n` and the cluster answers
+//! `OK\n` or `ERR <reason>\n`. From then on the connection is served like
+//! an accepted one: mTLS with the agent as the TLS server, then the agent's
+//! gRPC services, with the cluster issuing requests over it.
+
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tracing::{info, warn};
+
+use crate::config::ReverseConfig;
+
+/// First word of the hello
+pub const HELLO_PREFIX: &str = "DOCKTAIL-REVERSE/1";
+const MAX_REPLY_LEN: usize = 256;
+
+/// Travels with a dialed connection; once the server drops it (the
+/// connection closed) the agent dials again
+pub type DialGuard = oneshot::Sender<()>;
+
+/// Introduce this agent on `socket` and wait for the cluster to take it
+pub async fn handshake(socket: &mut TcpStream, agent_id: &str) -> io::Result<()> {
+    socket.write_all(format!("{} {}\n", HELLO_PREFIX, agent_id).as_bytes()).await?;
+
+    // A byte at a time: the cluster's TLS handshake follows the reply
+    let mut reply = Vec::new();
+    loop {
+        let byte = socket.read_u8().await?;
+        if byte == b'\n' {
+            break;
+        }
+        if reply.len() == MAX_REPLY_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "cluster reply too long"));
+        }
+        reply.push(byte);
+    }
+    match String::from_utf8_lossy(&reply).trim_end_matches('\r') {
+        "OK" => Ok(()),
+        reply => Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("cluster refused the connection: {}", reply.strip_prefix("ERR ").unwrap_or(reply)),
+        )),
+    }
+}
+
+/// Connections to the cluster, one at a time: the next is dialed once the
+/// previous one's guard is dropped. Ends at once when reverse mode is off.
+pub fn connections(config: ReverseConfig) -> impl Stream<Item = (TcpStream, DialGuard)> {
+    let (tx, rx) = mpsc::channel(1);
+    if config.enabled {
+        tokio::spawn(keep_dialed(config, tx));
+    }
+    ReceiverStream::new(rx)
+}
+
+async fn keep_dialed(config: ReverseConfig, tx: mpsc::Sender<(TcpStream, DialGuard)>) {
+    let initial_backoff = Duration::from_millis(config.initial_backoff_ms);
+    let max_backoff = Duration::from_millis(config.max_backoff_ms);
+    let mut backoff = initial_backoff;
+
+    loop {
+        let dialed = async {
+            let mut socket = TcpStream::connect(&config.cluster_address).await?;
+            handshake(&mut socket, &config.agent_id).await?;
+            Ok::<_, io::Error>(socket)
+        };
+        match dialed.await {
+            Ok(socket) => {
+                info!("Connected to cluster at {} (reverse mode)", config.cluster_address);
+                backoff = initial_backoff;
+                let (guard, closed) = oneshot::channel();
+                if tx.send((socket, guard)).await.is_err() {
+                    return; // Server shut down
+                }
+                let _ = closed.await;
+                info!("Reverse connection to cluster closed, dialing again");
+            }
+            Err(e) => {
+                warn!("Failed to connect to cluster at {}: {} (retrying in {:?})", config.cluster_address, e, backoff);
+            }
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(max_backoff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::proto::log_service_client::LogServiceClient;
+    use crate::service::proto::log_service_server::{LogService, LogServiceServer};
+    use crate::service::proto::{
+        EvictParserCacheRequest, EvictParserCacheResponse, LogRateBucket, LogRateRequest, LogStreamRequest,
+        NormalizedLogEntry, ParserCacheStatsRequest, ParserCacheStatsResponse, UpdateStreamFilterRequest,
+        UpdateStreamFilterResponse,
+    };
+    use hyper_util::rt::TokioIo;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+    use tonic::transport::{Endpoint, Server, Uri};
+    use tonic::{Request, Response, Status};
+
+    /// Answers every log request with one line naming the container
+    struct OneLine;
+
+    #[tonic::async_trait]
+    impl LogService for OneLine {
+        type StreamLogsStream = Pin<Box<dyn Stream<Item = Result<NormalizedLogEntry, Status>> + Send>>;
+        type StreamLogRateStream = Pin<Box<dyn Stream<Item = Result<LogRateBucket, Status>> + Send>>;
+
+        async fn stream_logs(&self, request: Request<LogStreamRequest>) -> Result<Response<Self::StreamLogsStream>, Status> {
+            let entry = NormalizedLogEntry {
+                container_id: request.into_inner().container_id,
+                raw_content: b"hello from behind the NAT".to_vec(),
+                ..Default::default()
+            };
+            Ok(Response::new(Box::pin(tokio_stream::once(Ok(entry)))))
+        }
+
+        async fn update_stream_filter(
+            &self,
+            _request: Request<UpdateStreamFilterRequest>,
+        ) -> Result<Response<UpdateStreamFilterResponse>, Status> {
+            Err(Status::unimplemented("not needed"))
+        }
+
+        async fn stream_log_rate(&self, _request: Request<LogRateRequest>) -> Result<Response<Self::StreamLogRateStream>, Status> {
+            Err(Status::unimplemented("not needed"))
+        }
+
+        async fn parser_cache_stats(
+            &self,
+            _request: Request<ParserCacheStatsRequest>,
+        ) -> Result<Response<ParserCacheStatsResponse>, Status> {
+            Err(Status::unimplemented("not needed"))
+        }
+
+        async fn evict_parser_cache(
+            &self,
+            _request: Request<EvictParserCacheRequest>,
+        ) -> Result<Response<EvictParserCacheResponse>, Status> {
+            Err(Status::unimplemented("not needed"))
+        }
+    }
+
+    /// Cluster-side connector handing out the one connection the agent dialed
+    struct Dialed(Option<TcpStream>);
+
+    impl tonic::codegen::Service<Uri> for Dialed {
+        type Response = TokioIo<TcpStream>;
+        type Error = io::Error;
+        type Future = std::future::Ready<io::Result<TokioIo<TcpStream>>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _uri: Uri) -> Self::Future {
+            std::future::ready(self.0.take().map(TokioIo::new).ok_or_else(|| io::Error::other("connection already used")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cluster_requests_logs_over_agent_dialed_connection() {
+        let cluster = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = ReverseConfig {
+            enabled: true,
+            cluster_address: cluster.local_addr().unwrap().to_string(),
+            agent_id: "edge-1".to_string(),
+            ..ReverseConfig::default()
+        };
+
+        // Agent: serve gRPC on what it dialed (TLS left out here)
+        let mut guards = Vec::new();
+        let incoming = connections(config).map(move |(socket, guard)| {
+            guards.push(guard);
+            Ok::<_, io::Error>(socket)
+        });
+        tokio::spawn(Server::builder().add_service(LogServiceServer::new(OneLine)).serve_with_incoming(incoming));
+
+        // Mock cluster: accept the agent, read its hello, then be the client
+        let (mut socket, _) = cluster.accept().await.unwrap();
+        let hello = format!("{} edge-1\n", HELLO_PREFIX);
+        let mut received = vec![0u8; hello.len()];
+        socket.read_exact(&mut received).await.unwrap();
+        assert_eq!(received, hello.as_bytes());
+        socket.write_all(b"OK\n").await.unwrap();
+
+        let channel = Endpoint::from_static("http://edge-1").connect_with_connector(Dialed(Some(socket))).await.unwrap();
+        let request = LogStreamRequest { container_id: "web".to_string(), ..Default::default() };
+        let mut logs = LogServiceClient::new(channel).stream_logs(request).await.unwrap().into_inner();
+
+        let entry = logs.message().await.unwrap().unwrap();
+        assert_eq!(entry.container_id, "web");
+        assert_eq!(entry.raw_content, b"hello from behind the NAT");
+        assert!(logs.message().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refused_handshake_reports_reason() {
+        let cluster = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = cluster.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = cluster.accept().await.unwrap();
+            let mut hello = [0u8; 32];
+            let _ = socket.read(&mut hello).await.unwrap();
+            socket.write_all(b"ERR agent 'edge-9' is not configured for reverse mode\n").await.unwrap();
+        });
+
+        let mut socket = TcpStream::connect(address).await.unwrap();
+        let err = handshake(&mut socket, "edge-9").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        assert!(err.to_string().contains("not configured for reverse mode"), "{}", err);
+    }
+}
@@ -0,0 +1,273 @@
+//! Priority-aware stream admission.
+//!
+//! As the agent approaches `max_concurrent_streams`, lower-priority streams are
+//! rejected first so that alerting / high-priority subscriptions still get a slot.
+//! Under memory pressure, low-priority streams are not admitted at all and
+//! running ones shed their less severe lines.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tonic::Status;
+
+use crate::config::StreamQosConfig;
+use crate::filter::severity::{Severity, SeverityFloor};
+use super::proto::StreamPriority;
+
+/// What a low-priority stream still sends under memory pressure. Lines
+/// without a level (markers included) can't be judged and are kept.
+pub const PRESSURE_FLOOR: SeverityFloor = SeverityFloor { min: Severity::Warn, drop_unleveled: false };
+
+/// How often the agent's memory use is re-read
+const MEMORY_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Tracks active streams and admits new ones based on their priority.
+#[derive(Debug)]
+pub struct StreamAdmission {
+    active: Arc<AtomicUsize>,
+    max_streams: usize,
+    qos: StreamQosConfig,
+    memory: MemoryPressure,
+}
+
+/// Whether the agent's resident memory is above `stream_qos.memory_pressure_mb`,
+/// re-read at most every `MEMORY_CHECK_INTERVAL` so streams can ask per line
+#[derive(Debug)]
+struct MemoryPressure {
+    /// 0 = never under pressure
+    threshold_bytes: u64,
+    read_rss: fn() -> Option<u64>,
+    started: Instant,
+    /// Millis since `started` of the last read, plus one (0 = never read)
+    checked_ms: AtomicU64,
+    under: AtomicBool,
+}
+
+impl MemoryPressure {
+    fn new(threshold_mb: u64, read_rss: fn() -> Option<u64>) -> Self {
+        Self {
+            threshold_bytes: threshold_mb.saturating_mul(1024 * 1024),
+            read_rss,
+            started: Instant::now(),
+            checked_ms: AtomicU64::new(0),
+            under: AtomicBool::new(false),
+        }
+    }
+
+    fn is_under(&self) -> bool {
+        if self.threshold_bytes == 0 {
+            return false;
+        }
+        let now_ms = self.started.elapsed().as_millis() as u64 + 1;
+        let checked = self.checked_ms.load(Ordering::Acquire);
+        let due = checked == 0 || now_ms - checked >= MEMORY_CHECK_INTERVAL.as_millis() as u64;
+        // One caller re-reads; the rest use the last answer
+        if due && self.checked_ms.compare_exchange(checked, now_ms, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            let rss = (self.read_rss)();
+            let under = rss.is_some_and(|rss| rss >= self.threshold_bytes);
+            if self.under.swap(under, Ordering::AcqRel) != under {
+                if under {
+                    tracing::warn!(rss = rss.unwrap_or_default(), threshold = self.threshold_bytes, "Memory pressure: shedding low-priority streams");
+                } else {
+                    tracing::info!(rss = rss.unwrap_or_default(), "Memory pressure relieved");
+                }
+            }
+        }
+        self.under.load(Ordering::Acquire)
+    }
+}
+
+/// Resident memory of this process, from `/proc/self/status` (Linux only)
+fn process_rss() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+/// RAII slot held for the lifetime of an admitted stream.
+#[derive(Debug)]
+pub struct StreamPermit {
+    active: Arc<AtomicUsize>,
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl StreamAdmission {
+    pub fn new(max_streams: usize, qos: StreamQosConfig) -> Self {
+        Self::with_rss_reader(max_streams, qos, process_rss)
+    }
+
+    fn with_rss_reader(max_streams: usize, qos: StreamQosConfig, read_rss: fn() -> Option<u64>) -> Self {
+        Self {
+            active: Arc::new(AtomicUsize::new(0)),
+            max_streams,
+            memory: MemoryPressure::new(qos.memory_pressure_mb, read_rss),
+            qos,
+        }
+    }
+
+    /// Whether an entry at `level` of a stream with this priority should be
+    /// dropped: low-priority streams keep only `PRESSURE_FLOOR` while the
+    /// agent is under memory pressure
+    pub fn sheds(&self, priority: StreamPriority, level: Option<&str>) -> bool {
+        priority == StreamPriority::Low && self.memory.is_under() && !PRESSURE_FLOOR.allows(level)
+    }
+
+    /// Number of concurrent streams a priority class may fill up to, rounded
+    /// up and never below one, so a small agent still admits every class.
+    /// Unspecified is treated as normal priority.
+    fn limit_for(&self, priority: StreamPriority) -> usize {
+        let pct = match priority {
+            StreamPriority::High => return self.max_streams,
+            StreamPriority::Low => self.qos.low_limit_pct,
+            StreamPriority::Normal | StreamPriority::Unspecified => self.qos.normal_limit_pct,
+        };
+        (self.max_streams * pct as usize).div_ceil(100).max(1)
+    }
+
+    /// Try to admit a new stream of the given priority.
+    pub fn try_admit(&self, priority: StreamPriority) -> Result<StreamPermit, Status> {
+        if priority == StreamPriority::Low && self.memory.is_under() {
+            tracing::warn!("Rejecting low priority stream: agent is under memory pressure");
+            return Err(Status::resource_exhausted(
+                "Agent is under memory pressure; low priority streams are not being admitted",
+            ));
+        }
+        let limit = self.limit_for(priority);
+
+        self.active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                if current < limit { Some(current + 1) } else { None }
+            })
+            .map(|_| StreamPermit { active: Arc::clone(&self.active) })
+            .map_err(|current| {
+                tracing::warn!(
+                    priority = priority.as_str_name(),
+                    active = current,
+                    limit,
+                    max = self.max_streams,
+                    "Rejecting stream: agent is near its stream limit"
+                );
+                Status::resource_exhausted(format!(
+                    "Agent is near its stream limit ({}/{} active); {} priority streams are not being admitted",
+                    current,
+                    self.max_streams,
+                    priority.as_str_name().trim_start_matches("STREAM_PRIORITY_").to_lowercase(),
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn admission(max: usize) -> StreamAdmission {
+        StreamAdmission::new(max, StreamQosConfig::default())
+    }
+
+    #[test]
+    fn test_low_priority_rejected_at_limit_high_admitted() {
+        // Defaults: low may fill 70%, normal 90%, high 100%
+        let admission = admission(10);
+        let mut permits = Vec::new();
+        for _ in 0..7 {
+            permits.push(admission.try_admit(StreamPriority::Low).expect("low admitted below 70%"));
+        }
+
+        let err = admission.try_admit(StreamPriority::Low).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::ResourceExhausted);
+
+        permits.push(admission.try_admit(StreamPriority::Normal).expect("normal admitted"));
+        permits.push(admission.try_admit(StreamPriority::Normal).expect("normal admitted"));
+        assert!(admission.try_admit(StreamPriority::Normal).is_err());
+
+        permits.push(admission.try_admit(StreamPriority::High).expect("high admitted up to max"));
+        assert!(admission.try_admit(StreamPriority::High).is_err(), "hard limit applies to high too");
+        assert_eq!(admission.active.load(Ordering::Acquire), 10);
+    }
+
+    #[test]
+    fn test_single_stream_agent_admits_every_priority() {
+        // 90% and 70% of one stream round up to one, not down to none
+        let single = admission(1);
+        for priority in [StreamPriority::Low, StreamPriority::Normal, StreamPriority::Unspecified, StreamPriority::High] {
+            let permit = single.try_admit(priority).expect("the one slot is free");
+            assert!(single.try_admit(StreamPriority::High).is_err());
+            drop(permit);
+        }
+
+        // A share that doesn't divide evenly rounds up: 70% of 3 is 3, not 2
+        let three = admission(3);
+        let _permits: Vec<_> = (0..3).map(|_| three.try_admit(StreamPriority::Low).unwrap()).collect();
+        assert!(three.try_admit(StreamPriority::Low).is_err());
+    }
+
+    #[test]
+    fn test_permit_drop_releases_slot() {
+        let admission = admission(1);
+        let permit = admission.try_admit(StreamPriority::High).unwrap();
+        assert!(admission.try_admit(StreamPriority::High).is_err());
+
+        drop(permit);
+        assert_eq!(admission.active.load(Ordering::Acquire), 0);
+        assert!(admission.try_admit(StreamPriority::High).is_ok());
+    }
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_memory_pressure_sheds_low_priority() {
+        let qos = StreamQosConfig { memory_pressure_mb: 512, ..StreamQosConfig::default() };
+        let admission = StreamAdmission::with_rss_reader(10, qos, || Some(GB));
+
+        let err = admission.try_admit(StreamPriority::Low).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::ResourceExhausted);
+        let _normal = admission.try_admit(StreamPriority::Normal).expect("normal still admitted");
+
+        // Running low-priority streams keep only warnings and worse
+        assert!(admission.sheds(StreamPriority::Low, Some("info")));
+        assert!(!admission.sheds(StreamPriority::Low, Some("error")));
+        assert!(!admission.sheds(StreamPriority::Low, None));
+        assert!(!admission.sheds(StreamPriority::Normal, Some("debug")));
+    }
+
+    #[test]
+    fn test_no_pressure_below_threshold_or_when_off() {
+        let qos = StreamQosConfig { memory_pressure_mb: 2048, ..StreamQosConfig::default() };
+        let admission = StreamAdmission::with_rss_reader(10, qos, || Some(GB));
+        assert!(admission.try_admit(StreamPriority::Low).is_ok());
+        assert!(!admission.sheds(StreamPriority::Low, Some("debug")));
+
+        // 0 turns the check off
+        let admission = StreamAdmission::with_rss_reader(10, StreamQosConfig::default(), || Some(u64::MAX));
+        assert!(!admission.sheds(StreamPriority::Low, Some("debug")));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_reads_own_rss() {
+        assert!(process_rss().is_some_and(|rss| rss > 0));
+    }
+
+    #[test]
+    fn test_unspecified_treated_as_normal() {
+        let admission = admission(10);
+        let _permits: Vec<_> = (0..9)
+            .map(|_| admission.try_admit(StreamPriority::Unspecified).unwrap())
+            .collect();
+        assert!(admission.try_admit(StreamPriority::Unspecified).is_err());
+        assert!(admission.try_admit(StreamPriority::High).is_ok());
+    }
+}
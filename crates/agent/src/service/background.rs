@@ -1,9 +1,10 @@
 use std::collections::HashSet;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::{self, MissedTickBehavior};
-use tracing::{info, warn, error};
-use crate::state::SharedState;
-use crate::docker::inventory::ContainerInfo;
+use tracing::{debug, info, warn, error};
+use crate::state::{AgentState, SharedState};
+use crate::docker::client::{DockerError, ReconnectPolicy};
+use crate::docker::inventory::{log_size_on_disk, ContainerInfo};
 use dashmap::DashMap;
 
 fn perform_mark_and_sweep(inventory: &DashMap<String, ContainerInfo>, containers: Vec<ContainerInfo>) {
@@ -43,58 +44,153 @@ pub async fn background_inventory_sync(state: SharedState, interval_secs: u64) {
     interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
     
     let mut sync_count: u64 = 0;
-    let mut consecutive_failures: u32 = 0;
+    let mut reconnect = ReconnectPolicy::new(state.config().docker_reconnect.clone());
+    let mut throttle = SyncThrottle::new(Duration::from_secs(interval_secs));
     
     loop {
         interval.tick().await;
+        if !throttle.ready(Instant::now()) {
+            continue;
+        }
         sync_count = sync_count.saturating_add(1);
-        
-        // Wrap the Docker call in a timeout to prevent hangs
-        let list_future = state.docker.list_containers();
-        let timeout_duration = Duration::from_secs(5);
-        
-        match time::timeout(timeout_duration, list_future).await {
-            Ok(Ok(containers)) => {
-                // Success: Docker returned valid data
-                consecutive_failures = 0;
-                
-                perform_mark_and_sweep(&state.inventory, containers);
-                
-                let cache_size = state.inventory.len();
-                
-                // Log periodically (every 30 syncs = ~1 minute at 2s interval)
-                if sync_count % 30 == 0 {
-                    info!("Inventory sync #{}: {} containers in cache", sync_count, cache_size);
+
+        if sync_once(&state, &mut reconnect, &mut throttle, SYNC_TIMEOUT).await && sync_count % 30 == 0 {
+            // Log periodically (every 30 syncs = ~1 minute at 2s interval)
+            info!("Inventory sync #{}: {} containers in cache", sync_count, state.inventory.len());
+        }
+    }
+}
+
+/// The list API carries no log driver, log options, log path or command. Take them from
+/// the cached entry, inspecting only containers not seen yet, then re-measure
+/// the log.
+async fn fill_inspect_details(state: &AgentState, containers: &mut [ContainerInfo]) {
+    for container in containers.iter_mut() {
+        let cached = state.inventory.get(&container.id).and_then(|entry| {
+            entry.log_driver.clone().map(|driver| {
+                (driver, entry.log_options.clone(), entry.log_path.clone(), entry.command.clone())
+            })
+        });
+
+        let (log_driver, log_options, log_path, command) = match cached {
+            Some(known) => known,
+            None => match state.docker.inspect_container(&container.id).await {
+                Ok(info) => (info.log_driver.unwrap_or_default(), info.log_options, info.log_path, info.command),
+                // Leave the rest for a later sync rather than add to the load
+                Err(e @ DockerError::RateLimited { .. }) => {
+                    debug!("Skipping remaining inspects: {}", e);
+                    break;
                 }
-            }
-            Ok(Err(e)) => {
-                // Docker returned an error
-                consecutive_failures = consecutive_failures.saturating_add(1);
-                error!("Docker list_containers failed (attempt {}): {}", consecutive_failures, e);
-                
-                // Keep old cache - stale data is better than no data
-                if consecutive_failures >= 3 {
-                    warn!("Docker API has failed {} times consecutively - check daemon health", 
-                        consecutive_failures);
+                Err(e) => {
+                    debug!("Inspect of {} failed, details unknown: {}", container.id, e);
+                    continue;
                 }
+            },
+        };
+
+        container.log_size_bytes = log_size_on_disk(Some(&log_driver), log_path.as_deref());
+        container.log_driver = Some(log_driver);
+        container.log_options = log_options;
+        container.log_path = log_path;
+        container.command = command;
+    }
+}
+
+/// Per-sync limit on the Docker list call
+const SYNC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest gap between syncs while Docker keeps rate limiting them
+const MAX_THROTTLE_BACKOFF: Duration = Duration::from_secs(120);
+
+/// Spaces out syncs while Docker is rate limiting: each throttled sync at
+/// least doubles the wait (never less than the daemon's retry-after), and a
+/// successful sync restores the normal interval.
+#[derive(Debug)]
+struct SyncThrottle {
+    interval: Duration,
+    backoff: Duration,
+    resume_at: Option<Instant>,
+}
+
+impl SyncThrottle {
+    fn new(interval: Duration) -> Self {
+        Self { interval, backoff: Duration::ZERO, resume_at: None }
+    }
+
+    fn ready(&self, now: Instant) -> bool {
+        self.resume_at.is_none_or(|at| now >= at)
+    }
+
+    /// Record a throttled sync. Returns the wait before the next one.
+    fn throttled(&mut self, retry_after: Duration, now: Instant) -> Duration {
+        self.backoff = (self.backoff * 2)
+            .max(self.interval)
+            .max(retry_after)
+            .min(MAX_THROTTLE_BACKOFF);
+        self.resume_at = Some(now + self.backoff);
+        self.backoff
+    }
+
+    fn clear(&mut self) {
+        self.backoff = Duration::ZERO;
+        self.resume_at = None;
+    }
+}
+
+/// One inventory sync. Failures keep the old cache, feed the reconnect
+/// policy (rebuilding the Docker client when due) and are reported to health.
+/// Rate limits back off instead: the daemon is up, just busy.
+/// Returns `true` on success.
+async fn sync_once(
+    state: &AgentState,
+    reconnect: &mut ReconnectPolicy,
+    throttle: &mut SyncThrottle,
+    timeout_duration: Duration,
+) -> bool {
+    // Wrap the Docker call in a timeout to prevent hangs
+    let list_future = state.docker.list_containers();
+
+    let failure = match time::timeout(timeout_duration, list_future).await {
+        Ok(Ok(mut containers)) => {
+            // Success: Docker returned valid data
+            if reconnect.record_success() {
+                info!("Docker daemon reachable again; inventory sync resumed");
             }
-            Err(_) => {
-                // Timeout: Docker is unresponsive
-                consecutive_failures = consecutive_failures.saturating_add(1);
-                warn!("Docker daemon timeout after {:?} (attempt {})", 
-                    timeout_duration, consecutive_failures);
-                
-                // Keep old cache - stale data is better than no data
-                if consecutive_failures >= 3 {
-                    error!("Docker daemon appears to be hung - {} consecutive timeouts", 
-                        consecutive_failures);
-                }
+            throttle.clear();
+            fill_inspect_details(state, &mut containers).await;
+            perform_mark_and_sweep(&state.inventory, containers);
+            None
+        }
+        Ok(Err(DockerError::RateLimited { retry_after, .. })) => {
+            let wait = throttle.throttled(retry_after, Instant::now());
+            warn!("Docker API is rate limiting inventory sync; next sync in {:?}", wait);
+            return false;
+        }
+        // Docker returned an error
+        Ok(Err(e)) => Some(format!("Docker list_containers failed: {}", e)),
+        // Timeout: Docker is unresponsive
+        Err(_) => Some(format!("Docker daemon timeout after {:?}", timeout_duration)),
+    };
+
+    if let Some(reason) = &failure {
+        // Keep old cache - stale data is better than no data
+        let due = reconnect.record_failure(Instant::now());
+        error!("{} (attempt {})", reason, reconnect.consecutive_failures());
+
+        if due {
+            warn!(
+                "Docker API has failed {} times consecutively - reconnecting to the daemon",
+                reconnect.consecutive_failures()
+            );
+            if let Err(e) = state.docker.reconnect() {
+                error!("Docker reconnect failed: {}", e);
             }
         }
-        
-        // Health reporting: Update global metrics
-        state.metrics.set_docker_failures(consecutive_failures as u64);
     }
+
+    // Health reporting: Update global metrics
+    state.metrics.set_docker_failures(reconnect.consecutive_failures() as u64);
+    failure.is_none()
 }
 
 #[cfg(test)]
@@ -111,10 +207,14 @@ mod tests {
             state: "running".to_string(),
             status: "Up 1 minute".to_string(),
             log_driver: Some("json-file".to_string()),
+            log_options: HashMap::new(),
             labels: HashMap::new(),
             created_at: 1000,
             ports: vec![],
             state_info: None,
+            log_path: None,
+            log_size_bytes: None,
+            command: None,
         }
     }
 
@@ -190,4 +290,177 @@ mod tests {
         assert_eq!(inventory.len(), 1);
         assert!(inventory.contains_key("1"));
     }
+
+    // ── Daemon loss and recovery ──────────────────────────────────
+
+    use crate::config::AgentConfig;
+    use crate::docker::client::DockerClient;
+    use crate::state::AgentState;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixListener;
+    use tokio::task::JoinHandle;
+
+    const CONTAINERS_JSON: &str = r#"[{"Id":"abc123","Names":["/web"],"Image":"nginx:latest","State":"running","Status":"Up 1 minute","Created":1000,"Labels":{}}]"#;
+
+    const RATE_LIMITED_JSON: &str = r#"{"message":"toomanyrequests: retry after 0s"}"#;
+
+    const STARTING_JSON: &str = r#"{"message":"daemon is starting"}"#;
+
+    /// Minimal Docker daemon: answers every request with one running container
+    fn fake_daemon(socket: &Path) -> JoinHandle<()> {
+        throttling_daemon(socket, 0, Arc::default())
+    }
+
+    /// Like `fake_daemon`, but the first `limited` requests get a 429.
+    /// `requests` counts every request answered.
+    fn throttling_daemon(socket: &Path, limited: u32, requests: Arc<AtomicU32>) -> JoinHandle<()> {
+        scripted_daemon(socket, limited, requests, Arc::new(AtomicBool::new(true)))
+    }
+
+    /// Like `fake_daemon`, but answers 503 while `ready` is false (the
+    /// socket is there, the daemon behind it isn't serving yet)
+    fn restarting_daemon(socket: &Path, ready: Arc<AtomicBool>) -> JoinHandle<()> {
+        scripted_daemon(socket, 0, Arc::default(), ready)
+    }
+
+    fn scripted_daemon(socket: &Path, limited: u32, requests: Arc<AtomicU32>, ready: Arc<AtomicBool>) -> JoinHandle<()> {
+        let _ = std::fs::remove_file(socket);
+        let listener = UnixListener::bind(socket).unwrap();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut conn, _)) = listener.accept().await else { return };
+                let requests = Arc::clone(&requests);
+                let ready = Arc::clone(&ready);
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 8192];
+                    while let Ok(n) = conn.read(&mut buf).await {
+                        if n == 0 {
+                            return;
+                        }
+                        let (status, body) = if !ready.load(Ordering::SeqCst) {
+                            ("503 Service Unavailable", STARTING_JSON)
+                        } else if requests.fetch_add(1, Ordering::SeqCst) < limited {
+                            ("429 Too Many Requests", RATE_LIMITED_JSON)
+                        } else {
+                            ("200 OK", CONTAINERS_JSON)
+                        };
+                        let response = format!(
+                            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                            status,
+                            body.len(),
+                            body
+                        );
+                        if conn.write_all(response.as_bytes()).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        })
+    }
+
+    fn socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("docktail-{}-{}.sock", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_sync_recovers_after_daemon_restart() {
+        let socket = socket_path("reconnect");
+        let mut config = AgentConfig::default();
+        config.docker_reconnect.initial_backoff_ms = 0;
+        let daemon = fake_daemon(&socket);
+        let docker = DockerClient::new(socket.to_str().unwrap()).unwrap();
+        let state = AgentState::new(docker, config.clone());
+        let mut policy = ReconnectPolicy::new(config.docker_reconnect.clone());
+        let mut throttle = SyncThrottle::new(Duration::from_secs(2));
+        let timeout = Duration::from_secs(2);
+
+        assert!(sync_once(&state, &mut policy, &mut throttle, timeout).await);
+        assert!(state.inventory.contains_key("abc123"));
+
+        // Daemon goes away: syncs fail, the stale inventory is kept, and
+        // reconnecting can't succeed while the socket is gone
+        daemon.abort();
+        let _ = daemon.await;
+        std::fs::remove_file(&socket).unwrap();
+        for _ in 0..3 {
+            assert!(!sync_once(&state, &mut policy, &mut throttle, timeout).await);
+        }
+        assert_eq!(state.docker.reconnects(), 0);
+        assert!(state.inventory.contains_key("abc123"));
+
+        // Restarted daemon is up but not serving yet: every further failure
+        // rebuilds the client (no backoff configured)
+        let ready = Arc::new(AtomicBool::new(false));
+        let daemon = restarting_daemon(&socket, Arc::clone(&ready));
+        for reconnects in 1..=2 {
+            assert!(!sync_once(&state, &mut policy, &mut throttle, timeout).await);
+            assert_eq!(state.docker.reconnects(), reconnects);
+        }
+        assert_eq!(state.metrics.snapshot().docker_consecutive_failures, 5);
+
+        // Daemon serving again: the next sync succeeds on the rebuilt client
+        state.inventory.clear();
+        ready.store(true, Ordering::SeqCst);
+        assert!(sync_once(&state, &mut policy, &mut throttle, timeout).await);
+        assert_eq!(state.docker.reconnects(), 2);
+        assert_eq!(state.metrics.snapshot().docker_consecutive_failures, 0);
+        assert!(state.inventory.contains_key("abc123"));
+
+        daemon.abort();
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_sync_backs_off() {
+        let socket = socket_path("ratelimit");
+        let requests = Arc::new(AtomicU32::new(0));
+        let daemon = throttling_daemon(&socket, 3, Arc::clone(&requests));
+        let config = AgentConfig::default();
+        let docker = DockerClient::new(socket.to_str().unwrap()).unwrap();
+        let state = AgentState::new(docker, config.clone());
+        let mut policy = ReconnectPolicy::new(config.docker_reconnect.clone());
+        let interval = Duration::from_secs(2);
+        let mut throttle = SyncThrottle::new(interval);
+        let timeout = Duration::from_secs(2);
+
+        // A 429 is its own error, not a generic Docker failure
+        match state.docker.list_containers().await {
+            Err(DockerError::RateLimited { retry_after, .. }) => assert_eq!(retry_after, Duration::ZERO),
+            other => panic!("expected RateLimited, got {:?}", other.map(|c| c.len())),
+        }
+
+        // Each throttled sync waits longer; none of them counts toward a reconnect
+        assert!(!sync_once(&state, &mut policy, &mut throttle, timeout).await);
+        assert_eq!(throttle.backoff, interval);
+        assert!(!throttle.ready(Instant::now()));
+        assert!(!sync_once(&state, &mut policy, &mut throttle, timeout).await);
+        assert_eq!(throttle.backoff, interval * 2);
+        assert_eq!(policy.consecutive_failures(), 0);
+        assert_eq!(state.metrics.snapshot().docker_consecutive_failures, 0);
+
+        // Once the daemon answers again the normal interval is restored
+        assert!(sync_once(&state, &mut policy, &mut throttle, timeout).await);
+        assert!(throttle.ready(Instant::now()));
+        assert!(state.inventory.contains_key("abc123"));
+
+        daemon.abort();
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn test_sync_throttle_honours_retry_after_and_caps() {
+        let mut throttle = SyncThrottle::new(Duration::from_secs(2));
+        let now = Instant::now();
+        assert_eq!(throttle.throttled(Duration::from_secs(30), now), Duration::from_secs(30));
+        assert!(!throttle.ready(now + Duration::from_secs(29)));
+        assert!(throttle.ready(now + Duration::from_secs(30)));
+        for _ in 0..10 {
+            throttle.throttled(Duration::ZERO, now);
+        }
+        assert_eq!(throttle.backoff, MAX_THROTTLE_BACKOFF);
+    }
 }
@@ -0,0 +1,111 @@
+use super::proto::NormalizedLogEntry;
+use tokio_stream::{Stream, StreamExt};
+use tonic::Status;
+
+/// Earliest retained log timestamp when the log no longer reaches back to
+/// `since_nanos`, i.e. older lines have rotated away.
+///
+/// Only a container that already existed at `since_nanos` can have lost
+/// lines from then; one created later simply has nothing older. A container
+/// that printed nothing between its creation and `since_nanos` looks the
+/// same as a rotated one, which is why this is a marker and not an error.
+pub fn backlog_gap(since_nanos: i64, created_nanos: i64, earliest_retained: Option<i64>) -> Option<i64> {
+    let earliest = earliest_retained?;
+    (created_nanos < since_nanos && earliest > since_nanos).then_some(earliest)
+}
+
+/// A synthetic entry saying the stream starts later than requested. Its
+/// timestamp is that of the oldest line still retained.
+pub fn backlog_marker(container_id: &str, earliest_retained: i64) -> NormalizedLogEntry {
+    NormalizedLogEntry {
+        container_id: container_id.to_string(),
+        timestamp_nanos: earliest_retained,
+        backlog_unavailable: true,
+        ..Default::default()
+    }
+}
+
+/// Drop entries from before `since_nanos`. Docker's `since` has whole-second
+/// precision, so the first second can include earlier lines.
+pub fn drop_before<S>(inner: S, since_nanos: i64) -> impl Stream<Item = Result<NormalizedLogEntry, Status>>
+where
+    S: Stream<Item = Result<NormalizedLogEntry, Status>>,
+{
+    inner.filter(move |item| item.as_ref().map_or(true, |entry| entry.timestamp_nanos >= since_nanos))
+}
+
+/// Send `marker` (if any) before everything else
+pub fn with_backlog_marker<S>(
+    marker: Option<NormalizedLogEntry>,
+    inner: S,
+) -> impl Stream<Item = Result<NormalizedLogEntry, Status>>
+where
+    S: Stream<Item = Result<NormalizedLogEntry, Status>>,
+{
+    tokio_stream::iter(marker.map(Ok)).chain(inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+
+    const SECOND: i64 = 1_000_000_000;
+    /// 2024-03-01 14:02:17.250 UTC, the incident start
+    const SINCE: i64 = 1_709_301_737 * SECOND + 250_000_000;
+
+    fn line(timestamp_nanos: i64, content: &[u8]) -> Result<NormalizedLogEntry, Status> {
+        Ok(NormalizedLogEntry {
+            container_id: "api".to_string(),
+            timestamp_nanos,
+            raw_content: content.to_vec(),
+            line_count: 1,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_backlog_gap() {
+        let created = SINCE - 3600 * SECOND;
+        // Oldest retained line is after the requested start: rotated away
+        assert_eq!(backlog_gap(SINCE, created, Some(SINCE + 90 * SECOND)), Some(SINCE + 90 * SECOND));
+        // The log still reaches back far enough
+        assert_eq!(backlog_gap(SINCE, created, Some(SINCE - SECOND)), None);
+        // Created after the requested start: nothing older ever existed
+        assert_eq!(backlog_gap(SINCE, SINCE + SECOND, Some(SINCE + 2 * SECOND)), None);
+        // Empty log
+        assert_eq!(backlog_gap(SINCE, created, None), None);
+    }
+
+    #[tokio::test]
+    async fn test_since_older_than_retention_marks_then_tails() {
+        let created = SINCE - 86_400 * SECOND;
+        let earliest = SINCE + 40 * SECOND;
+        let marker = backlog_gap(SINCE, created, Some(earliest)).map(|ts| backlog_marker("api", ts));
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut stream = Box::pin(with_backlog_marker(
+            marker,
+            drop_before(UnboundedReceiverStream::new(rx), SINCE),
+        ));
+
+        let first = stream.next().await.unwrap().unwrap();
+        assert!(first.backlog_unavailable);
+        assert_eq!(first.timestamp_nanos, earliest);
+        assert!(first.raw_content.is_empty());
+
+        // Live tail follows; a line from earlier in the same second is dropped
+        tx.send(line(SINCE - 100_000_000, b"before the incident")).unwrap();
+        tx.send(line(earliest, b"oldest retained")).unwrap();
+        tx.send(line(earliest + SECOND, b"live")).unwrap();
+        for expected in [&b"oldest retained"[..], b"live"] {
+            let entry = stream.next().await.unwrap().unwrap();
+            assert!(!entry.backlog_unavailable);
+            assert_eq!(entry.raw_content, expected);
+        }
+
+        drop(tx);
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -0,0 +1,82 @@
+//! Color groups for lines that share a structured field value.
+//!
+//! Hashing the value (FNV-1a, like `content_hash`) makes the group stable
+//! across containers and agents, so a UI comparing several containers can
+//! color every line of one `trace_id` alike.
+
+use super::content_hash::Fnv1a;
+use super::proto::{NormalizedLogEntry, ParsedLog};
+
+/// Groups a field value can fall in, numbered from 1
+pub const COLOR_GROUPS: u32 = 16;
+
+/// Group of lines without the field (or unparsed lines)
+pub const NO_FIELD_GROUP: u32 = 0;
+
+/// The entry's color group for `field`: 1..=COLOR_GROUPS by the hashed
+/// value, or `NO_FIELD_GROUP` when the line doesn't have it
+pub fn color_group(entry: &NormalizedLogEntry, field: &str) -> u32 {
+    let Some(value) = entry.parsed.as_ref().and_then(|parsed| field_value(parsed, field)) else {
+        return NO_FIELD_GROUP;
+    };
+    let mut hasher = Fnv1a::new();
+    hasher.write(value.as_bytes());
+    (hasher.finish() % COLOR_GROUPS as u64) as u32 + 1
+}
+
+/// A parsed field by key; the parser lifts some keys out of `fields`
+fn field_value<'a>(parsed: &'a ParsedLog, field: &str) -> Option<&'a str> {
+    let lifted = match field {
+        "level" => parsed.level.as_deref(),
+        "message" | "msg" => parsed.message.as_deref(),
+        "logger" => parsed.logger.as_deref(),
+        "request_id" => parsed.request.as_ref().and_then(|r| r.request_id.as_deref()),
+        _ => None,
+    };
+    lifted
+        .or_else(|| parsed.fields.iter().find(|kv| kv.key == field).map(|kv| kv.value.as_str()))
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::proto::KeyValuePair;
+
+    fn entry(container_id: &str, fields: &[(&str, &str)]) -> NormalizedLogEntry {
+        NormalizedLogEntry {
+            container_id: container_id.to_string(),
+            parsed: Some(ParsedLog {
+                fields: fields
+                    .iter()
+                    .map(|(key, value)| KeyValuePair { key: key.to_string(), value: value.to_string(), typed: None })
+                    .collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_same_value_same_group_across_containers() {
+        let api = entry("api", &[("trace_id", "4bf92f35"), ("user", "ann")]);
+        let db = entry("db", &[("trace_id", "4bf92f35")]);
+
+        let group = color_group(&api, "trace_id");
+        assert!((1..=COLOR_GROUPS).contains(&group));
+        assert_eq!(color_group(&db, "trace_id"), group);
+
+        // Distinct values spread over the groups
+        let groups: std::collections::HashSet<u32> = (0..100)
+            .map(|i| color_group(&entry("api", &[("trace_id", &format!("trace-{}", i))]), "trace_id"))
+            .collect();
+        assert!(groups.len() > 1);
+    }
+
+    #[test]
+    fn test_missing_field_is_default_group() {
+        assert_eq!(color_group(&entry("api", &[("user", "ann")]), "trace_id"), NO_FIELD_GROUP);
+        assert_eq!(color_group(&entry("api", &[("trace_id", "")]), "trace_id"), NO_FIELD_GROUP);
+        assert_eq!(color_group(&NormalizedLogEntry::default(), "trace_id"), NO_FIELD_GROUP);
+    }
+}
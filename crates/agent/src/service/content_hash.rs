@@ -0,0 +1,112 @@
+//! Cheap, non-cryptographic content hashing for log entries.
+//!
+//! Clients use the hash to dedup, cache, or spot repeated lines without
+//! comparing full payloads. FNV-1a is used because it's tiny, fast on short
+//! inputs, and stable across agent versions and platforms.
+
+use super::proto::NormalizedLogEntry;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Incremental 64-bit FNV-1a hasher
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hash of an entry's normalized content: the primary line and any grouped
+/// continuation lines, each with trailing whitespace (`\r\n`, spaces) trimmed.
+///
+/// Timestamps, sequence numbers and parse results are deliberately excluded so
+/// that the same message logged twice hashes the same.
+pub fn content_hash(entry: &NormalizedLogEntry) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(entry.raw_content.trim_ascii_end());
+    for line in &entry.grouped_lines {
+        hasher.write(b"\n");
+        hasher.write(line.content.trim_ascii_end());
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::proto::LogLine;
+
+    fn entry(content: &[u8], sequence: u64) -> NormalizedLogEntry {
+        NormalizedLogEntry {
+            container_id: "test".to_string(),
+            timestamp_nanos: sequence as i64 * 1_000,
+            sequence,
+            raw_content: content.to_vec(),
+            line_count: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identical_content_same_hash() {
+        // Different sequence/timestamp, same message
+        let a = entry(b"connection refused, retrying", 1);
+        let b = entry(b"connection refused, retrying", 2);
+        assert_eq!(content_hash(&a), content_hash(&b));
+    }
+
+    #[test]
+    fn test_different_content_different_hash() {
+        let a = entry(b"connection refused, retrying", 1);
+        let b = entry(b"connection refused, retrying!", 1);
+        assert_ne!(content_hash(&a), content_hash(&b));
+    }
+
+    #[test]
+    fn test_trailing_newline_normalized() {
+        assert_eq!(
+            content_hash(&entry(b"hello\r\n", 1)),
+            content_hash(&entry(b"hello", 2)),
+        );
+    }
+
+    #[test]
+    fn test_grouped_lines_included() {
+        let single = entry(b"ERROR boom", 1);
+        let mut grouped = entry(b"ERROR boom", 1);
+        grouped.grouped_lines.push(LogLine {
+            content: b"    at main".to_vec(),
+            timestamp_nanos: 0,
+            sequence: 2,
+        });
+        assert_ne!(content_hash(&single), content_hash(&grouped));
+    }
+
+    #[test]
+    fn test_known_fnv1a_vector() {
+        // Reference value for FNV-1a 64 of "a"
+        let mut h = Fnv1a::new();
+        h.write(b"a");
+        assert_eq!(h.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+}
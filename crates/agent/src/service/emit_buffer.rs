@@ -0,0 +1,103 @@
+use super::proto::NormalizedLogEntry;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::Status;
+
+/// Run a log entry stream in its own task, up to `capacity` entries ahead of
+/// the client. Reading and parsing go on while sending waits, until the
+/// buffer is full. The task ends with the stream, or once the client is gone.
+///
+/// `capacity` must be > 0.
+pub fn with_emit_buffer<S>(
+    mut inner: S,
+    capacity: usize,
+) -> impl Stream<Item = Result<NormalizedLogEntry, Status>>
+where
+    S: Stream<Item = Result<NormalizedLogEntry, Status>> + Unpin + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(capacity);
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                item = inner.next() => match item {
+                    Some(item) => {
+                        if tx.send(item).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                },
+                // A quiet followed container mustn't keep the stream alive
+                _ = tx.closed() => break,
+            }
+        }
+    });
+    ReceiverStream::new(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const CAPACITY: usize = 4;
+
+    fn line(sequence: u64) -> Result<NormalizedLogEntry, Status> {
+        Ok(NormalizedLogEntry {
+            container_id: "busy".to_string(),
+            sequence,
+            raw_content: b"tick".to_vec(),
+            line_count: 1,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_reads_ahead_up_to_capacity() {
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let source = {
+            let pulled = Arc::clone(&pulled);
+            tokio_stream::iter(0..100u64).map(move |seq| {
+                pulled.fetch_add(1, Ordering::SeqCst);
+                line(seq)
+            })
+        };
+        let mut stream = Box::pin(with_emit_buffer(source, CAPACITY));
+
+        // The client hasn't read yet: the buffer fills, plus the entry
+        // waiting for room
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pulled.load(Ordering::SeqCst), CAPACITY + 1);
+
+        // Each entry taken makes room for one more
+        assert_eq!(stream.next().await.unwrap().unwrap().sequence, 0);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pulled.load(Ordering::SeqCst), CAPACITY + 2);
+
+        let rest: Vec<u64> = stream.map(|item| item.unwrap().sequence).collect().await;
+        assert_eq!(rest, (1..100).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_gone_client_releases_quiet_stream() {
+        // Stands in for what the pipeline holds (Docker stream, admission permit)
+        let held = Arc::new(());
+        let source = {
+            let held = Arc::clone(&held);
+            Box::pin(async_stream::stream! {
+                let _held = held;
+                yield line(0);
+                std::future::pending::<()>().await;
+            })
+        };
+        let mut stream = Box::pin(with_emit_buffer(source, CAPACITY));
+        assert_eq!(stream.next().await.unwrap().unwrap().sequence, 0);
+
+        drop(stream);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(Arc::strong_count(&held), 1);
+    }
+}
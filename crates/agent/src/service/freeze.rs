@@ -0,0 +1,414 @@
+//! Pause-and-snapshot debugging ("freeze inspect").
+//!
+//! The container is paused so the process list, memory usage, open files and
+//! recent logs are captured from one consistent point in time, then unpaused.
+//! A [`PauseGuard`] makes sure the unpause happens even when the capture fails
+//! or the request is cancelled halfway through.
+
+use std::borrow::Cow;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bollard::models::ContainerTopResponse;
+use tonic::Status;
+use tracing::{error, warn};
+
+use crate::docker::client::DockerError;
+use crate::filter::redact::Redactor;
+use crate::state::{AgentState, SharedState};
+use super::proto::{FreezeInspectResponse, ProcessInfo};
+use super::stats::StatsServiceImpl;
+
+/// Log lines captured when the request doesn't say
+pub const DEFAULT_LOG_TAIL: u32 = 100;
+
+/// Upper bound on captured log lines, to keep the pause short
+pub const MAX_LOG_TAIL: u32 = 1000;
+
+/// Something that can pause and unpause containers
+#[tonic::async_trait]
+pub trait Freezable: Send + Sync + 'static {
+    async fn pause(&self, container_id: &str) -> Result<(), DockerError>;
+    async fn unpause(&self, container_id: &str) -> Result<(), DockerError>;
+}
+
+#[tonic::async_trait]
+impl Freezable for AgentState {
+    async fn pause(&self, container_id: &str) -> Result<(), DockerError> {
+        self.docker.pause_container(container_id).await
+    }
+
+    async fn unpause(&self, container_id: &str) -> Result<(), DockerError> {
+        self.docker.unpause_container(container_id).await
+    }
+}
+
+/// Keeps a container paused for as long as it is held.
+///
+/// Call [`release`](Self::release) to unpause and observe the result. If the
+/// guard is dropped instead (an early return or a cancelled request), the
+/// unpause is spawned onto the runtime so the container is never left frozen.
+pub struct PauseGuard<T: Freezable> {
+    target: Arc<T>,
+    container_id: String,
+    paused_at: Instant,
+    armed: bool,
+}
+
+impl<T: Freezable> PauseGuard<T> {
+    pub async fn pause(target: Arc<T>, container_id: &str) -> Result<Self, DockerError> {
+        target.pause(container_id).await?;
+        Ok(Self {
+            target,
+            container_id: container_id.to_string(),
+            paused_at: Instant::now(),
+            armed: true,
+        })
+    }
+
+    /// Unpause now. Returns how long the container was held paused.
+    pub async fn release(mut self) -> Result<Duration, DockerError> {
+        self.armed = false;
+        let held = self.paused_at.elapsed();
+        self.target.unpause(&self.container_id).await?;
+        Ok(held)
+    }
+}
+
+impl<T: Freezable> Drop for PauseGuard<T> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+
+        let target = Arc::clone(&self.target);
+        let container_id = std::mem::take(&mut self.container_id);
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(e) = target.unpause(&container_id).await {
+                        error!("Failed to unpause container {} after freeze inspect: {}", container_id, e);
+                    }
+                });
+            }
+            Err(_) => {
+                error!("No runtime to unpause container {}; it is still paused", container_id);
+            }
+        }
+    }
+}
+
+/// Run `capture` while the container is paused.
+///
+/// The container is unpaused before the capture result is inspected, so a
+/// failed capture still leaves it running. A capture still running after
+/// `timeout` is dropped and the container unpaused. Returns the capture
+/// result and how long the container was paused.
+pub async fn with_paused<T, F, Fut, R>(
+    target: Arc<T>,
+    container_id: &str,
+    timeout: Duration,
+    capture: F,
+) -> Result<(R, Duration), Status>
+where
+    T: Freezable,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<R, DockerError>>,
+{
+    let guard = PauseGuard::pause(target, container_id)
+        .await
+        .map_err(|e| docker_status("pause", container_id, e))?;
+
+    let captured = tokio::time::timeout(timeout, capture()).await;
+
+    let held = guard
+        .release()
+        .await
+        .map_err(|e| docker_status("unpause", container_id, e))?;
+    let value = captured
+        .map_err(|_| Status::deadline_exceeded(format!(
+            "Capturing container {} took longer than {:?}; it was unpaused",
+            container_id, timeout
+        )))?
+        .map_err(|e| docker_status("capture", container_id, e))?;
+
+    Ok((value, held))
+}
+
+/// Pause the container, capture processes, memory, open files and recent
+/// logs, and unpause it again.
+pub async fn freeze_inspect(
+    state: SharedState,
+    container_id: &str,
+    log_tail: u32,
+) -> Result<FreezeInspectResponse, Status> {
+    let paused_at = chrono::Utc::now().timestamp();
+    let timeout = Duration::from_millis(state.config().freeze_capture_timeout_ms);
+    let (mut snapshot, held) = with_paused(Arc::clone(&state), container_id, timeout, || {
+        capture(&state, container_id, log_tail)
+    })
+    .await?;
+
+    if held > Duration::from_secs(2) {
+        warn!("Container {} was paused for {:?} during freeze inspect", container_id, held);
+    }
+
+    snapshot.paused_at = paused_at;
+    snapshot.paused_ms = held.as_millis() as u64;
+    Ok(snapshot)
+}
+
+async fn capture(
+    state: &AgentState,
+    container_id: &str,
+    log_tail: u32,
+) -> Result<FreezeInspectResponse, DockerError> {
+    let top = state.docker.top_processes(container_id).await?;
+    let processes = processes_from_top(&top);
+
+    // One-shot: a regular sample waits a second for the CPU delta
+    let memory_stats = state.docker.stats_snapshot(container_id).await?
+        .and_then(|snapshot| StatsServiceImpl::convert_stats(container_id, snapshot).memory_stats);
+
+    let open_files = count_open_files(Path::new("/proc"), processes.iter().map(|p| p.pid));
+    let mut recent_logs = state.docker.recent_logs(container_id, log_tail).await?;
+    let redaction = state.config().redaction.clone();
+    if redaction.enabled {
+        let inspect = state.docker.inspect_container_raw(container_id).await?;
+        let env = inspect.config.and_then(|c| c.env).unwrap_or_default();
+        if let Some(redactor) = Redactor::for_container(&redaction, &env) {
+            for line in recent_logs.iter_mut() {
+                if let Cow::Owned(masked) = redactor.redact(line.as_bytes()) {
+                    *line = String::from_utf8_lossy(&masked).into_owned();
+                }
+            }
+        }
+    }
+
+    Ok(FreezeInspectResponse {
+        container_id: container_id.to_string(),
+        processes,
+        memory_stats,
+        open_files,
+        recent_logs,
+        paused_at: 0,
+        paused_ms: 0,
+    })
+}
+
+/// Map `docker top` (`ps -ef`) rows to processes, locating columns by title
+fn processes_from_top(top: &ContainerTopResponse) -> Vec<ProcessInfo> {
+    let titles = top.titles.as_deref().unwrap_or_default();
+    let column = |names: &[&str]| titles.iter().position(|t| names.contains(&t.as_str()));
+    let (Some(pid_col), user_col, cmd_col) = (
+        column(&["PID"]),
+        column(&["UID", "USER"]),
+        column(&["CMD", "COMMAND"]),
+    ) else {
+        return Vec::new();
+    };
+
+    top.processes
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|row| {
+            let pid = row.get(pid_col)?.parse().ok()?;
+            let field = |col: Option<usize>| col.and_then(|c| row.get(c)).cloned().unwrap_or_default();
+            Some(ProcessInfo {
+                pid,
+                user: field(user_col),
+                command: field(cmd_col),
+            })
+        })
+        .collect()
+}
+
+/// Count entries under `<proc_root>/<pid>/fd` across the given processes.
+/// Returns `None` if none of them could be read (e.g. the agent doesn't share
+/// the host's PID namespace).
+fn count_open_files(proc_root: &Path, pids: impl Iterator<Item = u32>) -> Option<u64> {
+    let mut total = None;
+    for pid in pids {
+        if let Ok(entries) = std::fs::read_dir(proc_root.join(pid.to_string()).join("fd")) {
+            *total.get_or_insert(0) += entries.count() as u64;
+        }
+    }
+    total
+}
+
+fn docker_status(action: &str, container_id: &str, e: DockerError) -> Status {
+    use bollard::errors::Error::DockerResponseServerError;
+
+    match e {
+        DockerError::BollardError(DockerResponseServerError { status_code: 404, message }) => {
+            Status::not_found(message)
+        }
+        // Not running, already paused, ...
+        DockerError::BollardError(DockerResponseServerError { status_code: 409, message }) => {
+            Status::failed_precondition(message)
+        }
+        DockerError::RateLimited { message, retry_after } => super::rate_limited_status(&message, retry_after),
+        e => Status::internal(format!("Failed to {} container {}: {}", action, container_id, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    const CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[derive(Default)]
+    struct FakeContainer {
+        paused: AtomicBool,
+        unpauses: AtomicU32,
+        fail_pause: bool,
+    }
+
+    impl FakeContainer {
+        fn is_paused(&self) -> bool {
+            self.paused.load(Ordering::SeqCst)
+        }
+    }
+
+    #[tonic::async_trait]
+    impl Freezable for FakeContainer {
+        async fn pause(&self, _container_id: &str) -> Result<(), DockerError> {
+            if self.fail_pause {
+                return Err(DockerError::PermissionDenied);
+            }
+            self.paused.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn unpause(&self, _container_id: &str) -> Result<(), DockerError> {
+            self.unpauses.fetch_add(1, Ordering::SeqCst);
+            self.paused.store(false, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paused_during_capture_and_unpaused_after() {
+        let container = Arc::new(FakeContainer::default());
+
+        let (paused_during, _) = with_paused(Arc::clone(&container), "web", CAPTURE_TIMEOUT, || async {
+            Ok(container.is_paused())
+        })
+        .await
+        .unwrap();
+
+        assert!(paused_during);
+        assert!(!container.is_paused());
+        assert_eq!(container.unpauses.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unpaused_when_capture_fails() {
+        let container = Arc::new(FakeContainer::default());
+
+        let result = with_paused(Arc::clone(&container), "web", CAPTURE_TIMEOUT, || async {
+            assert!(container.is_paused());
+            Err::<(), _>(DockerError::StreamClosed)
+        })
+        .await;
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::Internal);
+        assert!(!container.is_paused());
+        assert_eq!(container.unpauses.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unpaused_when_request_cancelled() {
+        let container = Arc::new(FakeContainer::default());
+
+        // Capture never finishes; the caller gives up mid-snapshot
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(10),
+            with_paused(Arc::clone(&container), "web", CAPTURE_TIMEOUT, || {
+                std::future::pending::<Result<(), DockerError>>()
+            }),
+        )
+        .await;
+        assert!(cancelled.is_err());
+
+        // The guard's drop spawned the unpause
+        for _ in 0..100 {
+            if !container.is_paused() {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert!(!container.is_paused());
+        assert_eq!(container.unpauses.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unpaused_when_capture_times_out() {
+        let container = Arc::new(FakeContainer::default());
+
+        // A Docker call that never answers: the pause ends at the timeout
+        let result = with_paused(Arc::clone(&container), "web", Duration::from_millis(10), || {
+            std::future::pending::<Result<(), DockerError>>()
+        })
+        .await;
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::DeadlineExceeded);
+        assert!(!container.is_paused());
+        assert_eq!(container.unpauses.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pause_failure_skips_capture() {
+        let container = Arc::new(FakeContainer { fail_pause: true, ..Default::default() });
+
+        let mut captured = false;
+        let result = with_paused(Arc::clone(&container), "web", CAPTURE_TIMEOUT, || {
+            captured = true;
+            async { Ok(()) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert!(!captured);
+        assert_eq!(container.unpauses.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_processes_from_top() {
+        let top = ContainerTopResponse {
+            titles: Some(["UID", "PID", "PPID", "C", "STIME", "TTY", "TIME", "CMD"].map(String::from).to_vec()),
+            processes: Some(vec![
+                ["root", "4211", "4190", "0", "10:02", "?", "00:00:01", "nginx: master process"].map(String::from).to_vec(),
+                ["101", "4260", "4211", "0", "10:02", "?", "00:00:00", "nginx: worker process"].map(String::from).to_vec(),
+            ]),
+        };
+
+        let processes = processes_from_top(&top);
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[0].pid, 4211);
+        assert_eq!(processes[0].user, "root");
+        assert_eq!(processes[1].command, "nginx: worker process");
+    }
+
+    #[test]
+    fn test_count_open_files() {
+        let root = std::env::temp_dir().join(format!("docktail-freeze-{}", std::process::id()));
+        for (pid, fds) in [(10, 3), (11, 2)] {
+            let dir = root.join(pid.to_string()).join("fd");
+            std::fs::create_dir_all(&dir).unwrap();
+            for fd in 0..fds {
+                std::fs::write(dir.join(fd.to_string()), b"").unwrap();
+            }
+        }
+
+        assert_eq!(count_open_files(&root, [10, 11, 99].into_iter()), Some(5));
+        assert_eq!(count_open_files(&root, [99].into_iter()), None);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio_stream::Stream;
@@ -8,6 +9,7 @@ use super::proto::{
     HealthCheckRequest, HealthCheckResponse,
     HealthStatus,
 };
+use crate::parser::cache::{CacheStats, ParserCache};
 use crate::parser::metrics::{ParsingMetrics, MetricsSnapshot};
 
 /// Implementation of the HealthService gRPC service
@@ -15,15 +17,20 @@ use crate::parser::metrics::{ParsingMetrics, MetricsSnapshot};
 pub struct HealthServiceImpl {
     /// Reference to the global parsing metrics for health determination
     metrics: Arc<ParsingMetrics>,
+    /// Detected formats per container, reported as a distribution
+    parser_cache: Arc<ParserCache>,
+    /// Consecutive Docker failures before the agent reports Degraded
+    /// (`docker_reconnect.failure_threshold`, when a reconnect is first due)
+    docker_failure_threshold: u32,
 }
 
 impl HealthServiceImpl {
-    pub fn new(metrics: Arc<ParsingMetrics>) -> Self {
-        Self { metrics }
+    pub fn new(metrics: Arc<ParsingMetrics>, parser_cache: Arc<ParserCache>, docker_failure_threshold: u32) -> Self {
+        Self { metrics, parser_cache, docker_failure_threshold }
     }
 
     /// Static health evaluation logic to ensure consistency between check() and watch()
-    fn evaluate_health(snapshot: &MetricsSnapshot) -> (HealthStatus, String) {
+    fn evaluate_health(snapshot: &MetricsSnapshot, docker_failure_threshold: u32) -> (HealthStatus, String) {
         // Critical Failure: Parser panics indicate serious bugs (catch_unwind triggered)
         if snapshot.parse_panics > 0 {
             return (
@@ -32,11 +39,12 @@ impl HealthServiceImpl {
             );
         }
 
-        // Critical Failure: Docker connectivity lost
-        if snapshot.docker_consecutive_failures >= 3 {
-             return (
-                HealthStatus::Unhealthy,
-                format!("Critical: Docker daemon unreachable ({} consecutive failures)", snapshot.docker_consecutive_failures)
+        // Degraded State: Docker connectivity lost; the inventory sync keeps
+        // reconnecting and clears this once the daemon answers again
+        if snapshot.docker_consecutive_failures >= u64::from(docker_failure_threshold) {
+            return (
+                HealthStatus::Degraded,
+                format!("Degraded: Docker daemon unreachable ({} consecutive failures), reconnecting", snapshot.docker_consecutive_failures)
             );
         }
 
@@ -59,6 +67,37 @@ impl HealthServiceImpl {
     }
 }
 
+impl HealthServiceImpl {
+    /// Parsing metrics, containers per detected format, and the agent's
+    /// wall clock and timezone, so the cluster can estimate per-agent clock
+    /// offsets and flag drift
+    fn metadata(snapshot: &MetricsSnapshot, formats: &CacheStats) -> HashMap<String, String> {
+        let mut metadata = snapshot.to_metadata_map();
+        let per_format = [
+            ("json", formats.json_containers),
+            ("logfmt", formats.logfmt_containers),
+            ("syslog", formats.syslog_containers),
+            ("httplog", formats.httplog_containers),
+            ("plain", formats.plain_containers),
+            ("unknown", formats.unknown_containers),
+        ];
+        for (format, count) in per_format {
+            metadata.insert(format!("format_containers_{}", format), count.to_string());
+        }
+        metadata.insert("clock_unix_ms".to_string(), chrono::Utc::now().timestamp_millis().to_string());
+        metadata.insert("timezone".to_string(), Self::timezone_name());
+        metadata
+    }
+
+    /// `TZ` when set (e.g. "Europe/Berlin"), otherwise the local UTC offset
+    fn timezone_name() -> String {
+        std::env::var("TZ")
+            .ok()
+            .filter(|tz| !tz.is_empty())
+            .unwrap_or_else(|| chrono::Local::now().format("UTC%:z").to_string())
+    }
+}
+
 #[tonic::async_trait]
 impl HealthService for HealthServiceImpl {
     async fn check(
@@ -66,13 +105,13 @@ impl HealthService for HealthServiceImpl {
         _request: Request<HealthCheckRequest>,
     ) -> Result<Response<HealthCheckResponse>, Status> {
         let snapshot = self.metrics.snapshot();
-        let (status, message) = Self::evaluate_health(&snapshot);
+        let (status, message) = Self::evaluate_health(&snapshot, self.docker_failure_threshold);
 
         let response = HealthCheckResponse {
             status: status as i32,
             message,
             timestamp: chrono::Utc::now().timestamp(),
-            metadata: snapshot.to_metadata_map(),
+            metadata: Self::metadata(&snapshot, &self.parser_cache.stats()),
         };
 
         Ok(Response::new(response))
@@ -86,19 +125,21 @@ impl HealthService for HealthServiceImpl {
     ) -> Result<Response<Self::WatchStream>, Status> {
         // Clone the Arc to move into the async stream
         let metrics = self.metrics.clone();
+        let parser_cache = self.parser_cache.clone();
+        let docker_failure_threshold = self.docker_failure_threshold;
 
         let stream = async_stream::stream! {
             loop {
                 // Re-evaluate health on every tick
                 let snapshot = metrics.snapshot();
                 
-                let (status, message) = HealthServiceImpl::evaluate_health(&snapshot);
+                let (status, message) = HealthServiceImpl::evaluate_health(&snapshot, docker_failure_threshold);
 
                 let response = HealthCheckResponse {
                     status: status as i32,
                     message,
                     timestamp: chrono::Utc::now().timestamp(),
-                    metadata: snapshot.to_metadata_map(),
+                    metadata: HealthServiceImpl::metadata(&snapshot, &parser_cache.stats()),
                 };
                 
                 yield Ok(response);
@@ -111,3 +152,55 @@ impl HealthService for HealthServiceImpl {
         Ok(Response::new(Box::pin(stream)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LogFormat;
+
+    #[test]
+    fn test_metadata_reports_clock_and_timezone() {
+        let snapshot = ParsingMetrics::new().snapshot();
+        let before = chrono::Utc::now().timestamp_millis();
+        let metadata = HealthServiceImpl::metadata(&snapshot, &CacheStats::default());
+        let after = chrono::Utc::now().timestamp_millis();
+
+        let clock: i64 = metadata["clock_unix_ms"].parse().unwrap();
+        assert!((before..=after).contains(&clock));
+        assert!(!metadata["timezone"].is_empty());
+        // Parsing metrics are still present
+        assert!(metadata.contains_key("total_parsed"));
+    }
+
+    #[test]
+    fn test_metadata_counts_containers_per_format() {
+        let cache = ParserCache::new();
+        cache.set_format("api".to_string(), LogFormat::Json);
+        cache.set_format("worker".to_string(), LogFormat::Json);
+        cache.set_format("nginx".to_string(), LogFormat::HttpLog);
+        cache.set_format("legacy".to_string(), LogFormat::PlainText);
+
+        let metadata = HealthServiceImpl::metadata(&ParsingMetrics::new().snapshot(), &cache.stats());
+        assert_eq!(metadata["format_containers_json"], "2");
+        assert_eq!(metadata["format_containers_httplog"], "1");
+        assert_eq!(metadata["format_containers_plain"], "1");
+        assert_eq!(metadata["format_containers_logfmt"], "0");
+    }
+
+    #[test]
+    fn test_docker_outage_is_degraded() {
+        let metrics = ParsingMetrics::new();
+        metrics.set_docker_failures(5);
+        let (status, message) = HealthServiceImpl::evaluate_health(&metrics.snapshot(), 5);
+        assert_eq!(status, HealthStatus::Degraded);
+        assert!(message.contains("reconnecting"));
+
+        // Below the configured threshold the agent is still healthy
+        let (status, _) = HealthServiceImpl::evaluate_health(&metrics.snapshot(), 6);
+        assert_eq!(status, HealthStatus::Healthy);
+
+        metrics.set_docker_failures(0);
+        let (status, _) = HealthServiceImpl::evaluate_health(&metrics.snapshot(), 5);
+        assert_eq!(status, HealthStatus::Healthy);
+    }
+}
@@ -0,0 +1,114 @@
+use super::proto::NormalizedLogEntry;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_stream::{Stream, StreamExt};
+use tonic::Status;
+
+/// Shortest heartbeat interval a client may ask for
+pub const MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A synthetic entry telling the client the stream is alive but quiet
+pub fn heartbeat_entry(container_id: &str) -> NormalizedLogEntry {
+    NormalizedLogEntry {
+        container_id: container_id.to_string(),
+        timestamp_nanos: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+        heartbeat: true,
+        ..Default::default()
+    }
+}
+
+/// Wrap a log entry stream so a heartbeat entry is emitted after every
+/// `interval` without output. Any real entry (or error) restarts the clock.
+pub fn with_heartbeats<S>(
+    mut inner: S,
+    container_id: String,
+    interval: Duration,
+) -> impl Stream<Item = Result<NormalizedLogEntry, Status>>
+where
+    S: Stream<Item = Result<NormalizedLogEntry, Status>> + Unpin,
+{
+    async_stream::stream! {
+        let silence = tokio::time::sleep(interval);
+        tokio::pin!(silence);
+
+        loop {
+            tokio::select! {
+                item = inner.next() => match item {
+                    Some(item) => {
+                        silence.as_mut().reset(Instant::now() + interval);
+                        yield item;
+                    }
+                    None => break,
+                },
+                _ = &mut silence => {
+                    silence.as_mut().reset(Instant::now() + interval);
+                    yield Ok(heartbeat_entry(&container_id));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    fn line(sequence: u64) -> Result<NormalizedLogEntry, Status> {
+        Ok(NormalizedLogEntry {
+            container_id: "quiet".to_string(),
+            sequence,
+            raw_content: b"tick".to_vec(),
+            line_count: 1,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_on_silence_stop_while_lines_flow() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut stream = Box::pin(with_heartbeats(UnboundedReceiverStream::new(rx), "quiet".to_string(), INTERVAL));
+
+        // Silent container: heartbeats at the configured cadence
+        let start = Instant::now();
+        for n in 1..=3u32 {
+            let entry = stream.next().await.unwrap().unwrap();
+            assert!(entry.heartbeat);
+            assert_eq!(entry.container_id, "quiet");
+            assert!(entry.raw_content.is_empty());
+            assert!(start.elapsed() >= INTERVAL * n, "heartbeat {} came early", n);
+        }
+
+        // Lines arriving faster than the interval keep heartbeats away
+        let producer = tokio::spawn(async move {
+            for seq in 0..8 {
+                tx.send(line(seq)).unwrap();
+                tokio::time::sleep(INTERVAL / 4).await;
+            }
+            tx
+        });
+        for seq in 0..8 {
+            let entry = stream.next().await.unwrap().unwrap();
+            assert!(!entry.heartbeat, "heartbeat while lines were flowing");
+            assert_eq!(entry.sequence, seq);
+        }
+
+        // Quiet again: heartbeats resume
+        let _tx = producer.await.unwrap();
+        assert!(stream.next().await.unwrap().unwrap().heartbeat);
+    }
+
+    #[tokio::test]
+    async fn test_ends_with_inner_stream() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut stream = Box::pin(with_heartbeats(UnboundedReceiverStream::new(rx), "c1".to_string(), INTERVAL));
+        tx.send(line(1)).unwrap();
+        drop(tx);
+
+        assert_eq!(stream.next().await.unwrap().unwrap().sequence, 1);
+        assert!(stream.next().await.is_none());
+    }
+}
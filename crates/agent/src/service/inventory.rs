@@ -1,17 +1,23 @@
 use tonic::{Request, Response, Status};
-use bollard::models::ContainerInspectResponse as BollardInspectResponse;
+use bollard::models::{ContainerInspectResponse as BollardInspectResponse, RestartPolicyNameEnum};
 
 use crate::docker::client::DockerError;
+use crate::docker::inventory::log_rotation_unbounded;
 use crate::state::SharedState;
+use super::freeze::{self, DEFAULT_LOG_TAIL, MAX_LOG_TAIL};
+use super::resources;
 
 use super::proto::{
     inventory_service_server::InventoryService,
     ContainerListRequest, ContainerListResponse,
     ContainerInspectRequest, ContainerInspectResponse,
+    FreezeInspectRequest, FreezeInspectResponse,
+    UpdateResourcesRequest, UpdateResourcesResponse,
     ContainerInfo as ProtoContainerInfo,
-    ContainerDetails, VolumeMount, NetworkInfo, ResourceLimits,
+    ContainerDetails, VolumeMount, NetworkInfo, ResourceLimits, Ulimit,
     ContainerStateFilter, PortMapping as ProtoPortMapping,
-    ContainerStateInfo as ProtoContainerStateInfo,
+    ContainerStateInfo as ProtoContainerStateInfo, LabelSelector,
+    ContainerCommand as ProtoContainerCommand,
     RestartPolicy as ProtoRestartPolicy,
     HealthcheckConfig as ProtoHealthcheckConfig,
 };
@@ -35,7 +41,9 @@ impl InventoryServiceImpl {
             image: info.image,
             state: info.state,
             status: info.status,
+            log_rotation_unbounded: log_rotation_unbounded(info.log_driver.as_deref(), &info.log_options),
             log_driver: info.log_driver,
+            log_options: info.log_options,
             labels: info.labels,
             created_at: info.created_at,
             ports: info.ports.into_iter().map(|p| ProtoPortMapping {
@@ -52,6 +60,14 @@ impl InventoryServiceImpl {
                 finished_at: si.finished_at,
                 restart_count: si.restart_count,
             }),
+            log_size_bytes: info.log_size_bytes,
+            command: info.command.map(|c| ProtoContainerCommand {
+                shell_form: c.is_shell_form(),
+                command_line: c.command_line(),
+                entrypoint: c.entrypoint,
+                cmd: c.cmd,
+                working_dir: c.working_dir,
+            }),
         }
     }
 
@@ -141,10 +157,25 @@ impl InventoryServiceImpl {
                  None
             };
 
+            // Docker reports "unlimited" as 0 or -1; those become absent
+            let limited = |value: Option<i64>| value.filter(|v| *v > 0);
+            let ulimits = hc.ulimits.iter().flatten()
+                .map(|u| Ulimit {
+                    name: u.name.clone().unwrap_or_default(),
+                    soft: u.soft.filter(|v| *v >= 0),
+                    hard: u.hard.filter(|v| *v >= 0),
+                })
+                .collect();
+
             ResourceLimits {
-                memory_limit_bytes: hc.memory,
+                memory_limit_bytes: limited(hc.memory),
                 cpu_limit,
-                pids_limit: hc.pids_limit,
+                pids_limit: limited(hc.pids_limit),
+                oom_score_adj: hc.oom_score_adj,
+                oom_kill_disable: hc.oom_kill_disable,
+                cpu_quota: limited(hc.cpu_quota),
+                cpu_period: limited(hc.cpu_period),
+                ulimits,
             }
         });
 
@@ -175,9 +206,7 @@ impl InventoryServiceImpl {
         let restart_policy = inspect.host_config.as_ref()
             .and_then(|hc| hc.restart_policy.as_ref())
             .map(|rp| ProtoRestartPolicy {
-                name: rp.name.as_ref()
-                    .map(|n| format!("{:?}", n).to_lowercase())
-                    .unwrap_or_else(|| "no".to_string()),
+                name: Self::restart_policy_name(rp.name.as_ref()),
                 max_retry_count: rp.maximum_retry_count
                     .map(|c| c as i32)
                     .unwrap_or(0),
@@ -222,6 +251,15 @@ impl InventoryServiceImpl {
         })
     }
 
+    /// Docker's wire name for a restart policy (`on-failure`, `unless-stopped`, ...).
+    /// An unset or empty policy is reported as `no`, which is what Docker applies.
+    fn restart_policy_name(name: Option<&RestartPolicyNameEnum>) -> String {
+        match name {
+            None | Some(RestartPolicyNameEnum::EMPTY) => "no".to_string(),
+            Some(name) => name.to_string(),
+        }
+    }
+
     fn apply_state_filter(
         containers: Vec<crate::docker::inventory::ContainerInfo>,
         filter: i32,
@@ -247,28 +285,13 @@ impl InventoryServiceImpl {
             .filter(|c| c.state.eq_ignore_ascii_case(target_state))
             .collect()
     }
-}
-
-#[tonic::async_trait]
-impl InventoryService for InventoryServiceImpl {
-    async fn list_containers(
-        &self,
-        request: Request<ContainerListRequest>,
-    ) -> Result<Response<ContainerListResponse>, Status> {
-        let req = request.into_inner();
-
-        // ARCHITECTURE: Read-only cache access
-        // The background sync task (background_inventory_sync) continuously updates
-        // this cache. This ensures:
-        // - Fast response times (pure memory read, no Docker API calls)
-        // - DoS protection (Docker is never hammered by concurrent requests)
-        // - Data may be up to N seconds stale (configurable via sync interval)
-        
-        let mut containers: Vec<_> = self.state.inventory
-            .iter()
-            .map(|entry| entry.value().clone())
-            .collect();
 
+    /// Apply the request's filters to the cached inventory and cut out the
+    /// requested page. Returns the page and the total number of matches.
+    fn select_page(
+        mut containers: Vec<crate::docker::inventory::ContainerInfo>,
+        req: &ContainerListRequest,
+    ) -> (Vec<crate::docker::inventory::ContainerInfo>, u32) {
         if let Some(state_filter) = req.state_filter {
             containers = Self::apply_state_filter(containers, state_filter);
         }
@@ -286,17 +309,68 @@ impl InventoryService for InventoryServiceImpl {
             containers.retain(|c| c.state.eq_ignore_ascii_case("running"));
         }
 
+        if let Some(ref pattern) = req.name_pattern {
+            containers.retain(|c| c.name.contains(pattern.as_str()));
+        }
+        if let Some(ref pattern) = req.image_pattern {
+            containers.retain(|c| c.image.contains(pattern.as_str()));
+        }
+        if !req.label_selectors.is_empty() {
+            containers.retain(|c| Self::matches_labels(c, &req.label_selectors));
+        }
+
         let total_count = containers.len() as u32;
 
-        // 3. Apply Limit
-        if let Some(limit) = req.limit {
-            let limit = limit as usize;
-            if limit < containers.len() {
-                containers.truncate(limit);
+        // The cache is a hash map; sort so offsets address the same containers
+        // from one page request to the next
+        containers.sort_unstable_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+
+        let offset = req.offset.unwrap_or(0) as usize;
+        let limit = req.limit.map_or(usize::MAX, |l| l as usize);
+        let page = containers.into_iter().skip(offset).take(limit).collect();
+
+        (page, total_count)
+    }
+
+    fn matches_labels(
+        container: &crate::docker::inventory::ContainerInfo,
+        selectors: &[LabelSelector],
+    ) -> bool {
+        selectors.iter().all(|selector| {
+            match (container.labels.get(&selector.key), &selector.value) {
+                (Some(actual), Some(expected)) => actual == expected,
+                (Some(_), None) => true,
+                (None, _) => false,
             }
-        }
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl InventoryService for InventoryServiceImpl {
+    async fn list_containers(
+        &self,
+        request: Request<ContainerListRequest>,
+    ) -> Result<Response<ContainerListResponse>, Status> {
+        let req = request.into_inner();
 
-        let proto_containers = containers
+        // ARCHITECTURE: Read-only cache access
+        // The background sync task (background_inventory_sync) continuously updates
+        // this cache. This ensures:
+        // - Fast response times (pure memory read, no Docker API calls)
+        // - DoS protection (Docker is never hammered by concurrent requests)
+        // - Data may be up to N seconds stale (configurable via sync interval)
+        
+        let containers: Vec<_> = self.state.inventory
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+
+        let (page, total_count) = Self::select_page(containers, &req);
+        let offset = req.offset.unwrap_or(0) as usize;
+        let has_more = offset + page.len() < total_count as usize;
+
+        let proto_containers = page
             .into_iter()
             .map(Self::convert_container_info)
             .collect();
@@ -304,6 +378,7 @@ impl InventoryService for InventoryServiceImpl {
         Ok(Response::new(ContainerListResponse {
             containers: proto_containers,
             total_count,
+            has_more,
         }))
     }
 
@@ -324,6 +399,7 @@ impl InventoryService for InventoryServiceImpl {
             .await
             .map_err(|e| match e {
                 DockerError::ContainerNotFound(msg) => Status::not_found(msg),
+                DockerError::RateLimited { message, retry_after } => super::rate_limited_status(&message, retry_after),
                 _ => Status::internal(format!("Docker inspect raw failed: {}", e)),
             })?;
 
@@ -339,6 +415,48 @@ impl InventoryService for InventoryServiceImpl {
             details, 
         }))
     }
+
+    async fn freeze_inspect(
+        &self,
+        request: Request<FreezeInspectRequest>,
+    ) -> Result<Response<FreezeInspectResponse>, Status> {
+        // Pausing interrupts the workload, so operators must opt in per agent
+        if !self.state.config().allow_freeze_inspect {
+            return Err(Status::permission_denied(
+                "FreezeInspect is disabled on this agent (set allow_freeze_inspect = true)",
+            ));
+        }
+
+        let req = request.into_inner();
+        let log_tail = req.log_tail_lines.unwrap_or(DEFAULT_LOG_TAIL).min(MAX_LOG_TAIL);
+
+        tracing::info!("Freeze inspect of container {}", req.container_id);
+        let snapshot = freeze::freeze_inspect(self.state.clone(), &req.container_id, log_tail).await?;
+        Ok(Response::new(snapshot))
+    }
+
+    async fn update_container_resources(
+        &self,
+        request: Request<UpdateResourcesRequest>,
+    ) -> Result<Response<UpdateResourcesResponse>, Status> {
+        // Changing limits can starve or OOM-kill the workload, so operators
+        // must opt in per agent
+        if !self.state.config().allow_resource_updates {
+            return Err(Status::permission_denied(
+                "UpdateContainerResources is disabled on this agent (set allow_resource_updates = true)",
+            ));
+        }
+
+        let req = request.into_inner();
+        let limits = resources::requested_limits(req.cpus, req.memory_bytes)?;
+
+        tracing::info!(
+            "Updating resources of container {} (cpus: {:?}, memory_bytes: {:?})",
+            req.container_id, req.cpus, req.memory_bytes
+        );
+        let applied = resources::update_resources(self.state.as_ref(), &req.container_id, limits).await?;
+        Ok(Response::new(applied))
+    }
 }
 
 #[cfg(test)]
@@ -356,10 +474,14 @@ mod tests {
             state: state.to_string(),
             status: "status".to_string(),
             log_driver: None,
+            log_options: HashMap::new(),
             labels: HashMap::new(),
             created_at: 0,
             ports: vec![],
             state_info: None,
+            log_path: None,
+            log_size_bytes: None,
+            command: None,
         }
     }
 
@@ -442,6 +564,80 @@ mod tests {
         assert_eq!(limits3.cpu_limit, None);
     }
 
+    #[test]
+    fn test_extract_container_details_ulimits_and_cgroup_limits() {
+        let ulimit = |name: &str, soft, hard| bollard::models::ResourcesUlimits {
+            name: Some(name.to_string()),
+            soft: Some(soft),
+            hard: Some(hard),
+        };
+        let inspect = BollardInspectResponse {
+            host_config: Some(HostConfig {
+                ulimits: Some(vec![ulimit("nofile", 1024, 65536), ulimit("nproc", 512, -1)]),
+                cpu_quota: Some(50_000),
+                cpu_period: Some(100_000),
+                memory: Some(256 * 1024 * 1024),
+                pids_limit: Some(-1),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let limits = InventoryServiceImpl::extract_container_details(&inspect)
+            .and_then(|d| d.limits)
+            .expect("Should have limits");
+        assert_eq!(
+            limits.ulimits,
+            vec![
+                Ulimit { name: "nofile".to_string(), soft: Some(1024), hard: Some(65536) },
+                Ulimit { name: "nproc".to_string(), soft: Some(512), hard: None },
+            ]
+        );
+        assert_eq!(limits.cpu_quota, Some(50_000));
+        assert_eq!(limits.cpu_period, Some(100_000));
+        assert_eq!(limits.memory_limit_bytes, Some(256 * 1024 * 1024));
+        assert_eq!(limits.pids_limit, None);
+
+        // Nothing set: all unlimited
+        let inspect = BollardInspectResponse {
+            host_config: Some(HostConfig { memory: Some(0), cpu_quota: Some(-1), ..Default::default() }),
+            ..Default::default()
+        };
+        let limits = InventoryServiceImpl::extract_container_details(&inspect)
+            .and_then(|d| d.limits)
+            .expect("Should have limits");
+        assert!(limits.ulimits.is_empty());
+        assert_eq!((limits.memory_limit_bytes, limits.cpu_quota, limits.cpu_period), (None, None, None));
+    }
+
+    #[test]
+    fn test_restart_policy_names() {
+        let policy = |name: Option<RestartPolicyNameEnum>| {
+            let inspect = BollardInspectResponse {
+                host_config: Some(HostConfig {
+                    restart_policy: Some(bollard::models::RestartPolicy {
+                        name,
+                        maximum_retry_count: Some(5),
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            InventoryServiceImpl::extract_container_details(&inspect)
+                .and_then(|d| d.restart_policy)
+                .expect("Should have restart policy")
+        };
+
+        assert_eq!(policy(Some(RestartPolicyNameEnum::UNLESS_STOPPED)).name, "unless-stopped");
+        assert_eq!(policy(Some(RestartPolicyNameEnum::ALWAYS)).name, "always");
+        assert_eq!(policy(Some(RestartPolicyNameEnum::EMPTY)).name, "no");
+        assert_eq!(policy(None).name, "no");
+
+        let on_failure = policy(Some(RestartPolicyNameEnum::ON_FAILURE));
+        assert_eq!(on_failure.name, "on-failure");
+        assert_eq!(on_failure.max_retry_count, 5);
+    }
+
     #[test]
     fn test_include_stopped_logic() {
         // Validate the boolean logic we implemented in list_containers
@@ -469,4 +665,93 @@ mod tests {
         // User explicitly says include_stopped=true -> Should NOT Filter (Return False)
         assert_eq!(check_filter(true, None), false);
     }
+
+    fn list_request() -> ContainerListRequest {
+        ContainerListRequest {
+            state_filter: None,
+            include_stopped: true,
+            limit: None,
+            offset: None,
+            name_pattern: None,
+            image_pattern: None,
+            label_selectors: vec![],
+        }
+    }
+
+    #[test]
+    fn test_select_page_boundaries() {
+        // Inserted out of order: pages follow name order regardless of cache order
+        let containers: Vec<_> = [3, 0, 4, 1, 2]
+            .iter()
+            .map(|i| create_test_container(&i.to_string(), "running"))
+            .collect();
+
+        let page = |offset, limit| {
+            let req = ContainerListRequest { offset, limit, ..list_request() };
+            let (page, total) = InventoryServiceImpl::select_page(containers.clone(), &req);
+            (page.into_iter().map(|c| c.id).collect::<Vec<_>>(), total)
+        };
+
+        assert_eq!(page(None, Some(2)), (vec!["0".to_string(), "1".to_string()], 5));
+        assert_eq!(page(Some(2), Some(2)), (vec!["2".to_string(), "3".to_string()], 5));
+        // Last, partial page
+        assert_eq!(page(Some(4), Some(2)), (vec!["4".to_string()], 5));
+        // Past the end
+        assert_eq!(page(Some(5), Some(2)), (vec![], 5));
+        // No limit: everything from offset on
+        assert_eq!(page(Some(3), None).0.len(), 2);
+    }
+
+    #[test]
+    fn test_select_page_status_filter_reduces_results() {
+        let containers = vec![
+            create_test_container("1", "running"),
+            create_test_container("2", "exited"),
+            create_test_container("3", "exited"),
+            create_test_container("4", "paused"),
+        ];
+
+        let (all, total_all) = InventoryServiceImpl::select_page(containers.clone(), &list_request());
+        assert_eq!((all.len(), total_all), (4, 4));
+
+        let req = ContainerListRequest {
+            state_filter: Some(ContainerStateFilter::Exited as i32),
+            include_stopped: false,
+            ..list_request()
+        };
+        let (exited, total) = InventoryServiceImpl::select_page(containers, &req);
+        assert_eq!(total, 2);
+        assert!(exited.iter().all(|c| c.state == "exited"));
+    }
+
+    #[test]
+    fn test_select_page_name_and_label_filters() {
+        let mut api = create_test_container("api", "running");
+        api.labels.insert("tier".to_string(), "backend".to_string());
+        let mut web = create_test_container("web", "running");
+        web.labels.insert("tier".to_string(), "frontend".to_string());
+        let db = create_test_container("db", "running");
+        let containers = vec![api, web, db];
+
+        let req = ContainerListRequest {
+            label_selectors: vec![LabelSelector { key: "tier".to_string(), value: None }],
+            ..list_request()
+        };
+        assert_eq!(InventoryServiceImpl::select_page(containers.clone(), &req).1, 2);
+
+        let req = ContainerListRequest {
+            label_selectors: vec![LabelSelector {
+                key: "tier".to_string(),
+                value: Some("frontend".to_string()),
+            }],
+            ..list_request()
+        };
+        let (page, _) = InventoryServiceImpl::select_page(containers.clone(), &req);
+        assert_eq!(page[0].id, "web");
+
+        let req = ContainerListRequest { name_pattern: Some("name-d".to_string()), ..list_request() };
+        let (page, total) = InventoryServiceImpl::select_page(containers, &req);
+        assert_eq!(total, 1);
+        assert_eq!(page[0].id, "db");
+    }
 }
@@ -0,0 +1,259 @@
+use super::content_hash::content_hash;
+use super::proto::NormalizedLogEntry;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_stream::{Stream, StreamExt};
+use tonic::Status;
+
+/// Longest dedup window a client may ask for; every line is held this long
+pub const MAX_DEDUP_WINDOW: Duration = Duration::from_secs(5);
+
+/// Distinct lines a stream holds at once. A burst of distinct lines beyond
+/// this releases the oldest early instead of growing without bound.
+pub const MAX_HELD_LINES: usize = 10_000;
+
+/// Collapses identical lines that arrive within a short window of each
+/// other, even when other lines are interleaved (the same error logged by
+/// several threads at once). Unlike `RepeatCollapser`, the lines need not be
+/// consecutive.
+///
+/// Each distinct line is held for `window` after its first occurrence and
+/// then emitted once, with `repeat_count` set to the number of copies seen.
+/// Lines come out in first-arrival order. Keyed on content hash and stream.
+/// At most `max_held` distinct lines are held; past that the oldest goes out
+/// before its window closes.
+pub struct WindowDeduper {
+    window: Duration,
+    max_held: usize,
+    pending: VecDeque<Pending>,
+    /// Key -> position in `pending`, offset by `popped`
+    index: HashMap<(u64, i32), u64>,
+    popped: u64,
+}
+
+struct Pending {
+    entry: NormalizedLogEntry,
+    key: (u64, i32),
+    count: u32,
+    first_seen: Instant,
+}
+
+impl WindowDeduper {
+    pub fn new(window: Duration) -> Self {
+        Self::with_max_held(window, MAX_HELD_LINES)
+    }
+
+    pub fn with_max_held(window: Duration, max_held: usize) -> Self {
+        Self {
+            window,
+            max_held: max_held.max(1),
+            pending: VecDeque::new(),
+            index: HashMap::new(),
+            popped: 0,
+        }
+    }
+
+    /// Hold an entry, or count it against an identical one already held.
+    /// An entry that already stands for several lines (a collapsed run)
+    /// counts as that many.
+    pub fn process(&mut self, entry: NormalizedLogEntry, now: Instant) -> Vec<NormalizedLogEntry> {
+        let mut expired = self.expired(now);
+        let key = (content_hash(&entry), entry.log_level);
+        let copies = entry.repeat_count.max(1);
+
+        if let Some(&position) = self.index.get(&key) {
+            let held = &mut self.pending[(position - self.popped) as usize];
+            held.count = held.count.saturating_add(copies);
+        } else {
+            self.index.insert(key, self.popped + self.pending.len() as u64);
+            self.pending.push_back(Pending { entry, key, count: copies, first_seen: now });
+            if self.pending.len() > self.max_held {
+                expired.extend(self.pop());
+            }
+        }
+        expired
+    }
+
+    /// Entries whose window has closed, oldest first
+    pub fn expired(&mut self, now: Instant) -> Vec<NormalizedLogEntry> {
+        let mut out = Vec::new();
+        while self.pending.front().is_some_and(|p| now >= p.first_seen + self.window) {
+            out.extend(self.pop());
+        }
+        out
+    }
+
+    /// When the oldest held entry is due
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.front().map(|p| p.first_seen + self.window)
+    }
+
+    /// Everything still held (call at stream end or before an error)
+    pub fn flush(&mut self) -> Vec<NormalizedLogEntry> {
+        std::iter::from_fn(|| self.pop()).collect()
+    }
+
+    fn pop(&mut self) -> Option<NormalizedLogEntry> {
+        let held = self.pending.pop_front()?;
+        self.index.remove(&held.key);
+        self.popped += 1;
+        let mut entry = held.entry;
+        entry.repeat_count = held.count;
+        Some(entry)
+    }
+}
+
+/// Wrap a log entry stream so identical lines within `window` of each other
+/// are emitted once with a count. Errors are passed through after flushing.
+pub fn dedup_window<S>(
+    mut inner: S,
+    window: Duration,
+) -> impl Stream<Item = Result<NormalizedLogEntry, Status>>
+where
+    S: Stream<Item = Result<NormalizedLogEntry, Status>> + Unpin,
+{
+    async_stream::stream! {
+        let mut deduper = WindowDeduper::new(window);
+
+        loop {
+            let deadline = deduper.next_deadline();
+            tokio::select! {
+                item = inner.next() => match item {
+                    Some(Ok(entry)) => {
+                        for ready in deduper.process(entry, Instant::now()) {
+                            yield Ok(ready);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        for held in deduper.flush() {
+                            yield Ok(held);
+                        }
+                        yield Err(e);
+                    }
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    for ready in deduper.expired(Instant::now()) {
+                        yield Ok(ready);
+                    }
+                }
+            }
+        }
+
+        for held in deduper.flush() {
+            yield Ok(held);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(50);
+
+    fn entry(content: &[u8], sequence: u64) -> NormalizedLogEntry {
+        NormalizedLogEntry {
+            container_id: "test".to_string(),
+            sequence,
+            raw_content: content.to_vec(),
+            line_count: 1,
+            ..Default::default()
+        }
+    }
+
+    fn summary(entries: &[NormalizedLogEntry]) -> Vec<(&[u8], u32, u64)> {
+        entries.iter().map(|e| (e.raw_content.as_slice(), e.repeat_count, e.sequence)).collect()
+    }
+
+    #[test]
+    fn test_interleaved_duplicates_within_window_collapse() {
+        let mut deduper = WindowDeduper::new(WINDOW);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // Four worker threads hit the same error while a request log interleaves
+        let arrivals: [(&[u8], u64); 7] = [
+            (b"pool exhausted: timeout after 30s", 1),
+            (b"GET /orders 200", 2),
+            (b"pool exhausted: timeout after 30s", 3),
+            (b"pool exhausted: timeout after 30s", 4),
+            (b"GET /orders 200", 5),
+            (b"GET /cart 200", 6),
+            (b"pool exhausted: timeout after 30s", 7),
+        ];
+        for (i, (content, seq)) in arrivals.iter().enumerate() {
+            assert!(deduper.process(entry(content, *seq), at(i as u64 * 5)).is_empty());
+        }
+        assert!(deduper.expired(at(49)).is_empty(), "window still open");
+
+        let out = deduper.expired(at(80));
+        assert_eq!(
+            summary(&out),
+            vec![
+                (&b"pool exhausted: timeout after 30s"[..], 4, 1),
+                (&b"GET /orders 200"[..], 2, 2),
+                (&b"GET /cart 200"[..], 1, 6),
+            ]
+        );
+        assert!(deduper.flush().is_empty());
+    }
+
+    #[test]
+    fn test_outside_window_not_collapsed() {
+        let mut deduper = WindowDeduper::new(WINDOW);
+        let start = Instant::now();
+
+        assert!(deduper.process(entry(b"tick", 1), start).is_empty());
+        let out = deduper.process(entry(b"tick", 2), start + WINDOW);
+        assert_eq!(summary(&out), vec![(&b"tick"[..], 1, 1)]);
+        assert_eq!(summary(&deduper.flush()), vec![(&b"tick"[..], 1, 2)]);
+    }
+
+    #[test]
+    fn test_held_lines_capped() {
+        let mut deduper = WindowDeduper::with_max_held(WINDOW, 2);
+        let now = Instant::now();
+
+        assert!(deduper.process(entry(b"a", 1), now).is_empty());
+        assert!(deduper.process(entry(b"b", 2), now).is_empty());
+        assert!(deduper.process(entry(b"a", 3), now).is_empty(), "a repeat holds nothing new");
+
+        // A third distinct line pushes the oldest out before its window closes
+        let out = deduper.process(entry(b"c", 4), now);
+        assert_eq!(summary(&out), vec![(&b"a"[..], 2, 1)]);
+        assert_eq!(summary(&deduper.flush()), vec![(&b"b"[..], 1, 2), (&b"c"[..], 1, 4)]);
+    }
+
+    #[test]
+    fn test_stdout_and_stderr_kept_apart() {
+        let mut deduper = WindowDeduper::new(WINDOW);
+        let now = Instant::now();
+        let stderr = NormalizedLogEntry { log_level: 1, ..entry(b"same", 2) };
+
+        deduper.process(entry(b"same", 1), now);
+        deduper.process(stderr, now);
+        assert_eq!(deduper.flush().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_emits_after_window() {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut stream = Box::pin(dedup_window(tokio_stream::wrappers::UnboundedReceiverStream::new(rx), WINDOW));
+
+        let start = Instant::now();
+        for (content, seq) in [(&b"boom"[..], 1), (b"ok", 2), (b"boom", 3)] {
+            tx.send(Ok(entry(content, seq))).unwrap();
+        }
+
+        // Emitted while the source is still open, once the window closes
+        let first = stream.next().await.unwrap().unwrap();
+        assert!(start.elapsed() >= WINDOW);
+        assert_eq!((first.raw_content.as_slice(), first.repeat_count), (&b"boom"[..], 2));
+        assert_eq!(stream.next().await.unwrap().unwrap().repeat_count, 1);
+
+        drop(tx);
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -1,26 +1,53 @@
+use std::borrow::Cow;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio_stream::{Stream, StreamExt};
 use tonic::{Request, Response, Status};
 use prost_types::Timestamp as ProtoTimestamp;
 
 use crate::docker::client::DockerError;
-use crate::docker::stream::{LogStreamRequest as InternalLogStreamRequest, LogLevel, LogLine};
+use crate::docker::interleave::MAX_INTERLEAVE_WINDOW;
+use crate::docker::stream::{LogStreamRequest as InternalLogStreamRequest, LogLevel};
 use crate::filter::engine::{FilterEngine, FilterMode};
+use crate::filter::live::LiveFilter;
+use crate::filter::redact::Redactor;
+use crate::filter::severity::{Severity, SeverityFloor};
+use crate::docker::inventory::ContainerInfo;
 use crate::state::SharedState;
-use crate::parser::{LogFormat, LogParser, strip_ansi_codes};
-use crate::parser::traits::ParsedLog;
-use crate::parser::formats::{JsonParser, LogfmtParser, PlainTextParser};
+use crate::parser::{LogDecoder, LogFormat, LogParser, strip_ansi_codes};
+use crate::parser::traits::{parse_guarded, FormatDetector, ParsedLog};
+use crate::parser::model::ParseError;
+use crate::parser::cache::LogEpoch;
+use crate::parser::coerce::{infer_type, TypedValue};
+use crate::parser::schema::CompiledSchema;
+use crate::parser::formats::{HttpLogDetector, JsonParser, JsonParserConfig, LogfmtParser, PlainTextParser, SyslogDetector};
 use super::multiline::MultilineGrouper;
+use super::multiline_json::{JsonAssembler, RawLine};
+use super::content_hash::content_hash;
+use super::color_group::color_group;
+use super::repeats::{collapse_repeats, REPEAT_FLUSH_TIMEOUT};
+use super::line_dedup::{dedup_window, MAX_DEDUP_WINDOW};
+use super::rate_limited_status;
+use super::heartbeat::{with_heartbeats, MIN_HEARTBEAT_INTERVAL};
+use super::emit_buffer::with_emit_buffer;
+use super::backlog::{backlog_gap, backlog_marker, drop_before, with_backlog_marker};
+use super::stop::{stop_marker_from_inspect, with_stop_marker};
+use super::rate::{tally, wall_clock_ticks, MAX_RATE_BUCKET_SECS};
 
 use super::proto::{
     log_service_server::LogService,
     LogStreamRequest, NormalizedLogEntry,
+    UpdateStreamFilterRequest, UpdateStreamFilterResponse,
+    ParserCacheStatsRequest, ParserCacheStatsResponse,
+    EvictParserCacheRequest, EvictParserCacheResponse,
+    LogRateRequest, LogRateBucket,
     FilterMode as ProtoFilterMode,
     ParsedLog as ProtoParsedLog, ParseMetadata as ProtoParseMetadata,
     RequestContext as ProtoRequestContext, ErrorContext as ProtoErrorContext,
-    KeyValuePair, LogFormat as ProtoLogFormat,
+    KeyValuePair, LogFormat as ProtoLogFormat, StreamPriority,
+    LogSeverity, UnleveledPolicy, TypedValue as ProtoTypedValue,
+    typed_value::Kind as TypedKind,
 };
 
 pub struct LogServiceImpl {
@@ -43,6 +70,7 @@ impl LogServiceImpl {
         labels: &std::collections::HashMap<String, String>,
         parser_cache: &crate::parser::cache::ParserCache,
         first_line: &[u8],
+        enabled_formats: &[LogFormat],
         metrics: &crate::parser::metrics::ParsingMetrics,
     ) -> LogFormat {
         // 1. Explicit label override: docktail.log_format=json|logfmt|plain
@@ -64,19 +92,58 @@ impl LogServiceImpl {
         }
 
         // Single-line heuristic: fast byte-level check on first line
-        let format = Self::quick_detect_format(first_line);
+        let format = Self::quick_detect_format_with(first_line, enabled_formats);
         parser_cache.set_format(container_id.to_string(), format);
         metrics.record_detection(format != LogFormat::Unknown);
         format
     }
 
+    /// Metadata for a line whose parse failed, counted by error kind. A line
+    /// the parser panicked on is reported as plain text.
+    fn failed_parse(
+        e: &ParseError,
+        format: LogFormat,
+        elapsed: Duration,
+        metrics: &crate::parser::metrics::ParsingMetrics,
+    ) -> ProtoParseMetadata {
+        metrics.record_error(e.into());
+        let detected_format = match e {
+            ParseError::ParserPanic(_) => LogFormat::PlainText,
+            _ => format,
+        };
+        ProtoParseMetadata {
+            detected_format: Self::convert_log_format(detected_format),
+            parse_success: false,
+            parse_error: Some(e.to_string()),
+            parse_time_nanos: i64::try_from(elapsed.as_nanos()).unwrap_or(i64::MAX),
+            line_too_large: matches!(e, ParseError::LineTooLarge(..)),
+        }
+    }
+
+    /// Log epoch for a container: the start time of its current process and
+    /// the oldest log line Docker retains (`first_line`, nanos)
+    fn log_epoch(info: &ContainerInfo, first_line: Option<i64>) -> Option<LogEpoch> {
+        let started_at = &info.state_info.as_ref()?.started_at;
+        let started_at = chrono::DateTime::parse_from_rfc3339(started_at).ok()?.timestamp_nanos_opt()?;
+        Some(LogEpoch { started_at, first_line })
+    }
+
     /// Fast single-line format detection (no buffering, no allocation).
     /// - First byte `{` + last byte `}` → JSON
+    /// - `<PRI>` prefix → Syslog
+    /// - Common/combined access log line → HttpLog
     /// - Contains multiple `key=value` pairs → Logfmt  
     /// - Everything else → PlainText (safe default)
     ///
     /// This runs ONCE per container on the first log line.
+    #[cfg(test)]
     fn quick_detect_format(line: &[u8]) -> LogFormat {
+        Self::quick_detect_format_with(line, &LogFormat::DETECTABLE)
+    }
+
+    /// Single-line detection restricted to the formats enabled in the agent
+    /// config. Disabled formats are never tried; plain text is the fallback.
+    fn quick_detect_format_with(line: &[u8], enabled: &[LogFormat]) -> LogFormat {
         if line.is_empty() {
             return LogFormat::PlainText;
         }
@@ -88,21 +155,96 @@ impl LogServiceImpl {
         let trimmed = if start < end { &line[start..end] } else { return LogFormat::PlainText; };
 
         // JSON: starts with '{', ends with '}'
-        if trimmed.starts_with(b"{") && trimmed.ends_with(b"}") {
+        if enabled.contains(&LogFormat::Json) && trimmed.starts_with(b"{") && trimmed.ends_with(b"}") {
             return LogFormat::Json;
         }
 
+        // Syslog and access logs before logfmt: RFC 5424 structured data and
+        // query strings both carry key=value pairs
+        if enabled.contains(&LogFormat::Syslog) && SyslogDetector.detect(trimmed).format == LogFormat::Syslog {
+            return LogFormat::Syslog;
+        }
+        if enabled.contains(&LogFormat::HttpLog) && HttpLogDetector.detect(trimmed).format == LogFormat::HttpLog {
+            return LogFormat::HttpLog;
+        }
+
         // Logfmt: contains multiple key=value pairs separated by spaces
         // e.g. "level=info msg=\"hello\" ts=2026-01-01"
         // Require the character before '=' to be alphanumeric or underscore
         // to avoid false positives on operators (>=, <=, ==, !=) and URLs (?a=1&b=2)
-        if trimmed.windows(2).filter(|w| (w[0].is_ascii_alphanumeric() || w[0] == b'_') && w[1] == b'=').count() >= 2 {
+        if enabled.contains(&LogFormat::Logfmt)
+            && trimmed.windows(2).filter(|w| (w[0].is_ascii_alphanumeric() || w[0] == b'_') && w[1] == b'=').count() >= 2
+        {
             return LogFormat::Logfmt;
         }
 
         LogFormat::PlainText
     }
 
+    /// Whether a parsed line fits the format it was parsed as. Plain text
+    /// always parses, so a plain-text line counts against the format only
+    /// when it is detected as an enabled structured format on its own.
+    fn line_fits(format: LogFormat, parsed_ok: bool, line: &[u8], enabled: &[LogFormat]) -> bool {
+        match format {
+            LogFormat::PlainText | LogFormat::Unknown => {
+                Self::quick_detect_format_with(line, enabled) == LogFormat::PlainText
+            }
+            _ => parsed_ok,
+        }
+    }
+
+    /// Detection over a sample of lines: the most common single-line verdict
+    /// wins, with ties going to plain text (the safe default).
+    fn detect_from_sample(lines: &[Vec<u8>], enabled: &[LogFormat]) -> LogFormat {
+        let mut counts: Vec<(LogFormat, usize)> = Vec::new();
+        for line in lines {
+            let format = Self::quick_detect_format_with(line, enabled);
+            match counts.iter_mut().find(|(f, _)| *f == format) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((format, 1)),
+            }
+        }
+
+        let plain = counts.iter()
+            .find(|(f, _)| *f == LogFormat::PlainText)
+            .map_or(0, |(_, c)| *c);
+        counts.into_iter()
+            .filter(|(_, count)| *count > plain)
+            .max_by_key(|(_, count)| *count)
+            .map_or(LogFormat::PlainText, |(format, _)| format)
+    }
+
+    /// Build the severity floor requested by the client, if any
+    fn severity_floor(min_level: i32, unleveled_policy: i32) -> Option<SeverityFloor> {
+        let min = match LogSeverity::try_from(min_level).unwrap_or(LogSeverity::Unspecified) {
+            LogSeverity::Unspecified => return None,
+            LogSeverity::Trace => Severity::Trace,
+            LogSeverity::Debug => Severity::Debug,
+            LogSeverity::Info => Severity::Info,
+            LogSeverity::Warn => Severity::Warn,
+            LogSeverity::Error => Severity::Error,
+            LogSeverity::Fatal => Severity::Fatal,
+        };
+        let drop_unleveled = UnleveledPolicy::try_from(unleveled_policy) == Ok(UnleveledPolicy::Drop);
+        Some(SeverityFloor { min, drop_unleveled })
+    }
+
+    /// Schema check of the document the JSON parser decoded. Other lines
+    /// (no document), and streams that didn't ask for validation, get no
+    /// annotation.
+    fn schema_annotations(
+        schema: Option<&CompiledSchema>,
+        document: Option<&serde_json::Value>,
+    ) -> (Option<bool>, Vec<String>) {
+        match (schema, document) {
+            (Some(schema), Some(document)) => {
+                let errors = schema.validate(document);
+                (Some(errors.is_empty()), errors)
+            }
+            _ => (None, Vec::new()),
+        }
+    }
+
     /// Convert protobuf FilterMode to internal FilterMode
     fn convert_filter_mode(proto_mode: i32) -> FilterMode {
         match ProtoFilterMode::try_from(proto_mode) {
@@ -112,10 +254,24 @@ impl LogServiceImpl {
         }
     }
 
+    /// Compile a requested filter; `None` when no pattern was given
+    fn build_filter(pattern: Option<&str>, proto_mode: i32) -> Result<Option<FilterEngine>, Status> {
+        let Some(pattern) = pattern else {
+            return Ok(None);
+        };
+        FilterEngine::new(pattern, false, Self::convert_filter_mode(proto_mode))
+            .map(Some)
+            .map_err(|e| Status::invalid_argument(format!("Invalid regex pattern: {}", e)))
+    }
+
     /// Convert protobuf LogStreamRequest to internal request
     fn convert_request(req: LogStreamRequest) -> Result<InternalLogStreamRequest, Status> {
+        // since_nanos wins; Docker gets its whole second and the rest is
+        // trimmed from the stream
+        let since = req.since_nanos.map(|nanos| nanos.div_euclid(1_000_000_000)).or(req.since);
+
         // Validate that since <= until when both are provided
-        if let (Some(since), Some(until)) = (req.since, req.until) {
+        if let (Some(since), Some(until)) = (since, req.until) {
             if since > until {
                 return Err(Status::invalid_argument(
                     format!("'since' ({}) must not be after 'until' ({})", since, until)
@@ -125,14 +281,19 @@ impl LogServiceImpl {
 
         let filter_mode = Self::convert_filter_mode(req.filter_mode);
 
+        // Ordering by timestamp needs the timestamps shown
+        let interleave_window = (req.timestamps && req.interleave_window_ms > 0)
+            .then(|| Duration::from_millis(req.interleave_window_ms.into()).min(MAX_INTERLEAVE_WINDOW));
+
         Ok(InternalLogStreamRequest {
             container_id: req.container_id,
-            since: req.since,
+            since,
             until: req.until,
             follow: req.follow,
             filter_pattern: req.filter_pattern,
             filter_mode,
             tail_lines: req.tail_lines,
+            interleave_window,
         })
     }
 
@@ -145,16 +306,34 @@ impl LogServiceImpl {
     }
 
     /// Get parser for a specific format
-    fn get_parser(format: LogFormat) -> Box<dyn LogParser> {
+    fn get_parser(format: LogFormat, flatten_fields: bool) -> Box<dyn LogParser> {
         match format {
-            LogFormat::Json => Box::new(JsonParser::new()),
+            LogFormat::Json => Box::new(JsonParser::with_config(JsonParserConfig {
+                flatten_nested: flatten_fields,
+                ..Default::default()
+            })),
             LogFormat::Logfmt => Box::new(LogfmtParser),
             _ => Box::new(PlainTextParser),
         }
     }
 
-    /// Convert internal ParsedLog to protobuf
-    fn convert_parsed_log(parsed: ParsedLog) -> ProtoParsedLog {
+    /// Proto form of an inferred field type
+    fn convert_typed_value(value: TypedValue) -> ProtoTypedValue {
+        let kind = match value {
+            TypedValue::Int(n) => TypedKind::IntValue(n),
+            TypedValue::Float(f) => TypedKind::FloatValue(f),
+            TypedValue::Bool(b) => TypedKind::BoolValue(b),
+            TypedValue::Timestamp(dt) => TypedKind::TimestampValue(ProtoTimestamp {
+                seconds: dt.timestamp(),
+                nanos: dt.timestamp_subsec_nanos() as i32,
+            }),
+        };
+        ProtoTypedValue { kind: Some(kind) }
+    }
+
+    /// Convert internal ParsedLog to protobuf, typing field values when
+    /// `infer_types` is set
+    fn convert_parsed_log(parsed: ParsedLog, infer_types: bool) -> ProtoParsedLog {
         ProtoParsedLog {
             level: parsed.level,
             message: parsed.message,
@@ -180,7 +359,14 @@ impl LogServiceImpl {
                 line: e.line,
             }),
             fields: parsed.fields.into_iter()
-                .map(|(k, v)| KeyValuePair { key: k, value: v })
+                .map(|(k, v)| KeyValuePair {
+                    typed: infer_types
+                        .then(|| infer_type(&v))
+                        .flatten()
+                        .map(Self::convert_typed_value),
+                    key: k,
+                    value: v,
+                })
                 .collect(),
         }
     }
@@ -201,6 +387,7 @@ impl LogServiceImpl {
 #[tonic::async_trait]
 impl LogService for LogServiceImpl {
     type StreamLogsStream = Pin<Box<dyn Stream<Item = Result<NormalizedLogEntry, Status>> + Send>>;
+    type StreamLogRateStream = Pin<Box<dyn Stream<Item = Result<LogRateBucket, Status>> + Send>>;
 
     async fn stream_logs(
         &self,
@@ -209,84 +396,204 @@ impl LogService for LogServiceImpl {
         let req = request.into_inner();
         let container_id = req.container_id.trim().to_string();
         let disable_parsing = req.disable_parsing;
+        let include_hash = req.include_hash;
+        let collapse = req.collapse_repeats;
+        let infer_field_types = req.infer_field_types;
+        let flatten_fields = req.flatten_fields;
+        let dedup = (req.dedup_window_ms > 0)
+            .then(|| Duration::from_millis(req.dedup_window_ms.into()).min(MAX_DEDUP_WINDOW));
+        let heartbeat_interval = (req.follow && req.heartbeat_interval_secs > 0)
+            .then(|| Duration::from_secs(req.heartbeat_interval_secs.into()).max(MIN_HEARTBEAT_INTERVAL));
+        let severity_floor = Self::severity_floor(req.min_level, req.unleveled_policy);
 
         if container_id.is_empty() {
             return Err(Status::invalid_argument("container_id must not be empty"));
         }
 
+        // Compiled once per stream
+        let schema = if req.validate_schema {
+            let Some(schema) = &self.state.log_schema else {
+                return Err(Status::failed_precondition(
+                    "No log schema is configured on this agent (set log_schema_path)",
+                ));
+            };
+            Some(CompiledSchema::compile(schema).map_err(|e| Status::internal(e.to_string()))?)
+        } else {
+            None
+        };
+
+        // Admit by QoS class; the permit is held until the stream is dropped
+        let priority = StreamPriority::try_from(req.priority).unwrap_or(StreamPriority::Unspecified);
+        let permit = self.state.streams.try_admit(priority)?;
+
         // Convert protobuf request to internal request
         let mut req_with_trimmed_id = req.clone();
         req_with_trimmed_id.container_id = container_id.clone();
         let internal_req = Self::convert_request(req_with_trimmed_id)
             .map_err(|e| Status::invalid_argument(format!("Invalid request: {}", e)))?;
 
-        // Create filter if pattern is provided
-        let filter = if let Some(pattern) = &req.filter_pattern {
-            let filter_mode = Self::convert_filter_mode(req.filter_mode);
-            
-            match FilterEngine::new(pattern, false, filter_mode) {
-                Ok(engine) => Some(Arc::new(engine)),
-                Err(e) => {
-                    return Err(Status::invalid_argument(format!("Invalid regex pattern: {}", e)));
-                }
-            }
+        // Create filter if pattern is provided; with a token it can be
+        // replaced while the stream runs
+        let filter = Arc::new(LiveFilter::new(Self::build_filter(req.filter_pattern.as_deref(), req.filter_mode)?));
+        let filter_registration = req.filter_token.as_deref()
+            .map(|token| self.state.live_filters.register(token, Arc::clone(&filter)));
+
+        // Get container labels for per-container multiline configuration
+        let raw_inspect = self.state.docker
+            .inspect_container_raw(&container_id)
+            .await
+            .map_err(|e| match e {
+                DockerError::RateLimited { message, retry_after } => rate_limited_status(&message, retry_after),
+                _ => Status::internal(format!("Failed to inspect container: {}", e)),
+            })?;
+
+        // Settings as of now; a config reload only affects streams opened after it
+        let config = self.state.config();
+
+        // Secrets from the container's environment are masked before parsing
+        let env = raw_inspect.config.as_ref().and_then(|c| c.env.clone()).unwrap_or_default();
+        let redactor = Redactor::for_container(&config.redaction, &env);
+        let container_info = ContainerInfo::from(raw_inspect);
+
+        // Timestamp of the oldest log line Docker retains, if anything needs it
+        let earliest = if config.redetect_on_log_reset || req.since_nanos.is_some() {
+            self.state.docker
+                .first_log_timestamp(&container_id)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::debug!(container_id = %container_id, "Oldest log line unknown: {}", e);
+                    None
+                })
         } else {
             None
         };
 
-        // Get container labels for per-container multiline configuration
-        let container_info = self.state.docker
-            .inspect_container(&container_id)
-            .await
-            .map_err(|e| Status::internal(format!("Failed to inspect container: {}", e)))?;
+        // A reset log (restart, or rotation that discarded the oldest lines)
+        // may now be written by a different app version: drop the cached
+        // detection so the first line is re-sampled. Checked as the stream
+        // opens; a stream already running keeps its parser.
+        if config.redetect_on_log_reset {
+            if let Some(epoch) = Self::log_epoch(&container_info, earliest) {
+                if self.state.parser_cache.observe_log_epoch(&container_id, epoch) {
+                    tracing::debug!(container_id = %container_id, "Log reset detected, re-detecting format");
+                }
+            }
+        }
+
+        // Anchored at a precise instant: say so up front if the log no longer
+        // reaches back that far
+        let backlog = req.since_nanos.and_then(|since_nanos| {
+            let created_nanos = container_info.created_at.saturating_mul(1_000_000_000);
+            backlog_gap(since_nanos, created_nanos, earliest).map(|ts| backlog_marker(&container_id, ts))
+        });
 
-        // Get log stream from Docker client with filter
+        // Create multiline grouper with config from state, applying container overrides
+        let container_config = config.multiline.for_container(
+            &container_info.name,
+            &container_info.labels
+        );
+
+        // The filter runs on entries after redaction (a pattern must not
+        // match a masked secret) and grouping (a match deep in a stack trace
+        // keeps the trace), so the Docker stream passes every line
         let mut log_stream = self.state.docker
-            .stream_logs(internal_req, filter.clone())
+            .stream_logs(internal_req, Arc::new(LiveFilter::default()))
             .await
             .map_err(|e| match e {
                 DockerError::ContainerNotFound(msg) => Status::not_found(msg),
                 DockerError::PermissionDenied => Status::permission_denied("Permission denied"),
                 DockerError::UnsupportedLogDriver(msg) => Status::failed_precondition(msg),
+                DockerError::RateLimited { message, retry_after } => rate_limited_status(&message, retry_after),
                 _ => Status::internal(format!("Docker error: {}", e)),
-            })?;
+            })?
+            .with_poll_budget(config.stream_buffers.scan_budget_lines);
 
         // Clone parser_cache and metrics for use in stream
         let parser_cache = Arc::clone(&self.state.parser_cache);
         let metrics = Arc::clone(&self.state.metrics);
         let container_labels = container_info.labels.clone();
+
+        // Adaptive detection never second-guesses an explicit format label
+        let mut tuning = self.state.detection_tuner.track(&container_id);
+        let adaptive = tuning.is_enabled() && !container_labels.contains_key("docktail.log_format");
         
-        // Create multiline grouper with config from state, applying container overrides
-        let container_config = self.state.config.multiline.for_container(
-            &container_info.name,
-            &container_info.labels
-        );
+        // Create multiline grouper
         let mut grouper = if container_config.enabled {
             Some(MultilineGrouper::new(&container_config))
         } else {
             None
         };
+        let mut assembler = (container_config.enabled && container_config.json && !disable_parsing)
+            .then(|| JsonAssembler::new(&container_config));
+
+        let enabled_formats = config.enabled_log_formats();
+
+        // Windows containers may log UTF-16 or with a BOM; transcode before parsing
+        let fallback_encoding = config.fallback_encoding
+            .as_deref()
+            .and_then(LogDecoder::fallback_from_label);
+        let mut decoder = LogDecoder::new(fallback_encoding);
+        let heartbeat_container_id = container_id.clone();
+        let stop_container_id = container_id.clone();
 
         // Create the response stream
         // No buffering. Resolve format on first line, then
         // process every subsequent line immediately. Parse failures yield raw content.
         let response_stream = async_stream::stream! {
+            let _permit = permit;
+            let _filter_registration = filter_registration;
+
             // Parser state: resolved lazily on first line, then reused
             let mut format_resolved = false;
             let mut current_format = LogFormat::PlainText;
             let mut current_parser: Option<Box<dyn LogParser>> = None;
+            // Locked formats skip lock tracking and the initial larger sample
+            let mut locked = false;
+            let mut lock_window = parser_cache.lock_window();
+            // A manual cache eviction re-detects running streams too
+            let mut seen_evictions = parser_cache.evictions();
+
+            // Lines collected for a multi-line (re-)detection; 0 = not sampling
+            let mut sample: Vec<Vec<u8>> = Vec::new();
+            let mut sample_target = 0usize;
 
             let mut timeout_interval = tokio::time::interval(tokio::time::Duration::from_millis(150));
             timeout_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
             loop {
-                let result = tokio::select! {
-                    item = log_stream.next() => {
-                        match item {
-                            Some(r) => r,
-                            None => break, // Stream ended
+                // Set when the Docker stream ends (`Ok`) or fails (`Err`)
+                let mut end = None;
+                let lines = tokio::select! {
+                    item = log_stream.next() => match item {
+                        Some(Ok(log_response)) => {
+                            // Docker timestamp is already stripped by convert_bollard_log in client.rs.
+                            // Decode to UTF-8, then strip ANSI escape codes
+                            let decoded = decoder.decode(&log_response.content);
+                            let mut cleaned = strip_ansi_codes(&decoded).into_owned();
+                            let masked = redactor.as_ref().and_then(|r| match r.redact(&cleaned) {
+                                Cow::Owned(masked) => Some(masked),
+                                Cow::Borrowed(_) => None,
+                            });
+                            if let Some(masked) = masked {
+                                cleaned = masked;
+                            }
+                            let line = RawLine::new(cleaned, log_response.timestamp, log_response.log_level, log_response.sequence);
+
+                            // Pretty-printed JSON is held until the object closes
+                            match assembler {
+                                Some(ref mut a) => a.push(line),
+                                None => vec![line],
+                            }
                         }
-                    }
+                        Some(Err(e)) => {
+                            end = Some(Err(e));
+                            assembler.as_mut().map(JsonAssembler::flush).unwrap_or_default()
+                        }
+                        None => {
+                            end = Some(Ok(()));
+                            assembler.as_mut().map(JsonAssembler::flush).unwrap_or_default()
+                        }
+                    },
                     _ = timeout_interval.tick() => {
                         // Periodic timeout check for pending multiline groups
                         if let Some(ref mut g) = grouper {
@@ -294,121 +601,200 @@ impl LogService for LogServiceImpl {
                                 yield Ok(pending);
                             }
                         }
-                        continue;
+                        // An unterminated JSON object goes out as plain lines
+                        assembler.as_mut().map(JsonAssembler::check_timeout).unwrap_or_default()
                     }
                 };
 
-                match result {
-                    Ok(log_response) => {
-                        let log_line = LogLine {
-                            timestamp: log_response.timestamp,
-                            stream_type: log_response.log_level,
-                            content: log_response.content,
-                        };
-                        let sequence = log_response.sequence;
-
-                        // Docker timestamp is already stripped by convert_bollard_log in client.rs.
-                        // Strip ANSI escape codes
-                        let cleaned = strip_ansi_codes(&log_line.content);
-                        let cleaned_bytes = cleaned.as_ref();
-
-                        // Resolve format on first line (one-time cost)
-                        // label → cache → heuristic
-                        if !format_resolved && !disable_parsing && !parser_cache.is_disabled(&container_id) {
-                            current_format = Self::resolve_format(
-                                &container_id,
-                                &container_labels,
-                                &parser_cache,
-                                cleaned_bytes,
-                                &metrics,
-                            );
-                            current_parser = Some(Self::get_parser(current_format));
-                            format_resolved = true;
+                for line in lines {
+                    let sequence = line.sequence;
+                    let cleaned_bytes = line.content.as_slice();
+
+                    let evictions = parser_cache.evictions();
+                    if evictions != seen_evictions {
+                        seen_evictions = evictions;
+                        if format_resolved && !parser_cache.contains(&container_id) {
+                            format_resolved = false;
+                            lock_window.reset();
+                            sample.clear();
+                            sample_target = 0;
+                        }
+                    }
+
+                    // Resolve format on first line (one-time cost)
+                    // label → cache → heuristic
+                    if !format_resolved && !disable_parsing && !parser_cache.is_disabled(&container_id) {
+                        current_format = Self::resolve_format(
+                            &container_id,
+                            &container_labels,
+                            &parser_cache,
+                            cleaned_bytes,
+                            &enabled_formats,
+                            &metrics,
+                        );
+                        current_parser = Some(Self::get_parser(current_format, flatten_fields));
+                        format_resolved = true;
+
+                        // Structured formats are self-contained per line — skip multiline grouping
+                        if matches!(current_format, LogFormat::Json | LogFormat::Logfmt) {
+                            if let Some(ref mut g) = grouper {
+                                g.set_passthrough(true);
+                            }
+                        }
+
+                        locked = parser_cache.is_locked(&container_id);
+
+                        // Containers that parsed badly before get a larger sample
+                        if adaptive && !locked {
+                            sample_target = tuning.sample_size();
+                        }
+                    }
 
-                            // Structured formats are self-contained per line — skip multiline grouping
-                            if matches!(current_format, LogFormat::Json | LogFormat::Logfmt) {
+                    // Collect the sample while lines keep flowing; once full,
+                    // settle on the majority format
+                    if sample_target > 1 {
+                        sample.push(cleaned_bytes.to_vec());
+                        if sample.len() >= sample_target {
+                            let format = Self::detect_from_sample(&sample, &enabled_formats);
+                            if format != current_format {
+                                tracing::debug!(
+                                    container_id = %container_id,
+                                    from = ?current_format,
+                                    to = ?format,
+                                    sample = sample.len(),
+                                    "Re-detected log format from a larger sample"
+                                );
+                                parser_cache.set_format(container_id.clone(), format);
+                                locked = parser_cache.is_locked(&container_id);
+                                lock_window.reset();
+                                current_format = format;
+                                current_parser = Some(Self::get_parser(format, flatten_fields));
                                 if let Some(ref mut g) = grouper {
-                                    g.set_passthrough(true);
+                                    g.set_passthrough(matches!(format, LogFormat::Json | LogFormat::Logfmt));
                                 }
                             }
+                            sample.clear();
+                            sample_target = 0;
                         }
+                    }
 
-                        // Parse the log line
-                        let (parsed, metadata) = if disable_parsing {
-                            (None, ProtoParseMetadata {
-                                detected_format: ProtoLogFormat::Unknown as i32,
-                                parse_success: false,
-                                parse_error: Some("Parsing disabled".to_string()),
-                                parse_time_nanos: 0,
-                            })
-                        } else if parser_cache.is_disabled(&container_id) {
-                            (None, ProtoParseMetadata {
-                                detected_format: ProtoLogFormat::PlainText as i32,
-                                parse_success: false,
-                                parse_error: Some("Parsing disabled for container".to_string()),
-                                parse_time_nanos: 0,
-                            })
-                        } else if let Some(parser) = &current_parser {
-                            let parse_start = Instant::now();
-                            match parser.parse(cleaned_bytes) {
-                                Ok(parsed_log) => {
-                                    let parse_time = parse_start.elapsed().as_nanos() as u64;
-                                    metrics.record_parse(current_format, parse_time);
-                                    (
-                                        Some(Self::convert_parsed_log(parsed_log)),
-                                        ProtoParseMetadata {
-                                            detected_format: Self::convert_log_format(current_format),
-                                            parse_success: true,
-                                            parse_error: None,
-                                            parse_time_nanos: i64::try_from(parse_time).unwrap_or(i64::MAX),
-                                        }
-                                    )
-                                }
-                                Err(e) => {
-                                    // parse failure → yield raw, don't crash.
-                                    // Metrics track error rate; operators can investigate.
-                                    metrics.record_error(crate::parser::metrics::MetricErrorType::Other);
-                                    let elapsed_nanos = parse_start.elapsed().as_nanos();
-                                    (None, ProtoParseMetadata {
+                    // Parse the log line
+                    let mut document = None;
+                    let mut parsed_ok = None;
+                    let (parsed, metadata) = if disable_parsing {
+                        (None, ProtoParseMetadata {
+                            detected_format: ProtoLogFormat::Unknown as i32,
+                            parse_success: false,
+                            parse_error: Some("Parsing disabled".to_string()),
+                            parse_time_nanos: 0,
+                            line_too_large: false,
+                        })
+                    } else if !locked && parser_cache.is_disabled(&container_id) {
+                        (None, ProtoParseMetadata {
+                            detected_format: ProtoLogFormat::PlainText as i32,
+                            parse_success: false,
+                            parse_error: Some("Parsing disabled for container".to_string()),
+                            parse_time_nanos: 0,
+                            line_too_large: false,
+                        })
+                    } else if let Some(parser) = &current_parser {
+                        let parse_start = Instant::now();
+                        match parse_guarded(parser.as_ref(), cleaned_bytes) {
+                            Ok(mut parsed_log) => {
+                                let parse_time = parse_start.elapsed().as_nanos() as u64;
+                                metrics.record_parse(current_format, parse_time);
+                                parsed_ok = Some(true);
+                                document = parsed_log.document.take();
+                                (
+                                    Some(Self::convert_parsed_log(parsed_log, infer_field_types)),
+                                    ProtoParseMetadata {
                                         detected_format: Self::convert_log_format(current_format),
-                                        parse_success: false,
-                                        parse_error: Some(e.to_string()),
-                                        parse_time_nanos: i64::try_from(elapsed_nanos).unwrap_or(i64::MAX),
-                                    })
-                                }
+                                        parse_success: true,
+                                        parse_error: None,
+                                        parse_time_nanos: i64::try_from(parse_time).unwrap_or(i64::MAX),
+                                        line_too_large: false,
+                                    }
+                                )
                             }
-                        } else {
-                            (None, ProtoParseMetadata {
-                                detected_format: ProtoLogFormat::PlainText as i32,
-                                parse_success: false,
-                                parse_error: None,
-                                parse_time_nanos: 0,
-                            })
-                        };
-
-                        let entry = NormalizedLogEntry {
-                            container_id: container_id.clone(),
-                            timestamp_nanos: log_line.timestamp,
-                            log_level: Self::convert_log_level(log_line.stream_type),
-                            sequence,
-                            raw_content: cleaned_bytes.to_vec(),
-                            parsed,
-                            metadata: Some(metadata),
-                            grouped_lines: Vec::new(),
-                            line_count: 1,
-                            is_grouped: false,
-                        };
-
-                        // Multiline grouping
-                        if let Some(ref mut g) = grouper {
-                            for grouped in g.process(entry) {
-                                yield Ok(grouped);
+                            Err(e) => {
+                                // parse failure → yield raw, don't crash.
+                                // Metrics track error rate; operators can investigate.
+                                parsed_ok = Some(false);
+                                (None, Self::failed_parse(&e, current_format, parse_start.elapsed(), &metrics))
                             }
-                        } else {
-                            yield Ok(entry);
                         }
+                    } else {
+                        (None, ProtoParseMetadata {
+                            detected_format: ProtoLogFormat::PlainText as i32,
+                            parse_success: false,
+                            parse_error: None,
+                            parse_time_nanos: 0,
+                            line_too_large: false,
+                        })
+                    };
+
+                    // How well lines fit the format decides the lock, and
+                    // keeps feeding the tuner after it: a locked format whose
+                    // lines stop fitting is re-detected too
+                    if let Some(parsed_ok) = parsed_ok.filter(|_| !locked || adaptive) {
+                        let fits = Self::line_fits(current_format, parsed_ok, cleaned_bytes, &enabled_formats);
+                        if !locked && lock_window.record(fits) && parser_cache.lock_format(&container_id, current_format) {
+                            tracing::debug!(
+                                container_id = %container_id,
+                                format = ?current_format,
+                                "Locked log format after stable detection"
+                            );
+                            locked = true;
+                            sample.clear();
+                            sample_target = 0;
+                        }
+                        if adaptive {
+                            if let Some(size) = tuning.record(fits) {
+                                sample.clear();
+                                sample_target = size;
+                            }
+                        }
+                    }
+
+                    let (schema_valid, schema_errors) =
+                        Self::schema_annotations(schema.as_ref(), document.as_ref());
+
+                    let entry = NormalizedLogEntry {
+                        container_id: container_id.clone(),
+                        timestamp_nanos: line.timestamp,
+                        log_level: Self::convert_log_level(line.stream_type),
+                        sequence,
+                        raw_content: line.content,
+                        parsed,
+                        metadata: Some(metadata),
+                        grouped_lines: Vec::new(),
+                        line_count: line.line_count,
+                        is_grouped: false,
+                        content_hash: None,
+                        repeat_count: 0,
+                        schema_valid,
+                        schema_errors,
+                        heartbeat: false,
+                        backlog_unavailable: false,
+                        container_stopped: false,
+                        exit_code: None,
+                        color_group: None,
+                    };
+
+                    // Multiline grouping
+                    if let Some(ref mut g) = grouper {
+                        for grouped in g.process(entry) {
+                            yield Ok(grouped);
+                        }
+                    } else {
+                        yield Ok(entry);
                     }
-                    Err(e) => {
+                }
+
+                match end {
+                    None => {}
+                    Some(Ok(())) => break, // Stream ended
+                    Some(Err(e)) => {
                         // Flush pending multiline group on error
                         if let Some(ref mut g) = grouper {
                             while let Some(pending) = g.flush() {
@@ -430,10 +816,209 @@ impl LogService for LogServiceImpl {
             }
         };
 
-        Ok(Response::new(Box::pin(response_stream)))
+        // Post-grouping stages: pattern filter over whole groups, severity
+        // floor (a group is judged by its first line, so stack traces stay
+        // with their error), collapse repeats and windowed duplicates, then
+        // hash so a group or a collapsed run hashes as one unit
+        let mut response_stream: Self::StreamLogsStream = Box::pin(response_stream);
+        if let Some(since_nanos) = req.since_nanos {
+            response_stream = Box::pin(drop_before(response_stream, since_nanos));
+        }
+        response_stream = Box::pin(filter_groups(response_stream, filter));
+        if let Some(floor) = severity_floor {
+            response_stream = Box::pin(response_stream.filter(move |item| {
+                item.as_ref().map_or(true, |entry| {
+                    floor.allows(entry.parsed.as_ref().and_then(|p| p.level.as_deref()))
+                })
+            }));
+        }
+        if priority == StreamPriority::Low {
+            let state = Arc::clone(&self.state);
+            response_stream = Box::pin(response_stream.filter(move |item| {
+                item.as_ref().map_or(true, |entry| {
+                    !state.streams.sheds(priority, entry.parsed.as_ref().and_then(|p| p.level.as_deref()))
+                })
+            }));
+        }
+        if collapse {
+            response_stream = Box::pin(collapse_repeats(response_stream, REPEAT_FLUSH_TIMEOUT));
+        }
+        if let Some(window) = dedup {
+            response_stream = Box::pin(dedup_window(response_stream, window));
+        }
+
+        if include_hash {
+            response_stream = Box::pin(response_stream.map(|item| {
+                item.map(|mut entry| {
+                    entry.content_hash = Some(content_hash(&entry));
+                    entry
+                })
+            }));
+        }
+
+        if let Some(field) = req.color_by_field.clone().filter(|f| !f.is_empty()) {
+            response_stream = Box::pin(response_stream.map(move |item| {
+                item.map(|mut entry| {
+                    entry.color_group = Some(color_group(&entry, &field));
+                    entry
+                })
+            }));
+        }
+
+        if backlog.is_some() {
+            response_stream = Box::pin(with_backlog_marker(backlog, response_stream));
+        }
+
+        // A followed container that stops ends the stream with a marker
+        // instead of an error or a bare end
+        if req.follow {
+            let state = Arc::clone(&self.state);
+            let stopped = async move {
+                let inspected = state.docker.inspect_container_raw(&stop_container_id).await;
+                stop_marker_from_inspect(&stop_container_id, inspected)
+            };
+            response_stream = Box::pin(with_stop_marker(response_stream, stopped));
+        }
+
+        // Last, so only what the client actually receives counts as output
+        if let Some(interval) = heartbeat_interval {
+            response_stream = Box::pin(with_heartbeats(response_stream, heartbeat_container_id, interval));
+        }
+
+        // Work ahead of a slow client, up to the configured number of entries
+        let emit_buffer = config.stream_buffers.emit_buffer_entries;
+        if emit_buffer > 0 {
+            response_stream = Box::pin(with_emit_buffer(response_stream, emit_buffer));
+        }
+
+        Ok(Response::new(response_stream))
+    }
+
+    async fn stream_log_rate(
+        &self,
+        request: Request<LogRateRequest>,
+    ) -> Result<Response<Self::StreamLogRateStream>, Status> {
+        let req = request.into_inner();
+        let container_id = req.container_id.trim().to_string();
+        if container_id.is_empty() {
+            return Err(Status::invalid_argument("container_id must not be empty"));
+        }
+        let bucket_secs = req.bucket_secs;
+        if !(1..=MAX_RATE_BUCKET_SECS).contains(&bucket_secs) {
+            return Err(Status::invalid_argument(format!(
+                "bucket_secs must be between 1 and {}, got {}",
+                MAX_RATE_BUCKET_SECS, bucket_secs
+            )));
+        }
+
+        // Holds a Docker log stream like any other subscription
+        let permit = self.state.streams.try_admit(StreamPriority::Normal)?;
+
+        // New lines only: the histogram starts now
+        let internal_req = InternalLogStreamRequest {
+            container_id: container_id.clone(),
+            since: None,
+            until: None,
+            follow: true,
+            filter_pattern: None,
+            filter_mode: FilterMode::Include,
+            tail_lines: Some(0),
+            interleave_window: None,
+        };
+        let log_stream = self.state.docker
+            .stream_logs(internal_req, Arc::new(LiveFilter::default()))
+            .await
+            .map_err(|e| match e {
+                DockerError::ContainerNotFound(msg) => Status::not_found(msg),
+                DockerError::PermissionDenied => Status::permission_denied("Permission denied"),
+                DockerError::RateLimited { message, retry_after } => rate_limited_status(&message, retry_after),
+                _ => Status::internal(format!("Docker error: {}", e)),
+            })?;
+
+        // A read error ends the tally like the end of the log does
+        let sizes = log_stream.map_while(move |line| match line {
+            Ok(line) => Some(line.content.len()),
+            Err(e) => {
+                tracing::warn!(container_id = %container_id, "Log rate stream ended: {}", e);
+                None
+            }
+        });
+        let (first_start, ticks) = wall_clock_ticks(bucket_secs);
+        let buckets = tally(Box::pin(sizes), Box::pin(ticks), first_start).map(move |bucket| {
+            let _permit = &permit;
+            Ok(LogRateBucket {
+                bucket_start: bucket.start,
+                bucket_secs,
+                lines: bucket.lines,
+                bytes: bucket.bytes,
+            })
+        });
+
+        Ok(Response::new(Box::pin(buckets)))
+    }
+
+    async fn update_stream_filter(
+        &self,
+        request: Request<UpdateStreamFilterRequest>,
+    ) -> Result<Response<UpdateStreamFilterResponse>, Status> {
+        let req = request.into_inner();
+        if req.filter_token.is_empty() {
+            return Err(Status::invalid_argument("filter_token must not be empty"));
+        }
+        let filter = Self::build_filter(req.filter_pattern.as_deref(), req.filter_mode)?;
+
+        let updated = self.state.live_filters.replace(&req.filter_token, filter);
+        if updated == 0 {
+            return Err(Status::not_found(format!("No running stream has filter_token '{}'", req.filter_token)));
+        }
+        tracing::debug!(filter_token = %req.filter_token, streams = updated, "Replaced live stream filter");
+
+        Ok(Response::new(UpdateStreamFilterResponse { streams_updated: updated as u32 }))
+    }
+
+    async fn parser_cache_stats(
+        &self,
+        _request: Request<ParserCacheStatsRequest>,
+    ) -> Result<Response<ParserCacheStatsResponse>, Status> {
+        let stats = self.state.parser_cache.stats();
+        Ok(Response::new(ParserCacheStatsResponse {
+            entries: stats.total_containers as u32,
+            disabled: stats.disabled_containers as u32,
+            locked: stats.locked_containers as u32,
+            hits: stats.hits,
+            misses: stats.misses,
+            hit_rate: stats.hit_rate(),
+        }))
+    }
+
+    async fn evict_parser_cache(
+        &self,
+        request: Request<EvictParserCacheRequest>,
+    ) -> Result<Response<EvictParserCacheResponse>, Status> {
+        let req = request.into_inner();
+        if req.container_id.is_empty() {
+            return Err(Status::invalid_argument("container_id must not be empty"));
+        }
+        let evicted = self.state.parser_cache.evict(&req.container_id);
+        tracing::info!(container_id = %req.container_id, evicted, "Parser cache evicted");
+
+        Ok(Response::new(EvictParserCacheResponse { evicted }))
     }
 }
 
+/// Keep the entries `filter` matches, judging each group by all its lines
+fn filter_groups<S>(stream: S, filter: Arc<LiveFilter>) -> impl Stream<Item = Result<NormalizedLogEntry, Status>>
+where
+    S: Stream<Item = Result<NormalizedLogEntry, Status>>,
+{
+    stream.filter(move |item| {
+        item.as_ref().map_or(true, |entry| {
+            let continuations = entry.grouped_lines.iter().map(|line| line.content.as_slice());
+            filter.should_include_any(std::iter::once(entry.raw_content.as_slice()).chain(continuations))
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -595,7 +1180,7 @@ mod tests {
         labels.insert("docktail.log_format".to_string(), "json".to_string());
 
         let format = LogServiceImpl::resolve_format(
-            "container-1", &labels, &cache, b"Server started!", &metrics,
+            "container-1", &labels, &cache, b"Server started!", &LogFormat::DETECTABLE, &metrics,
         );
 
         assert_eq!(format, LogFormat::Json, "Label should override heuristic");
@@ -610,7 +1195,7 @@ mod tests {
         labels.insert("docktail.log_format".to_string(), "JSON".to_string());
 
         let format = LogServiceImpl::resolve_format(
-            "c1", &labels, &cache, b"anything", &metrics,
+            "c1", &labels, &cache, b"anything", &LogFormat::DETECTABLE, &metrics,
         );
         assert_eq!(format, LogFormat::Json);
     }
@@ -623,7 +1208,7 @@ mod tests {
         labels.insert("docktail.log_format".to_string(), "xml".to_string()); // unsupported
 
         let format = LogServiceImpl::resolve_format(
-            "c1", &labels, &cache, b"anything", &metrics,
+            "c1", &labels, &cache, b"anything", &LogFormat::DETECTABLE, &metrics,
         );
         assert_eq!(format, LogFormat::PlainText, "Unknown label value → PlainText");
     }
@@ -638,7 +1223,7 @@ mod tests {
             labels.insert("docktail.log_format".to_string(), variant.to_string());
 
             let format = LogServiceImpl::resolve_format(
-                "c1", &labels, &cache, b"{\"json\":true}", &metrics,
+                "c1", &labels, &cache, b"{\"json\":true}", &LogFormat::DETECTABLE, &metrics,
             );
             assert_eq!(format, LogFormat::PlainText, "Variant '{}' should → PlainText", variant);
         }
@@ -653,7 +1238,7 @@ mod tests {
 
         // This line looks like plain text, but cache wins
         let format = LogServiceImpl::resolve_format(
-            "c1", &HashMap::new(), &cache, b"plain text line", &metrics,
+            "c1", &HashMap::new(), &cache, b"plain text line", &LogFormat::DETECTABLE, &metrics,
         );
         assert_eq!(format, LogFormat::Json, "Cache hit should override heuristic");
     }
@@ -665,7 +1250,7 @@ mod tests {
 
         let format = LogServiceImpl::resolve_format(
             "c1", &HashMap::new(), &cache,
-            br#"{"level":"info","msg":"started"}"#, &metrics,
+            br#"{"level":"info","msg":"started"}"#, &LogFormat::DETECTABLE, &metrics,
         );
         assert_eq!(format, LogFormat::Json);
         // Verify it was cached for subsequent lines
@@ -679,7 +1264,7 @@ mod tests {
 
         let format = LogServiceImpl::resolve_format(
             "c1", &HashMap::new(), &cache,
-            b"level=info msg=\"ready\" port=3000", &metrics,
+            b"level=info msg=\"ready\" port=3000", &LogFormat::DETECTABLE, &metrics,
         );
         assert_eq!(format, LogFormat::Logfmt);
         assert_eq!(cache.get_format("c1"), Some(LogFormat::Logfmt));
@@ -692,12 +1277,197 @@ mod tests {
 
         let format = LogServiceImpl::resolve_format(
             "c1", &HashMap::new(), &cache,
-            b"2026-01-01 INFO  Application started", &metrics,
+            b"2026-01-01 INFO  Application started", &LogFormat::DETECTABLE, &metrics,
         );
         assert_eq!(format, LogFormat::PlainText);
         assert_eq!(cache.get_format("c1"), Some(LogFormat::PlainText));
     }
 
+    #[test]
+    fn resolve_redetects_after_log_reset() {
+        // Simulate rotation onto a new app version that switched JSON → logfmt
+        let cache = ParserCache::new();
+        let metrics = ParsingMetrics::new();
+        let epoch = LogEpoch { started_at: 1_000, first_line: Some(1_500) };
+        cache.observe_log_epoch("c1", epoch);
+
+        let format = LogServiceImpl::resolve_format(
+            "c1", &HashMap::new(), &cache,
+            br#"{"level":"info","msg":"v1"}"#, &LogFormat::DETECTABLE, &metrics,
+        );
+        assert_eq!(format, LogFormat::Json);
+
+        // Same process, but rotation discarded the lines before 9_000
+        let rotated = LogEpoch { first_line: Some(9_000), ..epoch };
+        assert!(cache.observe_log_epoch("c1", rotated), "Rotation should invalidate");
+        let format = LogServiceImpl::resolve_format(
+            "c1", &HashMap::new(), &cache,
+            b"level=info msg=v2 port=3000", &LogFormat::DETECTABLE, &metrics,
+        );
+        assert_eq!(format, LogFormat::Logfmt, "Should re-detect after reset");
+        assert_eq!(cache.get_format("c1"), Some(LogFormat::Logfmt));
+    }
+
+    #[test]
+    fn severity_floor_from_request() {
+        assert!(LogServiceImpl::severity_floor(LogSeverity::Unspecified as i32, 0).is_none());
+
+        let floor = LogServiceImpl::severity_floor(
+            LogSeverity::Warn as i32,
+            UnleveledPolicy::Drop as i32,
+        ).expect("floor requested");
+        assert_eq!(floor.min, Severity::Warn);
+        assert!(floor.drop_unleveled);
+
+        // Unspecified policy passes unleveled lines through
+        let floor = LogServiceImpl::severity_floor(LogSeverity::Error as i32, 0).unwrap();
+        assert!(!floor.drop_unleveled);
+    }
+
+    #[test]
+    fn schema_annotations_for_json_lines() {
+        let schema = CompiledSchema::compile(&serde_json::json!({
+            "type": "object",
+            "required": ["level", "msg"],
+            "properties": {
+                "level": { "enum": ["debug", "info", "warn", "error"] },
+                "latency_ms": { "type": "number", "minimum": 0 }
+            }
+        }))
+        .unwrap();
+        // Annotations come from the parser's own decoded document
+        let annotate = |format, content: &str| {
+            let parsed = LogServiceImpl::get_parser(format, false).parse(content.as_bytes()).unwrap();
+            LogServiceImpl::schema_annotations(Some(&schema), parsed.document.as_ref())
+        };
+
+        let (valid, errors) = annotate(LogFormat::Json, r#"{"level":"info","msg":"ok","latency_ms":12}"#);
+        assert_eq!(valid, Some(true));
+        assert!(errors.is_empty());
+
+        let (valid, mut errors) = annotate(LogFormat::Json, r#"{"level":"loud","latency_ms":-1}"#);
+        assert_eq!(valid, Some(false));
+        errors.sort();
+        assert_eq!(errors, vec![
+            "/: \"msg\" is a required property",
+            "/latency_ms: -1 is less than the minimum of 0",
+            "/level: \"loud\" is not one of \"debug\", \"info\" or 2 other candidates",
+        ]);
+
+        // Non-JSON lines and unvalidated streams aren't annotated
+        assert_eq!(annotate(LogFormat::Logfmt, "level=info msg=ok"), (None, Vec::new()));
+        let document = serde_json::json!({"level": "loud"});
+        assert_eq!(LogServiceImpl::schema_annotations(None, Some(&document)), (None, Vec::new()));
+    }
+
+    #[test]
+    fn detect_from_sample_majority_wins() {
+        // First line happened to be a JSON banner; the app actually logs logfmt
+        let sample: Vec<Vec<u8>> = vec![
+            br#"{"banner":"starting"}"#.to_vec(),
+            b"level=info msg=ready port=8080".to_vec(),
+            b"level=warn msg=slow took=3s".to_vec(),
+            b"level=info msg=done code=0".to_vec(),
+        ];
+        assert_eq!(
+            LogServiceImpl::detect_from_sample(&sample, &LogFormat::DETECTABLE),
+            LogFormat::Logfmt
+        );
+    }
+
+    #[test]
+    fn detect_from_sample_tie_falls_back_to_plain() {
+        let sample: Vec<Vec<u8>> = vec![
+            br#"{"level":"info"}"#.to_vec(),
+            b"Server listening".to_vec(),
+        ];
+        assert_eq!(
+            LogServiceImpl::detect_from_sample(&sample, &LogFormat::DETECTABLE),
+            LogFormat::PlainText
+        );
+        assert_eq!(LogServiceImpl::detect_from_sample(&[], &LogFormat::DETECTABLE), LogFormat::PlainText);
+    }
+
+    #[test]
+    fn detect_from_sample_covers_every_enabled_format() {
+        let syslog: Vec<Vec<u8>> = vec![
+            b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed".to_vec(),
+            br#"<165>1 2003-10-11T22:14:15.003Z host evntslog - ID47 [id@32473 iut="3" eventSource="App"] started"#.to_vec(),
+        ];
+        let access: Vec<Vec<u8>> = vec![
+            br#"127.0.0.1 - - [29/Jan/2026:10:59:12 +0000] "GET /search?q=a&page=2 HTTP/1.1" 200 512"#.to_vec(),
+            br#"10.0.0.7 - frank [29/Jan/2026:10:59:13 +0000] "POST /api/v1/data HTTP/1.1" 201 64"#.to_vec(),
+        ];
+        assert_eq!(LogServiceImpl::detect_from_sample(&syslog, &LogFormat::DETECTABLE), LogFormat::Syslog);
+        assert_eq!(LogServiceImpl::detect_from_sample(&access, &LogFormat::DETECTABLE), LogFormat::HttpLog);
+
+        // Dropped from enabled_formats: never detected
+        let json_and_logfmt = [LogFormat::Json, LogFormat::Logfmt];
+        assert_eq!(LogServiceImpl::detect_from_sample(&syslog[..1], &json_and_logfmt), LogFormat::PlainText);
+        assert_eq!(LogServiceImpl::detect_from_sample(&access[1..], &json_and_logfmt), LogFormat::PlainText);
+    }
+
+    #[test]
+    fn plain_text_misdetection_grows_the_sample() {
+        // A plain banner was the first line; the app logs JSON after it.
+        // Plain text parses every line, so only the fit check notices.
+        let tuner = crate::parser::metrics::DetectionTuner::new(crate::config::AdaptiveDetectionConfig {
+            enabled: true,
+            min_sample: 1,
+            max_sample: 8,
+            window: 10,
+            low_success_pct: 50,
+            high_success_pct: 95,
+        });
+        let mut tuning = tuner.track("c1");
+        let line = br#"{"level":"info","msg":"ok"}"#;
+        let resample = (0..10)
+            .filter_map(|_| tuning.record(LogServiceImpl::line_fits(LogFormat::PlainText, true, line, &LogFormat::DETECTABLE)))
+            .last();
+        assert_eq!(resample, Some(2));
+
+        // Real plain text, and structured lines that parse, fit
+        assert!(LogServiceImpl::line_fits(LogFormat::PlainText, true, b"Server listening on :8080", &LogFormat::DETECTABLE));
+        assert!(LogServiceImpl::line_fits(LogFormat::Json, true, line, &LogFormat::DETECTABLE));
+        assert!(!LogServiceImpl::line_fits(LogFormat::Json, false, b"Server listening", &LogFormat::DETECTABLE));
+        // JSON detection turned off: a JSON-looking line is plain text here
+        assert!(LogServiceImpl::line_fits(LogFormat::PlainText, true, line, &[LogFormat::Logfmt]));
+    }
+
+    #[test]
+    fn log_epoch_from_started_at() {
+        let mut info = ContainerInfo {
+            id: "c1".to_string(),
+            name: "app".to_string(),
+            image: "app:latest".to_string(),
+            state: "running".to_string(),
+            status: "Up".to_string(),
+            log_driver: None,
+            log_options: HashMap::new(),
+            labels: HashMap::new(),
+            created_at: 0,
+            ports: Vec::new(),
+            state_info: None,
+            log_path: None,
+            log_size_bytes: None,
+            command: None,
+        };
+        assert_eq!(LogServiceImpl::log_epoch(&info, Some(7)), None);
+
+        info.state_info = Some(crate::docker::inventory::ContainerStateInfo {
+            oom_killed: false,
+            pid: 1,
+            exit_code: 0,
+            started_at: "2024-01-01T00:00:00.5Z".to_string(),
+            finished_at: String::new(),
+            restart_count: 0,
+        });
+        assert_eq!(
+            LogServiceImpl::log_epoch(&info, Some(7)),
+            Some(LogEpoch { started_at: 1_704_067_200_500_000_000, first_line: Some(7) })
+        );
+    }
+
     #[test]
     fn resolve_disabled_container_returns_no_cache() {
         // When parsing is disabled for a container, cache returns None,
@@ -712,7 +1482,7 @@ mod tests {
         // But if it DID get called, cache.get_format returns None → falls to heuristic
         let format = LogServiceImpl::resolve_format(
             "c1", &HashMap::new(), &cache,
-            br#"{"level":"error"}"#, &metrics,
+            br#"{"level":"error"}"#, &LogFormat::DETECTABLE, &metrics,
         );
         // Since cache is disabled → get_format returns None → heuristic runs
         assert_eq!(format, LogFormat::Json, "Heuristic should detect JSON");
@@ -730,7 +1500,7 @@ mod tests {
 
         let format = LogServiceImpl::resolve_format(
             "c1", &labels, &cache,
-            b"plain text line", &metrics,
+            b"plain text line", &LogFormat::DETECTABLE, &metrics,
         );
         assert_eq!(format, LogFormat::Json, "Label always wins");
         // Cache should now be updated to JSON
@@ -744,7 +1514,7 @@ mod tests {
         let metrics = ParsingMetrics::new();
 
         let format = LogServiceImpl::resolve_format(
-            "c1", &HashMap::new(), &cache, b"", &metrics,
+            "c1", &HashMap::new(), &cache, b"", &LogFormat::DETECTABLE, &metrics,
         );
         assert_eq!(format, LogFormat::PlainText);
     }
@@ -757,15 +1527,15 @@ mod tests {
 
         LogServiceImpl::resolve_format(
             "json-app", &HashMap::new(), &cache,
-            br#"{"msg":"hello"}"#, &metrics,
+            br#"{"msg":"hello"}"#, &LogFormat::DETECTABLE, &metrics,
         );
         LogServiceImpl::resolve_format(
             "logfmt-app", &HashMap::new(), &cache,
-            b"level=info msg=hello", &metrics,
+            b"level=info msg=hello", &LogFormat::DETECTABLE, &metrics,
         );
         LogServiceImpl::resolve_format(
             "plain-app", &HashMap::new(), &cache,
-            b"Server started", &metrics,
+            b"Server started", &LogFormat::DETECTABLE, &metrics,
         );
 
         assert_eq!(cache.get_format("json-app"), Some(LogFormat::Json));
@@ -781,17 +1551,37 @@ mod tests {
         // First call → heuristic detects JSON and caches
         LogServiceImpl::resolve_format(
             "c1", &HashMap::new(), &cache,
-            br#"{"level":"info"}"#, &metrics,
+            br#"{"level":"info"}"#, &LogFormat::DETECTABLE, &metrics,
         );
 
         // Second call with a plain text line → should still return JSON (cached)
         let format = LogServiceImpl::resolve_format(
             "c1", &HashMap::new(), &cache,
-            b"this is plain text", &metrics,
+            b"this is plain text", &LogFormat::DETECTABLE, &metrics,
         );
         assert_eq!(format, LogFormat::Json, "Second call should use cache, not re-detect");
     }
 
+    #[test]
+    fn resolve_disabled_format_never_detected() {
+        let cache = ParserCache::new();
+        let metrics = ParsingMetrics::new();
+        let enabled = [LogFormat::Logfmt];
+
+        // Unmistakable JSON, but the JSON detector is switched off
+        let format = LogServiceImpl::resolve_format(
+            "c1", &HashMap::new(), &cache,
+            br#"{"level":"info","msg":"a=1 b=2"}"#, &enabled, &metrics,
+        );
+        assert_eq!(format, LogFormat::Logfmt, "JSON must not be tried when disabled");
+
+        let format = LogServiceImpl::resolve_format(
+            "c2", &HashMap::new(), &cache,
+            br#"{"level":"info"}"#, &[], &metrics,
+        );
+        assert_eq!(format, LogFormat::PlainText, "Plain text remains the fallback");
+    }
+
     // ─────────────────────────────────────────────────────────
     // Adversarial / Tricky Edge Cases
     // ─────────────────────────────────────────────────────────
@@ -822,9 +1612,10 @@ mod tests {
 
     #[test]
     fn detect_syslog_like_line() {
-        // Syslog is not detected by quick_detect (handled by label or PlainText parser)
+        // A <PRI> prefix is syslog, unless syslog is left out of enabled_formats
         let line = b"<134>Jan  1 00:00:00 myhost myapp[1234]: connection established";
-        assert_eq!(LogServiceImpl::quick_detect_format(line), LogFormat::PlainText);
+        assert_eq!(LogServiceImpl::quick_detect_format(line), LogFormat::Syslog);
+        assert_eq!(LogServiceImpl::quick_detect_format_with(line, &[LogFormat::Json]), LogFormat::PlainText);
     }
 
     #[test]
@@ -866,7 +1657,7 @@ mod tests {
         let mut labels = HashMap::new();
         labels.insert("docktail.log_format".to_string(), "json".to_string());
 
-        LogServiceImpl::resolve_format("c1", &labels, &cache, b"", &metrics);
+        LogServiceImpl::resolve_format("c1", &labels, &cache, b"", &LogFormat::DETECTABLE, &metrics);
 
         let snap = metrics.snapshot();
         assert_eq!(snap.detection_attempts, 1);
@@ -880,7 +1671,7 @@ mod tests {
 
         LogServiceImpl::resolve_format(
             "c1", &HashMap::new(), &cache,
-            br#"{"level":"info"}"#, &metrics,
+            br#"{"level":"info"}"#, &LogFormat::DETECTABLE, &metrics,
         );
 
         let snap = metrics.snapshot();
@@ -896,10 +1687,116 @@ mod tests {
 
         // This should hit cache — no new detection recorded
         LogServiceImpl::resolve_format(
-            "c1", &HashMap::new(), &cache, b"anything", &metrics,
+            "c1", &HashMap::new(), &cache, b"anything", &LogFormat::DETECTABLE, &metrics,
         );
 
         let snap = metrics.snapshot();
         assert_eq!(snap.detection_attempts, 0, "Cache hit should not record detection");
     }
+
+    #[tokio::test]
+    async fn filter_matches_deep_in_grouped_stack_trace() {
+        let line = |content: &str, sequence| NormalizedLogEntry {
+            container_id: "api".to_string(),
+            sequence,
+            raw_content: content.as_bytes().to_vec(),
+            line_count: 1,
+            ..Default::default()
+        };
+        let trace = [
+            "ERROR Failed to place order: java.lang.IllegalStateException: connection closed",
+            "    at com.shop.db.Pool.release(Pool.java:102)",
+            "    at com.shop.db.Pool.acquire(Pool.java:88)",
+            "    at com.shop.db.Session.open(Session.java:41)",
+            "    at com.shop.orders.OrderRepository.save(OrderRepository.java:57)",
+        ];
+        let mut grouper = MultilineGrouper::new(&crate::config::MultilineConfig::default());
+        let mut entries = Vec::new();
+        for (i, content) in trace.iter().chain(&["INFO GET /health 200"]).enumerate() {
+            entries.extend(grouper.process(line(content, i as u64)));
+        }
+        entries.extend(std::iter::from_fn(|| grouper.flush()));
+        assert_eq!(entries.len(), 2);
+
+        let filter = FilterEngine::new("OrderRepository", true, FilterMode::Include).unwrap();
+        let kept: Vec<_> = filter_groups(
+            tokio_stream::iter(entries.into_iter().map(Ok)),
+            Arc::new(LiveFilter::new(Some(filter))),
+        )
+        .collect::<Vec<_>>()
+        .await;
+
+        // Matched on line 5, emitted whole
+        assert_eq!(kept.len(), 1);
+        let group = kept[0].as_ref().unwrap();
+        assert_eq!(group.raw_content, trace[0].as_bytes());
+        assert_eq!(group.line_count, 5);
+        let continuations: Vec<_> = group.grouped_lines.iter().map(|l| l.content.as_slice()).collect();
+        assert_eq!(continuations, trace[1..].iter().map(|l| l.as_bytes()).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn filter_cannot_match_redacted_secret() {
+        let redactor = Redactor::new(&["hunter2-prod".to_string()], Vec::new()).unwrap();
+        let entries: Vec<_> = [
+            "connecting to postgres://app:hunter2-prod@db:5432/orders",
+            "INFO GET /health 200",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, content)| NormalizedLogEntry {
+            container_id: "api".to_string(),
+            sequence: i as u64,
+            raw_content: redactor.redact(content.as_bytes()).into_owned(),
+            line_count: 1,
+            ..Default::default()
+        })
+        .collect();
+        let filtered = |pattern: &str| {
+            let filter = FilterEngine::new(pattern, true, FilterMode::Include).unwrap();
+            filter_groups(tokio_stream::iter(entries.clone().into_iter().map(Ok)), Arc::new(LiveFilter::new(Some(filter))))
+                .collect::<Vec<_>>()
+        };
+
+        // Probing for the secret finds nothing
+        assert!(filtered("hunter2").await.is_empty());
+
+        // Other text on the line still matches, masked
+        let kept = filtered("postgres").await;
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].as_ref().unwrap().raw_content, b"connecting to postgres://app:***@db:5432/orders");
+    }
+
+    /// Stands in for a parser with a bug on some malformed input
+    struct PanickingParser;
+
+    impl LogParser for PanickingParser {
+        fn parse(&self, raw: &[u8]) -> Result<ParsedLog, ParseError> {
+            if raw.starts_with(b"<") {
+                panic!("index out of bounds in priority field");
+            }
+            PlainTextParser.parse(raw)
+        }
+
+        fn format(&self) -> LogFormat {
+            LogFormat::Syslog
+        }
+    }
+
+    #[test]
+    fn parser_panic_degrades_line_to_plain_text() {
+        let metrics = ParsingMetrics::new();
+        let parser: Box<dyn LogParser> = Box::new(PanickingParser);
+
+        let e = parse_guarded(parser.as_ref(), b"<34 truncated").unwrap_err();
+        assert!(matches!(e, ParseError::ParserPanic(ref msg) if msg.contains("priority field")));
+        let metadata = LogServiceImpl::failed_parse(&e, LogFormat::Syslog, Duration::ZERO, &metrics);
+        assert_eq!(metadata.detected_format, ProtoLogFormat::PlainText as i32);
+        assert!(!metadata.parse_success);
+        assert_eq!(metrics.snapshot().parse_panics, 1);
+
+        // The next line is parsed as usual
+        assert!(parse_guarded(parser.as_ref(), b"next line").is_ok());
+        assert_eq!(metrics.snapshot().parse_panics, 1);
+    }
 }
@@ -3,8 +3,23 @@ pub mod inventory;
 pub mod health;
 pub mod stats;
 pub mod multiline;
+pub mod multiline_json;
 pub mod background;
+pub mod admission;
+pub mod content_hash;
+pub mod color_group;
+pub mod repeats;
+pub mod line_dedup;
+pub mod heartbeat;
+pub mod emit_buffer;
+pub mod backlog;
+pub mod freeze;
+pub mod resources;
+pub mod reload;
+pub mod rate;
+pub mod stop;
 
+#[allow(clippy::enum_variant_names)]
 pub mod proto {
     tonic::include_proto!("docktail.agent");
 }
@@ -20,3 +35,20 @@ pub use logs::LogServiceImpl;
 pub use inventory::InventoryServiceImpl;
 pub use health::HealthServiceImpl;
 pub use stats::StatsServiceImpl;
+
+/// gRPC metadata key with the seconds a client should wait before retrying
+pub const RETRY_AFTER_METADATA: &str = "retry-after";
+
+/// A Docker rate limit as RESOURCE_EXHAUSTED carrying `retry-after`, which
+/// tells it apart from stream admission rejections
+pub fn rate_limited_status(message: &str, retry_after: std::time::Duration) -> tonic::Status {
+    let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let mut status = tonic::Status::resource_exhausted(format!(
+        "Docker API is rate limiting this agent; retry after {}s ({})",
+        secs, message
+    ));
+    if let Ok(value) = secs.to_string().parse() {
+        status.metadata_mut().insert(RETRY_AFTER_METADATA, value);
+    }
+    status
+}
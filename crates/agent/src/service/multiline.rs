@@ -1,5 +1,5 @@
 use super::proto::NormalizedLogEntry;
-use crate::config::MultilineConfig;
+use crate::config::{MultilineConfig, MultilineProfile};
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
@@ -27,6 +27,8 @@ pub struct MultilineGrouper {
     last_update: Option<Instant>,
     max_lines: usize,
     require_error_anchor: bool,
+    /// Runtime-specific rules checked before the generic heuristics
+    profile: Option<MultilineProfile>,
     /// When true, all entries pass through ungrouped (JSON, logfmt, etc.)
     passthrough: bool,
 }
@@ -40,6 +42,7 @@ impl MultilineGrouper {
             last_update: None,
             max_lines: config.max_lines,
             require_error_anchor: config.require_error_anchor,
+            profile: config.profile,
             passthrough: false,
         }
     }
@@ -54,6 +57,7 @@ impl MultilineGrouper {
             last_update: None,
             max_lines: 0,
             require_error_anchor: false,
+            profile: None,
             passthrough: true,
         }
     }
@@ -130,12 +134,19 @@ impl MultilineGrouper {
                 );
                 GroupAction::FlushAndStartNew
             } else {
-                let pattern = is_continuation_line(
-                    content,
-                    &group.primary.raw_content,
-                    group.primary.log_level,
-                    self.require_error_anchor,
-                );
+                let pattern = self
+                    .profile
+                    .and_then(|profile| {
+                        is_profile_continuation(profile, content, &group.primary.raw_content)
+                    })
+                    .or_else(|| {
+                        is_continuation_line(
+                            content,
+                            &group.primary.raw_content,
+                            group.primary.log_level,
+                            self.require_error_anchor,
+                        )
+                    });
 
                 if let Some(ref p) = pattern {
                     tracing::trace!(pattern = ?p, "multiline: continuation detected");
@@ -246,8 +257,10 @@ impl LogGroup {
 
     fn into_entry(self) -> NormalizedLogEntry {
         let continuation_count = self.continuations.len();
-        // Safe u32 conversion — cap at u32::MAX instead of silent wrapping
-        let line_count = u32::try_from(1 + continuation_count).unwrap_or(u32::MAX);
+        // Safe u32 conversion — cap at u32::MAX instead of silent wrapping.
+        // The primary may already span several lines (reassembled JSON).
+        let line_count = self.primary.line_count.max(1)
+            .saturating_add(u32::try_from(continuation_count).unwrap_or(u32::MAX));
         let is_grouped = !self.continuations.is_empty();
         
         // Convert LogLine to proto LogLine
@@ -272,6 +285,16 @@ impl LogGroup {
             raw_content: self.primary.raw_content,
             parsed: self.primary.parsed,
             metadata: self.primary.metadata,
+            // Hashed after grouping, over the whole group, when requested
+            content_hash: None,
+            repeat_count: 0,
+            schema_valid: self.primary.schema_valid,
+            schema_errors: self.primary.schema_errors,
+            heartbeat: false,
+            backlog_unavailable: false,
+            container_stopped: false,
+            exit_code: None,
+            color_group: None,
         }
     }
 }
@@ -374,6 +397,106 @@ fn is_continuation_line(
     None
 }
 
+/// Runtime-specific continuation rules for containers that opted into a
+/// profile. These match trace lines the generic heuristics can't safely
+/// assume, such as Python's trailing exception line or Go's function frames.
+fn is_profile_continuation(
+    profile: MultilineProfile,
+    current: &[u8],
+    previous: &[u8],
+) -> Option<ContinuationPattern> {
+    if current.is_empty() {
+        // Go separates `panic:` from the goroutine dump with a blank line
+        if profile == MultilineProfile::Go && previous.starts_with(b"panic: ") {
+            return Some(ContinuationPattern::ContinueToken);
+        }
+        return None;
+    }
+
+    let trimmed = current.trim_ascii_start();
+    let is_indented = trimmed.len() < current.len();
+
+    match profile {
+        MultilineProfile::Java => {
+            if is_indented && (trimmed.starts_with(b"at ") || is_java_more_marker(trimmed)) {
+                return Some(ContinuationPattern::StackFrame);
+            }
+            if starts_with_any(trimmed, &[b"Caused by:", b"Suppressed:"]) {
+                return Some(ContinuationPattern::StackFrame);
+            }
+        }
+        MultilineProfile::Python => {
+            if starts_with_any(current, &[b"Traceback (most recent call last):", b"  File \""]) {
+                return Some(ContinuationPattern::StackFrame);
+            }
+            if starts_with_any(
+                current,
+                &[
+                    b"During handling of the above exception",
+                    b"The above exception was the direct cause",
+                ],
+            ) {
+                return Some(ContinuationPattern::ContinueToken);
+            }
+            // Source lines and carets under a `File "..."` frame
+            if current.starts_with(b"    ") {
+                return Some(ContinuationPattern::StackFrame);
+            }
+            if is_python_exception_line(current) {
+                return Some(ContinuationPattern::StackFrame);
+            }
+        }
+        MultilineProfile::Go => {
+            if current.starts_with(b"goroutine ") || current.starts_with(b"[signal ") {
+                return Some(ContinuationPattern::StackFrame);
+            }
+            // `\t/src/app/main.go:12 +0x1d` file/line frames
+            if current.starts_with(b"\t") {
+                return Some(ContinuationPattern::StackFrame);
+            }
+            if current.starts_with(b"created by ") || is_go_function_frame(current) {
+                return Some(ContinuationPattern::StackFrame);
+            }
+        }
+    }
+
+    None
+}
+
+/// `... 12 more` trailer of a truncated Java cause chain
+fn is_java_more_marker(trimmed: &[u8]) -> bool {
+    trimmed.starts_with(b"... ") && trimmed.ends_with(b" more")
+}
+
+/// Final line of a Python traceback, e.g. `ValueError: bad input` or
+/// `requests.exceptions.ConnectionError: ...`
+fn is_python_exception_line(line: &[u8]) -> bool {
+    let name_end = line
+        .iter()
+        .position(|&b| b == b':')
+        .unwrap_or(line.len());
+    let name = &line[..name_end];
+
+    !name.is_empty()
+        && name[0].is_ascii_alphabetic()
+        && name.iter().all(|&b| b.is_ascii_alphanumeric() || b == b'_' || b == b'.')
+        && [&b"Error"[..], b"Exception", b"Warning", b"Exit", b"Interrupt"]
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+/// Go stack function frame, e.g. `main.handler(0xc000010000, 0x1)` or
+/// `net/http.(*conn).serve(...)`
+fn is_go_function_frame(line: &[u8]) -> bool {
+    let Some(paren) = line.iter().position(|&b| b == b'(') else {
+        return false;
+    };
+    let func = &line[..paren];
+
+    line.ends_with(b")")
+        && func.contains(&b'.')
+        && !func.iter().any(|b| b.is_ascii_whitespace())
+}
 
 fn starts_with_any(haystack: &[u8], needles: &[&[u8]]) -> bool {
     needles.iter().any(|n| haystack.starts_with(n))
@@ -644,6 +767,15 @@ mod tests {
             grouped_lines: Vec::new(),
             line_count: 1,
             is_grouped: false,
+            content_hash: None,
+            repeat_count: 0,
+            schema_valid: None,
+            schema_errors: Vec::new(),
+            heartbeat: false,
+            backlog_unavailable: false,
+            container_stopped: false,
+            exit_code: None,
+            color_group: None,
         }
     }
 
@@ -653,6 +785,10 @@ mod tests {
             timeout_ms: 300,
             max_lines: 50,
             require_error_anchor: true,
+            profile: None,
+            json: true,
+            json_max_lines: 500,
+            json_max_bytes: 256 * 1024,
             container_overrides: std::collections::HashMap::new(),
         }
     }
@@ -1436,4 +1572,88 @@ mod tests {
         assert!(!f2.is_grouped);
         assert!(!f3.is_grouped);
     }
+
+    // ─── Label-selected profiles ────────────────────────────────
+
+    fn labeled_grouper(profile: &str) -> MultilineGrouper {
+        let mut labels = std::collections::HashMap::new();
+        labels.insert("docktail.multiline".to_string(), profile.to_string());
+        let config = default_test_config().for_container("app", &labels);
+        MultilineGrouper::new(&config)
+    }
+
+    fn group_all(grouper: &mut MultilineGrouper, lines: &[&[u8]]) -> Vec<NormalizedLogEntry> {
+        let mut outputs = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            outputs.extend(grouper.process(create_entry(line, 0, i as u64)));
+        }
+        outputs.extend(grouper.flush());
+        outputs
+    }
+
+    #[test]
+    fn test_python_label_groups_exception_line() {
+        let lines: &[&[u8]] = &[
+            b"Traceback (most recent call last):",
+            b"  File \"/app/main.py\", line 3, in <module>",
+            b"    int(\"x\")",
+            b"ValueError: invalid literal for int() with base 10: 'x'",
+        ];
+
+        let outputs = group_all(&mut labeled_grouper("python"), lines);
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].line_count, 4);
+
+        // Without the label the trailing exception line starts a new entry
+        let outputs = group_all(&mut MultilineGrouper::new(&default_test_config()), lines);
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[1].raw_content, lines[3]);
+    }
+
+    #[test]
+    fn test_go_label_groups_panic_dump() {
+        let lines: &[&[u8]] = &[
+            b"panic: runtime error: index out of range [3] with length 2",
+            b"",
+            b"goroutine 1 [running]:",
+            b"main.lookup(...)",
+            b"\t/src/app/main.go:12 +0x1d",
+            b"main.main()",
+            b"\t/src/app/main.go:7 +0x25",
+            b"exit status 2",
+        ];
+
+        let outputs = group_all(&mut labeled_grouper("go"), lines);
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].line_count, 7);
+        assert_eq!(outputs[1].raw_content, b"exit status 2");
+    }
+
+    #[test]
+    fn test_java_label_groups_cause_chain() {
+        let lines: &[&[u8]] = &[
+            b"Exception in thread \"main\" java.lang.IllegalStateException: boom",
+            b"    at com.example.App.run(App.java:10)",
+            b"Caused by: java.io.IOException: closed",
+            b"    at com.example.Io.read(Io.java:42)",
+            b"    ... 3 more",
+        ];
+
+        let outputs = group_all(&mut labeled_grouper("java"), lines);
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].line_count, 5);
+    }
+
+    #[test]
+    fn test_profile_still_splits_on_new_log_level() {
+        let lines: &[&[u8]] = &[
+            b"Traceback (most recent call last):",
+            b"KeyError: 'id'",
+            b"INFO request finished",
+        ];
+
+        let outputs = group_all(&mut labeled_grouper("python"), lines);
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].line_count, 2);
+    }
 }
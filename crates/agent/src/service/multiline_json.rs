@@ -0,0 +1,323 @@
+//! Reassembly of pretty-printed JSON objects that span several log lines.
+//!
+//! A line opening an object (`{`) whose braces don't balance starts a
+//! document; following lines from the same stream are collected until the
+//! braces close, then the whole document is handed on as a single line for
+//! format detection and parsing. One-object-per-line output never starts a
+//! document, so ordinary JSON logs pass straight through.
+//!
+//! Anything that doesn't turn out to be one valid object — a size or line
+//! limit hit, a timeout, trailing data after the closing brace, or invalid
+//! JSON — is released as the original individual lines.
+
+use crate::config::MultilineConfig;
+use crate::docker::stream::LogLevel;
+use std::time::{Duration, Instant};
+
+/// A decoded log line on its way to parsing
+#[derive(Debug, Clone)]
+pub struct RawLine {
+    pub content: Vec<u8>,
+    pub timestamp: i64,
+    pub stream_type: LogLevel,
+    pub sequence: u64,
+    /// Source lines this line was assembled from (1 for an ordinary line)
+    pub line_count: u32,
+}
+
+impl RawLine {
+    pub fn new(content: Vec<u8>, timestamp: i64, stream_type: LogLevel, sequence: u64) -> Self {
+        Self { content, timestamp, stream_type, sequence, line_count: 1 }
+    }
+}
+
+pub struct JsonAssembler {
+    pending: Option<PendingDocument>,
+    max_lines: usize,
+    max_bytes: usize,
+    timeout: Duration,
+}
+
+struct PendingDocument {
+    lines: Vec<RawLine>,
+    bytes: usize,
+    scanner: BraceScanner,
+    started: Instant,
+}
+
+impl JsonAssembler {
+    pub fn new(config: &MultilineConfig) -> Self {
+        Self {
+            pending: None,
+            max_lines: config.json_max_lines,
+            max_bytes: config.json_max_bytes,
+            timeout: Duration::from_millis(config.timeout_ms),
+        }
+    }
+
+    /// Feed a line. Returns the lines ready to be parsed, in order.
+    pub fn push(&mut self, line: RawLine) -> Vec<RawLine> {
+        let Some(mut doc) = self.pending.take() else {
+            return self.start(line);
+        };
+
+        // Lines from the other stream aren't part of the document
+        if line.stream_type != doc.lines[0].stream_type {
+            self.pending = Some(doc);
+            return vec![line];
+        }
+
+        let scan = doc.scanner.feed(&line.content);
+        doc.bytes += line.content.len() + 1;
+        doc.lines.push(line);
+
+        match scan {
+            Scan::Open if doc.lines.len() >= self.max_lines || doc.bytes > self.max_bytes => {
+                tracing::debug!(lines = doc.lines.len(), bytes = doc.bytes, "multiline json: limit reached");
+                doc.lines
+            }
+            Scan::Open => {
+                self.pending = Some(doc);
+                Vec::new()
+            }
+            Scan::Closed => doc.assemble(),
+            // `} {` — a stream of objects, not one document
+            Scan::Trailing => doc.lines,
+        }
+    }
+
+    /// Release a document that has been open longer than the timeout as
+    /// plain lines. Call periodically so an unterminated object isn't held.
+    pub fn check_timeout(&mut self) -> Vec<RawLine> {
+        match self.pending {
+            Some(ref doc) if doc.started.elapsed() > self.timeout => self.flush(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Release any open document as plain lines (call at stream end).
+    pub fn flush(&mut self) -> Vec<RawLine> {
+        self.pending.take().map(|doc| doc.lines).unwrap_or_default()
+    }
+
+    fn start(&mut self, line: RawLine) -> Vec<RawLine> {
+        if !line.content.trim_ascii_start().starts_with(b"{") {
+            return vec![line];
+        }
+
+        let mut scanner = BraceScanner::default();
+        if scanner.feed(&line.content) != Scan::Open || self.max_lines < 2 {
+            return vec![line];
+        }
+
+        self.pending = Some(PendingDocument {
+            bytes: line.content.len(),
+            lines: vec![line],
+            scanner,
+            started: Instant::now(),
+        });
+        Vec::new()
+    }
+}
+
+impl PendingDocument {
+    /// Join the lines into one, or give them back if the result isn't JSON
+    fn assemble(self) -> Vec<RawLine> {
+        let mut content = Vec::with_capacity(self.bytes);
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                content.push(b'\n');
+            }
+            content.extend_from_slice(line.content.trim_ascii_end());
+        }
+
+        if serde_json::from_slice::<serde::de::IgnoredAny>(&content).is_err() {
+            tracing::debug!(lines = self.lines.len(), "multiline json: braces balanced but not valid JSON");
+            return self.lines;
+        }
+
+        let first = &self.lines[0];
+        vec![RawLine {
+            content,
+            timestamp: first.timestamp,
+            stream_type: first.stream_type,
+            sequence: first.sequence,
+            line_count: u32::try_from(self.lines.len()).unwrap_or(u32::MAX),
+        }]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Scan {
+    /// Braces still open
+    Open,
+    /// Closed at the end of the line
+    Closed,
+    /// Closed with more data after it on the same line
+    Trailing,
+}
+
+/// Tracks brace depth across lines, ignoring braces inside strings
+#[derive(Default)]
+struct BraceScanner {
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl BraceScanner {
+    fn feed(&mut self, line: &[u8]) -> Scan {
+        for (i, &b) in line.iter().enumerate() {
+            if self.in_string {
+                match b {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match b {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 0 {
+                        return if line[i + 1..].trim_ascii().is_empty() {
+                            Scan::Closed
+                        } else {
+                            Scan::Trailing
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+        Scan::Open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::formats::JsonParser;
+    use crate::parser::LogParser;
+
+    fn config() -> MultilineConfig {
+        MultilineConfig {
+            timeout_ms: 60_000,
+            ..MultilineConfig::default()
+        }
+    }
+
+    fn line(content: &str, sequence: u64) -> RawLine {
+        RawLine::new(content.as_bytes().to_vec(), sequence as i64 * 1_000, LogLevel::Stdout, sequence)
+    }
+
+    fn push_all(assembler: &mut JsonAssembler, lines: &[&str]) -> Vec<RawLine> {
+        lines
+            .iter()
+            .enumerate()
+            .flat_map(|(i, l)| assembler.push(line(l, i as u64)))
+            .collect()
+    }
+
+    #[test]
+    fn test_pretty_printed_object_assembled() {
+        let mut assembler = JsonAssembler::new(&config());
+        let out = push_all(&mut assembler, &[
+            "{",
+            r#"  "level": "error","#,
+            r#"  "msg": "payment failed {retrying}","#,
+            r#"  "ctx": {"order": 42}"#,
+            "}",
+        ]);
+
+        assert_eq!(out.len(), 1);
+        let doc = &out[0];
+        assert_eq!(doc.line_count, 5);
+        assert_eq!(doc.sequence, 0, "first line's position is kept");
+
+        // The assembled document goes through the regular JSON parser
+        let parsed = JsonParser::new().parse(&doc.content).unwrap();
+        assert_eq!(parsed.level.as_deref(), Some("error"));
+        assert_eq!(parsed.message.as_deref(), Some("payment failed {retrying}"));
+        assert!(assembler.flush().is_empty());
+    }
+
+    #[test]
+    fn test_single_line_objects_pass_through() {
+        let mut assembler = JsonAssembler::new(&config());
+        let out = push_all(&mut assembler, &[
+            r#"{"level":"info","msg":"a"}"#,
+            r#"{"level":"info","msg":"b"} {"level":"info","msg":"c"}"#,
+            "plain text",
+        ]);
+        assert_eq!(out.len(), 3);
+        assert!(out.iter().all(|l| l.line_count == 1));
+    }
+
+    #[test]
+    fn test_object_stream_not_merged() {
+        let mut assembler = JsonAssembler::new(&config());
+        let out = push_all(&mut assembler, &[
+            "{",
+            r#"  "msg": "a""#,
+            r#"} {"msg": "b"}"#,
+        ]);
+        assert_eq!(out.len(), 3);
+        assert!(out.iter().all(|l| l.line_count == 1));
+    }
+
+    #[test]
+    fn test_malformed_object_times_out_to_plain_text() {
+        let mut assembler = JsonAssembler::new(&MultilineConfig {
+            timeout_ms: 0,
+            ..MultilineConfig::default()
+        });
+        let out = push_all(&mut assembler, &["{", r#"  "msg": "never closed","#, "  oops"]);
+        assert!(out.is_empty());
+
+        std::thread::sleep(Duration::from_millis(2));
+        let released = assembler.check_timeout();
+        assert_eq!(released.len(), 3);
+        assert_eq!(released[1].content, br#"  "msg": "never closed","#);
+        assert!(released.iter().all(|l| l.line_count == 1));
+    }
+
+    #[test]
+    fn test_balanced_but_invalid_released_as_lines() {
+        let mut assembler = JsonAssembler::new(&config());
+        let out = push_all(&mut assembler, &["{", "  not json", "}"]);
+        assert_eq!(out.len(), 3);
+    }
+
+    #[test]
+    fn test_line_limit_releases_lines() {
+        let mut assembler = JsonAssembler::new(&MultilineConfig {
+            json_max_lines: 3,
+            ..config()
+        });
+        let out = push_all(&mut assembler, &["{", r#"  "a": 1,"#, r#"  "b": 2,"#]);
+        assert_eq!(out.len(), 3);
+
+        // The limit only affects the document that hit it
+        let out = push_all(&mut assembler, &["{", r#"  "a": 1"#, "}"]);
+        assert_eq!(out.len(), 1);
+    }
+
+    #[test]
+    fn test_other_stream_not_absorbed() {
+        let mut assembler = JsonAssembler::new(&config());
+        assert!(assembler.push(line("{", 0)).is_empty());
+
+        let mut stderr = line("warning: something", 1);
+        stderr.stream_type = LogLevel::Stderr;
+        assert_eq!(assembler.push(stderr).len(), 1);
+
+        let out = assembler.push(line("}", 2));
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].line_count, 2);
+    }
+}
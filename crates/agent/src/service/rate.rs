@@ -0,0 +1,129 @@
+//! Log throughput histogram: line and byte counts per wall-clock bucket,
+//! tallied on a container's live log stream without sending the lines.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_stream::{Stream, StreamExt};
+
+/// Longest bucket a client may ask for
+pub const MAX_RATE_BUCKET_SECS: u32 = 3600;
+
+/// Lines and bytes logged in one bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateBucket {
+    /// Unix seconds, a multiple of the bucket size
+    pub start: i64,
+    pub lines: u64,
+    pub bytes: u64,
+}
+
+/// Tally line sizes from `lines` into buckets. Each item of `ticks` closes
+/// the open bucket and starts the one beginning at that time; ticks are
+/// checked first so a busy log can't hold a bucket open. Buckets with no
+/// lines are still sent. Ends with `lines`.
+pub fn tally<L, T>(lines: L, ticks: T, first_start: i64) -> impl Stream<Item = RateBucket>
+where
+    L: Stream<Item = usize> + Unpin,
+    T: Stream<Item = i64> + Unpin,
+{
+    let open = RateBucket { start: first_start, lines: 0, bytes: 0 };
+    futures_util::stream::unfold((lines, ticks, open), |(mut lines, mut ticks, mut open)| async move {
+        loop {
+            tokio::select! {
+                biased;
+                Some(start) = ticks.next() => {
+                    let closed = open;
+                    open = RateBucket { start, lines: 0, bytes: 0 };
+                    return Some((closed, (lines, ticks, open)));
+                }
+                line = lines.next() => match line {
+                    Some(bytes) => {
+                        open.lines += 1;
+                        open.bytes += bytes as u64;
+                    }
+                    None => return None,
+                },
+            }
+        }
+    })
+}
+
+/// Start of the bucket `now` falls in, and how long until the next one
+pub fn bucket_position(now: Duration, bucket_secs: u32) -> (i64, Duration) {
+    let bucket = Duration::from_secs(bucket_secs.into());
+    let into_bucket = Duration::from_nanos((now.as_nanos() % bucket.as_nanos()) as u64);
+    let start = (now - into_bucket).as_secs() as i64;
+    (start, bucket - into_bucket)
+}
+
+/// Bucket boundaries on the wall clock: each item is the Unix second a new
+/// bucket starts. Returns the start of the current (first) bucket too.
+pub fn wall_clock_ticks(bucket_secs: u32) -> (i64, impl Stream<Item = i64>) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (first_start, until_next) = bucket_position(now, bucket_secs);
+    let period = Duration::from_secs(bucket_secs.into());
+    let interval = tokio::time::interval_at(tokio::time::Instant::now() + until_next, period);
+    let mut elapsed = 0;
+    let ticks = tokio_stream::wrappers::IntervalStream::new(interval).map(move |_| {
+        elapsed += 1;
+        first_start + elapsed * i64::from(bucket_secs)
+    });
+    (first_start, ticks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+
+    #[test]
+    fn test_buckets_align_to_wall_clock() {
+        let (start, until_next) = bucket_position(Duration::from_millis(1_700_000_007_250), 10);
+        assert_eq!(start, 1_700_000_000);
+        assert_eq!(until_next, Duration::from_millis(2_750));
+
+        let (start, until_next) = bucket_position(Duration::from_secs(1_700_000_010), 10);
+        assert_eq!(start, 1_700_000_010);
+        assert_eq!(until_next, Duration::from_secs(10));
+    }
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    async fn test_counts_per_bucket() {
+        let (line_tx, line_rx) = mpsc::unbounded_channel();
+        let (tick_tx, tick_rx) = mpsc::unbounded_channel();
+        let buckets = tally(UnboundedReceiverStream::new(line_rx), UnboundedReceiverStream::new(tick_rx), 1_000);
+        let (bucket_tx, mut bucket_rx) = mpsc::unbounded_channel();
+        let collector = tokio::spawn(async move {
+            let mut buckets = Box::pin(buckets);
+            while let Some(bucket) = buckets.next().await {
+                bucket_tx.send(bucket).unwrap();
+            }
+        });
+
+        // 50 lines of 100 bytes in the first 10s, then a quiet bucket, then 20 of 40
+        for _ in 0..50 {
+            line_tx.send(100).unwrap();
+        }
+        settle().await;
+        tick_tx.send(1_010).unwrap();
+        assert_eq!(bucket_rx.recv().await, Some(RateBucket { start: 1_000, lines: 50, bytes: 5_000 }));
+
+        tick_tx.send(1_020).unwrap();
+        assert_eq!(bucket_rx.recv().await, Some(RateBucket { start: 1_010, lines: 0, bytes: 0 }));
+
+        for _ in 0..20 {
+            line_tx.send(40).unwrap();
+        }
+        settle().await;
+        tick_tx.send(1_030).unwrap();
+        assert_eq!(bucket_rx.recv().await, Some(RateBucket { start: 1_020, lines: 20, bytes: 800 }));
+
+        drop(line_tx);
+        collector.await.unwrap();
+        assert_eq!(bucket_rx.recv().await, None);
+    }
+}
@@ -0,0 +1,173 @@
+//! Configuration reload without a restart.
+//!
+//! On SIGHUP the agent reads its configuration again, from the same file and
+//! environment as at startup. Settings a stream reads when it opens (multiline
+//! rules, redaction, encodings, enabled formats, ...) apply to streams opened
+//! afterwards; running streams keep what they started with. Settings wired up
+//! at startup keep their old values and are reported as needing a restart.
+
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::config::AgentConfig;
+use crate::state::{AgentState, SharedState};
+
+/// Changed settings, by whether the reload applied them
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ConfigReload {
+    pub applied: Vec<&'static str>,
+    pub restart_required: Vec<&'static str>,
+}
+
+fn changed<T: Serialize>(old: &T, new: &T) -> bool {
+    serde_json::to_value(old).ok() != serde_json::to_value(new).ok()
+}
+
+/// Validate `new` and make its reloadable settings current. On error the
+/// configuration in effect is left as it was.
+pub fn reload_config(state: &AgentState, new: AgentConfig) -> Result<ConfigReload, String> {
+    new.validate_values()?;
+
+    let old = state.config();
+    let mut merged = new;
+    let mut reload = ConfigReload::default();
+
+    macro_rules! applied {
+        ($($field:ident),* $(,)?) => {$(
+            if changed(&old.$field, &merged.$field) {
+                reload.applied.push(stringify!($field));
+            }
+        )*};
+    }
+    macro_rules! restart_required {
+        ($($field:ident),* $(,)?) => {$(
+            if changed(&old.$field, &merged.$field) {
+                reload.restart_required.push(stringify!($field));
+                merged.$field = old.$field.clone();
+            }
+        )*};
+    }
+
+    applied!(
+        multiline,
+        redaction,
+        redetect_on_log_reset,
+        fallback_encoding,
+        enabled_formats,
+        allow_freeze_inspect,
+        freeze_capture_timeout_ms,
+        allow_resource_updates,
+    );
+    restart_required!(
+        bind_address,
+        tls_cert_path,
+        tls_key_path,
+        tls_ca_path,
+        tls,
+        docker_socket,
+        max_concurrent_streams,
+        audit_log_path,
+        stream_qos,
+        adaptive_detection,
+        format_lock,
+        docker_reconnect,
+        stream_buffers,
+        reverse,
+        inventory_sync_interval_secs,
+        log_schema_path,
+    );
+
+    if !reload.applied.is_empty() {
+        state.set_config(merged);
+    }
+    Ok(reload)
+}
+
+/// Reload the configuration each time the agent receives SIGHUP
+#[cfg(unix)]
+pub async fn reload_on_sighup(state: SharedState) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            warn!("Failed to install SIGHUP handler, config reload disabled: {}", e);
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        info!("Received SIGHUP, reloading configuration...");
+        let config = match AgentConfig::load() {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Config reload failed, keeping the current configuration: {}", e);
+                continue;
+            }
+        };
+        match reload_config(&state, config) {
+            Ok(reload) => {
+                if reload.applied.is_empty() && reload.restart_required.is_empty() {
+                    info!("Config reload: no changes");
+                }
+                if !reload.applied.is_empty() {
+                    info!("Config reload applied to new streams: {}", reload.applied.join(", "));
+                }
+                if !reload.restart_required.is_empty() {
+                    warn!("Config reload: changes to {} need a restart and were not applied", reload.restart_required.join(", "));
+                }
+            }
+            Err(e) => error!("Invalid configuration, keeping the current one: {}", e),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn reload_on_sighup(_state: SharedState) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::client::DockerClient;
+    use std::collections::HashMap;
+
+    /// State whose Docker client is never called
+    fn state(name: &str, config: AgentConfig) -> AgentState {
+        let socket = std::env::temp_dir().join(format!("docktail-reload-{}-{}.sock", name, std::process::id()));
+        std::fs::File::create(&socket).unwrap();
+        let docker = DockerClient::new(socket.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&socket);
+        AgentState::new(docker, config)
+    }
+
+    #[test]
+    fn test_reloaded_multiline_config_used_by_new_streams() {
+        let state = state("multiline", AgentConfig::default());
+        let mut new = AgentConfig::default();
+        new.multiline.max_lines = 7;
+        new.multiline.timeout_ms = 900;
+        new.max_concurrent_streams += 10;
+
+        let reload = reload_config(&state, new).unwrap();
+        assert_eq!(reload.applied, vec!["multiline"]);
+        assert_eq!(reload.restart_required, vec!["max_concurrent_streams"]);
+
+        // What a stream opened now gets
+        let config = state.config();
+        let for_stream = config.multiline.for_container("web", &HashMap::new());
+        assert_eq!(for_stream.max_lines, 7);
+        assert_eq!(for_stream.timeout_ms, 900);
+        assert_eq!(config.max_concurrent_streams, AgentConfig::default().max_concurrent_streams);
+    }
+
+    #[test]
+    fn test_invalid_config_keeps_current_one() {
+        let state = state("invalid", AgentConfig::default());
+        let mut new = AgentConfig::default();
+        new.multiline.enabled = true;
+        new.multiline.max_lines = 0;
+
+        assert!(reload_config(&state, new).is_err());
+        assert_eq!(state.config().multiline.max_lines, AgentConfig::default().multiline.max_lines);
+    }
+}
@@ -0,0 +1,182 @@
+use super::content_hash::content_hash;
+use super::proto::NormalizedLogEntry;
+use std::time::{Duration, Instant};
+use tokio_stream::{Stream, StreamExt};
+use tonic::Status;
+
+/// How long a run of identical lines is held before its count is emitted.
+/// Also bounds how stale an endless retry loop's count can get.
+pub const REPEAT_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Collapses consecutive identical log entries into a single entry carrying
+/// a `repeat_count` ("last message repeated N times").
+///
+/// Entries are compared by content hash and stream (stdout/stderr). The first
+/// entry of a run is kept, so the emitted entry has the timestamp and sequence
+/// of the first occurrence.
+pub struct RepeatCollapser {
+    pending: Option<PendingRun>,
+    timeout: Duration,
+}
+
+struct PendingRun {
+    entry: NormalizedLogEntry,
+    key: (u64, i32),
+    count: u32,
+    started: Instant,
+}
+
+impl PendingRun {
+    fn into_entry(self) -> NormalizedLogEntry {
+        let mut entry = self.entry;
+        entry.repeat_count = self.count;
+        entry
+    }
+}
+
+impl RepeatCollapser {
+    pub fn new(timeout: Duration) -> Self {
+        Self { pending: None, timeout }
+    }
+
+    /// Process an entry. Returns the previous run once a different line
+    /// arrives or the run has been held longer than the timeout.
+    pub fn process(&mut self, entry: NormalizedLogEntry) -> Option<NormalizedLogEntry> {
+        let key = (content_hash(&entry), entry.log_level);
+
+        if let Some(ref mut run) = self.pending {
+            if run.key == key && run.started.elapsed() <= self.timeout {
+                run.count = run.count.saturating_add(1);
+                return None;
+            }
+        }
+
+        let flushed = self.flush();
+        self.pending = Some(PendingRun {
+            entry,
+            key,
+            count: 1,
+            started: Instant::now(),
+        });
+        flushed
+    }
+
+    /// Emit the pending run if it has been held longer than the timeout.
+    /// Call periodically so a final run is emitted when the container goes quiet.
+    pub fn check_timeout(&mut self) -> Option<NormalizedLogEntry> {
+        match self.pending {
+            Some(ref run) if run.started.elapsed() > self.timeout => self.flush(),
+            _ => None,
+        }
+    }
+
+    /// Emit the pending run (call at stream end).
+    pub fn flush(&mut self) -> Option<NormalizedLogEntry> {
+        self.pending.take().map(PendingRun::into_entry)
+    }
+}
+
+/// Wrap a log entry stream so consecutive identical entries are collapsed.
+/// Errors are passed through after flushing the pending run.
+pub fn collapse_repeats<S>(
+    mut inner: S,
+    timeout: Duration,
+) -> impl Stream<Item = Result<NormalizedLogEntry, Status>>
+where
+    S: Stream<Item = Result<NormalizedLogEntry, Status>> + Unpin,
+{
+    async_stream::stream! {
+        let mut collapser = RepeatCollapser::new(timeout);
+        let mut tick = tokio::time::interval(timeout / 4);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            tokio::select! {
+                item = inner.next() => match item {
+                    Some(Ok(entry)) => {
+                        if let Some(run) = collapser.process(entry) {
+                            yield Ok(run);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        if let Some(run) = collapser.flush() {
+                            yield Ok(run);
+                        }
+                        yield Err(e);
+                    }
+                    None => break,
+                },
+                _ = tick.tick() => {
+                    if let Some(run) = collapser.check_timeout() {
+                        yield Ok(run);
+                    }
+                }
+            }
+        }
+
+        if let Some(run) = collapser.flush() {
+            yield Ok(run);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content: &[u8], sequence: u64) -> NormalizedLogEntry {
+        NormalizedLogEntry {
+            container_id: "test".to_string(),
+            sequence,
+            raw_content: content.to_vec(),
+            line_count: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collapses_identical_run() {
+        let mut collapser = RepeatCollapser::new(Duration::from_secs(60));
+
+        for seq in 0..100 {
+            assert!(collapser.process(entry(b"retrying connection to db:5432", seq)).is_none());
+        }
+
+        let collapsed = collapser.process(entry(b"connected", 100)).expect("run flushed");
+        assert_eq!(collapsed.repeat_count, 100);
+        assert_eq!(collapsed.sequence, 0, "first occurrence is kept");
+        assert_eq!(collapsed.raw_content, b"retrying connection to db:5432");
+
+        let last = collapser.flush().unwrap();
+        assert_eq!(last.raw_content, b"connected");
+        assert_eq!(last.repeat_count, 1);
+        assert!(collapser.flush().is_none());
+    }
+
+    #[test]
+    fn test_different_streams_not_collapsed() {
+        let mut collapser = RepeatCollapser::new(Duration::from_secs(60));
+        let stdout = entry(b"same", 1);
+        let mut stderr = entry(b"same", 2);
+        stderr.log_level = 1;
+
+        assert!(collapser.process(stdout).is_none());
+        let flushed = collapser.process(stderr).unwrap();
+        assert_eq!(flushed.repeat_count, 1);
+    }
+
+    #[test]
+    fn test_timeout_flushes_run() {
+        let mut collapser = RepeatCollapser::new(Duration::from_millis(0));
+        assert!(collapser.process(entry(b"tick", 1)).is_none());
+        std::thread::sleep(Duration::from_millis(2));
+
+        let flushed = collapser.check_timeout().expect("held past timeout");
+        assert_eq!(flushed.repeat_count, 1);
+
+        // An identical line after the window starts a new run
+        assert!(collapser.process(entry(b"tick", 2)).is_none());
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(collapser.process(entry(b"tick", 3)).unwrap().sequence, 2);
+    }
+}
@@ -0,0 +1,202 @@
+//! Live CPU and memory limit updates ("docker update") for standalone
+//! containers. The cgroup limits change in place; the container keeps
+//! running and isn't recreated.
+
+use tonic::Status;
+
+use crate::docker::client::DockerError;
+use crate::state::AgentState;
+use super::proto::UpdateResourcesResponse;
+
+/// Docker rejects memory limits below 6 MiB
+pub const MIN_MEMORY_BYTES: i64 = 6 * 1024 * 1024;
+
+/// Upper bound on a CPU limit, well past any host
+pub const MAX_CPUS: f64 = 1024.0;
+
+const NANO_CPUS_PER_CPU: f64 = 1e9;
+
+/// CPU and memory limits; `None` leaves a limit unchanged (or, when read
+/// back, means unlimited)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub nano_cpus: Option<i64>,
+    pub memory_bytes: Option<i64>,
+}
+
+/// Something whose containers' resource limits can be changed
+#[tonic::async_trait]
+pub trait ResourceTarget: Send + Sync {
+    async fn update_limits(&self, container_id: &str, limits: ResourceLimits) -> Result<(), DockerError>;
+    async fn current_limits(&self, container_id: &str) -> Result<ResourceLimits, DockerError>;
+}
+
+#[tonic::async_trait]
+impl ResourceTarget for AgentState {
+    async fn update_limits(&self, container_id: &str, limits: ResourceLimits) -> Result<(), DockerError> {
+        self.docker
+            .update_container_resources(container_id, limits.nano_cpus, limits.memory_bytes)
+            .await
+    }
+
+    async fn current_limits(&self, container_id: &str) -> Result<ResourceLimits, DockerError> {
+        let inspect = self.docker.inspect_container_raw(container_id).await?;
+        let host = inspect.host_config.unwrap_or_default();
+        Ok(ResourceLimits {
+            nano_cpus: host.nano_cpus.filter(|n| *n > 0),
+            memory_bytes: host.memory.filter(|m| *m > 0),
+        })
+    }
+}
+
+/// Check requested limits and convert them for Docker
+pub fn requested_limits(cpus: Option<f64>, memory_bytes: Option<i64>) -> Result<ResourceLimits, Status> {
+    if cpus.is_none() && memory_bytes.is_none() {
+        return Err(Status::invalid_argument("Set cpus, memory_bytes or both"));
+    }
+    if let Some(cpus) = cpus {
+        if !(cpus.is_finite() && cpus > 0.0 && cpus <= MAX_CPUS) {
+            return Err(Status::invalid_argument(format!(
+                "cpus must be greater than 0 and at most {}, got {}",
+                MAX_CPUS, cpus
+            )));
+        }
+    }
+    if let Some(memory) = memory_bytes {
+        if memory < MIN_MEMORY_BYTES {
+            return Err(Status::invalid_argument(format!(
+                "memory_bytes must be at least {} (6 MiB), got {}",
+                MIN_MEMORY_BYTES, memory
+            )));
+        }
+    }
+    Ok(ResourceLimits {
+        nano_cpus: cpus.map(|c| (c * NANO_CPUS_PER_CPU).round() as i64),
+        memory_bytes,
+    })
+}
+
+/// Apply the limits, then read back the ones now in effect
+pub async fn update_resources<T: ResourceTarget>(
+    target: &T,
+    container_id: &str,
+    limits: ResourceLimits,
+) -> Result<UpdateResourcesResponse, Status> {
+    target
+        .update_limits(container_id, limits)
+        .await
+        .map_err(|e| docker_status(container_id, e))?;
+    let applied = target
+        .current_limits(container_id)
+        .await
+        .map_err(|e| docker_status(container_id, e))?;
+
+    Ok(UpdateResourcesResponse {
+        container_id: container_id.to_string(),
+        cpus: applied.nano_cpus.map(|n| n as f64 / NANO_CPUS_PER_CPU).unwrap_or(0.0),
+        memory_bytes: applied.memory_bytes.unwrap_or(0),
+    })
+}
+
+fn docker_status(container_id: &str, e: DockerError) -> Status {
+    use bollard::errors::Error::DockerResponseServerError;
+
+    match e {
+        DockerError::BollardError(DockerResponseServerError { status_code: 404, message }) => {
+            Status::not_found(message)
+        }
+        // Windows/Hyper-V isolation, or a kernel without the CPU/memory
+        // cgroup controllers: the daemon can't change limits live
+        DockerError::BollardError(DockerResponseServerError { message, .. })
+            if message.to_ascii_lowercase().contains("not support") =>
+        {
+            Status::unimplemented(format!("Live resource updates aren't supported on this host: {}", message))
+        }
+        // Below current usage or the swap limit, ...
+        DockerError::BollardError(DockerResponseServerError { status_code: 400, message }) => {
+            Status::invalid_argument(message)
+        }
+        // Container being removed, ...
+        DockerError::BollardError(DockerResponseServerError { status_code: 409, message }) => {
+            Status::failed_precondition(message)
+        }
+        DockerError::RateLimited { message, retry_after } => super::rate_limited_status(&message, retry_after),
+        e => Status::internal(format!("Failed to update resources of container {}: {}", container_id, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Keeps the limits it's given, like a cgroup; or refuses like a host
+    /// that can't update live
+    #[derive(Default)]
+    struct FakeContainer {
+        limits: Mutex<ResourceLimits>,
+        unsupported: bool,
+    }
+
+    #[tonic::async_trait]
+    impl ResourceTarget for FakeContainer {
+        async fn update_limits(&self, _container_id: &str, limits: ResourceLimits) -> Result<(), DockerError> {
+            if self.unsupported {
+                return Err(DockerError::BollardError(bollard::errors::Error::DockerResponseServerError {
+                    status_code: 500,
+                    message: "Cannot update container: Updating resources is not supported on Windows".into(),
+                }));
+            }
+            let mut current = self.limits.lock().unwrap();
+            current.nano_cpus = limits.nano_cpus.or(current.nano_cpus);
+            current.memory_bytes = limits.memory_bytes.or(current.memory_bytes);
+            Ok(())
+        }
+
+        async fn current_limits(&self, _container_id: &str) -> Result<ResourceLimits, DockerError> {
+            Ok(*self.limits.lock().unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits_applied_and_reported() {
+        let container = FakeContainer::default();
+
+        let limits = requested_limits(Some(0.5), Some(256 * 1024 * 1024)).unwrap();
+        let response = update_resources(&container, "web", limits).await.unwrap();
+        assert_eq!(response.cpus, 0.5);
+        assert_eq!(response.memory_bytes, 256 * 1024 * 1024);
+
+        // Only the CPU limit changes; memory stays as set
+        let limits = requested_limits(Some(2.0), None).unwrap();
+        let response = update_resources(&container, "web", limits).await.unwrap();
+        assert_eq!(container.limits.lock().unwrap().nano_cpus, Some(2_000_000_000));
+        assert_eq!(response.memory_bytes, 256 * 1024 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_host_is_unimplemented() {
+        let container = FakeContainer { unsupported: true, ..Default::default() };
+
+        let limits = requested_limits(Some(1.0), None).unwrap();
+        let status = update_resources(&container, "web", limits).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unimplemented);
+        assert_eq!(*container.limits.lock().unwrap(), ResourceLimits::default());
+    }
+
+    #[test]
+    fn test_invalid_limits_rejected() {
+        for (cpus, memory) in [
+            (None, None),
+            (Some(0.0), None),
+            (Some(-1.0), None),
+            (Some(f64::NAN), None),
+            (Some(MAX_CPUS + 1.0), None),
+            (None, Some(MIN_MEMORY_BYTES - 1)),
+        ] {
+            let status = requested_limits(cpus, memory).unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument, "{:?} {:?}", cpus, memory);
+        }
+        assert!(requested_limits(None, Some(MIN_MEMORY_BYTES)).is_ok());
+    }
+}
@@ -1,16 +1,60 @@
 use tonic::{Request, Response, Status};
 use tracing::{debug, error};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use tokio_stream::StreamExt;
 
+use crate::docker::client::DockerError;
 use crate::state::SharedState;
+use super::rate_limited_status;
 use super::proto::{
     stats_service_server::StatsService,
     ContainerStatsRequest, ContainerStatsResponse,
     CpuStats, MemoryStats, NetworkStats, BlockIoStats,
-    BlockIoDeviceStats, CpuThrottlingStats,
+    BlockIoDeviceStats, CpuThrottlingStats, StreamPriority,
 };
 
+/// Docker samples stats about once a second; finer intervals can't be served
+pub const MIN_STATS_INTERVAL_MS: u32 = 1000;
+
+/// Coarsest sampling a stream may ask for
+pub const MAX_STATS_INTERVAL_MS: u32 = 300_000;
+
+/// Thins Docker's ~1s stats samples to one per requested interval.
+///
+/// Due times advance by whole intervals from the first sample, so emissions
+/// don't drift by the jitter of Docker's cadence. After a gap longer than the
+/// interval the schedule restarts from the next sample.
+#[derive(Debug)]
+pub struct StatsSampler {
+    interval: Duration,
+    next_due: Option<Instant>,
+}
+
+impl StatsSampler {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, next_due: None }
+    }
+
+    /// Whether a sample arriving at `now` should be emitted
+    pub fn admit(&mut self, now: Instant) -> bool {
+        // Docker's own cadence wobbles; accept a sample slightly early
+        // rather than wait a whole extra second for the next one
+        let slack = Duration::from_millis(u64::from(MIN_STATS_INTERVAL_MS) / 4);
+        match self.next_due {
+            Some(due) if now + slack < due => false,
+            Some(due) if now < due + self.interval => {
+                self.next_due = Some(due + self.interval);
+                true
+            }
+            _ => {
+                self.next_due = Some(now + self.interval);
+                true
+            }
+        }
+    }
+}
+
 /// Provides real-time container resource statistics
 pub struct StatsServiceImpl {
     state: SharedState,
@@ -26,9 +70,12 @@ impl StatsServiceImpl {
         let mut stats_stream = self.state.docker
             .stats(container_id, false)
             .await
-            .map_err(|e| {
-                error!("Failed to get stats for container {}: {}", container_id, e);
-                Status::not_found(format!("Container not found: {}", container_id))
+            .map_err(|e| match e {
+                DockerError::RateLimited { message, retry_after } => rate_limited_status(&message, retry_after),
+                e => {
+                    error!("Failed to get stats for container {}: {}", container_id, e);
+                    Status::not_found(format!("Container not found: {}", container_id))
+                }
             })?;
 
         // Get the first (and only) stats snapshot
@@ -48,7 +95,7 @@ impl StatsServiceImpl {
     }
 
     /// Convert bollard ContainerStatsResponse to protobuf ContainerStatsResponse
-    fn convert_stats(container_id: &str, stats: bollard::models::ContainerStatsResponse) -> ContainerStatsResponse {
+    pub(crate) fn convert_stats(container_id: &str, stats: bollard::models::ContainerStatsResponse) -> ContainerStatsResponse {
         // Prefer Docker's own measurement timestamp; fall back to wall clock.
         // BollardDate is a String (RFC 3339) when no chrono/time feature is enabled.
         let timestamp = stats.read
@@ -320,23 +367,43 @@ impl StatsService for StatsServiceImpl {
         if container_id.is_empty() {
             return Err(Status::invalid_argument("container_id must not be empty"));
         }
+        if req.interval_ms > 0 && !(MIN_STATS_INTERVAL_MS..=MAX_STATS_INTERVAL_MS).contains(&req.interval_ms) {
+            return Err(Status::invalid_argument(format!(
+                "interval_ms must be between {} and {} (or 0 for every sample), got {}",
+                MIN_STATS_INTERVAL_MS, MAX_STATS_INTERVAL_MS, req.interval_ms
+            )));
+        }
+
+        // Admit by QoS class; the permit is held until the stream is dropped
+        let priority = StreamPriority::try_from(req.priority).unwrap_or(StreamPriority::Unspecified);
+        let permit = self.state.streams.try_admit(priority)?;
 
-        debug!("Starting stats stream for container: {}", container_id);
+        debug!("Starting stats stream for container: {} (interval: {}ms)", container_id, req.interval_ms);
 
         // Start streaming stats (updates every ~1 second)
         let stats_stream = self.state.docker
             .stats(&container_id, true)
             .await
-            .map_err(|e| {
-                error!("Failed to start stats stream for {}: {}", container_id, e);
-                Status::not_found(format!("Container not found: {}", container_id))
+            .map_err(|e| match e {
+                DockerError::RateLimited { message, retry_after } => rate_limited_status(&message, retry_after),
+                e => {
+                    error!("Failed to start stats stream for {}: {}", container_id, e);
+                    Status::not_found(format!("Container not found: {}", container_id))
+                }
             })?;
 
         let container_id_clone = container_id.clone();
+        let mut sampler = (req.interval_ms > 0)
+            .then(|| StatsSampler::new(Duration::from_millis(req.interval_ms.into())));
 
-        // Convert bollard stream to gRPC stream
+        // Convert bollard stream to gRPC stream, dropping samples between intervals
         // Using Self::convert_stats (associated function) avoids allocating a service instance per update
-        let output_stream = stats_stream.map(move |result| {
+        let sampled = stats_stream.filter(move |result| match (&mut sampler, result) {
+            (Some(sampler), Ok(_)) => sampler.admit(Instant::now()),
+            _ => true,
+        });
+        let output_stream = sampled.map(move |result| {
+            let _permit = &permit;
             match result {
                 Ok(stats) => Ok(Self::convert_stats(&container_id_clone, stats)),
                 Err(e) => {
@@ -766,4 +833,35 @@ mod tests {
         assert_eq!(mem.swap, None);
         assert_eq!(mem.usage, 1024);
     }
+
+    #[test]
+    fn test_sampler_emits_at_requested_interval() {
+        let start = Instant::now();
+        let mut sampler = StatsSampler::new(Duration::from_secs(5));
+
+        // Docker's cadence: about one sample per second, with some jitter
+        let jitter = [0, 20, -15, 30, -40, 10, 0, 25, -20, 5, -30, 15, 0, -10, 35, 0];
+        let emitted: Vec<Duration> = jitter
+            .iter()
+            .enumerate()
+            .map(|(i, ms)| Duration::from_millis((i as i64 * 1000 + ms) as u64))
+            .filter(|offset| sampler.admit(start + *offset))
+            .collect();
+
+        let seconds: Vec<u64> = emitted.iter().map(|d| (d.as_millis() as u64 + 500) / 1000).collect();
+        assert_eq!(seconds, vec![0, 5, 10, 15], "one sample per 5s, not Docker's 1s");
+    }
+
+    #[test]
+    fn test_sampler_restarts_after_gap() {
+        let start = Instant::now();
+        let mut sampler = StatsSampler::new(Duration::from_secs(2));
+        assert!(sampler.admit(start));
+        assert!(!sampler.admit(start + Duration::from_secs(1)));
+
+        // The container was paused for a while; the next sample goes out at once
+        assert!(sampler.admit(start + Duration::from_secs(30)));
+        assert!(!sampler.admit(start + Duration::from_secs(31)));
+        assert!(sampler.admit(start + Duration::from_secs(32)));
+    }
 }
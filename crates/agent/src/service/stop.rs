@@ -0,0 +1,146 @@
+use super::proto::NormalizedLogEntry;
+use crate::docker::client::DockerError;
+use bollard::models::ContainerInspectResponse;
+use std::future::Future;
+use tokio_stream::{Stream, StreamExt};
+use tonic::Status;
+
+/// A synthetic entry saying the followed container stopped. Its timestamp is
+/// when the container finished.
+pub fn stopped_marker(container_id: &str, exit_code: Option<i32>, finished_nanos: i64) -> NormalizedLogEntry {
+    NormalizedLogEntry {
+        container_id: container_id.to_string(),
+        timestamp_nanos: finished_nanos,
+        container_stopped: true,
+        exit_code,
+        ..Default::default()
+    }
+}
+
+/// The stop marker for a container whose log stream ended, from inspecting it
+/// afterwards. `None` while it is still running (or restarting), or when its
+/// state couldn't be read. A container that is gone (e.g. `--rm`) stopped
+/// with an unknown exit code.
+pub fn stop_marker_from_inspect(
+    container_id: &str,
+    inspected: Result<ContainerInspectResponse, DockerError>,
+) -> Option<NormalizedLogEntry> {
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    match inspected {
+        Ok(inspect) => {
+            let state = inspect.state?;
+            if state.running.unwrap_or(false) {
+                return None;
+            }
+            let finished = state.finished_at
+                .as_deref()
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+                .and_then(|at| at.timestamp_nanos_opt())
+                .filter(|nanos| *nanos > 0); // Docker's zero time: never finished
+            let exit_code = state.exit_code.and_then(|code| i32::try_from(code).ok());
+            Some(stopped_marker(container_id, exit_code, finished.unwrap_or(now)))
+        }
+        Err(DockerError::BollardError(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. })) => {
+            Some(stopped_marker(container_id, None, now))
+        }
+        Err(_) => None,
+    }
+}
+
+/// Once a followed stream ends, whether Docker closed it or failed reading
+/// it, ask `stopped` for the container's stop marker. If it stopped, the
+/// marker goes out and the stream ends cleanly in place of any error;
+/// otherwise the error (if any) is passed on.
+pub fn with_stop_marker<S, F>(inner: S, stopped: F) -> impl Stream<Item = Result<NormalizedLogEntry, Status>>
+where
+    S: Stream<Item = Result<NormalizedLogEntry, Status>>,
+    F: Future<Output = Option<NormalizedLogEntry>>,
+{
+    async_stream::stream! {
+        tokio::pin!(inner);
+        let mut error = None;
+        while let Some(item) = inner.next().await {
+            match item {
+                Ok(entry) => yield Ok(entry),
+                Err(status) => {
+                    error = Some(status);
+                    break;
+                }
+            }
+        }
+        match stopped.await {
+            Some(marker) => yield Ok(marker),
+            None => if let Some(status) = error {
+                yield Err(status);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bollard::models::ContainerState;
+
+    const FINISHED: i64 = 1_709_301_737_000_000_000;
+
+    fn line(content: &[u8]) -> Result<NormalizedLogEntry, Status> {
+        Ok(NormalizedLogEntry { container_id: "api".to_string(), raw_content: content.to_vec(), ..Default::default() })
+    }
+
+    fn inspected(running: bool, exit_code: i64) -> Result<ContainerInspectResponse, DockerError> {
+        Ok(ContainerInspectResponse {
+            state: Some(ContainerState {
+                running: Some(running),
+                exit_code: Some(exit_code),
+                finished_at: Some("2024-03-01T14:02:17Z".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    async fn collect<S: Stream<Item = Result<NormalizedLogEntry, Status>>>(stream: S) -> Vec<Result<NormalizedLogEntry, Status>> {
+        Box::pin(stream).collect().await
+    }
+
+    #[tokio::test]
+    async fn test_stopped_container_completes_with_marker() {
+        // Docker either closes the log or fails reading it; both end the same way
+        for end in [None, Some(Status::internal("Stream error: connection reset"))] {
+            let inner = tokio_stream::iter([line(b"shutting down")].into_iter().chain(end.map(Err)));
+            let out = collect(with_stop_marker(inner, async { stop_marker_from_inspect("api", inspected(false, 137)) })).await;
+
+            assert_eq!(out.len(), 2);
+            assert_eq!(out[0].as_ref().unwrap().raw_content, b"shutting down");
+            let marker = out[1].as_ref().unwrap();
+            assert!(marker.container_stopped);
+            assert_eq!(marker.exit_code, Some(137));
+            assert_eq!(marker.timestamp_nanos, FINISHED);
+            assert!(marker.raw_content.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_running_container_keeps_error() {
+        let inner = tokio_stream::iter([line(b"ok"), Err(Status::internal("Stream error: broken pipe"))]);
+        let out = collect(with_stop_marker(inner, async { stop_marker_from_inspect("api", inspected(true, 0)) })).await;
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[1].as_ref().unwrap_err().message(), "Stream error: broken pipe");
+    }
+
+    #[test]
+    fn test_removed_container_stopped_with_unknown_exit_code() {
+        let gone = Err(DockerError::BollardError(bollard::errors::Error::DockerResponseServerError {
+            status_code: 404,
+            message: "No such container: api".to_string(),
+        }));
+        let marker = stop_marker_from_inspect("api", gone).expect("Removed container counts as stopped");
+        assert!(marker.container_stopped);
+        assert_eq!(marker.exit_code, None);
+
+        let unreachable = Err(DockerError::ConnectionFailed("daemon down".to_string()));
+        assert!(stop_marker_from_inspect("api", unreachable).is_none());
+    }
+}
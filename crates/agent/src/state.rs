@@ -1,17 +1,26 @@
 use dashmap::DashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use crate::docker::client::DockerClient;
 use crate::docker::inventory::ContainerInfo;
 use crate::config::AgentConfig;
-use crate::parser::metrics::ParsingMetrics;
+use crate::parser::metrics::{DetectionTuner, ParsingMetrics};
 use crate::parser::cache::ParserCache;
+use crate::service::admission::StreamAdmission;
+use crate::filter::live::LiveFilters;
 
 pub struct AgentState {
     pub inventory: DashMap<String, ContainerInfo>,
     pub docker: DockerClient,
-    pub config: AgentConfig,
+    /// Swapped by a config reload; streams read it once when they open
+    config: RwLock<Arc<AgentConfig>>,
     pub metrics: Arc<ParsingMetrics>,
     pub parser_cache: Arc<ParserCache>,
+    pub detection_tuner: Arc<DetectionTuner>,
+    pub streams: StreamAdmission,
+    /// Filters of streams opened with a `filter_token`
+    pub live_filters: LiveFilters,
+    /// Schema from `log_schema_path`, loaded at startup
+    pub log_schema: Option<Arc<serde_json::Value>>,
 }
 
 impl AgentState {
@@ -19,11 +28,24 @@ impl AgentState {
         Self {
             inventory: DashMap::new(),
             docker,
-            config,
             metrics: Arc::new(ParsingMetrics::new()),
-            parser_cache: Arc::new(ParserCache::new()),
+            parser_cache: Arc::new(ParserCache::with_format_lock(config.format_lock.clone())),
+            detection_tuner: Arc::new(DetectionTuner::new(config.adaptive_detection.clone())),
+            streams: StreamAdmission::new(config.max_concurrent_streams, config.stream_qos.clone()),
+            live_filters: LiveFilters::default(),
+            log_schema: None,
+            config: RwLock::new(Arc::new(config)),
         }
     }
+
+    /// Configuration in effect now
+    pub fn config(&self) -> Arc<AgentConfig> {
+        Arc::clone(&self.config.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn set_config(&self, config: AgentConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+    }
 }
 
 pub type SharedState = Arc<AgentState>;
\ No newline at end of file
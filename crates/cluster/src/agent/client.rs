@@ -1,8 +1,10 @@
-use super::Result;
+use super::{CallLimiter, Result};
+use tonic::codec::CompressionEncoding;
 use tonic::transport::Channel;
 
 // Include the generated protobuf code
-mod proto {
+#[allow(clippy::enum_variant_names)]
+pub(crate) mod proto {
     tonic::include_proto!("docktail.agent");
 }
 
@@ -13,12 +15,19 @@ pub use proto::{
     stats_service_client::StatsServiceClient,
     // Request/Response types
     LogStreamRequest, NormalizedLogEntry,
-    ContainerListRequest, ContainerListResponse,
-    ContainerInspectRequest, ContainerInspectResponse,
+    UpdateStreamFilterRequest, UpdateStreamFilterResponse,
+    ParserCacheStatsRequest, ParserCacheStatsResponse,
+    EvictParserCacheRequest, EvictParserCacheResponse,
+    LogRateRequest, LogRateBucket,
+    ContainerListRequest, ContainerListResponse, LabelSelector,
+    ContainerInspectRequest, ContainerInspectResponse, ContainerInfo, ContainerCommand,
+    FreezeInspectRequest, FreezeInspectResponse,
+    UpdateResourcesRequest, UpdateResourcesResponse,
     HealthCheckRequest, HealthCheckResponse,
+    KeyValuePair, typed_value,
     ContainerStatsRequest, ContainerStatsResponse,
     // Enums
-    LogLevel, FilterMode, LogFormat,
+    LogLevel, FilterMode, LogFormat, StreamPriority, LogSeverity, UnleveledPolicy,
 };
 
 /// Wrapper around generated gRPC clients for a single agent
@@ -31,16 +40,30 @@ pub struct AgentGrpcClient {
     inventory_client: InventoryServiceClient<Channel>,
     health_client: HealthServiceClient<Channel>,
     stats_client: StatsServiceClient<Channel>,
+    limiter: CallLimiter,
 }
 
 impl AgentGrpcClient {
     /// Create a new client from a gRPC channel
-    pub fn new(channel: Channel) -> Self {
+    ///
+    /// When `compression` is set, the log client advertises gzip so the agent
+    /// compresses log frames on the wire. Every call takes a slot from
+    /// `limiter` first; for streams the slot is released once the stream
+    /// is open.
+    pub fn new(channel: Channel, compression: bool, limiter: CallLimiter) -> Self {
+        let mut log_client = LogServiceClient::new(channel.clone());
+        if compression {
+            log_client = log_client
+                .accept_compressed(CompressionEncoding::Gzip)
+                .send_compressed(CompressionEncoding::Gzip);
+        }
+
         Self {
-            log_client: LogServiceClient::new(channel.clone()),
+            log_client,
             inventory_client: InventoryServiceClient::new(channel.clone()),
             health_client: HealthServiceClient::new(channel.clone()),
             stats_client: StatsServiceClient::new(channel),
+            limiter,
         }
     }
 
@@ -49,6 +72,7 @@ impl AgentGrpcClient {
         &mut self,
         request: LogStreamRequest,
     ) -> Result<tonic::Streaming<NormalizedLogEntry>> {
+        let _slot = self.limiter.acquire().await?;
         let response = self
             .log_client
             .stream_logs(tonic::Request::new(request))
@@ -57,11 +81,65 @@ impl AgentGrpcClient {
         Ok(response.into_inner())
     }
 
+    /// Stream a container's log line and byte counts per time bucket
+    pub async fn stream_log_rate(
+        &mut self,
+        request: LogRateRequest,
+    ) -> Result<tonic::Streaming<LogRateBucket>> {
+        let _slot = self.limiter.acquire().await?;
+        let response = self
+            .log_client
+            .stream_log_rate(tonic::Request::new(request))
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Replace the filter of running log streams opened with a filter token
+    pub async fn update_stream_filter(
+        &mut self,
+        request: UpdateStreamFilterRequest,
+    ) -> Result<UpdateStreamFilterResponse> {
+        let _slot = self.limiter.acquire().await?;
+        let response = self
+            .log_client
+            .update_stream_filter(tonic::Request::new(request))
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Entries, lookups and locked formats of the agent's parser cache
+    pub async fn parser_cache_stats(&mut self) -> Result<ParserCacheStatsResponse> {
+        let _slot = self.limiter.acquire().await?;
+        let response = self
+            .log_client
+            .parser_cache_stats(tonic::Request::new(ParserCacheStatsRequest {}))
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Drop a container's cached log format so it's detected again
+    pub async fn evict_parser_cache(
+        &mut self,
+        request: EvictParserCacheRequest,
+    ) -> Result<EvictParserCacheResponse> {
+        let _slot = self.limiter.acquire().await?;
+        let response = self
+            .log_client
+            .evict_parser_cache(tonic::Request::new(request))
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
     /// List containers on the agent
     pub async fn list_containers(
         &mut self,
         request: ContainerListRequest,
     ) -> Result<ContainerListResponse> {
+        let _slot = self.limiter.acquire().await?;
         let response = self
             .inventory_client
             .list_containers(tonic::Request::new(request))
@@ -75,6 +153,7 @@ impl AgentGrpcClient {
         &mut self,
         request: ContainerInspectRequest,
     ) -> Result<ContainerInspectResponse> {
+        let _slot = self.limiter.acquire().await?;
         let response = self
             .inventory_client
             .inspect_container(tonic::Request::new(request))
@@ -83,7 +162,36 @@ impl AgentGrpcClient {
         Ok(response.into_inner())
     }
 
-    /// Health check
+    /// Pause a container, capture a debugging snapshot, and unpause it
+    pub async fn freeze_inspect(
+        &mut self,
+        request: FreezeInspectRequest,
+    ) -> Result<FreezeInspectResponse> {
+        let _slot = self.limiter.acquire().await?;
+        let response = self
+            .inventory_client
+            .freeze_inspect(tonic::Request::new(request))
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Change a container's CPU and memory limits in place
+    pub async fn update_container_resources(
+        &mut self,
+        request: UpdateResourcesRequest,
+    ) -> Result<UpdateResourcesResponse> {
+        let _slot = self.limiter.acquire().await?;
+        let response = self
+            .inventory_client
+            .update_container_resources(tonic::Request::new(request))
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Health check. Not counted against the call limit, so a busy agent
+    /// isn't mistaken for an unhealthy one.
     pub async fn check_health(
         &mut self,
         request: HealthCheckRequest,
@@ -96,7 +204,7 @@ impl AgentGrpcClient {
         Ok(response.into_inner())
     }
 
-    /// Watch health status (streaming)
+    /// Watch health status (streaming, not counted against the call limit)
     pub async fn watch_health(
         &mut self,
         request: HealthCheckRequest,
@@ -114,6 +222,7 @@ impl AgentGrpcClient {
         &mut self,
         request: ContainerStatsRequest,
     ) -> Result<ContainerStatsResponse> {
+        let _slot = self.limiter.acquire().await?;
         let response = self
             .stats_client
             .get_container_stats(tonic::Request::new(request))
@@ -127,6 +236,7 @@ impl AgentGrpcClient {
         &mut self,
         request: ContainerStatsRequest,
     ) -> Result<tonic::Streaming<ContainerStatsResponse>> {
+        let _slot = self.limiter.acquire().await?;
         let response = self
             .stats_client
             .stream_container_stats(tonic::Request::new(request))
@@ -0,0 +1,319 @@
+//! Agent identity pinning. A certificate that chains to the agent's CA is
+//! still refused unless one of its subject alternative names is the
+//! identity configured for that agent (`tls_identity`), so a valid
+//! certificate issued to one agent can't be used to pose as another.
+//!
+//! tonic's TLS settings don't take a custom verifier, so a pinned agent's
+//! TLS is done here, in the connector, and the channel speaks plain HTTP/2
+//! over the stream it returns.
+
+use futures::future::BoxFuture;
+use hyper_util::rt::TokioIo;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use std::io;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use tonic::transport::Uri;
+use tracing::warn;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use super::{AgentError, Result};
+
+/// DNS and URI subject alternative names of a certificate
+fn cert_identities(cert: &CertificateDer<'_>) -> std::result::Result<Vec<String>, String> {
+    let (_, cert) = X509Certificate::from_der(cert).map_err(|e| format!("unreadable certificate: {}", e))?;
+    let san = cert.subject_alternative_name().map_err(|e| format!("unreadable SAN extension: {}", e))?;
+    Ok(san
+        .map(|san| {
+            san.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) | GeneralName::URI(name) => Some(name.to_string()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Whether `identity` is one of the names: DNS names compare without case,
+/// URIs (such as SPIFFE IDs) exactly
+fn has_identity(names: &[String], identity: &str) -> bool {
+    let is_uri = identity.contains("://");
+    names.iter().any(|name| if is_uri { name == identity } else { name.eq_ignore_ascii_case(identity) })
+}
+
+/// Verifies the chain like the standard verifier, then requires the pinned
+/// identity among the certificate's SANs. The pinned identity takes the
+/// place of the `tls_domain` name check, since SPIFFE certificates often
+/// carry no DNS name.
+#[derive(Debug)]
+struct PinnedVerifier {
+    chain: Arc<WebPkiServerVerifier>,
+    identity: String,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        match self.chain.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now) {
+            Ok(_)
+            | Err(rustls::Error::InvalidCertificate(
+                CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+            )) => {}
+            Err(e) => return Err(e),
+        }
+        let names = cert_identities(end_entity).map_err(rustls::Error::General)?;
+        if has_identity(&names, &self.identity) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(format!(
+                "certificate identities [{}] don't include pinned identity '{}'",
+                names.join(", "),
+                self.identity
+            )))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.chain.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.chain.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.chain.supported_verify_schemes()
+    }
+}
+
+/// mTLS client settings for an agent pinned to `identity`, from PEM files'
+/// contents. Uses the process-wide crypto provider (`agents.tls`).
+pub fn pinned_client_config(cert: &[u8], key: &[u8], ca: &[u8], identity: &str) -> Result<Arc<ClientConfig>> {
+    let certs = CertificateDer::pem_slice_iter(cert)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AgentError::Tls(format!("Invalid client cert: {}", e)))?;
+    let key = PrivateKeyDer::from_pem_slice(key).map_err(|e| AgentError::Tls(format!("Invalid client key: {}", e)))?;
+    let mut roots = RootCertStore::empty();
+    for ca_cert in CertificateDer::pem_slice_iter(ca) {
+        let ca_cert = ca_cert.map_err(|e| AgentError::Tls(format!("Invalid CA cert: {}", e)))?;
+        roots.add(ca_cert).map_err(|e| AgentError::Tls(format!("Invalid CA cert: {}", e)))?;
+    }
+    let chain = WebPkiServerVerifier::builder(Arc::new(roots))
+        .build()
+        .map_err(|e| AgentError::Tls(format!("TLS config error: {}", e)))?;
+
+    let mut config = ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PinnedVerifier { chain, identity: identity.to_string() }))
+        .with_client_auth_cert(certs, key)
+        .map_err(|e| AgentError::Tls(format!("TLS config error: {}", e)))?;
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// Dials the host and port of the URI it's called with, giving up after
+/// `timeout`
+#[derive(Clone, Copy)]
+pub struct TcpDialer {
+    timeout: Duration,
+}
+
+impl TcpDialer {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl tower::Service<Uri> for TcpDialer {
+    type Response = TokioIo<TcpStream>;
+    type Error = io::Error;
+    type Future = BoxFuture<'static, io::Result<TokioIo<TcpStream>>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let timeout = self.timeout;
+        Box::pin(async move {
+            let authority = uri
+                .authority()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("no host in '{}'", uri)))?;
+            tokio::time::timeout(timeout, TcpStream::connect(authority.as_str()))
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, format!("no TCP connection within {}ms", timeout.as_millis())))?
+                .map(TokioIo::new)
+        })
+    }
+}
+
+/// Runs the pinned TLS handshake over the connections `inner` makes
+#[derive(Clone)]
+pub struct PinnedTlsConnector<C> {
+    inner: C,
+    tls: TlsConnector,
+    server_name: ServerName<'static>,
+    agent_id: Arc<str>,
+}
+
+impl<C> PinnedTlsConnector<C> {
+    pub fn new(inner: C, config: Arc<ClientConfig>, tls_domain: &str, agent_id: &str) -> Result<Self> {
+        let server_name = ServerName::try_from(tls_domain.to_string())
+            .map_err(|e| AgentError::InvalidConfig(format!("Invalid tls_domain '{}': {}", tls_domain, e)))?;
+        Ok(Self { inner, tls: TlsConnector::from(config), server_name, agent_id: agent_id.into() })
+    }
+}
+
+impl<C> tower::Service<Uri> for PinnedTlsConnector<C>
+where
+    C: tower::Service<Uri, Response = TokioIo<TcpStream>, Error = io::Error>,
+    C::Future: Send + 'static,
+{
+    type Response = TokioIo<TlsStream<TcpStream>>;
+    type Error = io::Error;
+    type Future = BoxFuture<'static, io::Result<TokioIo<TlsStream<TcpStream>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = self.inner.call(uri);
+        let (tls, server_name, agent_id) = (self.tls.clone(), self.server_name.clone(), self.agent_id.clone());
+        Box::pin(async move {
+            let socket = connecting.await?.into_inner();
+            tls.connect(server_name, socket)
+                .await
+                .map(TokioIo::new)
+                .inspect_err(|e| warn!("TLS handshake with agent {} failed: {}", agent_id, e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, Issuer, KeyPair, SanType};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tower::Service;
+
+    struct Pki {
+        ca: String,
+        client_cert: String,
+        client_key: String,
+        agent_cert: CertificateDer<'static>,
+        agent_key: PrivateKeyDer<'static>,
+    }
+
+    /// A CA, a client certificate and an agent certificate for "localhost"
+    /// carrying `spiffe_id` as a URI SAN
+    fn pki(spiffe_id: &str) -> Pki {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let issuer = Issuer::from_params(&ca_params, &ca_key);
+
+        let agent_key = KeyPair::generate().unwrap();
+        let mut agent_params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        agent_params.subject_alt_names.push(SanType::URI(spiffe_id.try_into().unwrap()));
+        let agent = agent_params.signed_by(&agent_key, &issuer).unwrap();
+        let client_key = KeyPair::generate().unwrap();
+        let client = CertificateParams::new(vec!["docktail-cluster".to_string()])
+            .unwrap()
+            .signed_by(&client_key, &issuer)
+            .unwrap();
+
+        Pki {
+            ca: ca.pem(),
+            client_cert: client.pem(),
+            client_key: client_key.serialize_pem(),
+            agent_cert: agent.der().clone(),
+            agent_key: PrivateKeyDer::try_from(agent_key.serialize_der()).unwrap(),
+        }
+    }
+
+    /// Serve one TLS connection as the agent and answer "pong" to "ping"
+    async fn agent(pki: &Pki) -> String {
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![pki.agent_cert.clone()], pki.agent_key.clone_key())
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            if let Ok(mut stream) = acceptor.accept(socket).await {
+                let mut ping = [0u8; 4];
+                stream.read_exact(&mut ping).await.unwrap();
+                stream.write_all(b"pong").await.unwrap();
+            }
+        });
+        address
+    }
+
+    async fn connect(pki: &Pki, identity: &str) -> io::Result<TokioIo<TlsStream<TcpStream>>> {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let address = agent(pki).await;
+        let config = pinned_client_config(
+            pki.client_cert.as_bytes(),
+            pki.client_key.as_bytes(),
+            pki.ca.as_bytes(),
+            identity,
+        )
+        .unwrap();
+        let mut connector = PinnedTlsConnector::new(TcpDialer::new(Duration::from_secs(5)), config, "localhost", "edge-1").unwrap();
+        connector.call(format!("http://{}", address).parse().unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn test_matching_identity_is_accepted() {
+        let pki = pki("spiffe://docktail/agent/edge-1");
+        let mut stream = connect(&pki, "spiffe://docktail/agent/edge-1").await.unwrap().into_inner();
+        stream.write_all(b"ping").await.unwrap();
+        let mut pong = [0u8; 4];
+        stream.read_exact(&mut pong).await.unwrap();
+        assert_eq!(&pong, b"pong");
+
+        // A DNS SAN can be pinned too
+        assert!(connect(&pki, "LOCALHOST").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_identity_is_rejected() {
+        // Chains to the trusted CA, but was issued to another agent
+        let pki = pki("spiffe://docktail/agent/edge-2");
+        let err = connect(&pki, "spiffe://docktail/agent/edge-1").await.unwrap_err();
+        assert!(err.to_string().contains("don't include pinned identity 'spiffe://docktail/agent/edge-1'"), "{}", err);
+    }
+}
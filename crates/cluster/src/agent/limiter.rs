@@ -0,0 +1,97 @@
+use super::{AgentError, Result};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Bounds the gRPC calls in flight to one agent, so a burst of dashboard
+/// queries can't pile up on a single slow host.
+///
+/// A call over the cap waits up to `queue_timeout` for a slot, then fails
+/// with `AgentError::Saturated`. Shared by every clone of the agent's client
+/// and kept across reconnects.
+#[derive(Clone, Debug)]
+pub struct CallLimiter {
+    permits: Option<Arc<Semaphore>>,
+    max_in_flight: usize,
+    queue_timeout: Duration,
+}
+
+impl CallLimiter {
+    /// `None` (or zero) for no cap
+    pub fn new(max_in_flight: Option<usize>, queue_timeout: Duration) -> Self {
+        let max_in_flight = max_in_flight.unwrap_or(0);
+        Self {
+            permits: (max_in_flight > 0).then(|| Arc::new(Semaphore::new(max_in_flight))),
+            max_in_flight,
+            queue_timeout,
+        }
+    }
+
+    /// A slot for one call, held until the permit is dropped
+    pub async fn acquire(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        let Some(permits) = &self.permits else {
+            return Ok(None);
+        };
+        match tokio::time::timeout(self.queue_timeout, permits.clone().acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            // The semaphore is never closed
+            Ok(Err(_)) => Ok(None),
+            Err(_) => Err(AgentError::Saturated {
+                max_in_flight: self.max_in_flight,
+                retry_after_secs: self.queue_timeout.as_secs_f64().ceil().max(1.0) as u64,
+            }),
+        }
+    }
+
+    /// Calls currently holding a slot
+    #[allow(dead_code)]
+    pub fn in_flight(&self) -> usize {
+        self.permits
+            .as_ref()
+            .map_or(0, |p| self.max_in_flight - p.available_permits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUEUE_TIMEOUT: Duration = Duration::from_millis(100);
+
+    #[tokio::test]
+    async fn test_saturated_calls_queue_then_reject() {
+        let limiter = CallLimiter::new(Some(2), QUEUE_TIMEOUT);
+        let first = limiter.acquire().await.unwrap();
+        let second = limiter.acquire().await.unwrap();
+        assert_eq!(limiter.in_flight(), 2);
+
+        // Over the cap: queued, and admitted once a call finishes
+        let queued = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await.map(|p| p.is_some()) }
+        });
+        tokio::time::sleep(QUEUE_TIMEOUT / 4).await;
+        assert!(!queued.is_finished(), "should wait for a slot");
+        drop(first);
+        assert!(queued.await.unwrap().unwrap());
+
+        // Still saturated past the queue timeout: rejected with a retry hint
+        let _third = limiter.acquire().await.unwrap();
+        let started = tokio::time::Instant::now();
+        match limiter.acquire().await {
+            Err(AgentError::Saturated { max_in_flight, retry_after_secs }) => {
+                assert_eq!((max_in_flight, retry_after_secs), (2, 1));
+            }
+            other => panic!("expected saturation, got {:?}", other.map(|p| p.is_some())),
+        }
+        assert!(started.elapsed() >= QUEUE_TIMEOUT);
+        drop(second);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_never_waits() {
+        let limiter = CallLimiter::new(None, Duration::ZERO);
+        let permits: Vec<_> = futures::future::join_all((0..64).map(|_| limiter.acquire())).await;
+        assert!(permits.iter().all(|p| matches!(p, Ok(None))));
+    }
+}
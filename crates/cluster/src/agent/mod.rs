@@ -1,8 +1,12 @@
 pub mod client;
+pub mod identity;
+pub mod limiter;
 pub mod pool;
 pub mod registry;
+pub mod reverse;
 
 pub use client::AgentGrpcClient;
+pub use limiter::CallLimiter;
 pub use pool::{AgentConnection, AgentPool, HealthStatus};
 pub use registry::AgentRegistry;
 
@@ -38,4 +42,7 @@ pub enum AgentError {
 
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
+
+    #[error("Agent has {max_in_flight} calls in flight; retry after {retry_after_secs}s")]
+    Saturated { max_in_flight: usize, retry_after_secs: u64 },
 }
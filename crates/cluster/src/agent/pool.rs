@@ -1,4 +1,6 @@
-use super::{AgentError, AgentGrpcClient, Result};
+use super::identity::{pinned_client_config, PinnedTlsConnector, TcpDialer};
+use super::reverse::ReverseAgents;
+use super::{AgentError, AgentGrpcClient, CallLimiter, Result};
 use crate::config::{AgentConfig, AgentRegistryConfig};
 use dashmap::DashMap;
 use std::collections::HashMap;
@@ -6,9 +8,12 @@ use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, RwLock};
-use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
 use tracing::{debug, error, info, warn};
 
+/// How often `wait_for_healthy` re-checks agents
+const STARTUP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Agent health status
 /// Matches the gRPC HealthStatus enum from agent.proto
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -52,12 +57,50 @@ impl AgentInfo {
     }
 }
 
+/// Agent clock as observed during the last health check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClockReport {
+    /// Estimated agent clock minus cluster clock, in milliseconds
+    pub offset_ms: i64,
+    /// Timezone the agent reports (TZ name or UTC offset)
+    pub timezone: Option<String>,
+    /// Whether |offset| exceeds the configured skew threshold
+    pub skewed: bool,
+}
+
+impl ClockReport {
+    /// Build a report from health check metadata.
+    ///
+    /// The agent's clock was read somewhere between sending the request and
+    /// receiving the response, so it is compared against the midpoint of that
+    /// window (as NTP does). Returns `None` for agents that don't report a clock.
+    pub fn from_metadata(
+        metadata: &HashMap<String, String>,
+        sent_unix_ms: i64,
+        received_unix_ms: i64,
+        skew_threshold_ms: u64,
+    ) -> Option<Self> {
+        let agent_ms: i64 = metadata.get("clock_unix_ms")?.parse().ok()?;
+        let midpoint = sent_unix_ms + (received_unix_ms - sent_unix_ms) / 2;
+        let offset_ms = agent_ms - midpoint;
+
+        Some(Self {
+            offset_ms,
+            timezone: metadata.get("timezone").cloned(),
+            skewed: offset_ms.unsigned_abs() > skew_threshold_ms,
+        })
+    }
+}
+
 /// A single agent connection
 pub struct AgentConnection {
     pub info: AgentInfo,
     pub client: Arc<Mutex<AgentGrpcClient>>,
     health_status: Arc<AtomicU8>,
     last_seen: Arc<RwLock<Instant>>,
+    clock: Arc<std::sync::RwLock<Option<ClockReport>>>,
+    clock_skew_threshold_ms: u64,
+    limiter: CallLimiter,
 }
 
 impl AgentConnection {
@@ -116,6 +159,26 @@ impl AgentConnection {
         *self.last_seen.write().await = Instant::now();
     }
 
+    /// Clock offset and timezone from the last successful health check
+    pub fn clock(&self) -> Option<ClockReport> {
+        self.clock.read().ok().and_then(|c| c.clone())
+    }
+
+    fn update_clock(&self, report: Option<ClockReport>) {
+        if let Some(ref r) = report {
+            let was_skewed = self.clock().is_some_and(|c| c.skewed);
+            if r.skewed && !was_skewed {
+                warn!(
+                    "Agent {} clock is {}ms off the cluster clock (threshold {}ms)",
+                    self.info.id, r.offset_ms, self.clock_skew_threshold_ms
+                );
+            }
+        }
+        if let Ok(mut guard) = self.clock.write() {
+            *guard = report;
+        }
+    }
+
     /// Perform health check with a dedicated 5-second timeout
     pub async fn check_health(&self) -> Result<()> {
         use super::client::HealthCheckRequest;
@@ -135,6 +198,7 @@ impl AgentConnection {
         // Use a dedicated short timeout for health checks to avoid
         // one slow agent blocking the entire health-check cycle
         let health_check_timeout = Duration::from_secs(5);
+        let sent_unix_ms = chrono::Utc::now().timestamp_millis();
         let result = tokio::time::timeout(
             health_check_timeout,
             client.check_health(request),
@@ -157,6 +221,12 @@ impl AgentConnection {
                 // Update status based on what the agent reported
                 self.update_health_status(response.status);
                 self.update_last_seen().await;
+                self.update_clock(ClockReport::from_metadata(
+                    &response.metadata,
+                    sent_unix_ms,
+                    chrono::Utc::now().timestamp_millis(),
+                    self.clock_skew_threshold_ms,
+                ));
                 
                 let status = self.health_status();
                 match status {
@@ -190,6 +260,11 @@ pub struct AgentPool {
     /// Map: agent_id -> AgentConnection
     connections: DashMap<String, Arc<AgentConnection>>,
     config: AgentRegistryConfig,
+    /// Connections from agents in reverse mode
+    reverse: Arc<ReverseAgents>,
+    /// Static agents that couldn't be added yet (e.g. unreachable at
+    /// startup), retried on each health check
+    pending: DashMap<String, AgentConfig>,
 }
 
 impl AgentPool {
@@ -198,6 +273,8 @@ impl AgentPool {
         Self {
             connections: DashMap::new(),
             config,
+            reverse: Arc::new(ReverseAgents::default()),
+            pending: DashMap::new(),
         }
     }
 
@@ -213,31 +290,77 @@ impl AgentPool {
                 Err(e) => {
                     error!("✗ Failed to add agent '{}' ({}): {}", agent_config.name, agent_config.id, e);
                     // Continue with other agents - don't fail the entire initialization
+                    self.pending.insert(agent_config.id.clone(), agent_config.clone());
                 }
             }
         }
 
-        info!("Agent pool initialized with {} agents", self.connections.len());
+        info!(
+            "Agent pool initialized with {} agents ({} to retry)",
+            self.connections.len(),
+            self.pending.len()
+        );
         Ok(())
     }
 
+    /// Retry adding the static agents that couldn't be added so far.
+    /// Returns how many joined the pool.
+    pub async fn connect_pending(&self) -> usize {
+        let configs: Vec<AgentConfig> = self.pending.iter().map(|entry| entry.value().clone()).collect();
+        let attempts = configs.into_iter().map(|config| async move {
+            let joined = self.add_agent(config.clone()).await.is_ok();
+            if joined {
+                self.pending.remove(&config.id);
+                info!("✓ Agent '{}' ({}) joined after startup", config.name, config.id);
+            }
+            joined
+        });
+        futures::future::join_all(attempts).await.into_iter().filter(|joined| *joined).count()
+    }
+
+    /// Wait until an agent is healthy, retrying unreachable agents and
+    /// re-checking health meanwhile. False if none is within `timeout`.
+    pub async fn wait_for_healthy(&self, timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if self.count_healthy() > 0 {
+                return true;
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            let _ = tokio::time::timeout_at(deadline, self.health_check_all()).await;
+            tokio::time::sleep_until(deadline.min(now + STARTUP_POLL_INTERVAL)).await;
+        }
+    }
+
     /// Add a new agent to the pool
     pub async fn add_agent(&self, config: AgentConfig) -> Result<()> {
         debug!("Adding agent: {} ({})", config.name, config.id);
 
         // Create mTLS channel
         let channel = self.create_channel(&config).await?;
-        let client = AgentGrpcClient::new(channel);
+        let limiter = CallLimiter::new(
+            config.max_in_flight,
+            Duration::from_millis(self.config.call_queue_timeout_ms),
+        );
+        let client = AgentGrpcClient::new(channel, self.config.enable_compression, limiter.clone());
 
         let connection = Arc::new(AgentConnection {
             info: AgentInfo::from_config(&config),
             client: Arc::new(Mutex::new(client)),
             health_status: Arc::new(AtomicU8::new(HealthStatus::Unknown as u8)),
             last_seen: Arc::new(RwLock::new(Instant::now())),
+            clock: Arc::new(std::sync::RwLock::new(None)),
+            clock_skew_threshold_ms: self.config.clock_skew_threshold_ms,
+            limiter,
         });
 
-        // Perform initial health check
-        if let Err(e) = connection.check_health().await {
+        // Perform initial health check; a reverse agent is checked once it dials in
+        if config.reverse {
+            info!("Agent {} is in reverse mode, waiting for it to dial in", config.id);
+        } else if let Err(e) = connection.check_health().await {
             warn!("Initial health check failed for agent {}: {}", config.id, e);
             // Still add the agent, but mark it as unhealthy
             connection.mark_unhealthy();
@@ -274,6 +397,10 @@ impl AgentPool {
                 return Ok(());
             }
         };
+        if config.reverse {
+            debug!("Agent {} is in reverse mode, its channel reconnects when it dials in", agent_id);
+            return Ok(());
+        }
 
         let backoff_base = Duration::from_secs(self.config.reconnect_backoff);
         let max_attempts = self.config.max_reconnect_attempts;
@@ -286,10 +413,13 @@ impl AgentPool {
 
             match self.create_channel(&config).await {
                 Ok(channel) => {
-                    let client = AgentGrpcClient::new(channel);
-
-                    // Update the existing connection's client
+                    // Update the existing connection's client, keeping its call limit
                     if let Some(conn) = self.connections.get(agent_id) {
+                        let client = AgentGrpcClient::new(
+                            channel,
+                            self.config.enable_compression,
+                            conn.limiter.clone(),
+                        );
                         let mut guard = conn.client.lock().await;
                         *guard = client;
                     }
@@ -329,6 +459,11 @@ impl AgentPool {
         )))
     }
 
+    /// Connections from agents in reverse mode
+    pub fn reverse_agents(&self) -> &ReverseAgents {
+        &self.reverse
+    }
+
     /// Get an agent connection by ID
     pub fn get_agent(&self, agent_id: &str) -> Option<Arc<AgentConnection>> {
         self.connections.get(agent_id).map(|entry| entry.value().clone())
@@ -393,6 +528,9 @@ impl AgentPool {
 
     /// Perform health check on all agents, attempting reconnection for unhealthy ones
     pub async fn health_check_all(&self) {
+        if !self.pending.is_empty() {
+            self.connect_pending().await;
+        }
         debug!("Running health check on all {} agents", self.connections.len());
         
         // Collect agents upfront to release DashMap shard locks before async work
@@ -462,34 +600,163 @@ impl AgentPool {
             .await
             .map_err(|e| AgentError::Tls(format!("Failed to read CA cert: {}", e)))?;
 
-        // Build mTLS config
-        let identity = Identity::from_pem(cert, key);
-        let ca_cert = Certificate::from_pem(ca);
-
-        let tls_config = ClientTlsConfig::new()
-            .identity(identity)
-            .ca_certificate(ca_cert)
-            .domain_name(&config.tls_domain); // Must match the SAN in agent's certificate
-
-        // Create endpoint
-        let endpoint = Channel::from_shared(format!("https://{}", config.address))
+        // Create endpoint (a reverse agent has no address to dial)
+        let host = if config.reverse { &config.tls_domain } else { &config.address };
+        // A pinned identity needs a verifier tonic's TLS settings can't take,
+        // so its connector does the TLS and the channel speaks plain HTTP/2
+        let scheme = if config.tls_identity.is_some() { "http" } else { "https" };
+        let mut endpoint = Channel::from_shared(format!("{}://{}", scheme, host))
             .map_err(|e| AgentError::InvalidConfig(format!("Invalid address: {}", e)))?
-            .tls_config(tls_config)
-            .map_err(|e| AgentError::Tls(format!("TLS config error: {}", e)))?
-            .connect_timeout(Duration::from_secs(5))
             .timeout(Duration::from_secs(30))
             .tcp_keepalive(Some(Duration::from_secs(60)));
 
-        // Connect
-        let channel = endpoint
-            .connect()
-            .await
-            .map_err(|e| {
-                error!("Failed to connect to agent {} at {}: {:?}", config.id, config.address, e);
-                AgentError::ConnectionFailed(format!("Failed to connect to {}: {}", config.address, e))
-            })?;
+        let pinned = match &config.tls_identity {
+            Some(identity) => Some(pinned_client_config(&cert, &key, &ca, identity)?),
+            None => {
+                // Build mTLS config
+                let tls_config = ClientTlsConfig::new()
+                    .identity(Identity::from_pem(cert, key))
+                    .ca_certificate(Certificate::from_pem(ca))
+                    .domain_name(&config.tls_domain); // Must match the SAN in agent's certificate
+                endpoint = endpoint
+                    .tls_config(tls_config)
+                    .map_err(|e| AgentError::Tls(format!("TLS config error: {}", e)))?;
+                None
+            }
+        };
+
+        if config.reverse {
+            let reverse = self.reverse.connector(&config.id);
+            return Ok(match pinned {
+                Some(tls) => endpoint.connect_with_connector_lazy(
+                    PinnedTlsConnector::new(reverse, tls, &config.tls_domain, &config.id)?,
+                ),
+                None => endpoint.connect_with_connector_lazy(reverse),
+            });
+        }
+
+        let connect_timeout = Duration::from_millis(self.config.connect_timeout_ms);
+        let pinned = pinned
+            .map(|tls| PinnedTlsConnector::new(TcpDialer::new(connect_timeout), tls, &config.tls_domain, &config.id))
+            .transpose()?;
+        let channel = connect_within(
+            endpoint,
+            pinned,
+            &config.address,
+            connect_timeout,
+            Duration::from_millis(self.config.tls_handshake_timeout_ms),
+        )
+        .await
+        .inspect_err(|e| error!("Failed to connect to agent {} at {}: {}", config.id, config.address, e))?;
 
         debug!("✓ mTLS channel established to agent {}", config.id);
         Ok(channel)
     }
 }
+
+/// Connect, allowing `connect_timeout` for TCP and then `handshake_timeout`
+/// for TLS. An address that drops packets, or a peer that accepts but never
+/// answers, fails instead of stalling pool initialization. A `pinned`
+/// connector does the TLS itself and times its own TCP connect.
+async fn connect_within(
+    endpoint: Endpoint,
+    pinned: Option<PinnedTlsConnector<TcpDialer>>,
+    address: &str,
+    connect_timeout: Duration,
+    handshake_timeout: Duration,
+) -> Result<Channel> {
+    let connecting = async {
+        match pinned {
+            Some(connector) => endpoint.connect_with_connector(connector).await,
+            None => endpoint.connect_timeout(connect_timeout).connect().await,
+        }
+    };
+    match tokio::time::timeout(connect_timeout + handshake_timeout, connecting).await {
+        Ok(Ok(channel)) => Ok(channel),
+        Ok(Err(e)) => Err(AgentError::ConnectionFailed(format!("Failed to connect to {}: {}", address, e))),
+        Err(_) => Err(AgentError::ConnectionFailed(format!(
+            "Timed out connecting to {} (no TLS handshake within {}ms)",
+            address,
+            handshake_timeout.as_millis()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const CONNECT: Duration = Duration::from_millis(200);
+    const HANDSHAKE: Duration = Duration::from_millis(200);
+
+    fn tls_endpoint(address: &str) -> Endpoint {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        Channel::from_shared(format!("https://{}", address))
+            .unwrap()
+            .tls_config(ClientTlsConfig::new().domain_name("localhost"))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_silent_agent_fails_within_timeouts() {
+        // Accepts TCP, never speaks TLS: the handshake timeout must fire
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let _accepting = tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let started = std::time::Instant::now();
+        let err = connect_within(tls_endpoint(&address), None, &address, CONNECT, HANDSHAKE).await.unwrap_err();
+        assert!(matches!(err, AgentError::ConnectionFailed(ref msg) if msg.contains("Timed out")), "{}", err);
+        assert!(started.elapsed() < CONNECT + HANDSHAKE + Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_agent_fails_fast() {
+        // TEST-NET-1 is never routed; packets go nowhere or are refused
+        let address = "192.0.2.1:50051";
+        let started = std::time::Instant::now();
+        let err = connect_within(tls_endpoint(address), None, address, CONNECT, HANDSHAKE).await.unwrap_err();
+        assert!(matches!(err, AgentError::ConnectionFailed(_)));
+        assert!(started.elapsed() < CONNECT + HANDSHAKE + Duration::from_secs(1));
+    }
+
+    fn metadata(agent_ms: i64) -> HashMap<String, String> {
+        HashMap::from([
+            ("clock_unix_ms".to_string(), agent_ms.to_string()),
+            ("timezone".to_string(), "Europe/Berlin".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_clock_report_in_sync_agent() {
+        let now = chrono::Utc::now().timestamp_millis();
+        // Agent read its clock mid-request; 40ms round trip
+        let report = ClockReport::from_metadata(&metadata(now + 20), now, now + 40, 1000).unwrap();
+
+        assert!(report.offset_ms.abs() <= 1, "offset was {}ms", report.offset_ms);
+        assert!(!report.skewed);
+        assert_eq!(report.timezone.as_deref(), Some("Europe/Berlin"));
+    }
+
+    #[test]
+    fn test_clock_report_flags_skewed_agent() {
+        let now = chrono::Utc::now().timestamp_millis();
+        let behind = ClockReport::from_metadata(&metadata(now - 5 * 60_000), now, now, 1000).unwrap();
+        assert_eq!(behind.offset_ms, -5 * 60_000);
+        assert!(behind.skewed);
+
+        let ahead = ClockReport::from_metadata(&metadata(now + 1500), now, now, 1000).unwrap();
+        assert!(ahead.skewed);
+    }
+
+    #[test]
+    fn test_clock_report_missing_for_old_agents() {
+        assert!(ClockReport::from_metadata(&HashMap::new(), 0, 0, 1000).is_none());
+    }
+}
@@ -0,0 +1,348 @@
+//! Agent-initiated connections, for agents the cluster can't dial (NAT,
+//! firewalls). The agent connects to `agents.reverse_listen_address` and
+//! names itself; the cluster then runs its usual mTLS gRPC client over that
+//! socket, so every call works as it does on a connection the cluster made.
+//!
+//! Handshake, in plain text before TLS starts:
+//!   agent   → `DOCKTAIL-REVERSE/1 <agent id>\n`
+//!   cluster → `OK\n` or `ERR <reason>\n`
+//! After `OK` the cluster is the TLS client and the agent the TLS server,
+//! with the same certificates as a dialed connection.
+//!
+//! The hello is unauthenticated, so an admitted socket only parks until the
+//! channel takes it: a later dial for the same agent replaces it, and one
+//! left unclaimed for `PARKED_TIMEOUT` is closed. Anyone can name an agent,
+//! but a socket from a peer without the agent's certificate fails the TLS
+//! handshake and can't hold the agent's place.
+
+use super::AgentPool;
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use hyper_util::rt::TokioIo;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tonic::transport::Uri;
+use tracing::{debug, info, warn};
+
+/// First word of an agent's hello
+pub const HELLO_PREFIX: &str = "DOCKTAIL-REVERSE/1";
+const MAX_HELLO_LEN: usize = 256;
+/// Time an agent has to send its hello after connecting
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time an admitted socket waits for its channel before it is closed
+const PARKED_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sockets of reverse agents that dialed in, waiting for their channel
+pub struct ReverseAgents {
+    waiting: DashMap<String, Arc<Slot>>,
+    parked_timeout: Duration,
+}
+
+impl Default for ReverseAgents {
+    fn default() -> Self {
+        Self::new(PARKED_TIMEOUT)
+    }
+}
+
+impl ReverseAgents {
+    pub fn new(parked_timeout: Duration) -> Self {
+        Self {
+            waiting: DashMap::new(),
+            parked_timeout,
+        }
+    }
+
+    /// Connector for `agent_id`'s channel. Each connect waits for the agent
+    /// to dial in, so a dropped connection comes back when the agent redials.
+    pub fn connector(&self, agent_id: &str) -> ReverseConnector {
+        let slot = Arc::new(Slot::default());
+        if let Some(previous) = self.waiting.insert(agent_id.to_string(), slot.clone()) {
+            previous.close();
+        }
+        ReverseConnector { slot }
+    }
+
+    /// Read an agent's hello and park its socket for the agent's channel,
+    /// closing any socket parked before it. Returns the agent's ID.
+    async fn admit(&self, mut socket: TcpStream) -> Result<String, String> {
+        let agent_id = tokio::time::timeout(HELLO_TIMEOUT, read_hello(&mut socket))
+            .await
+            .map_err(|_| "no hello within 10s".to_string())??;
+        let Some(slot) = self.waiting.get(&agent_id).map(|slot| slot.clone()) else {
+            return Err(reject(socket, format!("agent '{}' is not configured for reverse mode", agent_id)).await);
+        };
+        socket.write_all(b"OK\n").await.map_err(|e| e.to_string())?;
+
+        let parked = slot.park(socket);
+        let timeout = self.parked_timeout;
+        let expiring = agent_id.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            if slot.expire(parked) {
+                debug!("Closed reverse connection from agent {} left unclaimed for {:?}", expiring, timeout);
+            }
+        });
+        Ok(agent_id)
+    }
+}
+
+/// The one socket parked for an agent's channel
+#[derive(Default)]
+struct Slot {
+    /// (admission number, socket)
+    parked: parking_lot::Mutex<Option<(u64, TcpStream)>>,
+    admitted: AtomicU64,
+    arrived: Notify,
+    /// The agent's channel was replaced; its connector gives up
+    closed: AtomicBool,
+}
+
+impl Slot {
+    /// Park a socket in place of any parked before. Returns its admission
+    /// number.
+    fn park(&self, socket: TcpStream) -> u64 {
+        let admission = self.admitted.fetch_add(1, Ordering::Relaxed);
+        if self.parked.lock().replace((admission, socket)).is_some() {
+            debug!("Replaced a reverse connection that was still waiting");
+        }
+        self.arrived.notify_one();
+        admission
+    }
+
+    /// Close the socket of `admission` if it is still parked
+    fn expire(&self, admission: u64) -> bool {
+        let mut parked = self.parked.lock();
+        if parked.as_ref().is_some_and(|(a, _)| *a == admission) {
+            *parked = None;
+            return true;
+        }
+        false
+    }
+
+    fn take(&self) -> Option<TcpStream> {
+        self.parked.lock().take().map(|(_, socket)| socket)
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.parked.lock().take();
+        self.arrived.notify_one();
+    }
+}
+
+/// Tell the agent why it was turned away; the reason is also returned
+async fn reject(mut socket: TcpStream, reason: String) -> String {
+    let _ = socket.write_all(format!("ERR {}\n", reason).as_bytes()).await;
+    reason
+}
+
+/// Read `DOCKTAIL-REVERSE/1 <agent id>\n` a byte at a time, so nothing of
+/// the TLS handshake that follows is consumed
+async fn read_hello(socket: &mut TcpStream) -> Result<String, String> {
+    let mut line = Vec::new();
+    loop {
+        let byte = socket.read_u8().await.map_err(|e| format!("reading hello: {}", e))?;
+        if byte == b'\n' {
+            break;
+        }
+        if line.len() == MAX_HELLO_LEN {
+            return Err("hello too long".to_string());
+        }
+        line.push(byte);
+    }
+    let line = String::from_utf8(line).map_err(|_| "hello is not UTF-8".to_string())?;
+    match line.trim_end_matches('\r').split_once(' ') {
+        Some((HELLO_PREFIX, agent_id)) if !agent_id.is_empty() => Ok(agent_id.to_string()),
+        _ => Err(format!("unexpected hello '{}'", line)),
+    }
+}
+
+/// Accept agent connections on `listener` for as long as the cluster runs.
+/// An admitted agent is health checked right away instead of at the next
+/// scheduled check.
+pub async fn accept_agents(pool: Arc<AgentPool>, listener: TcpListener) {
+    loop {
+        let (socket, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Reverse listener accept error: {}", e);
+                continue;
+            }
+        };
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            match pool.reverse_agents().admit(socket).await {
+                Ok(agent_id) => {
+                    info!("Agent {} dialed in from {}", agent_id, peer);
+                    if let Some(conn) = pool.get_agent(&agent_id) {
+                        let _ = conn.check_health().await;
+                    }
+                }
+                Err(reason) => warn!("Rejected reverse connection from {}: {}", peer, reason),
+            }
+        });
+    }
+}
+
+/// Hands out the sockets a reverse agent dials in with, as connections for
+/// its gRPC channel
+#[derive(Clone)]
+pub struct ReverseConnector {
+    slot: Arc<Slot>,
+}
+
+impl tower::Service<Uri> for ReverseConnector {
+    type Response = TokioIo<TcpStream>;
+    type Error = io::Error;
+    type Future = BoxFuture<'static, io::Result<TokioIo<TcpStream>>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let slot = self.slot.clone();
+        Box::pin(async move {
+            debug!("Waiting for reverse agent to dial in");
+            loop {
+                if slot.closed.load(Ordering::Relaxed) {
+                    // Pass the wakeup on to any other waiting connect
+                    slot.arrived.notify_one();
+                    return Err(io::Error::new(io::ErrorKind::NotConnected, "agent was removed from reverse mode"));
+                }
+                if let Some(socket) = slot.take() {
+                    return Ok(TokioIo::new(socket));
+                }
+                slot.arrived.notified().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::Service;
+
+    async fn dial(address: std::net::SocketAddr, hello: &str) -> (TcpStream, String) {
+        let mut socket = TcpStream::connect(address).await.unwrap();
+        socket.write_all(hello.as_bytes()).await.unwrap();
+        let mut reply = Vec::new();
+        while !reply.ends_with(b"\n") {
+            reply.push(socket.read_u8().await.unwrap());
+        }
+        (socket, String::from_utf8(reply).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_configured_agent_connection_reaches_its_channel() {
+        let agents = Arc::new(ReverseAgents::default());
+        let mut connector = agents.connector("edge-1");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let admitting = {
+            let agents = agents.clone();
+            tokio::spawn(async move {
+                let mut admitted = Vec::new();
+                for _ in 0..2 {
+                    let (socket, _) = listener.accept().await.unwrap();
+                    admitted.push(agents.admit(socket).await);
+                }
+                admitted
+            })
+        };
+
+        let (_, reply) = dial(address, "DOCKTAIL-REVERSE/1 stranger\n").await;
+        assert_eq!(reply, "ERR agent 'stranger' is not configured for reverse mode\n");
+
+        let (mut agent_side, reply) = dial(address, "DOCKTAIL-REVERSE/1 edge-1\n").await;
+        assert_eq!(reply, "OK\n");
+        assert_eq!(
+            admitting.await.unwrap(),
+            vec![Err("agent 'stranger' is not configured for reverse mode".to_string()), Ok("edge-1".to_string())]
+        );
+
+        // The channel's next connection is the socket the agent dialed
+        let mut cluster_side = connector.call(Uri::from_static("https://localhost")).await.unwrap().into_inner();
+        agent_side.write_all(b"ping").await.unwrap();
+        let mut received = [0u8; 4];
+        cluster_side.read_exact(&mut received).await.unwrap();
+        assert_eq!(&received, b"ping");
+    }
+
+    async fn admit_all(agents: Arc<ReverseAgents>, listener: TcpListener) {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let agents = agents.clone();
+            tokio::spawn(async move { agents.admit(socket).await });
+        }
+    }
+
+    /// True once the cluster closed the agent's socket
+    async fn closed_by_cluster(socket: &mut TcpStream) -> bool {
+        let mut byte = [0u8; 1];
+        matches!(tokio::time::timeout(Duration::from_secs(5), socket.read(&mut byte)).await, Ok(Ok(0)))
+    }
+
+    #[tokio::test]
+    async fn test_later_dial_replaces_parked_socket() {
+        let agents = Arc::new(ReverseAgents::default());
+        let mut connector = agents.connector("edge-1");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(admit_all(agents.clone(), listener));
+
+        // Someone names edge-1 first; the real agent is still let in
+        let (mut squatter, reply) = dial(address, "DOCKTAIL-REVERSE/1 edge-1\n").await;
+        assert_eq!(reply, "OK\n");
+        let (mut agent_side, reply) = dial(address, "DOCKTAIL-REVERSE/1 edge-1\n").await;
+        assert_eq!(reply, "OK\n");
+        assert!(closed_by_cluster(&mut squatter).await);
+
+        let mut cluster_side = connector.call(Uri::from_static("https://localhost")).await.unwrap().into_inner();
+        agent_side.write_all(b"ping").await.unwrap();
+        let mut received = [0u8; 4];
+        cluster_side.read_exact(&mut received).await.unwrap();
+        assert_eq!(&received, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_unclaimed_socket_is_closed() {
+        let agents = Arc::new(ReverseAgents::new(Duration::from_millis(50)));
+        let mut connector = agents.connector("edge-1");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(admit_all(agents.clone(), listener));
+
+        let (mut parked, reply) = dial(address, "DOCKTAIL-REVERSE/1 edge-1\n").await;
+        assert_eq!(reply, "OK\n");
+        assert!(closed_by_cluster(&mut parked).await);
+
+        // The channel waits for the next dial rather than the expired socket
+        let connecting = tokio::spawn(async move {
+            connector.call(Uri::from_static("https://localhost")).await.map(|io| io.into_inner())
+        });
+        let (mut agent_side, _) = dial(address, "DOCKTAIL-REVERSE/1 edge-1\n").await;
+        let mut cluster_side = connecting.await.unwrap().unwrap();
+        agent_side.write_all(b"ping").await.unwrap();
+        let mut received = [0u8; 4];
+        cluster_side.read_exact(&mut received).await.unwrap();
+        assert_eq!(&received, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_replaced_channel_stops_waiting() {
+        let agents = ReverseAgents::default();
+        let mut old = agents.connector("edge-1");
+        let _new = agents.connector("edge-1");
+
+        let err = old.call(Uri::from_static("https://localhost")).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    }
+}
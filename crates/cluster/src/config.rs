@@ -1,6 +1,9 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::metrics::SubscriptionKind;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ClusterConfig {
@@ -9,6 +12,16 @@ pub struct ClusterConfig {
     pub security: SecurityConfig,
     pub logging: LoggingConfig,
     pub graphql: GraphQLConfig,
+    #[serde(default)]
+    pub log_defaults: LogDefaultsConfig,
+    #[serde(default)]
+    pub readiness: ReadinessConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub inventory_cache: InventoryCacheConfig,
+    #[serde(default)]
+    pub subscription_lifetimes: SubscriptionLifetimeConfig,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -28,6 +41,132 @@ pub struct AgentRegistryConfig {
     pub health_check_interval: u64,
     pub reconnect_backoff: u64,
     pub max_reconnect_attempts: u32,
+    /// Request gzip compression on agent log streams
+    #[serde(default)]
+    pub enable_compression: bool,
+    /// Flag agents whose clock differs from the cluster's by more than this
+    #[serde(default = "default_clock_skew_threshold_ms")]
+    pub clock_skew_threshold_ms: u64,
+    /// TLS versions and cipher suites offered to agents
+    #[serde(default)]
+    pub tls: TlsPolicyConfig,
+    /// How long a call waits for a slot on an agent at its `max_in_flight`
+    #[serde(default = "default_call_queue_timeout_ms")]
+    pub call_queue_timeout_ms: u64,
+    /// Give up on an agent's TCP connect after this long
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Give up on an agent's TLS handshake after this long, once connected
+    #[serde(default = "default_tls_handshake_timeout_ms")]
+    pub tls_handshake_timeout_ms: u64,
+    /// Accept connections from agents in reverse mode on this address
+    /// (off when unset)
+    #[serde(default)]
+    pub reverse_listen_address: Option<String>,
+    /// Refuse to start unless every agent has a `tls_identity` pinned
+    #[serde(default)]
+    pub require_tls_identity: bool,
+    /// Whether startup waits for a healthy agent or starts without one
+    #[serde(default)]
+    pub startup_mode: StartupMode,
+    /// How long a `strict` startup waits for a healthy agent
+    #[serde(default = "default_startup_timeout_secs")]
+    pub startup_timeout_secs: u64,
+}
+
+/// What the cluster does when no agent is healthy at startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupMode {
+    /// Start anyway; agents join as they become reachable or dial in
+    #[default]
+    Lenient,
+    /// Fail to start unless an agent is healthy within `startup_timeout_secs`
+    Strict,
+}
+
+/// TLS policy for agent connections
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TlsPolicyConfig {
+    /// Lowest TLS version offered: "1.2" or "1.3"
+    pub min_version: String,
+    /// Allowed cipher suites by IANA name (e.g. "TLS13_AES_256_GCM_SHA384").
+    /// Empty allows rustls' defaults, which are all AEAD with forward secrecy.
+    pub cipher_suites: Vec<String>,
+}
+
+impl Default for TlsPolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_version: "1.2".to_string(),
+            cipher_suites: Vec::new(),
+        }
+    }
+}
+
+impl TlsPolicyConfig {
+    /// The crypto provider for agent channels. The gRPC client offers every
+    /// version the provider has suites for, so leaving out the TLS 1.2 suites
+    /// is what pins TLS 1.3.
+    pub fn crypto_provider(&self) -> Result<rustls::crypto::CryptoProvider> {
+        use rustls::crypto::aws_lc_rs::{default_provider, ALL_CIPHER_SUITES};
+
+        let min = match self.min_version.as_str() {
+            "1.2" => rustls::ProtocolVersion::TLSv1_2,
+            "1.3" => rustls::ProtocolVersion::TLSv1_3,
+            other => anyhow::bail!("agents.tls.min_version must be \"1.2\" or \"1.3\", got '{}'", other),
+        };
+        let mut provider = default_provider();
+        if !self.cipher_suites.is_empty() {
+            provider.cipher_suites = self
+                .cipher_suites
+                .iter()
+                .map(|name| {
+                    ALL_CIPHER_SUITES
+                        .iter()
+                        .find(|s| s.suite().as_str() == Some(name.as_str()))
+                        .copied()
+                        .with_context(|| format!("agents.tls.cipher_suites: unknown suite '{}'", name))
+                })
+                .collect::<Result<_>>()?;
+        }
+        provider.cipher_suites.retain(|s| u16::from(s.version().version) >= u16::from(min));
+        if provider.cipher_suites.is_empty() {
+            anyhow::bail!(
+                "agents.tls.cipher_suites: none of the allowed suites work with TLS {} or later",
+                self.min_version
+            );
+        }
+        Ok(provider)
+    }
+
+    /// Make this policy the process-wide default, which agent channels use
+    pub fn install(&self) -> Result<()> {
+        self.crypto_provider()?
+            .install_default()
+            .map_err(|_| anyhow::anyhow!("a TLS crypto provider was already installed"))
+    }
+}
+
+fn default_clock_skew_threshold_ms() -> u64 {
+    1000
+}
+
+fn default_call_queue_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_tls_handshake_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_startup_timeout_secs() -> u64 {
+    30
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -41,8 +180,20 @@ pub struct AgentConfig {
     /// TLS domain name for certificate verification (defaults to "localhost")
     #[serde(default = "default_tls_domain")]
     pub tls_domain: String,
+    /// Identity the agent's certificate must carry as a SAN: a DNS name or a
+    /// URI such as a SPIFFE ID. Certificates from the CA without it are
+    /// refused, and it replaces the `tls_domain` name check.
+    #[serde(default)]
+    pub tls_identity: Option<String>,
     #[serde(default)]
     pub labels: HashMap<String, String>,
+    /// Most gRPC calls in flight to this agent at once (unlimited if unset)
+    #[serde(default)]
+    pub max_in_flight: Option<usize>,
+    /// The agent dials in to `agents.reverse_listen_address` instead of
+    /// being dialed at `address` (for agents behind NAT or a firewall)
+    #[serde(default)]
+    pub reverse: bool,
 }
 
 fn default_tls_domain() -> String {
@@ -81,8 +232,198 @@ pub enum LogOutput {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GraphQLConfig {
     pub enable_graphiql: bool,
+    /// Allow schema introspection (`__schema` / `__type`). GraphiQL needs this.
+    pub enable_introspection: bool,
     pub max_depth: usize,
     pub max_complexity: usize,
+    /// Show container names instead of IDs in `containerName` fields
+    /// (resolved from agent inventory, falling back to the ID) and agent
+    /// names in `nodeName` fields
+    #[serde(default = "default_prefer_names")]
+    pub prefer_names: bool,
+    /// WebSocket subprotocols accepted on `/ws`; the client's first offer
+    /// must be one of them
+    #[serde(default = "default_ws_protocols")]
+    pub ws_protocols: Vec<String>,
+}
+
+/// Options applied to log subscriptions when the client sends no `options`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LogDefaultsConfig {
+    /// Lines of history sent before following (0 = the whole log)
+    pub tail: i32,
+    /// Include timestamps in entries
+    pub timestamps: bool,
+    /// Lines matching any of these regexes are dropped from every log
+    /// subscription, whatever its options, unless it sets `skipDefaultExcludes`
+    pub exclude_patterns: Vec<String>,
+    /// Lines held for a subscription paused with `pauseLogStream`; later
+    /// ones are dropped and counted
+    pub pause_buffer_lines: usize,
+}
+
+impl Default for LogDefaultsConfig {
+    fn default() -> Self {
+        Self {
+            tail: 50,
+            timestamps: true,
+            exclude_patterns: Vec::new(),
+            pause_buffer_lines: crate::graphql::subscriptions::DEFAULT_PAUSE_BUFFER,
+        }
+    }
+}
+
+impl LogDefaultsConfig {
+    /// `exclude_patterns` compiled, or `None` when there are none
+    pub fn exclude_set(&self) -> Result<Option<regex::RegexSet>> {
+        if self.exclude_patterns.is_empty() {
+            return Ok(None);
+        }
+        let set = regex::RegexSet::new(&self.exclude_patterns)
+            .context("Invalid log_defaults.exclude_patterns")?;
+        Ok(Some(set))
+    }
+}
+
+/// Highest `limits.max_container_streams` a config may set
+pub const MAX_CONTAINER_STREAMS_CEILING: usize = 200;
+
+/// Per-request limits that keep one client from tying up the cluster and agents
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Containers one `logsFromContainers` subscription may stream from
+    /// (at most 200)
+    pub max_container_streams: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self { max_container_streams: 20 }
+    }
+}
+
+impl LimitsConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_CONTAINER_STREAMS_CEILING).contains(&self.max_container_streams) {
+            anyhow::bail!(
+                "limits.max_container_streams must be between 1 and {}, got {}",
+                MAX_CONTAINER_STREAMS_CEILING, self.max_container_streams
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Highest `inventory_cache.ttl_secs` a config may set
+pub const MAX_INVENTORY_CACHE_TTL_SECS: u64 = 300;
+
+/// Cluster-side cache of `containers` query results
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InventoryCacheConfig {
+    /// How long a container listing is served from cache (0 disables it)
+    pub ttl_secs: u64,
+}
+
+impl Default for InventoryCacheConfig {
+    fn default() -> Self {
+        Self { ttl_secs: 2 }
+    }
+}
+
+impl InventoryCacheConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.ttl_secs > MAX_INVENTORY_CACHE_TTL_SECS {
+            anyhow::bail!(
+                "inventory_cache.ttl_secs must be at most {}, got {}",
+                MAX_INVENTORY_CACHE_TTL_SECS, self.ttl_secs
+            );
+        }
+        Ok(())
+    }
+
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_secs)
+    }
+}
+
+/// Longest each kind of subscription may run before the cluster completes
+/// it, in seconds (0 = no limit)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SubscriptionLifetimeConfig {
+    /// Log subscriptions (`logStream`, `logsFromContainers`,
+    /// `logsByContainerGroup`, `logRateHistogram`)
+    pub log_secs: u64,
+    /// Stats subscriptions (`containerStatsStream`, `statsAnomalies`)
+    pub stats_secs: u64,
+    /// `agentHealthStream`
+    pub health_secs: u64,
+}
+
+impl SubscriptionLifetimeConfig {
+    /// Max lifetime of a kind of subscription, `None` when unlimited
+    pub fn max_lifetime(&self, kind: SubscriptionKind) -> Option<Duration> {
+        let secs = match kind {
+            SubscriptionKind::Log => self.log_secs,
+            SubscriptionKind::Stats => self.stats_secs,
+            SubscriptionKind::Health => self.health_secs,
+        };
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+}
+
+/// When `/ready` reports the cluster ready to take traffic
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReadinessConfig {
+    /// Healthy agents required: a count ("2") or a share of the configured
+    /// agents ("50%", rounded up). A count above the number of agents
+    /// requires all of them.
+    pub min_healthy_agents: String,
+}
+
+impl Default for ReadinessConfig {
+    fn default() -> Self {
+        Self { min_healthy_agents: "1".to_string() }
+    }
+}
+
+impl ReadinessConfig {
+    /// Healthy agents needed out of `total` configured
+    pub fn required_healthy(&self, total: usize) -> Result<usize> {
+        let value = self.min_healthy_agents.trim();
+        let required = match value.strip_suffix('%') {
+            Some(percent) => {
+                let percent: usize = percent.trim().parse()
+                    .ok()
+                    .filter(|p| *p <= 100)
+                    .with_context(|| format!("Invalid readiness.min_healthy_agents '{}' (expected 0%-100%)", value))?;
+                (total * percent).div_ceil(100)
+            }
+            None => value.parse::<usize>()
+                .with_context(|| format!("Invalid readiness.min_healthy_agents '{}' (expected a count or a percentage)", value))?,
+        };
+        Ok(required.min(total))
+    }
+
+    /// Ready when enough agents are healthy. With no agents configured there
+    /// is nothing to wait for, so that counts as ready.
+    pub fn is_ready(&self, total: usize, healthy: usize) -> bool {
+        // `validate` rejects bad values at startup; fall back to the old "any healthy agent"
+        let required = self.required_healthy(total).unwrap_or(1.min(total));
+        total == 0 || healthy >= required
+    }
+}
+
+fn default_prefer_names() -> bool {
+    true
+}
+
+fn default_ws_protocols() -> Vec<String> {
+    crate::graphql::websocket::DEFAULT_WS_PROTOCOLS.iter().map(|p| p.to_string()).collect()
 }
 
 impl ClusterConfig {
@@ -135,8 +476,56 @@ impl ClusterConfig {
         self.server.bind_address.parse::<std::net::SocketAddr>()
             .context("Invalid bind_address")?;
 
+        if self.graphql.ws_protocols.is_empty() {
+            anyhow::bail!("graphql.ws_protocols must list at least one subprotocol");
+        }
+        for protocol in &self.graphql.ws_protocols {
+            if !crate::graphql::websocket::DEFAULT_WS_PROTOCOLS.contains(&protocol.as_str()) {
+                anyhow::bail!(
+                    "Unsupported graphql.ws_protocols entry '{}' (supported: graphql-transport-ws, graphql-ws)",
+                    protocol
+                );
+            }
+        }
+
+        self.agents.tls.crypto_provider()?;
+        if self.agents.startup_mode == StartupMode::Strict && self.agents.startup_timeout_secs == 0 {
+            anyhow::bail!("agents.startup_timeout_secs must be > 0 with startup_mode = \"strict\"");
+        }
+
+        if self.log_defaults.tail < 0 {
+            anyhow::bail!("log_defaults.tail must be >= 0 (0 streams the whole log)");
+        }
+        self.log_defaults.exclude_set()?;
+        self.readiness.required_healthy(0)?;
+        self.limits.validate()?;
+        self.inventory_cache.validate()?;
+
+        if let Some(address) = &self.agents.reverse_listen_address {
+            address.parse::<std::net::SocketAddr>()
+                .context("Invalid agents.reverse_listen_address")?;
+        }
+
         // Validate agent configurations
         for agent in &self.agents.static_agents {
+            if agent.reverse && self.agents.reverse_listen_address.is_none() {
+                anyhow::bail!(
+                    "Agent '{}' is in reverse mode but agents.reverse_listen_address is not set",
+                    agent.id
+                );
+            }
+            match &agent.tls_identity {
+                Some(identity) if identity.trim().is_empty() => {
+                    anyhow::bail!("Agent '{}' has an empty tls_identity", agent.id);
+                }
+                None if self.agents.require_tls_identity => {
+                    anyhow::bail!(
+                        "Agent '{}' has no tls_identity but agents.require_tls_identity is set",
+                        agent.id
+                    );
+                }
+                _ => {}
+            }
             // Check that all TLS cert/key/ca files exist
             let tls_files = [
                 ("cert", &agent.tls_cert),
@@ -182,6 +571,16 @@ impl Default for ClusterConfig {
                 health_check_interval: 30,
                 reconnect_backoff: 5,
                 max_reconnect_attempts: 3,
+                enable_compression: false,
+                clock_skew_threshold_ms: default_clock_skew_threshold_ms(),
+                tls: TlsPolicyConfig::default(),
+                call_queue_timeout_ms: default_call_queue_timeout_ms(),
+                connect_timeout_ms: default_connect_timeout_ms(),
+                tls_handshake_timeout_ms: default_tls_handshake_timeout_ms(),
+                reverse_listen_address: None,
+                require_tls_identity: false,
+                startup_mode: StartupMode::default(),
+                startup_timeout_secs: default_startup_timeout_secs(),
             },
             security: SecurityConfig {
                 jwt_secret: None,
@@ -194,9 +593,141 @@ impl Default for ClusterConfig {
             },
             graphql: GraphQLConfig {
                 enable_graphiql: false,
+                enable_introspection: true,
                 max_depth: 15,
                 max_complexity: 1000,
+                prefer_names: true,
+                ws_protocols: default_ws_protocols(),
             },
+            log_defaults: LogDefaultsConfig::default(),
+            readiness: ReadinessConfig::default(),
+            limits: LimitsConfig::default(),
+            inventory_cache: InventoryCacheConfig::default(),
+            subscription_lifetimes: SubscriptionLifetimeConfig::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(min_version: &str, cipher_suites: &[&str]) -> TlsPolicyConfig {
+        TlsPolicyConfig {
+            min_version: min_version.to_string(),
+            cipher_suites: cipher_suites.iter().map(|s| s.to_string()).collect(),
         }
     }
+
+    fn suite_names(policy: &TlsPolicyConfig) -> Vec<&'static str> {
+        let provider = policy.crypto_provider().unwrap();
+        provider.cipher_suites.iter().filter_map(|s| s.suite().as_str()).collect()
+    }
+
+    #[test]
+    fn test_tls_policy_suites() {
+        // Pinning 1.3 leaves no TLS 1.2 suite for the client to offer
+        let pinned = suite_names(&policy("1.3", &[]));
+        assert!(!pinned.is_empty());
+        assert!(pinned.iter().all(|name| name.starts_with("TLS13_")), "{:?}", pinned);
+        assert!(suite_names(&policy("1.2", &[])).iter().any(|name| !name.starts_with("TLS13_")));
+
+        let chosen = policy("1.2", &["TLS13_AES_256_GCM_SHA384", "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"]);
+        assert_eq!(suite_names(&chosen), vec!["TLS13_AES_256_GCM_SHA384", "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"]);
+    }
+
+    #[test]
+    fn test_tls_policy_rejects_bad_settings() {
+        assert!(policy("1.1", &[]).crypto_provider().is_err());
+        assert!(policy("1.2", &["TLS_RSA_WITH_RC4_128_SHA"]).crypto_provider().is_err());
+        assert!(policy("1.3", &["TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"]).crypto_provider().is_err());
+    }
+
+    fn readiness(min_healthy_agents: &str) -> ReadinessConfig {
+        ReadinessConfig { min_healthy_agents: min_healthy_agents.to_string() }
+    }
+
+    #[test]
+    fn test_readiness_threshold() {
+        // Default: any healthy agent
+        let default = ReadinessConfig::default();
+        assert!(default.is_ready(5, 1));
+        assert!(!default.is_ready(5, 0));
+
+        let count = readiness("3");
+        assert!(count.is_ready(5, 3));
+        assert!(!count.is_ready(5, 2));
+        // More than configured: all of them
+        assert_eq!(count.required_healthy(2).unwrap(), 2);
+        assert!(count.is_ready(2, 2));
+
+        let share = readiness("50%");
+        assert_eq!(share.required_healthy(5).unwrap(), 3);
+        assert!(share.is_ready(5, 3));
+        assert!(!share.is_ready(5, 2));
+        assert!(share.is_ready(4, 2));
+    }
+
+    #[test]
+    fn test_readiness_with_no_agents() {
+        for threshold in ["1", "3", "100%"] {
+            assert!(readiness(threshold).is_ready(0, 0), "{}", threshold);
+        }
+    }
+
+    #[test]
+    fn test_limits_capped_at_ceiling() {
+        assert!(LimitsConfig::default().validate().is_ok());
+        let raised = LimitsConfig { max_container_streams: MAX_CONTAINER_STREAMS_CEILING };
+        assert!(raised.validate().is_ok());
+
+        let too_many = LimitsConfig { max_container_streams: MAX_CONTAINER_STREAMS_CEILING + 1 };
+        assert!(too_many.validate().is_err());
+        let config = ClusterConfig { limits: too_many, ..ClusterConfig::default() };
+        assert!(config.validate().is_err());
+        assert!(LimitsConfig { max_container_streams: 0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_inventory_cache_ttl_capped() {
+        assert!(InventoryCacheConfig::default().validate().is_ok());
+        assert!(InventoryCacheConfig { ttl_secs: 0 }.validate().is_ok());
+        let too_long = InventoryCacheConfig { ttl_secs: MAX_INVENTORY_CACHE_TTL_SECS + 1 };
+        let config = ClusterConfig { inventory_cache: too_long, ..ClusterConfig::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_readiness_rejects_bad_threshold() {
+        for bad in ["", "many", "-1", "150%", "%"] {
+            assert!(readiness(bad).required_healthy(4).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_require_tls_identity() {
+        let mut config = ClusterConfig::default();
+        config.agents.require_tls_identity = true;
+        config.agents.static_agents.push(AgentConfig {
+            id: "edge-1".to_string(),
+            name: "Edge 1".to_string(),
+            address: "edge-1:50051".to_string(),
+            tls_cert: "Cargo.toml".to_string(),
+            tls_key: "Cargo.toml".to_string(),
+            tls_ca: "Cargo.toml".to_string(),
+            tls_domain: default_tls_domain(),
+            tls_identity: None,
+            labels: HashMap::new(),
+            max_in_flight: None,
+            reverse: false,
+        });
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("no tls_identity"), "{}", err);
+
+        config.agents.static_agents[0].tls_identity = Some(" ".to_string());
+        assert!(config.validate().unwrap_err().to_string().contains("empty tls_identity"));
+
+        config.agents.static_agents[0].tls_identity = Some("spiffe://docktail/agent/edge-1".to_string());
+        config.validate().unwrap();
+    }
 }
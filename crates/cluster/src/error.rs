@@ -1,16 +1,24 @@
 use thiserror::Error;
 use async_graphql::ErrorExtensions;
+use tonic::Code;
+
+use crate::agent::AgentError;
+
+/// Metadata key an agent sets on RESOURCE_EXHAUSTED when Docker throttled it
+const RETRY_AFTER_METADATA: &str = "retry-after";
+
+/// Used when the agent's retry-after can't be read
+const DEFAULT_RETRY_AFTER_SECS: u64 = 5;
 
 #[derive(Debug, Error)]
 pub enum ApiError {
     #[error("Container not found: {0}")]
-    #[allow(dead_code)]
     ContainerNotFound(String),
 
     #[error("Agent not found: {0}")]
     AgentNotFound(String),
 
-    #[error("Agent unavailable: {0}")]
+    #[error("Agent '{0}' is not healthy. Try again later or check agent status.")]
     AgentUnavailable(String),
 
     #[error("Invalid request: {0}")]
@@ -21,9 +29,17 @@ pub enum ApiError {
     Unauthorized(String),
 
     #[error("Forbidden: {0}")]
-    #[allow(dead_code)]
     Forbidden(String),
 
+    #[error("Not supported: {0}")]
+    Unsupported(String),
+
+    #[error("Agent '{agent_id}' is at capacity: {message}")]
+    ResourceExhausted { agent_id: String, message: String, retry_after_secs: Option<u64> },
+
+    #[error("Agent '{agent_id}' is being rate limited by Docker; retry after {retry_after_secs}s")]
+    RateLimited { agent_id: String, retry_after_secs: u64 },
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -40,33 +56,266 @@ pub type ApiResult<T> = Result<T, ApiError>;
 
 // GraphQL integration: Add structured error codes to ApiError
 impl ApiError {
-    /// Convert ApiError to async_graphql::Error with structured error codes.
+    /// Classify a failed agent call. Statuses the agent means for the client
+    /// (admission limits, bad arguments) keep their message; everything else
+    /// becomes an internal error with `context` for the server log.
+    pub fn from_agent(agent_id: &str, context: &str, err: AgentError) -> Self {
+        match err {
+            AgentError::Status(status) => Self::from_status(agent_id, context, status),
+            AgentError::Transport(_) | AgentError::ConnectionFailed(_) | AgentError::Unhealthy(_) => {
+                Self::AgentUnavailable(agent_id.to_string())
+            }
+            AgentError::NotFound(_) => Self::AgentNotFound(agent_id.to_string()),
+            saturated @ AgentError::Saturated { retry_after_secs, .. } => Self::ResourceExhausted {
+                agent_id: agent_id.to_string(),
+                message: saturated.to_string(),
+                retry_after_secs: Some(retry_after_secs),
+            },
+            other => Self::Internal(format!("{}: {}", context, other)),
+        }
+    }
+
+    /// Like [`from_agent`](Self::from_agent), for a call about one container:
+    /// the agent answers NOT_FOUND when that container doesn't exist
+    pub fn from_container_agent(agent_id: &str, container_id: &str, context: &str, err: AgentError) -> Self {
+        match err {
+            AgentError::Status(status) => Self::from_container_status(agent_id, container_id, context, status),
+            other => Self::from_agent(agent_id, context, other),
+        }
+    }
+
+    /// Like [`from_status`](Self::from_status), for a call or stream about
+    /// one container
+    pub fn from_container_status(agent_id: &str, container_id: &str, context: &str, status: tonic::Status) -> Self {
+        match status.code() {
+            Code::NotFound => Self::ContainerNotFound(container_id.to_string()),
+            _ => Self::from_status(agent_id, context, status),
+        }
+    }
+
+    /// Classify a gRPC status returned by an agent (call or stream item)
+    pub fn from_status(agent_id: &str, context: &str, status: tonic::Status) -> Self {
+        match status.code() {
+            // Docker throttling on the agent, as opposed to stream admission
+            Code::ResourceExhausted if status.metadata().contains_key(RETRY_AFTER_METADATA) => Self::RateLimited {
+                agent_id: agent_id.to_string(),
+                retry_after_secs: status
+                    .metadata()
+                    .get(RETRY_AFTER_METADATA)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_RETRY_AFTER_SECS),
+            },
+            Code::ResourceExhausted => Self::ResourceExhausted {
+                agent_id: agent_id.to_string(),
+                message: status.message().to_string(),
+                retry_after_secs: None,
+            },
+            Code::InvalidArgument | Code::FailedPrecondition => {
+                Self::InvalidRequest(status.message().to_string())
+            }
+            Code::Unavailable => Self::AgentUnavailable(agent_id.to_string()),
+            Code::PermissionDenied => Self::Forbidden(status.message().to_string()),
+            // The agent's host can't do it, or the agent predates the call
+            Code::Unimplemented => Self::Unsupported(status.message().to_string()),
+            _ => Self::Internal(format!("{}: {}", context, status)),
+        }
+    }
+
+    /// Stable, machine-readable code exposed as `extensions.code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::ContainerNotFound(_) => "CONTAINER_NOT_FOUND",
+            ApiError::AgentNotFound(_) => "AGENT_NOT_FOUND",
+            ApiError::AgentUnavailable(_) => "AGENT_UNAVAILABLE",
+            ApiError::InvalidRequest(_) => "INVALID_REQUEST",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::Forbidden(_) => "FORBIDDEN",
+            ApiError::Unsupported(_) => "UNSUPPORTED",
+            ApiError::ResourceExhausted { .. } => "RESOURCE_EXHAUSTED",
+            ApiError::RateLimited { .. } => "RATE_LIMITED",
+            ApiError::Internal(_) | ApiError::Grpc(_) | ApiError::Config(_) => "INTERNAL",
+        }
+    }
+
+    /// Convert ApiError to async_graphql::Error with a stable `code` and the
+    /// structured fields a client needs to act on it (`agentId`, `containerId`).
     /// Internal errors are sanitized to avoid leaking backend details.
     pub fn extend(self) -> async_graphql::Error {
-        let (code, message) = match &self {
-            ApiError::ContainerNotFound(_) => ("CONTAINER_NOT_FOUND", self.to_string()),
-            ApiError::AgentNotFound(_) => ("AGENT_NOT_FOUND", self.to_string()),
-            ApiError::AgentUnavailable(_) => ("AGENT_UNAVAILABLE", self.to_string()),
-            ApiError::Unauthorized(_) => ("UNAUTHORIZED", self.to_string()),
-            ApiError::Forbidden(_) => ("FORBIDDEN", self.to_string()),
-            ApiError::InvalidRequest(_) => ("BAD_REQUEST", self.to_string()),
+        let code = self.code();
+        let message = match &self {
             ApiError::Internal(ref detail) => {
                 // Log the full detail server-side but don't expose to client
                 tracing::error!("Internal error: {}", detail);
-                ("INTERNAL_SERVER_ERROR", "An internal error occurred".to_string())
+                "An internal error occurred".to_string()
             }
             ApiError::Grpc(ref status) => {
                 // Log gRPC details server-side but sanitize for client
                 tracing::error!("gRPC error: {}", status);
-                ("GRPC_ERROR", "A backend communication error occurred".to_string())
+                "A backend communication error occurred".to_string()
             }
             ApiError::Config(ref err) => {
                 tracing::error!("Config error: {}", err);
-                ("CONFIG_ERROR", "A configuration error occurred".to_string())
+                "A configuration error occurred".to_string()
             }
+            _ => self.to_string(),
         };
 
-        async_graphql::Error::new(message)
-            .extend_with(|_err, e| e.set("code", code))
+        let agent_id = match &self {
+            ApiError::AgentNotFound(id)
+            | ApiError::AgentUnavailable(id)
+            | ApiError::ResourceExhausted { agent_id: id, .. }
+            | ApiError::RateLimited { agent_id: id, .. } => Some(id.clone()),
+            _ => None,
+        };
+        let retry_after = match &self {
+            ApiError::RateLimited { retry_after_secs, .. } => Some(*retry_after_secs),
+            ApiError::ResourceExhausted { retry_after_secs, .. } => *retry_after_secs,
+            _ => None,
+        };
+        let container_id = match &self {
+            ApiError::ContainerNotFound(id) => Some(id.clone()),
+            _ => None,
+        };
+
+        async_graphql::Error::new(message).extend_with(|_err, e| {
+            e.set("code", code);
+            if let Some(agent_id) = agent_id {
+                e.set("agentId", agent_id);
+            }
+            if let Some(container_id) = container_id {
+                e.set("containerId", container_id);
+            }
+            if let Some(secs) = retry_after {
+                e.set("retryAfter", secs);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_graphql::Value;
+
+    fn extension(err: &async_graphql::Error, key: &str) -> Option<Value> {
+        err.extensions.as_ref().and_then(|ext| ext.get(key).cloned())
+    }
+
+    fn string(value: &str) -> Option<Value> {
+        Some(Value::String(value.to_string()))
+    }
+
+    #[test]
+    fn test_each_variant_carries_code_and_fields() {
+        let cases = [
+            (ApiError::ContainerNotFound("abc123".into()), "CONTAINER_NOT_FOUND", None, string("abc123")),
+            (ApiError::AgentNotFound("agent-1".into()), "AGENT_NOT_FOUND", string("agent-1"), None),
+            (ApiError::AgentUnavailable("agent-1".into()), "AGENT_UNAVAILABLE", string("agent-1"), None),
+            (ApiError::InvalidRequest("tail must be positive".into()), "INVALID_REQUEST", None, None),
+            (ApiError::Unauthorized("no token".into()), "UNAUTHORIZED", None, None),
+            (ApiError::Forbidden("introspection is disabled".into()), "FORBIDDEN", None, None),
+            (ApiError::Unsupported("live resource updates".into()), "UNSUPPORTED", None, None),
+            (
+                ApiError::ResourceExhausted { agent_id: "agent-1".into(), message: "low priority streams are not being admitted".into(), retry_after_secs: None },
+                "RESOURCE_EXHAUSTED",
+                string("agent-1"),
+                None,
+            ),
+            (
+                ApiError::RateLimited { agent_id: "agent-1".into(), retry_after_secs: 30 },
+                "RATE_LIMITED",
+                string("agent-1"),
+                None,
+            ),
+            (ApiError::Internal("db exploded".into()), "INTERNAL", None, None),
+            (ApiError::Grpc(tonic::Status::internal("boom")), "INTERNAL", None, None),
+            (ApiError::Config(anyhow::anyhow!("bad config")), "INTERNAL", None, None),
+        ];
+
+        for (error, code, agent_id, container_id) in cases {
+            let err = error.extend();
+            assert_eq!(extension(&err, "code"), string(code), "{}", err.message);
+            assert_eq!(extension(&err, "agentId"), agent_id, "{}", code);
+            assert_eq!(extension(&err, "containerId"), container_id, "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_internal_details_are_sanitized() {
+        let err = ApiError::Internal("connection string postgres://secret".into()).extend();
+        assert_eq!(err.message, "An internal error occurred");
+    }
+
+    #[test]
+    fn test_agent_status_classification() {
+        let err = ApiError::from_agent(
+            "agent-1",
+            "Failed to open log stream",
+            AgentError::Status(tonic::Status::resource_exhausted("Agent is near its stream limit")),
+        );
+        assert_eq!(err.code(), "RESOURCE_EXHAUSTED");
+        assert!(err.to_string().contains("near its stream limit"));
+
+        let err = ApiError::from_status("agent-1", "Stream error", tonic::Status::invalid_argument("Invalid regex pattern"));
+        assert_eq!(err.code(), "INVALID_REQUEST");
+
+        let err = ApiError::from_status("agent-1", "Stream error", tonic::Status::unavailable("gone"));
+        assert!(matches!(err, ApiError::AgentUnavailable(ref id) if id == "agent-1"));
+
+        let err = ApiError::from_status("agent-1", "Freeze inspect failed", tonic::Status::permission_denied("FreezeInspect is disabled"));
+        assert_eq!(err.code(), "FORBIDDEN");
+
+        let err = ApiError::from_status("agent-1", "Resource update failed", tonic::Status::unimplemented("not supported on Windows"));
+        assert_eq!(err.code(), "UNSUPPORTED");
+
+        let err = ApiError::from_status("agent-1", "Stream error", tonic::Status::internal("Docker error"));
+        assert_eq!(err.code(), "INTERNAL");
+    }
+
+    #[test]
+    fn test_missing_container_is_container_not_found() {
+        let err = ApiError::from_container_agent(
+            "agent-1",
+            "abc123",
+            "Failed to open log stream",
+            AgentError::Status(tonic::Status::not_found("Container abc123 not found")),
+        );
+        assert!(matches!(err, ApiError::ContainerNotFound(ref id) if id == "abc123"));
+        let err = err.extend();
+        assert_eq!(extension(&err, "code"), string("CONTAINER_NOT_FOUND"));
+        assert_eq!(extension(&err, "containerId"), string("abc123"));
+        assert_eq!(err.message, "Container not found: abc123");
+
+        // Other statuses are classified as for any agent call
+        let err = ApiError::from_container_status("agent-1", "abc123", "Stream error", tonic::Status::unavailable("gone"));
+        assert!(matches!(err, ApiError::AgentUnavailable(ref id) if id == "agent-1"));
+        let err = ApiError::from_container_agent("agent-1", "abc123", "Failed", AgentError::NotFound("agent-1".into()));
+        assert_eq!(err.code(), "AGENT_NOT_FOUND");
+    }
+
+    #[test]
+    fn test_saturated_agent_carries_retry_after() {
+        let err = ApiError::from_agent(
+            "agent-1",
+            "Failed to list containers",
+            AgentError::Saturated { max_in_flight: 8, retry_after_secs: 2 },
+        );
+        assert_eq!(err.code(), "RESOURCE_EXHAUSTED");
+        let err = err.extend();
+        assert_eq!(extension(&err, "agentId"), string("agent-1"));
+        assert_eq!(extension(&err, "retryAfter"), Some(Value::from(2)));
+    }
+
+    #[test]
+    fn test_docker_rate_limit_is_distinct() {
+        let mut status = tonic::Status::resource_exhausted("Docker API is rate limiting this agent");
+        status.metadata_mut().insert(RETRY_AFTER_METADATA, "30".parse().unwrap());
+
+        let err = ApiError::from_status("agent-1", "Stream error", status);
+        assert!(matches!(err, ApiError::RateLimited { retry_after_secs: 30, .. }));
+        let err = err.extend();
+        assert_eq!(extension(&err, "code"), string("RATE_LIMITED"));
+        assert_eq!(extension(&err, "retryAfter"), Some(Value::from(30)));
     }
 }
@@ -0,0 +1,73 @@
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextParseQuery};
+use async_graphql::parser::types::{ExecutableDocument, Selection, SelectionSet};
+use async_graphql::{Pos, ServerResult, Variables};
+use std::sync::Arc;
+
+use crate::error::ApiError;
+
+/// Rejects introspection queries (`__schema` / `__type`) with a clear error.
+///
+/// Installed alongside `disable_introspection()` when
+/// `graphql.enable_introspection = false`, so clients get a FORBIDDEN error
+/// instead of an opaque unknown-field failure.
+pub struct IntrospectionGuard;
+
+impl ExtensionFactory for IntrospectionGuard {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(IntrospectionGuardExtension)
+    }
+}
+
+struct IntrospectionGuardExtension;
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for IntrospectionGuardExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        let introspects = document
+            .operations
+            .iter()
+            .any(|(_, op)| selects_introspection(&document, &op.node.selection_set.node, 0));
+
+        if introspects {
+            return Err(ApiError::Forbidden("GraphQL introspection is disabled".to_string())
+                .extend()
+                .into_server_error(Pos::default()));
+        }
+
+        Ok(document)
+    }
+}
+
+/// Maximum fragment nesting followed when scanning for introspection fields
+const MAX_FRAGMENT_DEPTH: usize = 16;
+
+/// Introspection fields only exist on the query root, so only root-level
+/// selections (including those reached through fragments) need checking.
+fn selects_introspection(document: &ExecutableDocument, selection_set: &SelectionSet, depth: usize) -> bool {
+    if depth > MAX_FRAGMENT_DEPTH {
+        return false;
+    }
+
+    selection_set.items.iter().any(|selection| match &selection.node {
+        Selection::Field(field) => {
+            matches!(field.node.name.node.as_str(), "__schema" | "__type")
+        }
+        Selection::FragmentSpread(spread) => document
+            .fragments
+            .get(&spread.node.fragment_name.node)
+            .is_some_and(|fragment| {
+                selects_introspection(document, &fragment.node.selection_set.node, depth + 1)
+            }),
+        Selection::InlineFragment(fragment) => {
+            selects_introspection(document, &fragment.node.selection_set.node, depth + 1)
+        }
+    })
+}
@@ -1,5 +1,8 @@
 pub mod schema;
 pub mod types;
 pub mod subscriptions;
+pub mod mutations;
+pub mod websocket;
+pub mod introspection;
 
 pub use schema::{build_schema, ClusterSchema};
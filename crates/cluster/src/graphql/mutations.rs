@@ -0,0 +1,208 @@
+use async_graphql::{Context, Object, Result};
+
+use crate::agent::client::{EvictParserCacheRequest, FreezeInspectRequest, UpdateResourcesRequest, UpdateStreamFilterRequest};
+use crate::agent::AgentError;
+use crate::error::ApiError;
+use crate::graphql::types::log::FilterMode;
+use crate::graphql::types::stats::{ContainerResources, FreezeSnapshot};
+use crate::state::AppState;
+
+/// Root mutation type
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Pause a container, capture its processes, memory, open files and recent
+    /// logs from one consistent moment, then unpause it.
+    ///
+    /// The agent unpauses the container even if the capture fails. Returns
+    /// FORBIDDEN unless the agent was started with `allow_freeze_inspect`.
+    async fn freeze_inspect(
+        &self,
+        ctx: &Context<'_>,
+        container_id: String,
+        agent_id: String,
+        log_tail_lines: Option<u32>,
+    ) -> Result<FreezeSnapshot> {
+        let state = ctx.data::<AppState>()?;
+
+        let agent = state.agent_pool.get_agent(&agent_id)
+            .ok_or_else(|| ApiError::AgentNotFound(agent_id.clone()).extend())?;
+
+        let mut client = {
+            let guard = agent.client.lock().await;
+            guard.clone()
+        };
+
+        let response = client
+            .freeze_inspect(FreezeInspectRequest {
+                container_id: container_id.clone(),
+                log_tail_lines,
+            })
+            .await
+            .map_err(|e| {
+                tracing::warn!("Freeze inspect of container {} on agent {} failed: {}", container_id, agent_id, e);
+                ApiError::from_container_agent(&agent_id, &container_id, "Failed to freeze-inspect container", e).extend()
+            })?;
+
+        tracing::info!(
+            "Freeze inspect of container {} on agent {} held it paused for {}ms",
+            container_id, agent_id, response.paused_ms
+        );
+        Ok(FreezeSnapshot::from_proto(response))
+    }
+
+    /// Change a running standalone container's CPU (cores) and memory
+    /// (bytes) limits in place, without recreating it; a limit left out
+    /// stays as it is. Returns the limits now in effect.
+    ///
+    /// Returns FORBIDDEN unless the agent was started with
+    /// `allow_resource_updates`, UNSUPPORTED where the host can't change
+    /// limits live (e.g. Windows, or cgroups without the controllers).
+    async fn update_container_resources(
+        &self,
+        ctx: &Context<'_>,
+        container_id: String,
+        agent_id: String,
+        cpus: Option<f64>,
+        memory_bytes: Option<i64>,
+    ) -> Result<ContainerResources> {
+        let state = ctx.data::<AppState>()?;
+
+        let agent = state.agent_pool.get_agent(&agent_id)
+            .ok_or_else(|| ApiError::AgentNotFound(agent_id.clone()).extend())?;
+
+        let mut client = {
+            let guard = agent.client.lock().await;
+            guard.clone()
+        };
+
+        let response = client
+            .update_container_resources(UpdateResourcesRequest {
+                container_id: container_id.clone(),
+                cpus,
+                memory_bytes,
+            })
+            .await
+            .map_err(|e| {
+                tracing::warn!("Resource update of container {} on agent {} failed: {}", container_id, agent_id, e);
+                ApiError::from_container_agent(&agent_id, &container_id, "Failed to update container resources", e).extend()
+            })?;
+
+        tracing::info!(
+            "Updated resources of container {} on agent {}: cpus {}, memory {} bytes",
+            container_id, agent_id, response.cpus, response.memory_bytes
+        );
+        Ok(ContainerResources::from_proto(response))
+    }
+
+    /// Drop a container's cached log format on its agent, so the next line
+    /// (in running subscriptions too) is detected again. Also clears a
+    /// format lock or parsing turned off after failures. Returns false if
+    /// nothing was cached for the container.
+    async fn evict_parser_cache(
+        &self,
+        ctx: &Context<'_>,
+        agent_id: String,
+        container_id: String,
+    ) -> Result<bool> {
+        let state = ctx.data::<AppState>()?;
+
+        let agent = state.agent_pool.get_agent(&agent_id)
+            .ok_or_else(|| ApiError::AgentNotFound(agent_id.clone()).extend())?;
+
+        let mut client = {
+            let guard = agent.client.lock().await;
+            guard.clone()
+        };
+
+        let response = client
+            .evict_parser_cache(EvictParserCacheRequest { container_id: container_id.clone() })
+            .await
+            .map_err(|e| ApiError::from_container_agent(&agent_id, &container_id, "Failed to evict parser cache", e).extend())?;
+
+        tracing::info!(
+            "Evicted parser cache of container {} on agent {} (cached: {})",
+            container_id, agent_id, response.evicted
+        );
+        Ok(response.evicted)
+    }
+
+    /// Drop cached container listings, for one agent or all of them, so the
+    /// next `containers` query asks the agents again
+    async fn refresh_inventory(&self, ctx: &Context<'_>, agent_id: Option<String>) -> Result<bool> {
+        let state = ctx.data::<AppState>()?;
+        match agent_id {
+            Some(agent_id) => {
+                if state.agent_pool.get_agent(&agent_id).is_none() {
+                    return Err(ApiError::AgentNotFound(agent_id).extend());
+                }
+                state.inventory.invalidate(&agent_id);
+            }
+            None => state.inventory.invalidate_all(),
+        }
+        Ok(true)
+    }
+
+    /// Hold back the log subscription opened with this `pauseToken`. The
+    /// stream stays open and keeps reading; up to `log_defaults.pause_buffer_lines`
+    /// lines are held for `resumeLogStream`, the rest dropped and counted.
+    async fn pause_log_stream(&self, ctx: &Context<'_>, pause_token: String) -> Result<bool> {
+        set_paused(ctx, &pause_token, true)
+    }
+
+    /// Resume a paused log subscription: held lines first, then a
+    /// `droppedWhilePaused` entry if any were dropped, then live lines
+    async fn resume_log_stream(&self, ctx: &Context<'_>, pause_token: String) -> Result<bool> {
+        set_paused(ctx, &pause_token, false)
+    }
+
+    /// Replace `filter` / `filterMode` on the running log subscription opened
+    /// with this `filterToken`, without reopening it. Only lines read after
+    /// the change are affected; no `filter` removes filtering. Returns how
+    /// many container streams took the new filter.
+    async fn set_log_stream_filter(
+        &self,
+        ctx: &Context<'_>,
+        filter_token: String,
+        filter: Option<String>,
+        #[graphql(default_with = "FilterMode::Include")] filter_mode: FilterMode,
+    ) -> Result<i32> {
+        let state = ctx.data::<AppState>()?;
+        let agent_ids = state.filter_tokens.agents(&filter_token).ok_or_else(|| {
+            ApiError::InvalidRequest(format!("No open log subscription has filterToken '{}'", filter_token)).extend()
+        })?;
+
+        let request = UpdateStreamFilterRequest {
+            filter_token: filter_token.clone(),
+            filter_pattern: filter,
+            filter_mode: crate::agent::client::FilterMode::from(filter_mode) as i32,
+        };
+        let mut updated = 0;
+        for agent_id in agent_ids {
+            let Some(agent) = state.agent_pool.get_agent(&agent_id) else {
+                continue;
+            };
+            let mut client = agent.client.lock().await.clone();
+            match client.update_stream_filter(request.clone()).await {
+                Ok(response) => updated += response.streams_updated,
+                // This agent's streams for the subscription have already ended
+                Err(AgentError::Status(status)) if status.code() == tonic::Code::NotFound => {}
+                Err(e) => return Err(ApiError::from_agent(&agent_id, "Failed to update stream filter", e).extend()),
+            }
+        }
+        Ok(i32::try_from(updated).unwrap_or(i32::MAX))
+    }
+}
+
+fn set_paused(ctx: &Context<'_>, pause_token: &str, paused: bool) -> Result<bool> {
+    let state = ctx.data::<AppState>()?;
+    if !state.pause_controls.set_paused(pause_token, paused) {
+        return Err(ApiError::InvalidRequest(format!(
+            "No open log subscription has pauseToken '{}'",
+            pause_token
+        ))
+        .extend());
+    }
+    Ok(true)
+}
@@ -1,15 +1,27 @@
-use async_graphql::{Context, EmptyMutation, Schema};
+use async_graphql::{Context, Schema};
 use crate::state::AppState;
 use crate::error::ApiError;
-use super::types::agent::{AgentView, AgentHealthSummary, agent_view_from_connection};
-use super::types::container::{Container, ContainerFilter, ContainerState, ContainerDetailsCache, ContainerStateInfoGql};
-use super::types::stats::ContainerStats;
-use super::types::log::{LogEntry, LogStreamOptions, ContainerLookupCache};
+use super::types::agent::{AgentView, AgentHealthSummary, AgentFormatDistribution, FormatDistribution, ParserCacheStats, agent_view_from_connection};
+use super::types::container::{Container, ContainerCommandGql, ContainerConnection, ContainerFilter, ContainerState, ContainerDetailsCache, ContainerStateInfoGql, RestartPolicyName};
+use super::types::stats::{ContainerStats, MemoryProfile};
+use super::types::log::{ContainerSource, FilterMode, LogEntry, LogFieldKey, LogStreamOptions, StreamPriority, ContainerLookupCache, SubscriptionStats};
+use super::types::search::{search_sources, LogSearchResult, SourceMatches, DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT, MAX_SEARCH_SOURCES, SEARCH_SCAN_BUDGET};
 use super::subscriptions::SubscriptionRoot;
-use crate::agent::client::ContainerListRequest;
+use super::mutations::MutationRoot;
+use super::introspection::IntrospectionGuard;
+use crate::agent::client::{ContainerInspectRequest, ContainerListRequest, HealthCheckRequest, LabelSelector};
 use futures::StreamExt;
 
-pub type ClusterSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+pub type ClusterSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
+
+/// Concurrent inspect calls per agent when filtering by restart policy
+const RESTART_POLICY_INSPECT_CONCURRENCY: usize = 16;
+
+/// Recent lines `logFieldKeys` samples when the client doesn't say
+const DEFAULT_FIELD_KEY_SAMPLE: i32 = 200;
+
+/// Upper bound on `logFieldKeys` sample size
+const MAX_FIELD_KEY_SAMPLE: i32 = 2000;
 
 /// Root Query type
 pub struct QueryRoot;
@@ -83,160 +95,82 @@ impl QueryRoot {
         })
     }
 
-    /// Get containers from one or more agents
-    async fn containers(
+    /// How many containers each agent's parser detected as JSON, logfmt,
+    /// plain text, etc., per agent and summed. Shows how much of the fleet
+    /// isn't logging structured output. Defaults to all healthy agents;
+    /// agents that can't be reached are left out.
+    async fn format_distribution(
         &self,
         ctx: &Context<'_>,
         agent_ids: Option<Vec<String>>,
-        filter: Option<ContainerFilter>,
-    ) -> async_graphql::Result<Vec<Container>> {
+    ) -> async_graphql::Result<FormatDistribution> {
         let state = ctx.data::<AppState>()?;
-        
-        // Determine which agents to query
-        let agents = if let Some(ids) = agent_ids {
-            // Query specific agents
-            ids.iter()
-                .filter_map(|id| state.agent_pool.get_agent(id))
-                .collect::<Vec<_>>()
-        } else {
-            // Query all healthy agents
-            state.agent_pool.list_agents()
+
+        let agents = match agent_ids {
+            Some(ids) => ids.iter().filter_map(|id| state.agent_pool.get_agent(id)).collect::<Vec<_>>(),
+            None => state.agent_pool.list_agents()
                 .into_iter()
                 .filter(|a| a.health_status() == crate::agent::HealthStatus::Healthy)
-                .collect()
+                .collect(),
         };
 
-        // Define per-agent tasks - capture filter by reference
-        let filter_ref = &filter;
-        
         let futures = agents.into_iter().map(|agent| async move {
-            // ✅ Clone client to release lock immediately (non-blocking)
-            let mut client = {
-                let guard = agent.client.lock().await;
-                guard.clone()
-            };
-
-            // Build the request based on filter
-            let request = ContainerListRequest {
-                state_filter: filter_ref.as_ref()
-                    .and_then(|f| f.state.as_ref())
-                    .map(|s| match s {
-                        ContainerState::Running => 2,
-                        ContainerState::Paused => 3,
-                        ContainerState::Exited => 4,
-                        ContainerState::Created => 5,
-                        _ => 1,
-                    }),
-                include_stopped: filter_ref.as_ref()
-                    .and_then(|f| f.include_stopped)
-                    .unwrap_or(false),
-                limit: filter_ref.as_ref()
-                    .and_then(|f| f.limit)
-                    .and_then(|l| if l > 0 { Some(l as u32) } else { None }),
-            };
-
-            // Perform network call (lock already released)
-            match client.list_containers(request).await {
-                Ok(response) => Some((agent.info.id.clone(), response.containers)),
+            let mut client = agent.client.lock().await.clone();
+            match client.check_health(HealthCheckRequest { service: String::new() }).await {
+                Ok(response) => Some(AgentFormatDistribution::from_metadata(agent.info.id.clone(), &response.metadata)),
                 Err(e) => {
-                    tracing::warn!("Failed to list containers from agent {}: {}", agent.info.id, e);
-                    None // Skip failed agents
+                    tracing::warn!("Failed to read format distribution from agent {}: {}", agent.info.id, e);
+                    None
                 }
             }
         });
+        let per_agent = futures::future::join_all(futures).await.into_iter().flatten().collect();
 
-        // ✅ Execute all agent requests in parallel
-        let results = futures::future::join_all(futures).await;
-
-        // Flatten and post-process results
-        let mut all_containers = Vec::new();
-
-        for (agent_id, containers) in results.into_iter().flatten() {
-            for container_info in containers {
-                // Convert proto port mappings to GraphQL port mappings
-                let ports = container_info.ports.into_iter().map(|p| {
-                    super::types::container::PortMapping {
-                        container_port: p.container_port as i32,
-                        protocol: p.protocol,
-                        host_ip: p.host_ip,
-                        host_port: p.host_port.map(|p| p as i32),
-                    }
-                }).collect();
-                
-                // Convert proto to GraphQL type
-                let ts = chrono::DateTime::from_timestamp(container_info.created_at, 0);
-                if ts.is_none() {
-                    tracing::warn!(
-                        container_id = %container_info.id,
-                        created_at = container_info.created_at,
-                        "Invalid created_at timestamp from agent, substituting current time"
-                    );
-                }
-                let container = Container {
-                    id: container_info.id,
-                    agent_id: agent_id.clone(),
-                    name: container_info.name,
-                    image: container_info.image,
-                    state: ContainerState::from(container_info.state.as_str()),
-                    status: container_info.status,
-                    labels_map: container_info.labels,
-                    created_at: ts.unwrap_or_else(chrono::Utc::now),
-                    log_driver: container_info.log_driver,
-                    ports,
-                    state_info: container_info.state_info.map(|si| ContainerStateInfoGql {
-                        oom_killed: si.oom_killed,
-                        pid: si.pid,
-                        exit_code: si.exit_code,
-                        started_at: si.started_at,
-                        finished_at: si.finished_at,
-                        restart_count: si.restart_count,
-                    }),
-                };
+        Ok(FormatDistribution::from_agents(per_agent))
+    }
 
-                // Apply post-query filters
-                if let Some(ref filt) = filter {
-                    // Name pattern filter
-                    if let Some(ref pattern) = filt.name_pattern {
-                        if !container.name.contains(pattern) {
-                            continue;
-                        }
-                    }
+    /// An agent's log format cache: containers with a cached format, how
+    /// often lookups hit it, and how many formats are locked
+    async fn parser_cache_stats(
+        &self,
+        ctx: &Context<'_>,
+        agent_id: String,
+    ) -> async_graphql::Result<ParserCacheStats> {
+        let state = ctx.data::<AppState>()?;
 
-                    // Image pattern filter
-                    if let Some(ref pattern) = filt.image_pattern {
-                        if !container.image.contains(pattern) {
-                            continue;
-                        }
-                    }
+        let agent = state.agent_pool.get_agent(&agent_id)
+            .ok_or_else(|| ApiError::AgentNotFound(agent_id.clone()).extend())?;
+        let mut client = agent.client.lock().await.clone();
 
-                    // Label filters
-                    if let Some(ref label_filters) = filt.labels {
-                        let mut matches_all = true;
-                        for label_filter in label_filters {
-                            if let Some(container_value) = container.labels_map.get(&label_filter.key) {
-                                if let Some(ref filter_value) = label_filter.value {
-                                    if container_value != filter_value {
-                                        matches_all = false;
-                                        break;
-                                    }
-                                }
-                                // If no value specified, just check key exists
-                            } else {
-                                matches_all = false;
-                                break;
-                            }
-                        }
-                        if !matches_all {
-                            continue;
-                        }
-                    }
-                }
+        let response = client.parser_cache_stats().await
+            .map_err(|e| ApiError::from_agent(&agent_id, "Failed to read parser cache stats", e).extend())?;
+        Ok(ParserCacheStats::from_proto(agent_id, response))
+    }
 
-                all_containers.push(container);
-            }
-        }
+    /// Get containers from one or more agents. `offset` and `limit` page
+    /// the merged list; `containerPage` also returns the total.
+    async fn containers(
+        &self,
+        ctx: &Context<'_>,
+        agent_ids: Option<Vec<String>>,
+        filter: Option<ContainerFilter>,
+    ) -> async_graphql::Result<Vec<Container>> {
+        let state = ctx.data::<AppState>()?;
+        let matching = matching_containers(state, agent_ids, &filter).await;
+        Ok(ContainerConnection::page(matching, filter.as_ref()).items)
+    }
 
-        Ok(all_containers)
+    /// Get one page of containers from one or more agents, with the number
+    /// of matching containers and whether more follow
+    async fn container_page(
+        &self,
+        ctx: &Context<'_>,
+        agent_ids: Option<Vec<String>>,
+        filter: Option<ContainerFilter>,
+    ) -> async_graphql::Result<ContainerConnection> {
+        let state = ctx.data::<AppState>()?;
+        let matching = matching_containers(state, agent_ids, &filter).await;
+        Ok(ContainerConnection::page(matching, filter.as_ref()))
     }
 
     /// Get a specific container by ID
@@ -306,6 +240,10 @@ impl QueryRoot {
                             labels_map: info.labels,
                             created_at: ts.unwrap_or_else(chrono::Utc::now),
                             log_driver: info.log_driver,
+                            log_options: info.log_options,
+                            log_rotation_unbounded: info.log_rotation_unbounded,
+                            log_size_bytes: info.log_size_bytes,
+                            command: info.command.map(ContainerCommandGql::from),
                             ports,
                             state_info: info.state_info.map(|si| ContainerStateInfoGql {
                                 oom_killed: si.oom_killed,
@@ -326,7 +264,83 @@ impl QueryRoot {
 
         // Execute all searches in parallel, return first found
         let results = futures::future::join_all(futures).await;
-        Ok(results.into_iter().flatten().next())
+        let found = results.into_iter().flatten().next();
+        if let Some(ref container) = found {
+            state.container_names.record(&container.agent_id, &container.id, &container.name, &container.labels_map);
+        }
+        Ok(found)
+    }
+
+    /// Find containers on an agent that use the given restart policy
+    /// (e.g. services left on `no` that should be `always`)
+    async fn containers_by_restart_policy(
+        &self,
+        ctx: &Context<'_>,
+        agent_id: String,
+        policy: RestartPolicyName,
+    ) -> async_graphql::Result<Vec<Container>> {
+        let state = ctx.data::<AppState>()?;
+
+        let agent = state.agent_pool.get_agent(&agent_id)
+            .ok_or_else(|| ApiError::AgentNotFound(agent_id.clone()).extend())?;
+
+        // Clone client to release lock immediately
+        let client = {
+            let guard = agent.client.lock().await;
+            guard.clone()
+        };
+
+        let listed = client.clone()
+            .list_containers(ContainerListRequest {
+                include_stopped: true,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| {
+                tracing::warn!("Failed to list containers from agent {}: {}", agent_id, e);
+                ApiError::from_agent(&agent_id, "Failed to list containers", e).extend()
+            })?;
+
+        // The restart policy only comes with inspect, so inspect each
+        // container with bounded concurrency
+        let inspected: Vec<_> = futures::stream::iter(listed.containers)
+            .map(|info| {
+                let mut client = client.clone();
+                async move {
+                    client.inspect_container(ContainerInspectRequest { container_id: info.id })
+                        .await
+                        .ok()
+                }
+            })
+            .buffer_unordered(RESTART_POLICY_INSPECT_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut containers: Vec<Container> = inspected.into_iter()
+            .flatten()
+            .filter(|response| RestartPolicyName::from_inspect(response) == Some(policy))
+            .filter_map(|response| response.info)
+            .map(|info| Container::from_proto(info, agent_id.clone()))
+            .collect();
+        containers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(containers)
+    }
+
+    /// Line length stats of the open log streams (max and average line
+    /// length, oversized lines), optionally for one agent
+    async fn subscription_stats(
+        &self,
+        ctx: &Context<'_>,
+        agent_id: Option<String>,
+    ) -> async_graphql::Result<Vec<SubscriptionStats>> {
+        let state = ctx.data::<AppState>()?;
+        Ok(state.metrics
+            .line_stats()
+            .into_iter()
+            .filter(|s| agent_id.as_ref().is_none_or(|id| *id == s.agent_id))
+            .map(SubscriptionStats::from)
+            .collect())
     }
 
     /// Get real-time statistics for a specific container
@@ -352,17 +366,65 @@ impl QueryRoot {
         match client.get_container_stats(crate::agent::client::ContainerStatsRequest {
             container_id: id.clone(),
             stream: false,
+            priority: crate::agent::client::StreamPriority::Unspecified as i32, // Unary: no admission
+            interval_ms: 0,
         }).await {
             Ok(response) => {
-                Ok(Some(ContainerStats::from_proto(response)))
+                Ok(Some(ContainerStats::from_proto(response, agent_id)))
             }
             Err(e) => {
                 tracing::warn!("Failed to get stats for container {} on agent {}: {}", id, agent_id, e);
-                Err(ApiError::Internal(format!("Failed to get container stats: {}", e)).extend())
+                Err(ApiError::from_container_agent(&agent_id, &id, "Failed to get container stats", e).extend())
             }
         }
     }
 
+    /// Get a memory profile (limit, peak/current usage, cache vs RSS, OOM settings)
+    /// for right-sizing a container's memory limit
+    async fn memory_profile(
+        &self,
+        ctx: &Context<'_>,
+        container_id: String,
+        agent_id: String,
+    ) -> async_graphql::Result<MemoryProfile> {
+        let state = ctx.data::<AppState>()?;
+        
+        let agent = state.agent_pool.get_agent(&agent_id)
+            .ok_or_else(|| ApiError::AgentNotFound(agent_id.clone()).extend())?;
+
+        // Clone client to release lock immediately
+        let client = {
+            let guard = agent.client.lock().await;
+            guard.clone()
+        };
+        let mut stats_client = client.clone();
+        let mut inspect_client = client;
+
+        // Fetch stats and inspect in parallel
+        let (stats, inspect) = futures::future::join(
+            stats_client.get_container_stats(crate::agent::client::ContainerStatsRequest {
+                container_id: container_id.clone(),
+                stream: false,
+                priority: crate::agent::client::StreamPriority::Unspecified as i32, // Unary: no admission
+                interval_ms: 0,
+            }),
+            inspect_client.inspect_container(crate::agent::client::ContainerInspectRequest {
+                container_id: container_id.clone(),
+            }),
+        ).await;
+
+        let stats = stats.map_err(|e| {
+            tracing::warn!("Failed to get stats for container {} on agent {}: {}", container_id, agent_id, e);
+            ApiError::from_container_agent(&agent_id, &container_id, "Failed to get container stats", e).extend()
+        })?;
+        let inspect = inspect.map_err(|e| {
+            tracing::warn!("Failed to inspect container {} on agent {}: {}", container_id, agent_id, e);
+            ApiError::from_container_agent(&agent_id, &container_id, "Failed to inspect container", e).extend()
+        })?;
+
+        Ok(MemoryProfile::from_parts(&stats, &inspect))
+    }
+
     /// Get historical logs from a container (non-streaming, paginated)
     async fn logs(
         &self,
@@ -385,13 +447,8 @@ impl QueryRoot {
 
         // Build the gRPC request from GraphQL options
         let mut opts = options.unwrap_or(LogStreamOptions {
-            since: None,
-            until: None,
             tail: Some(100), // Default to last 100 lines
-            follow: false,   // Never follow for queries (only subscriptions)
-            filter: None,
-            filter_mode: super::types::log::FilterMode::None,
-            timestamps: true,
+            ..LogStreamOptions::default()
         });
 
         // ✅ Enforce maximum limit and validate to prevent OOM and integer overflow
@@ -414,28 +471,15 @@ impl QueryRoot {
             opts.tail = Some(MAX_LOG_LINES);
         }
 
-        // Convert timestamps to Unix seconds
-        let since = opts.since.map(|dt| dt.timestamp());
-        let until = opts.until.map(|dt| dt.timestamp());
-
         let request = crate::agent::client::LogStreamRequest {
-            container_id: container_id.clone(),
-            since,
-            until,
             follow: false, // Never follow for queries
-            tail_lines: opts.tail.map(|t| t as u32),
-            filter_pattern: opts.filter.clone(),
-            filter_mode: {
-                let proto_mode: crate::agent::client::FilterMode = opts.filter_mode.into();
-                proto_mode as i32
-            },
-            timestamps: opts.timestamps,
-            disable_parsing: false,  // Enable parsing by default
+            filter_token: None,
+            ..opts.to_request(container_id.clone())
         };
 
         // Stream logs from the agent and collect them
         let mut stream = client.stream_logs(request).await
-            .map_err(|e| ApiError::Internal(format!("Failed to stream logs: {}", e)).extend())?;
+            .map_err(|e| ApiError::from_container_agent(&agent_id, &container_id, "Failed to stream logs", e).extend())?;
 
         let mut log_entries = Vec::new();
         
@@ -456,6 +500,135 @@ impl QueryRoot {
 
         Ok(log_entries)
     }
+
+    /// Structured field keys in a container's recent log lines, with the kind
+    /// of values seen for each, for field autocomplete in a search box.
+    /// Samples the last `sampleSize` lines (default 200, at most 2000);
+    /// containers logging plain text have no keys.
+    async fn log_field_keys(
+        &self,
+        ctx: &Context<'_>,
+        container_id: String,
+        agent_id: String,
+        sample_size: Option<i32>,
+    ) -> async_graphql::Result<Vec<LogFieldKey>> {
+        let state = ctx.data::<AppState>()?;
+
+        let sample_size = sample_size.unwrap_or(DEFAULT_FIELD_KEY_SAMPLE);
+        if !(1..=MAX_FIELD_KEY_SAMPLE).contains(&sample_size) {
+            return Err(ApiError::InvalidRequest(format!(
+                "sampleSize must be between 1 and {}, got {}",
+                MAX_FIELD_KEY_SAMPLE, sample_size
+            )).extend());
+        }
+
+        let agent = state.agent_pool.get_agent(&agent_id)
+            .ok_or_else(|| ApiError::AgentNotFound(agent_id.clone()).extend())?;
+        let mut client = {
+            let guard = agent.client.lock().await;
+            guard.clone()
+        };
+
+        // The agent parses the tail as it would for a stream, typing values
+        let request = LogStreamOptions {
+            tail: Some(sample_size),
+            priority: StreamPriority::Low,
+            infer_field_types: true,
+            flatten_fields: true,
+            ..LogStreamOptions::default()
+        }
+        .to_request(container_id.clone());
+        let stream = client.stream_logs(request).await
+            .map_err(|e| ApiError::from_container_agent(&agent_id, &container_id, "Failed to sample logs", e).extend())?;
+
+        let sample: Vec<_> = stream
+            .filter_map(|result| async move { result.ok() })
+            .collect()
+            .await;
+        Ok(LogFieldKey::from_entries(sample))
+    }
+
+    /// Search several containers, on any agents, for lines matching
+    /// `pattern` (regex), concurrently. Matches are merged in time order and
+    /// the `limit` most recent kept (default 500, at most 5000), with how
+    /// many each container matched. Reading is bounded: 50,000 recent lines
+    /// in total, split evenly between the containers. Containers on
+    /// unhealthy or unknown agents are skipped with a warning; a stream
+    /// that fails partway, or a line that can't be read, keeps what was
+    /// found and adds a warning.
+    async fn search_all_logs(
+        &self,
+        ctx: &Context<'_>,
+        pattern: String,
+        containers: Vec<ContainerSource>,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        until: Option<chrono::DateTime<chrono::Utc>>,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<LogSearchResult> {
+        let state = ctx.data::<AppState>()?;
+
+        if let Err(e) = regex::Regex::new(&pattern) {
+            return Err(ApiError::InvalidRequest(format!("Invalid pattern: {}", e)).extend());
+        }
+        if containers.is_empty() || containers.len() > MAX_SEARCH_SOURCES {
+            return Err(ApiError::InvalidRequest(format!(
+                "containers must list between 1 and {} containers, got {}",
+                MAX_SEARCH_SOURCES, containers.len()
+            )).extend());
+        }
+        let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        if !(1..=MAX_SEARCH_LIMIT).contains(&limit) {
+            return Err(ApiError::InvalidRequest(format!(
+                "limit must be between 1 and {}, got {}",
+                MAX_SEARCH_LIMIT, limit
+            )).extend());
+        }
+
+        let scan_per_container = SEARCH_SCAN_BUDGET / containers.len() as u32;
+        let pattern = &pattern;
+        let result = search_sources(containers, limit as usize, |source| async move {
+            let agent = state.agent_pool.get_agent(&source.agent_id)
+                .ok_or_else(|| "agent not found".to_string())?;
+            if agent.health_status() == crate::agent::HealthStatus::Unhealthy {
+                return Err("agent is unhealthy".to_string());
+            }
+            let mut client = agent.client.lock().await.clone();
+
+            let request = LogStreamOptions {
+                since,
+                until,
+                tail: Some(scan_per_container as i32),
+                filter: Some(pattern.clone()),
+                filter_mode: FilterMode::Include,
+                priority: StreamPriority::Low,
+                ..LogStreamOptions::default()
+            }
+            .to_request(source.container_id.clone());
+            let mut stream = client.stream_logs(request).await.map_err(|e| e.to_string())?;
+
+            let mut found = SourceMatches::default();
+            while let Some(result) = stream.next().await {
+                let entry = match result {
+                    Ok(entry) => entry,
+                    Err(status) => {
+                        found.warnings.push(format!("search stopped early: {}", status.message()));
+                        break;
+                    }
+                };
+                if entry.backlog_unavailable {
+                    continue;
+                }
+                match LogEntry::from_proto(entry, source.agent_id.clone()) {
+                    Ok(entry) => found.entries.push(entry),
+                    Err(e) => found.warnings.push(format!("skipped a line: {}", e.message)),
+                }
+            }
+            Ok(found)
+        })
+        .await;
+
+        Ok(result)
+    }
 }
 
 /// Health status type
@@ -466,15 +639,283 @@ pub struct HealthStatus {
 }
 
 /// Build the GraphQL schema
+/// Containers on the queried agents (all healthy ones by default) that
+/// match the filter, unpaged. Each agent returns its whole filtered listing,
+/// so one page can be cut from the merged list; agents that fail are skipped.
+async fn matching_containers(
+    state: &AppState,
+    agent_ids: Option<Vec<String>>,
+    filter: &Option<ContainerFilter>,
+) -> Vec<Container> {
+    // Determine which agents to query
+    let agents = if let Some(ids) = agent_ids {
+        // Query specific agents
+        ids.iter()
+            .filter_map(|id| state.agent_pool.get_agent(id))
+            .collect::<Vec<_>>()
+    } else {
+        // Query all healthy agents
+        state.agent_pool.list_agents()
+            .into_iter()
+            .filter(|a| a.health_status() == crate::agent::HealthStatus::Healthy)
+            .collect()
+    };
+
+    // Define per-agent tasks - capture filter by reference
+    let filter_ref = filter;
+
+    let futures = agents.into_iter().map(|agent| async move {
+        // ✅ Clone client to release lock immediately (non-blocking)
+        let mut client = {
+            let guard = agent.client.lock().await;
+            guard.clone()
+        };
+
+        // Build the request based on filter
+        let request = ContainerListRequest {
+            state_filter: filter_ref.as_ref()
+                .and_then(|f| f.state.as_ref())
+                .map(|s| match s {
+                    ContainerState::Running => 2,
+                    ContainerState::Paused => 3,
+                    ContainerState::Exited => 4,
+                    ContainerState::Created => 5,
+                    _ => 1,
+                }),
+            include_stopped: filter_ref.as_ref()
+                .and_then(|f| f.include_stopped)
+                .unwrap_or(false),
+            name_pattern: filter_ref.as_ref().and_then(|f| f.name_pattern.clone()),
+            image_pattern: filter_ref.as_ref().and_then(|f| f.image_pattern.clone()),
+            label_selectors: filter_ref.as_ref()
+                .and_then(|f| f.labels.as_ref())
+                .map(|labels| labels.iter().map(|l| LabelSelector {
+                    key: l.key.clone(),
+                    value: l.value.clone(),
+                }).collect())
+                .unwrap_or_default(),
+            // Paging applies to the merged listing, not per agent
+            limit: None,
+            offset: None,
+        };
+
+        // Served from the inventory cache while fresh; otherwise a
+        // network call (lock already released)
+        let listed = state.inventory
+            .list(&agent.info.id, request, |request| async move {
+                client.list_containers(request).await.map(|response| response.containers)
+            })
+            .await;
+        match listed {
+            Ok(containers) => Some((agent.info.id.clone(), containers)),
+            Err(e) => {
+                tracing::warn!("Failed to list containers from agent {}: {}", agent.info.id, e);
+                None // Skip failed agents
+            }
+        }
+    });
+
+    // ✅ Execute all agent requests in parallel
+    let results = futures::future::join_all(futures).await;
+
+    // Flatten and post-process results
+    let mut all_containers = Vec::new();
+
+    for (agent_id, containers) in results.into_iter().flatten() {
+        for container_info in containers {
+            state.container_names.record(&agent_id, &container_info.id, &container_info.name, &container_info.labels);
+
+            // Convert proto port mappings to GraphQL port mappings
+            let ports = container_info.ports.into_iter().map(|p| {
+                super::types::container::PortMapping {
+                    container_port: p.container_port as i32,
+                    protocol: p.protocol,
+                    host_ip: p.host_ip,
+                    host_port: p.host_port.map(|p| p as i32),
+                }
+            }).collect();
+
+            // Convert proto to GraphQL type
+            let ts = chrono::DateTime::from_timestamp(container_info.created_at, 0);
+            if ts.is_none() {
+                tracing::warn!(
+                    container_id = %container_info.id,
+                    created_at = container_info.created_at,
+                    "Invalid created_at timestamp from agent, substituting current time"
+                );
+            }
+            let container = Container {
+                id: container_info.id,
+                agent_id: agent_id.clone(),
+                name: container_info.name,
+                image: container_info.image,
+                state: ContainerState::from(container_info.state.as_str()),
+                status: container_info.status,
+                labels_map: container_info.labels,
+                created_at: ts.unwrap_or_else(chrono::Utc::now),
+                log_driver: container_info.log_driver,
+                log_options: container_info.log_options,
+                log_rotation_unbounded: container_info.log_rotation_unbounded,
+                log_size_bytes: container_info.log_size_bytes,
+                command: container_info.command.map(ContainerCommandGql::from),
+                ports,
+                state_info: container_info.state_info.map(|si| ContainerStateInfoGql {
+                    oom_killed: si.oom_killed,
+                    pid: si.pid,
+                    exit_code: si.exit_code,
+                    started_at: si.started_at,
+                    finished_at: si.finished_at,
+                    restart_count: si.restart_count,
+                }),
+            };
+
+            // Apply post-query filters. The agent already applies these
+            // server-side; re-checking keeps results correct for agents
+            // that predate filter push-down.
+            if let Some(ref filt) = filter {
+                // Name pattern filter
+                if let Some(ref pattern) = filt.name_pattern {
+                    if !container.name.contains(pattern) {
+                        continue;
+                    }
+                }
+
+                // Image pattern filter
+                if let Some(ref pattern) = filt.image_pattern {
+                    if !container.image.contains(pattern) {
+                        continue;
+                    }
+                }
+
+                // Label filters
+                if let Some(ref label_filters) = filt.labels {
+                    let mut matches_all = true;
+                    for label_filter in label_filters {
+                        if let Some(container_value) = container.labels_map.get(&label_filter.key) {
+                            if let Some(ref filter_value) = label_filter.value {
+                                if container_value != filter_value {
+                                    matches_all = false;
+                                    break;
+                                }
+                            }
+                            // If no value specified, just check key exists
+                        } else {
+                            matches_all = false;
+                            break;
+                        }
+                    }
+                    if !matches_all {
+                        continue;
+                    }
+                }
+            }
+
+            all_containers.push(container);
+        }
+    }
+
+    all_containers
+}
+
 pub fn build_schema(state: AppState) -> ClusterSchema {
     let max_depth = state.config.graphql.max_depth;
     let max_complexity = state.config.graphql.max_complexity;
+    let enable_introspection = state.config.graphql.enable_introspection;
 
-    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+    let mut builder = Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
         .data(state)
         .data(ContainerDetailsCache::new())
         .data(ContainerLookupCache::new())
         .limit_depth(max_depth)
-        .limit_complexity(max_complexity)
-        .finish()
+        .limit_complexity(max_complexity);
+
+    if !enable_introspection {
+        builder = builder
+            .disable_introspection()
+            .extension(IntrospectionGuard);
+    }
+
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClusterConfig;
+
+    const INTROSPECTION_QUERY: &str = "{ __schema { queryType { name } } }";
+
+    fn schema_with_introspection(enabled: bool) -> ClusterSchema {
+        let mut config = ClusterConfig::default();
+        config.graphql.enable_introspection = enabled;
+        build_schema(AppState::new(config))
+    }
+
+    #[tokio::test]
+    async fn test_introspection_enabled() {
+        let schema = schema_with_introspection(true);
+        let response = schema.execute(INTROSPECTION_QUERY).await;
+        assert!(response.errors.is_empty(), "unexpected errors: {:?}", response.errors);
+    }
+
+    #[tokio::test]
+    async fn test_introspection_disabled() {
+        let schema = schema_with_introspection(false);
+
+        let response = schema.execute(INTROSPECTION_QUERY).await;
+        assert_eq!(response.errors.len(), 1);
+        assert!(response.errors[0].message.contains("introspection is disabled"));
+
+        // Introspection hidden behind a fragment is blocked too
+        let response = schema
+            .execute("query { ...Root } fragment Root on QueryRoot { __type(name: \"QueryRoot\") { name } }")
+            .await;
+        assert_eq!(response.errors.len(), 1);
+
+        // Regular queries (including __typename) still work
+        let response = schema.execute("{ version __typename }").await;
+        assert!(response.errors.is_empty(), "unexpected errors: {:?}", response.errors);
+    }
+
+    struct LogProbe;
+
+    #[async_graphql::Object]
+    impl LogProbe {
+        async fn entry(&self) -> async_graphql::Result<LogEntry> {
+            let response = crate::agent::client::NormalizedLogEntry {
+                container_id: "3f2a9c1b7d4e".to_string(),
+                raw_content: b"ready".to_vec(),
+                ..Default::default()
+            };
+            LogEntry::from_proto(response, "agent-1".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_carries_name_and_id() {
+        let state = AppState::new(ClusterConfig::default());
+        let labels = std::collections::HashMap::from([
+            ("com.docker.compose.service".to_string(), "payments".to_string()),
+        ]);
+        state.container_names.record("agent-1", "3f2a9c1b7d4e", "payments-api", &labels);
+        let schema = Schema::build(LogProbe, async_graphql::EmptyMutation, async_graphql::EmptySubscription)
+            .data(state)
+            .finish();
+
+        let response = schema
+            .execute("{ entry { containerId containerName serviceName agentId nodeName } }")
+            .await;
+        assert!(response.errors.is_empty(), "unexpected errors: {:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            serde_json::json!({"entry": {
+                "containerId": "3f2a9c1b7d4e",
+                "containerName": "payments-api",
+                "serviceName": "payments",
+                "agentId": "agent-1",
+                // Unknown agents fall back to their ID
+                "nodeName": "agent-1",
+            }})
+        );
+    }
 }
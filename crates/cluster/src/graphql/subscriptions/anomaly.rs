@@ -0,0 +1,141 @@
+use crate::graphql::types::stats::{ContainerStats, StatsAnomaly, StatsMetric};
+
+/// Deviations (in standard deviations) reported when the client doesn't say
+pub const DEFAULT_ANOMALY_SIGMA: f64 = 3.0;
+/// Samples learned before anything is reported, when the client doesn't say
+pub const DEFAULT_WARMUP_SAMPLES: u32 = 30;
+
+/// Weight of each new sample in the rolling mean and variance
+const EWMA_ALPHA: f64 = 0.1;
+/// Spread never taken as less than this share of the mean, so a perfectly
+/// flat series doesn't turn every wobble into an anomaly
+const MIN_RELATIVE_SPREAD: f64 = 0.01;
+
+/// Rolling mean and variance of one metric (exponentially weighted)
+#[derive(Debug, Default)]
+struct Baseline {
+    mean: f64,
+    variance: f64,
+    samples: u32,
+}
+
+impl Baseline {
+    /// Expected value and spread before taking `value` in
+    fn observe(&mut self, value: f64) -> (f64, f64) {
+        let expected = (self.mean, self.variance.sqrt().max(self.mean.abs() * MIN_RELATIVE_SPREAD));
+        if self.samples == 0 {
+            self.mean = value;
+        } else {
+            let diff = value - self.mean;
+            self.mean += EWMA_ALPHA * diff;
+            self.variance = (1.0 - EWMA_ALPHA) * (self.variance + EWMA_ALPHA * diff * diff);
+        }
+        self.samples = self.samples.saturating_add(1);
+        expected
+    }
+}
+
+/// Learns CPU and memory baselines from a container's stats and reports
+/// samples too far from them
+pub struct AnomalyDetector {
+    cpu: Baseline,
+    memory: Baseline,
+    sigma: f64,
+    warmup: u32,
+}
+
+impl AnomalyDetector {
+    pub fn new(sigma: f64, warmup: u32) -> Self {
+        Self { cpu: Baseline::default(), memory: Baseline::default(), sigma, warmup }
+    }
+
+    /// Take in a sample; anomalies in it once the warm-up is over. Every
+    /// sample, outliers included, moves the baseline, so a lasting change
+    /// becomes the new normal.
+    pub fn check(&mut self, stats: &ContainerStats) -> Vec<StatsAnomaly> {
+        let samples = [
+            (StatsMetric::Cpu, stats.cpu_stats.cpu_percentage),
+            (StatsMetric::Memory, stats.memory_stats.usage as f64),
+        ];
+        let mut anomalies = Vec::new();
+        for (metric, observed) in samples {
+            let baseline = match metric {
+                StatsMetric::Cpu => &mut self.cpu,
+                StatsMetric::Memory => &mut self.memory,
+            };
+            let warmed_up = baseline.samples >= self.warmup;
+            let (expected, spread) = baseline.observe(observed);
+            if !warmed_up || spread <= 0.0 {
+                continue;
+            }
+            let deviation = (observed - expected) / spread;
+            if deviation.abs() > self.sigma {
+                anomalies.push(StatsAnomaly {
+                    container_id: stats.container_id.clone(),
+                    timestamp: stats.timestamp,
+                    metric,
+                    observed,
+                    expected,
+                    stddev: spread,
+                    deviation,
+                });
+            }
+        }
+        anomalies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphql::types::stats::{BlockIoStats, CpuStats, MemoryStats};
+
+    fn sample(timestamp: i64, cpu_percentage: f64, memory: i64) -> ContainerStats {
+        ContainerStats {
+            container_id: "api".to_string(),
+            agent_id: "agent-1".to_string(),
+            timestamp,
+            cpu_stats: CpuStats {
+                cpu_percentage,
+                total_usage: 0,
+                system_usage: 0,
+                online_cpus: 2,
+                per_cpu_usage: Vec::new(),
+                throttling: None,
+            },
+            memory_stats: MemoryStats { usage: memory, ..MemoryStats::from_proto(None) },
+            network_stats: Vec::new(),
+            block_io_stats: BlockIoStats { read_bytes: 0, write_bytes: 0, read_ops: 0, write_ops: 0, devices: Vec::new() },
+            pids_count: None,
+        }
+    }
+
+    #[test]
+    fn test_single_anomaly_after_warmup() {
+        let mut detector = AnomalyDetector::new(3.0, 10);
+        let mut anomalies = Vec::new();
+
+        // A spike during warm-up is learned, not reported
+        anomalies.extend(detector.check(&sample(0, 95.0, 200_000_000)));
+        // Stable: ~20% CPU, ~200 MB
+        for t in 1..60 {
+            let wobble = if t % 2 == 0 { 0.5 } else { -0.5 };
+            anomalies.extend(detector.check(&sample(t, 20.0 + wobble, 200_000_000 + (wobble * 1e6) as i64)));
+        }
+        assert!(anomalies.is_empty(), "{:?}", anomalies);
+
+        // CPU spike; memory stays put
+        anomalies.extend(detector.check(&sample(60, 90.0, 200_000_000)));
+        for t in 61..70 {
+            anomalies.extend(detector.check(&sample(t, 20.0, 200_000_000)));
+        }
+
+        assert_eq!(anomalies.len(), 1, "{:?}", anomalies);
+        let spike = &anomalies[0];
+        assert_eq!(spike.metric, StatsMetric::Cpu);
+        assert_eq!(spike.timestamp, 60);
+        assert_eq!(spike.observed, 90.0);
+        assert!((spike.expected - 20.0).abs() < 1.0, "{}", spike.expected);
+        assert!(spike.deviation > 3.0);
+    }
+}
@@ -0,0 +1,86 @@
+use async_graphql::Result;
+use futures::{Stream, StreamExt};
+use regex::RegexSet;
+use std::sync::Arc;
+
+use crate::graphql::types::log::{LogEntry, LogStreamOptions};
+
+/// The configured default excludes for this subscription, unless the client
+/// opted out with `skipDefaultExcludes`
+pub fn for_subscription(configured: &Option<Arc<RegexSet>>, opts: &LogStreamOptions) -> Option<Arc<RegexSet>> {
+    configured.clone().filter(|_| !opts.skip_default_excludes)
+}
+
+/// Whether `entry` is noise under the default excludes. Synthetic entries
+/// (heartbeats and markers) have no content and are never dropped.
+fn is_excluded(excludes: &RegexSet, entry: &LogEntry) -> bool {
+    let synthetic = entry.heartbeat
+        || entry.backlog_unavailable
+        || entry.container_recreated
+        || entry.container_stopped
+        || entry.dropped_while_paused > 0;
+    !synthetic && excludes.is_match(&entry.content)
+}
+
+/// Drop entries matching any default exclude pattern. Runs after the
+/// agent has applied the subscription's own filter.
+pub fn drop_excluded<S>(inner: S, excludes: Option<Arc<RegexSet>>) -> impl Stream<Item = Result<LogEntry>>
+where
+    S: Stream<Item = Result<LogEntry>>,
+{
+    inner.filter(move |item| {
+        let keep = match (item, &excludes) {
+            (Ok(entry), Some(excludes)) => !is_excluded(excludes, entry),
+            _ => true,
+        };
+        futures::future::ready(keep)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LogDefaultsConfig;
+    use futures::stream;
+
+    fn line(content: &str) -> Result<LogEntry> {
+        Ok(LogEntry { content: content.to_string(), container_recreated: false, ..LogEntry::recreated("web".into(), "agent-1".into()) })
+    }
+
+    async fn delivered(opts: &LogStreamOptions, configured: &Option<Arc<RegexSet>>) -> Vec<String> {
+        let lines = ["GET /health 200", "GET /orders 200", "GET /healthz 200", "payment failed"];
+        let inner = stream::iter(lines.map(line));
+        drop_excluded(inner, for_subscription(configured, opts))
+            .map(|entry| entry.unwrap().content)
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_default_excludes_drop_probe_noise_unless_skipped() {
+        let defaults = LogDefaultsConfig {
+            exclude_patterns: vec![r"GET /health\S* 200".to_string()],
+            ..Default::default()
+        };
+        let configured = defaults.exclude_set().unwrap().map(Arc::new);
+        let opts = super::super::subscription_options(None, &defaults);
+
+        assert_eq!(delivered(&opts, &configured).await, ["GET /orders 200", "payment failed"]);
+
+        let opted_out = LogStreamOptions { skip_default_excludes: true, ..opts };
+        assert_eq!(delivered(&opted_out, &configured).await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_markers_survive_catch_all_pattern() {
+        let configured = Some(Arc::new(RegexSet::new([".*"]).unwrap()));
+        let opts = super::super::subscription_options(None, &LogDefaultsConfig::default());
+        let marker = Ok(LogEntry::recreated("web".into(), "agent-1".into()));
+
+        let out: Vec<_> = drop_excluded(stream::iter([line("anything"), marker]), for_subscription(&configured, &opts))
+            .collect()
+            .await;
+        assert_eq!(out.len(), 1);
+        assert!(out[0].as_ref().unwrap().container_recreated);
+    }
+}
@@ -0,0 +1,229 @@
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use std::time::Duration;
+
+/// How often a `followByName` stream looks for the replacement container
+pub const REPLACEMENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+enum Phase<T> {
+    /// Following a container; the timestamp is that of the last item sent
+    Attached(BoxStream<'static, T>, String, Option<i64>),
+    Waiting(String, Option<i64>),
+}
+
+/// Keep following a container by name across restarts (same ID) and
+/// recreation (same name, new ID, as with `compose up`).
+///
+/// `first` is the stream for `container_id`. When a stream ends, `find` is
+/// polled every `poll` for the ID of the running container holding the
+/// name. A new ID is attached with `open(id, None)` after `marker`; the same
+/// ID, restarted, is attached with `open(id, Some(after))` to pick up after
+/// the last item sent, whose timestamp `stamp` gives (Unix nanos). Follows
+/// until the client goes away.
+pub fn follow_by_name<T, O, F, M, S>(
+    first: BoxStream<'static, T>,
+    container_id: String,
+    open: O,
+    find: F,
+    marker: M,
+    stamp: S,
+    poll: Duration,
+) -> impl Stream<Item = T>
+where
+    T: Send + 'static,
+    O: FnMut(String, Option<i64>) -> BoxFuture<'static, Option<BoxStream<'static, T>>> + Send + 'static,
+    F: FnMut() -> BoxFuture<'static, Option<String>> + Send + 'static,
+    M: FnMut(String) -> T + Send + 'static,
+    S: FnMut(&T) -> Option<i64> + Send + 'static,
+{
+    let state = (Phase::Attached(first, container_id, None), open, find, marker, stamp);
+    stream::unfold(state, move |(mut phase, mut open, mut find, mut marker, mut stamp)| async move {
+        loop {
+            phase = match phase {
+                Phase::Attached(mut inner, id, last) => match inner.next().await {
+                    Some(item) => {
+                        let last = stamp(&item).or(last);
+                        return Some((item, (Phase::Attached(inner, id, last), open, find, marker, stamp)));
+                    }
+                    None => {
+                        tracing::debug!(container_id = %id, "Followed container's stream ended, waiting for it to run again");
+                        Phase::Waiting(id, last)
+                    }
+                },
+                Phase::Waiting(id, last) => {
+                    tokio::time::sleep(poll).await;
+                    match find().await {
+                        Some(running) if running == id => match open(id.clone(), last.map(|t| t + 1)).await {
+                            Some(inner) => {
+                                tracing::info!(container_id = %id, "Container restarted, reattaching log stream");
+                                Phase::Attached(inner, id, last)
+                            }
+                            None => Phase::Waiting(id, last),
+                        },
+                        Some(new_id) => match open(new_id.clone(), None).await {
+                            Some(inner) => {
+                                tracing::info!(old = %id, new = %new_id, "Container recreated, reattaching log stream");
+                                let item = marker(new_id.clone());
+                                return Some((item, (Phase::Attached(inner, new_id, None), open, find, marker, stamp)));
+                            }
+                            // Created but not ready yet; try again next poll
+                            None => Phase::Waiting(id, last),
+                        },
+                        None => Phase::Waiting(id, last),
+                    }
+                }
+            };
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::FutureExt;
+    use std::sync::{Arc, Mutex};
+
+    const POLL: Duration = Duration::from_millis(10);
+
+    #[derive(Debug, PartialEq)]
+    enum Item {
+        Line(&'static str),
+        /// Stop marker, with the time the container finished
+        Stopped(i64),
+        Recreated(String),
+    }
+
+    fn stamp(item: &Item) -> Option<i64> {
+        match item {
+            Item::Stopped(at) => Some(*at),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recreated_container_is_reattached() {
+        // Who holds the name "web" right now, and the new container's log
+        let holder = Arc::new(Mutex::new(Some("old".to_string())));
+        let (new_tx, new_rx) = mpsc::unbounded();
+        let mut new_rx = Some(new_rx);
+        let opened = Arc::new(Mutex::new(Vec::new()));
+
+        let (old_tx, old_rx) = mpsc::unbounded();
+        let mut followed = Box::pin(follow_by_name(
+            old_rx.boxed(),
+            "old".to_string(),
+            {
+                let opened = opened.clone();
+                move |id: String, _| {
+                    opened.lock().unwrap().push(id);
+                    let inner = new_rx.take().map(|rx| rx.boxed());
+                    async move { inner }.boxed()
+                }
+            },
+            {
+                let holder = holder.clone();
+                move || {
+                    let current = holder.lock().unwrap().clone();
+                    async move { current }.boxed()
+                }
+            },
+            Item::Recreated,
+            stamp,
+            POLL,
+        ));
+
+        old_tx.unbounded_send(Item::Line("old: serving")).unwrap();
+        assert_eq!(followed.next().await, Some(Item::Line("old: serving")));
+
+        // `compose up`: the old container stops and is removed, then a new
+        // one takes its name a few polls later
+        drop(old_tx);
+        *holder.lock().unwrap() = None;
+        let recreate = tokio::spawn(async move {
+            tokio::time::sleep(POLL * 3).await;
+            *holder.lock().unwrap() = Some("new".to_string());
+            new_tx.unbounded_send(Item::Line("new: listening")).unwrap();
+            new_tx
+        });
+
+        assert_eq!(followed.next().await, Some(Item::Recreated("new".to_string())));
+        assert_eq!(followed.next().await, Some(Item::Line("new: listening")));
+        assert_eq!(*opened.lock().unwrap(), vec!["new".to_string()]);
+        let _new_tx = recreate.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_waits_while_container_is_stopped() {
+        let (old_tx, old_rx) = mpsc::unbounded::<Item>();
+        let mut followed = Box::pin(follow_by_name(
+            old_rx.boxed(),
+            "old".to_string(),
+            |_, _| async { None }.boxed(),
+            || async { None }.boxed(),
+            Item::Recreated,
+            stamp,
+            POLL,
+        ));
+        old_tx.unbounded_send(Item::Stopped(100)).unwrap();
+        drop(old_tx);
+        assert_eq!(followed.next().await, Some(Item::Stopped(100)));
+
+        // Stopped and neither restarted nor replaced: the stream stays open
+        // with nothing to send
+        let waited = tokio::time::timeout(POLL * 5, followed.next()).await;
+        assert!(waited.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restarted_container_is_reattached_after_its_stop() {
+        let running = Arc::new(Mutex::new(None));
+        let (restarted_tx, restarted_rx) = mpsc::unbounded();
+        let mut restarted_rx = Some(restarted_rx);
+        let opened = Arc::new(Mutex::new(Vec::new()));
+
+        let (tx, rx) = mpsc::unbounded();
+        let mut followed = Box::pin(follow_by_name(
+            rx.boxed(),
+            "web".to_string(),
+            {
+                let opened = opened.clone();
+                move |id: String, after| {
+                    opened.lock().unwrap().push((id, after));
+                    let inner = restarted_rx.take().map(|rx| rx.boxed());
+                    async move { inner }.boxed()
+                }
+            },
+            {
+                let running = running.clone();
+                move || {
+                    let current = running.lock().unwrap().clone();
+                    async move { current }.boxed()
+                }
+            },
+            Item::Recreated,
+            stamp,
+            POLL,
+        ));
+
+        // The container crashes; its stop marker ends the stream
+        tx.unbounded_send(Item::Line("web: panic")).unwrap();
+        tx.unbounded_send(Item::Stopped(100)).unwrap();
+        drop(tx);
+        assert_eq!(followed.next().await, Some(Item::Line("web: panic")));
+        assert_eq!(followed.next().await, Some(Item::Stopped(100)));
+
+        // The restart policy brings the same container back
+        let restart = tokio::spawn(async move {
+            tokio::time::sleep(POLL * 3).await;
+            *running.lock().unwrap() = Some("web".to_string());
+            restarted_tx.unbounded_send(Item::Line("web: listening")).unwrap();
+            restarted_tx
+        });
+
+        // Reattached from just after the stop, without a recreation marker
+        assert_eq!(followed.next().await, Some(Item::Line("web: listening")));
+        assert_eq!(*opened.lock().unwrap(), vec![("web".to_string(), Some(101))]);
+        let _restarted_tx = restart.await.unwrap();
+    }
+}
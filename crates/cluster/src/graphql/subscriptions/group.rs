@@ -0,0 +1,141 @@
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream, SelectAll, Stream, StreamExt};
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// How often `logsByContainerGroup` looks for containers joining the group
+pub const GROUP_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+enum Event<T> {
+    Item(T),
+    Ended(String),
+}
+
+fn member<T: Send + 'static>(id: String, stream: BoxStream<'static, T>) -> BoxStream<'static, Event<T>> {
+    stream.map(Event::Item).chain(stream::once(async move { Event::Ended(id) })).boxed()
+}
+
+struct Group<T, L, O> {
+    members: SelectAll<BoxStream<'static, Event<T>>>,
+    active: HashSet<String>,
+    list: L,
+    open: O,
+    poll: tokio::time::Interval,
+}
+
+/// Merge the logs of a group of containers whose membership changes.
+///
+/// `first` holds the streams of the members found at the start. Every `poll`
+/// `list` returns the IDs of the running members, and `open` attaches to any
+/// not being streamed. A member whose stream ends (an init container exits,
+/// a sidecar stops) just leaves the group; if it starts again it is picked
+/// up on a later poll. Follows until the client goes away.
+pub fn follow_group<T, L, O>(
+    first: Vec<(String, BoxStream<'static, T>)>,
+    list: L,
+    open: O,
+    poll: Duration,
+) -> impl Stream<Item = T>
+where
+    T: Send + 'static,
+    L: FnMut() -> BoxFuture<'static, Option<Vec<String>>> + Send + 'static,
+    O: FnMut(String) -> BoxFuture<'static, Option<BoxStream<'static, T>>> + Send + 'static,
+{
+    let mut members = SelectAll::new();
+    let mut active = HashSet::new();
+    for (id, stream) in first {
+        active.insert(id.clone());
+        members.push(member(id, stream));
+    }
+    let mut poll = tokio::time::interval_at(tokio::time::Instant::now() + poll, poll);
+    poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let group = Group { members, active, list, open, poll };
+
+    stream::unfold(group, |mut group| async move {
+        loop {
+            tokio::select! {
+                Some(event) = group.members.next(), if !group.members.is_empty() => match event {
+                    Event::Item(item) => return Some((item, group)),
+                    Event::Ended(id) => {
+                        tracing::debug!(container_id = %id, "Group member's log stream ended");
+                        group.active.remove(&id);
+                    }
+                },
+                _ = group.poll.tick() => {
+                    // Listing failed: keep what we have and try next time
+                    let Some(ids) = (group.list)().await else { continue };
+                    for id in ids {
+                        if group.active.contains(&id) {
+                            continue;
+                        }
+                        if let Some(stream) = (group.open)(id.clone()).await {
+                            tracing::info!(container_id = %id, "Container joined the group, attaching log stream");
+                            group.active.insert(id.clone());
+                            group.members.push(member(id, stream));
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::FutureExt;
+    use std::sync::{Arc, Mutex};
+
+    const POLL: Duration = Duration::from_millis(10);
+
+    #[tokio::test]
+    async fn test_member_exit_leaves_the_rest_streaming() {
+        // "web" and its "migrate" init container; "migrate" exits early
+        let running = Arc::new(Mutex::new(vec!["web".to_string(), "migrate".to_string()]));
+        let (web_tx, web_rx) = mpsc::unbounded();
+        let (init_tx, init_rx) = mpsc::unbounded();
+        let (sidecar_tx, sidecar_rx) = mpsc::unbounded();
+        let mut sidecar_rx = Some(sidecar_rx);
+        let opened = Arc::new(Mutex::new(Vec::new()));
+
+        let mut group = Box::pin(follow_group(
+            vec![("web".to_string(), web_rx.boxed()), ("migrate".to_string(), init_rx.boxed())],
+            {
+                let running = running.clone();
+                move || {
+                    let ids = running.lock().unwrap().clone();
+                    async move { Some(ids) }.boxed()
+                }
+            },
+            {
+                let opened = opened.clone();
+                move |id: String| {
+                    opened.lock().unwrap().push(id.clone());
+                    let stream = (id == "proxy").then(|| sidecar_rx.take()).flatten().map(|rx| rx.boxed());
+                    async move { stream }.boxed()
+                }
+            },
+            POLL,
+        ));
+
+        init_tx.unbounded_send("migrate: applied 3 migrations").unwrap();
+        assert_eq!(group.next().await, Some("migrate: applied 3 migrations"));
+
+        // The init container exits and drops out of the listing
+        drop(init_tx);
+        running.lock().unwrap().retain(|id| id != "migrate");
+        web_tx.unbounded_send("web: listening on :8080").unwrap();
+        assert_eq!(group.next().await, Some("web: listening on :8080"));
+
+        // A sidecar joins later and is merged in
+        running.lock().unwrap().push("proxy".to_string());
+        sidecar_tx.unbounded_send("proxy: ready").unwrap();
+        assert_eq!(group.next().await, Some("proxy: ready"));
+        web_tx.unbounded_send("web: GET / 200").unwrap();
+        assert_eq!(group.next().await, Some("web: GET / 200"));
+
+        // The exited member was never reattached
+        assert_eq!(*opened.lock().unwrap(), vec!["proxy".to_string()]);
+    }
+}
@@ -0,0 +1,64 @@
+use futures::stream::{BoxStream, Stream, StreamExt};
+
+use crate::config::SubscriptionLifetimeConfig;
+use crate::metrics::SubscriptionKind;
+
+/// End `stream` once it has run for its kind's configured max lifetime. The
+/// client sees the subscription complete; it may subscribe again.
+pub fn with_max_lifetime<S, T>(
+    stream: S,
+    kind: SubscriptionKind,
+    config: &SubscriptionLifetimeConfig,
+) -> BoxStream<'static, T>
+where
+    S: Stream<Item = T> + Send + 'static,
+{
+    let Some(max) = config.max_lifetime(kind) else {
+        return stream.boxed();
+    };
+    let expired = async move {
+        tokio::time::sleep(max).await;
+        tracing::debug!(kind = kind.as_str(), "Subscription reached its max lifetime of {:?}", max);
+    };
+    stream.take_until(expired).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn ticks() -> impl Stream<Item = u32> + Send {
+        futures::stream::unfold(0u32, |n| async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Some((n, n + 1))
+        })
+    }
+
+    /// Runs a stream to its end, returning how long it ran
+    async fn run_time(kind: SubscriptionKind, config: &SubscriptionLifetimeConfig) -> Duration {
+        let started = Instant::now();
+        let delivered = with_max_lifetime(ticks(), kind, config).count().await;
+        assert!(delivered > 0);
+        started.elapsed()
+    }
+
+    #[tokio::test]
+    async fn test_stats_closes_at_shorter_lifetime_than_logs() {
+        let config = SubscriptionLifetimeConfig { log_secs: 2, stats_secs: 1, health_secs: 0 };
+
+        let (stats, logs) = tokio::join!(
+            run_time(SubscriptionKind::Stats, &config),
+            run_time(SubscriptionKind::Log, &config),
+        );
+        assert!(stats >= Duration::from_secs(1) && stats < Duration::from_millis(1500), "stats ran {:?}", stats);
+        assert!(logs >= Duration::from_secs(2), "logs ran {:?}", logs);
+    }
+
+    #[tokio::test]
+    async fn test_zero_means_no_limit() {
+        let config = SubscriptionLifetimeConfig { log_secs: 0, stats_secs: 0, health_secs: 0 };
+        let delivered = with_max_lifetime(ticks().take(5), SubscriptionKind::Health, &config).count().await;
+        assert_eq!(delivered, 5);
+    }
+}
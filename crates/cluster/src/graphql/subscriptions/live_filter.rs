@@ -0,0 +1,43 @@
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use std::sync::Arc;
+
+/// Agents serving each open log subscription that set a `filterToken`, so
+/// `setLogStreamFilter` knows whom to send the new filter to
+#[derive(Default)]
+pub struct FilterTokens {
+    tokens: DashMap<String, Vec<String>>,
+}
+
+/// Keeps a token registered while its subscription is alive
+pub struct FilterTokenRegistration {
+    tokens: Arc<FilterTokens>,
+    token: String,
+}
+
+impl Drop for FilterTokenRegistration {
+    fn drop(&mut self) {
+        self.tokens.tokens.remove(&self.token);
+    }
+}
+
+impl FilterTokens {
+    /// Claim `token` for a subscription streaming from `agent_ids`; `None`
+    /// if it is already in use
+    pub fn register(self: &Arc<Self>, token: &str, mut agent_ids: Vec<String>) -> Option<FilterTokenRegistration> {
+        match self.tokens.entry(token.to_string()) {
+            Entry::Occupied(_) => None,
+            Entry::Vacant(slot) => {
+                agent_ids.sort();
+                agent_ids.dedup();
+                slot.insert(agent_ids);
+                Some(FilterTokenRegistration { tokens: Arc::clone(self), token: token.to_string() })
+            }
+        }
+    }
+
+    /// Agents streaming for the subscription holding `token`
+    pub fn agents(&self, token: &str) -> Option<Vec<String>> {
+        self.tokens.get(token).map(|agents| agents.clone())
+    }
+}
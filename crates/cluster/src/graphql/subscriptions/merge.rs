@@ -0,0 +1,123 @@
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use std::cmp::Ordering;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Entries sorted together when a subscription doesn't say
+pub const DEFAULT_MERGE_CHUNK_SIZE: u32 = 10;
+
+/// Upper bound on `mergeChunkSize`; a chunk is buffered in memory
+pub const MAX_MERGE_CHUNK_SIZE: u32 = 1000;
+
+/// Upper bound on `mergeHoldMs`; beyond this, live tailing stops feeling live
+pub const MAX_MERGE_HOLD_MS: u32 = 5000;
+
+/// Rough ordering for a merged multi-container stream: entries are sorted in
+/// chunks of up to `chunk_size`.
+///
+/// With no hold, a chunk is whatever is ready right now (never waits). With a
+/// hold, a chunk stays open for up to `hold` after its first entry so late
+/// neighbours can be sorted in; an entry is never delayed longer than that.
+pub fn sorted_chunks<S, T, F>(
+    inner: S,
+    chunk_size: usize,
+    hold: Duration,
+    compare: F,
+) -> impl Stream<Item = T>
+where
+    S: Stream<Item = T> + Unpin + Send + 'static,
+    T: Send + 'static,
+    F: FnMut(&T, &T) -> Ordering + Clone,
+{
+    let chunks: BoxStream<'static, Vec<T>> = if hold.is_zero() {
+        inner.ready_chunks(chunk_size).boxed()
+    } else {
+        held_chunks(inner, chunk_size, hold).boxed()
+    };
+    chunks.flat_map(move |mut chunk| {
+        chunk.sort_by(compare.clone());
+        stream::iter(chunk)
+    })
+}
+
+/// Chunks that close when full, when `hold` has passed since their first
+/// item, or when the stream ends
+fn held_chunks<S, T>(inner: S, chunk_size: usize, hold: Duration) -> impl Stream<Item = Vec<T>>
+where
+    S: Stream<Item = T> + Unpin,
+{
+    stream::unfold(Some(inner), move |inner| async move {
+        let mut inner = inner?;
+        let first = inner.next().await?;
+
+        let deadline = Instant::now() + hold;
+        let mut chunk = Vec::with_capacity(chunk_size);
+        chunk.push(first);
+        let mut ended = false;
+        while chunk.len() < chunk_size {
+            match tokio::time::timeout_at(deadline, inner.next()).await {
+                Ok(Some(item)) => chunk.push(item),
+                Ok(None) => {
+                    ended = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        Some((chunk, (!ended).then_some(inner)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+
+    /// Two containers' timestamps, interleaved the way select_all delivers them
+    const ARRIVALS: [u32; 12] = [7, 1, 8, 2, 9, 3, 10, 4, 11, 5, 12, 6];
+
+    fn inversions(order: &[u32]) -> usize {
+        order
+            .iter()
+            .enumerate()
+            .map(|(i, a)| order[i + 1..].iter().filter(|b| *b < a).count())
+            .sum()
+    }
+
+    async fn merge(chunk_size: usize) -> Vec<u32> {
+        let ready = stream::iter(ARRIVALS);
+        sorted_chunks(ready, chunk_size, Duration::ZERO, u32::cmp).collect().await
+    }
+
+    #[tokio::test]
+    async fn test_larger_chunks_order_better() {
+        let small = merge(2).await;
+        let default = merge(DEFAULT_MERGE_CHUNK_SIZE as usize).await;
+        let large = merge(ARRIVALS.len()).await;
+
+        assert!(inversions(&default) < inversions(&small), "{:?} vs {:?}", default, small);
+        assert_eq!(inversions(&large), 0);
+        assert_eq!(large.len(), ARRIVALS.len(), "nothing lost");
+    }
+
+    #[tokio::test]
+    async fn test_hold_sorts_late_arrivals_and_bounds_latency() {
+        let hold = Duration::from_millis(80);
+        let (tx, rx) = mpsc::unbounded();
+        let mut merged = Box::pin(sorted_chunks(rx, 100, hold, u32::cmp));
+
+        // The newer line arrives first; its older neighbour shortly after
+        let sent = Instant::now();
+        tx.unbounded_send(9).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        tx.unbounded_send(4).unwrap();
+
+        assert_eq!(merged.next().await, Some(4));
+        assert_eq!(merged.next().await, Some(9));
+        // The chunk wasn't full, yet nothing waited much past the hold
+        assert!(sent.elapsed() < hold + Duration::from_millis(200), "held {:?}", sent.elapsed());
+
+        drop(tx);
+        assert_eq!(merged.next().await, None);
+    }
+}
@@ -1,29 +1,231 @@
 use async_graphql::{Context, Result, Subscription};
-use futures::{Stream, StreamExt};
+use futures::stream::BoxStream;
+use futures::{FutureExt, Stream, StreamExt};
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::config::LogDefaultsConfig;
 use crate::state::AppState;
 use crate::error::ApiError;
-use crate::graphql::types::log::{LogEntry, LogStreamOptions};
+use crate::graphql::types::log::{ContainerStopBehavior, LogEntry, LogRateBucket, LogStreamOptions, StreamPriority};
 use crate::graphql::types::agent::{AgentHealthEvent, AgentStatus, MetadataEntry};
-use crate::graphql::types::stats::ContainerStats;
-use crate::agent::client::{LogStreamRequest, HealthCheckRequest, ContainerStatsRequest};
-use crate::metrics::SubscriptionMetrics;
+use crate::graphql::types::stats::{ContainerStats, StatsAnomaly};
+use crate::agent::AgentGrpcClient;
+use crate::agent::client::{LogStreamRequest, LogRateRequest, HealthCheckRequest, ContainerStatsRequest, ContainerListRequest, LabelSelector, NormalizedLogEntry};
+use crate::inventory::InventoryCache;
+use crate::metrics::{grpc_message_size, SubscriptionKind, SubscriptionMetrics};
+
+mod anomaly;
+mod excludes;
+mod follow;
+mod group;
+mod lifetime;
+mod live_filter;
+mod merge;
+mod pause;
+
+use lifetime::with_max_lifetime;
+pub use live_filter::FilterTokens;
+pub use pause::{PauseControls, DEFAULT_PAUSE_BUFFER};
 
 /// RAII guard that ensures subscription_ended is called when the stream is dropped,
 /// even on abrupt client disconnects.
 struct SubscriptionGuard {
     metrics: Arc<SubscriptionMetrics>,
     agent_id: String,
+    kind: SubscriptionKind,
 }
 
 impl Drop for SubscriptionGuard {
     fn drop(&mut self) {
-        self.metrics.subscription_ended(&self.agent_id);
-        tracing::debug!(agent_id = %self.agent_id, "Subscription guard dropped, metrics updated");
+        self.metrics.subscription_ended(&self.agent_id, self.kind);
+        tracing::debug!(agent_id = %self.agent_id, kind = self.kind.as_str(), "Subscription guard dropped, metrics updated");
+    }
+}
+
+/// The client's options, or the configured defaults when it sent none
+fn subscription_options(options: Option<LogStreamOptions>, defaults: &LogDefaultsConfig) -> LogStreamOptions {
+    options.unwrap_or(LogStreamOptions {
+        tail: Some(defaults.tail),
+        follow: true,  // Always follow for subscriptions
+        timestamps: defaults.timestamps,
+        ..LogStreamOptions::default()
+    })
+}
+
+/// Whether a `logStream` stays open once its container stops, waiting for
+/// the name to run again
+fn waits_for_restart(opts: &LogStreamOptions) -> std::result::Result<bool, ApiError> {
+    let by_name = opts.follow && opts.follow_by_name;
+    match opts.on_container_stop {
+        Some(ContainerStopBehavior::WaitForRestart) if !by_name => Err(ApiError::InvalidRequest(
+            "onContainerStop: WAIT_FOR_RESTART needs follow and followByName".to_string(),
+        )),
+        Some(ContainerStopBehavior::WaitForRestart) => Ok(true),
+        Some(ContainerStopBehavior::Complete) => Ok(false),
+        None => Ok(by_name),
     }
 }
 
+/// Chunk size and hold time for merging several containers' streams
+fn merge_settings(opts: &LogStreamOptions) -> std::result::Result<(usize, Duration), ApiError> {
+    let chunk_size = opts.merge_chunk_size.unwrap_or(merge::DEFAULT_MERGE_CHUNK_SIZE);
+    if !(1..=merge::MAX_MERGE_CHUNK_SIZE).contains(&chunk_size) {
+        return Err(ApiError::InvalidRequest(format!(
+            "mergeChunkSize must be between 1 and {}, got {}",
+            merge::MAX_MERGE_CHUNK_SIZE, chunk_size
+        )));
+    }
+    let hold_ms = opts.merge_hold_ms.unwrap_or(0);
+    if hold_ms > merge::MAX_MERGE_HOLD_MS {
+        return Err(ApiError::InvalidRequest(format!(
+            "mergeHoldMs must be at most {}, got {}",
+            merge::MAX_MERGE_HOLD_MS, hold_ms
+        )));
+    }
+    Ok((chunk_size as usize, Duration::from_millis(hold_ms.into())))
+}
+
+/// Reject a `logsFromContainers` request over `limits.max_container_streams`
+fn container_stream_limit(requested: usize, limit: usize) -> std::result::Result<(), ApiError> {
+    if requested > limit {
+        return Err(ApiError::InvalidRequest(format!(
+            "Too many containers requested ({}). Maximum is {}",
+            requested, limit
+        )));
+    }
+    Ok(())
+}
+
+/// Convert an agent log stream to GraphQL entries, counting each message.
+/// The guard is moved into the stream; when the stream is dropped (client
+/// disconnect, error, or normal completion), its Drop implementation calls
+/// subscription_ended automatically.
+fn log_entries(
+    grpc_stream: tonic::Streaming<NormalizedLogEntry>,
+    agent_id: String,
+    container_id: &str,
+    metrics: Arc<SubscriptionMetrics>,
+    inventory: Arc<InventoryCache>,
+    guard: Arc<SubscriptionGuard>,
+) -> BoxStream<'static, Result<LogEntry>> {
+    let lines = metrics.track_lines(&agent_id, container_id);
+    grpc_stream
+        .map(move |result| {
+            // Keep guard alive as long as the stream is alive
+            let _guard = &guard;
+            match result {
+                Ok(response) => {
+                    metrics.log_message_sent(&response);
+                    lines.record(&response);
+                    if response.container_stopped {
+                        inventory.invalidate(&agent_id);
+                    }
+
+                    // Convert proto response to LogEntry
+                    LogEntry::from_proto(response, agent_id.clone())
+                }
+                Err(e) => {
+                    // Let errors bubble up to frontend so they know why connection closed
+                    Err(ApiError::from_status(&agent_id, "Stream error", e).extend())
+                }
+            }
+        })
+        .boxed()
+}
+
+/// Name of the container with this ID (or ID prefix), from the agent's inventory
+async fn container_name(client: &mut AgentGrpcClient, container_id: &str) -> Option<String> {
+    let listed = client
+        .list_containers(ContainerListRequest { include_stopped: true, ..Default::default() })
+        .await
+        .ok()?;
+    listed.containers.into_iter().find(|c| c.id.starts_with(container_id)).map(|c| c.name)
+}
+
+/// ID of the running container holding `name`, if any
+async fn running_container_by_name(client: &mut AgentGrpcClient, name: &str) -> Option<String> {
+    let listed = client
+        .list_containers(ContainerListRequest {
+            include_stopped: false,
+            name_pattern: Some(name.to_string()),
+            ..Default::default()
+        })
+        .await
+        .ok()?;
+    listed.containers.into_iter().find(|c| c.name == name).map(|c| c.id)
+}
+
+/// Claim the subscription's `pauseToken`, if it has one
+fn pause_registration(
+    state: &AppState,
+    opts: &LogStreamOptions,
+) -> std::result::Result<Option<(pause::PauseRegistration, tokio::sync::watch::Receiver<bool>)>, ApiError> {
+    let Some(token) = &opts.pause_token else {
+        return Ok(None);
+    };
+    state
+        .pause_controls
+        .register(token)
+        .map(Some)
+        .ok_or_else(|| ApiError::InvalidRequest(format!("pauseToken '{}' is already in use", token)))
+}
+
+/// Let `pauseLogStream` hold back `stream` while it keeps reading. Markers
+/// for dropped lines carry `container_id` and `agent_id`.
+fn with_pause(
+    stream: BoxStream<'static, Result<LogEntry>>,
+    registration: Option<(pause::PauseRegistration, tokio::sync::watch::Receiver<bool>)>,
+    bound: usize,
+    container_id: String,
+    agent_id: String,
+) -> BoxStream<'static, Result<LogEntry>> {
+    let Some((registration, control)) = registration else {
+        return stream;
+    };
+    let marker = move |dropped| Ok(LogEntry::pause_drop_marker(container_id.clone(), agent_id.clone(), dropped));
+    pause::pausable(stream, control, bound, marker)
+        .map(move |item| {
+            // The token stays claimed as long as the stream is alive
+            let _registration = &registration;
+            item
+        })
+        .boxed()
+}
+
+/// Claim the subscription's `filterToken`, if it has one, for the agents it
+/// streams from
+fn filter_token_registration(
+    state: &AppState,
+    opts: &LogStreamOptions,
+    agent_ids: Vec<String>,
+) -> std::result::Result<Option<live_filter::FilterTokenRegistration>, ApiError> {
+    let Some(token) = &opts.filter_token else {
+        return Ok(None);
+    };
+    state
+        .filter_tokens
+        .register(token, agent_ids)
+        .map(Some)
+        .ok_or_else(|| ApiError::InvalidRequest(format!("filterToken '{}' is already in use", token)))
+}
+
+/// Keep the `filterToken` claimed as long as `stream` is alive
+fn with_filter_token(
+    stream: BoxStream<'static, Result<LogEntry>>,
+    registration: Option<live_filter::FilterTokenRegistration>,
+) -> BoxStream<'static, Result<LogEntry>> {
+    let Some(registration) = registration else {
+        return stream;
+    };
+    stream
+        .map(move |item| {
+            let _registration = &registration;
+            item
+        })
+        .boxed()
+}
+
 /// Root subscription type
 pub struct SubscriptionRoot;
 
@@ -66,7 +268,7 @@ impl SubscriptionRoot {
         let state = ctx.data::<AppState>()?;
         
         // Track subscription metrics
-        state.metrics.subscription_started(&agent_id);
+        state.metrics.subscription_started(&agent_id, SubscriptionKind::Log);
         let metrics = state.metrics.clone();
         
         // Create a RAII guard that will call subscription_ended when the stream is dropped.
@@ -74,6 +276,7 @@ impl SubscriptionRoot {
         let guard = Arc::new(SubscriptionGuard {
             metrics: metrics.clone(),
             agent_id: agent_id.clone(),
+            kind: SubscriptionKind::Log,
         });
         
         // Get agent connection
@@ -88,38 +291,26 @@ impl SubscriptionRoot {
         // Check agent health
         if !agent_conn.is_healthy() {
             state.metrics.subscription_failed();
-            return Err(ApiError::AgentUnavailable(format!(
-                "Agent '{}' is not healthy. Try again later or check agent status.",
-                agent_id
-            )).extend());
+            return Err(ApiError::AgentUnavailable(agent_id.clone()).extend());
         }
         
-        // Default options with follow=true for subscriptions
-        let opts = options.unwrap_or(LogStreamOptions {
-            since: None,
-            until: None,
-            tail: Some(50),
-            follow: true,  // Always follow for subscriptions
-            filter: None,
-            filter_mode: crate::graphql::types::log::FilterMode::None,
-            timestamps: true,
-        });
+        // Cluster defaults (with follow=true) unless the client sent options
+        let opts = subscription_options(options, &state.config.log_defaults);
+        let wait_for_restart = waits_for_restart(&opts).map_err(|e| {
+            metrics.subscription_failed();
+            e.extend()
+        })?;
+        let pause = pause_registration(state, &opts).map_err(|e| {
+            metrics.subscription_failed();
+            e.extend()
+        })?;
+        let filter_token = filter_token_registration(state, &opts, vec![agent_id.clone()]).map_err(|e| {
+            metrics.subscription_failed();
+            e.extend()
+        })?;
         
         // Build gRPC request
-        let request = LogStreamRequest {
-            container_id: container_id.clone(),
-            since: opts.since.map(|dt| dt.timestamp()),
-            until: opts.until.map(|dt| dt.timestamp()),
-            tail_lines: opts.tail.and_then(|t| if t > 0 { Some(t as u32) } else { None }),
-            follow: opts.follow,
-            filter_pattern: opts.filter.clone(),
-            filter_mode: {
-                let proto_mode: crate::agent::client::FilterMode = opts.filter_mode.into();
-                proto_mode as i32
-            },
-            timestamps: opts.timestamps,
-            disable_parsing: false,  // Enable parsing by default
-        };
+        let request = opts.to_request(container_id.clone());
         
         // ⚡ FIX 1: Clone client to release lock immediately
         let mut client = {
@@ -129,41 +320,78 @@ impl SubscriptionRoot {
         
         // Get gRPC client and open stream
         let grpc_stream = client
-            .stream_logs(request)
+            .stream_logs(request.clone())
             .await
             .map_err(|e| {
                 metrics.subscription_failed();
-                ApiError::Internal(format!("Failed to open log stream: {}. Check agent logs for details.", e)).extend()
+                ApiError::from_container_agent(&agent_id, &container_id, "Failed to open log stream", e).extend()
             })?;
         
-        // Clone metrics for use in stream closure
-        let metrics_for_stream = metrics.clone();
-        
-        // Convert gRPC stream to GraphQL stream with metrics tracking.
-        // The guard is moved into the stream closure; when the stream is dropped
-        // (client disconnect, error, or normal completion), the guard's Drop
-        // implementation calls subscription_ended automatically.
-        let log_stream = grpc_stream
-            .map(move |result| {
-                // Keep guard alive as long as the stream is alive
-                let _guard = &guard;
-                match result {
-                    Ok(response) => {
-                        // Track message sent
-                        let byte_count = response.raw_content.len();
-                        metrics_for_stream.message_sent(byte_count);
-                        
-                        // Convert proto response to LogEntry
-                        LogEntry::from_proto(response, agent_id.clone())
-                    }
-                    Err(e) => {
-                        // Let errors bubble up to frontend so they know why connection closed
-                        Err(ApiError::Internal(format!("Stream error: {}", e)).extend())
-                    }
+        let inventory = state.inventory.clone();
+        let excludes = excludes::for_subscription(&state.default_excludes, &opts);
+        let pause_bound = state.config.log_defaults.pause_buffer_lines;
+        let log_stream = log_entries(grpc_stream, agent_id.clone(), &container_id, metrics.clone(), inventory.clone(), guard.clone());
+        if !wait_for_restart {
+            let log_stream = excludes::drop_excluded(log_stream, excludes).boxed();
+            let log_stream = with_filter_token(with_pause(log_stream, pause, pause_bound, container_id, agent_id), filter_token);
+            return Ok(with_max_lifetime(log_stream, SubscriptionKind::Log, &state.config.subscription_lifetimes));
+        }
+
+        // Follow by name: find the name now, then reattach when it runs again,
+        // from a recreated container's first line or after a restarted one's stop
+        let name = container_name(&mut client, &container_id).await.ok_or_else(|| {
+            metrics.subscription_failed();
+            ApiError::InvalidRequest(format!("followByName: container '{}' not found", container_id)).extend()
+        })?;
+        let open = {
+            let agent_conn = agent_conn.clone();
+            let agent_id = agent_id.clone();
+            move |new_id: String, after: Option<i64>| {
+                let agent_conn = agent_conn.clone();
+                let (agent_id, metrics, guard) = (agent_id.clone(), metrics.clone(), guard.clone());
+                let inventory = inventory.clone();
+                let request = LogStreamRequest {
+                    container_id: new_id,
+                    since: None,
+                    since_nanos: after,
+                    tail_lines: None,
+                    ..request.clone()
+                };
+                async move {
+                    let mut client = agent_conn.client.lock().await.clone();
+                    let container_id = request.container_id.clone();
+                    let stream = client.stream_logs(request).await.ok()?;
+                    Some(log_entries(stream, agent_id, &container_id, metrics, inventory, guard))
                 }
-            });
-        
-        Ok(log_stream)
+                .boxed()
+            }
+        };
+        let find = move || {
+            let (agent_conn, name) = (agent_conn.clone(), name.clone());
+            async move {
+                let mut client = agent_conn.client.lock().await.clone();
+                running_container_by_name(&mut client, &name).await
+            }
+            .boxed()
+        };
+        let marker = {
+            let agent_id = agent_id.clone();
+            move |new_id| Ok(LogEntry::recreated(new_id, agent_id.clone()))
+        };
+
+        let stamp = |item: &Result<LogEntry>| item.as_ref().ok().and_then(|entry| entry.timestamp.timestamp_nanos_opt());
+        let followed = follow::follow_by_name(
+            log_stream,
+            container_id.clone(),
+            open,
+            find,
+            marker,
+            stamp,
+            follow::REPLACEMENT_POLL_INTERVAL,
+        );
+        let followed = excludes::drop_excluded(followed, excludes).boxed();
+        let followed = with_filter_token(with_pause(followed, pause, pause_bound, container_id, agent_id), filter_token);
+        Ok(with_max_lifetime(followed, SubscriptionKind::Log, &state.config.subscription_lifetimes))
     }
     
     /// Stream logs from multiple containers across multiple agents, aggregated and sorted by timestamp
@@ -203,35 +431,36 @@ impl SubscriptionRoot {
         }
 
         // Limit the number of concurrent container streams to prevent resource exhaustion
-        const MAX_CONTAINER_STREAMS: usize = 20;
-        if containers.len() > MAX_CONTAINER_STREAMS {
-            return Err(ApiError::InvalidRequest(format!(
-                "Too many containers requested ({}). Maximum is {}",
-                containers.len(),
-                MAX_CONTAINER_STREAMS
-            )).extend());
-        }
+        container_stream_limit(containers.len(), state.config.limits.max_container_streams)
+            .map_err(|e| e.extend())?;
 
         // Track subscription metrics for each container source
         let mut guards = Vec::new();
         for cs in &containers {
-            state.metrics.subscription_started(&cs.agent_id);
+            state.metrics.subscription_started(&cs.agent_id, SubscriptionKind::Log);
             guards.push(Arc::new(SubscriptionGuard {
                 metrics: state.metrics.clone(),
                 agent_id: cs.agent_id.clone(),
+                kind: SubscriptionKind::Log,
             }));
         }
         
-        // Default options with follow=true for subscriptions
-        let opts = options.unwrap_or(LogStreamOptions {
-            since: None,
-            until: None,
-            tail: Some(50),
-            follow: true,
-            filter: None,
-            filter_mode: crate::graphql::types::log::FilterMode::None,
-            timestamps: true,
-        });
+        // Cluster defaults (with follow=true) unless the client sent options
+        let opts = subscription_options(options, &state.config.log_defaults);
+        let (chunk_size, merge_hold) = merge_settings(&opts).map_err(|e| {
+            state.metrics.subscription_failed();
+            e.extend()
+        })?;
+        let excludes = excludes::for_subscription(&state.default_excludes, &opts);
+        let pause = pause_registration(state, &opts).map_err(|e| {
+            state.metrics.subscription_failed();
+            e.extend()
+        })?;
+        let agent_ids = containers.iter().map(|c| c.agent_id.clone()).collect();
+        let filter_token = filter_token_registration(state, &opts, agent_ids).map_err(|e| {
+            state.metrics.subscription_failed();
+            e.extend()
+        })?;
         
         // Open a stream for each container (potentially across multiple agents)
         let mut streams = Vec::new();
@@ -258,20 +487,7 @@ impl SubscriptionRoot {
                 continue;
             }
             
-            let request = LogStreamRequest {
-                container_id: container_id.clone(),
-                since: opts.since.map(|dt| dt.timestamp()),
-                until: opts.until.map(|dt| dt.timestamp()),
-                tail_lines: opts.tail.and_then(|t| if t > 0 { Some(t as u32) } else { None }),
-                follow: opts.follow,
-                filter_pattern: opts.filter.clone(),
-                filter_mode: {
-                    let proto_mode: crate::agent::client::FilterMode = opts.filter_mode.into();
-                    proto_mode as i32
-                },
-                timestamps: opts.timestamps,
-                disable_parsing: false,  // Enable parsing by default
-            };
+            let request = opts.to_request(container_id.clone());
             
             // ⚡ FIX 1: Clone client to release lock immediately
             let mut client = {
@@ -285,14 +501,22 @@ impl SubscriptionRoot {
                     // Clone agent_id for use in the closure and after
                     let agent_id_for_stream = agent_id.clone();
                     let container_id_for_log = container_id.clone();
+                    let metrics = state.metrics.clone();
+                    let lines = state.metrics.track_lines(&agent_id, &container_id);
+                    let inventory = state.inventory.clone();
                     
                     // Convert gRPC stream to LogEntry stream
                     // ⚡ No timeout - let errors bubble up naturally
                     let log_stream = grpc_stream.map(move |result| match result {
                         Ok(response) => {
+                            metrics.log_message_sent(&response);
+                            lines.record(&response);
+                            if response.container_stopped {
+                                inventory.invalidate(&agent_id_for_stream);
+                            }
                             LogEntry::from_proto(response, agent_id_for_stream.clone())
                         }
-                        Err(e) => Err(ApiError::Internal(format!("Stream error: {}", e)).extend()),
+                        Err(e) => Err(ApiError::from_status(&agent_id_for_stream, "Stream error", e).extend()),
                     });
                     
                     streams.push(Box::pin(log_stream));
@@ -331,28 +555,192 @@ impl SubscriptionRoot {
         
         // Merge all streams using select_all (interleaves items as they arrive)
         // ⚡ FIX 2: No timeout on stream items - quiet containers are normal
-        // The brilliant ready_chunks(10) + flat_map provides rough timestamp ordering
-        // without buffering thousands of lines or creating head-of-line blocking
-        let merged_stream = futures::stream::select_all(streams)
-            .ready_chunks(10)
-            .flat_map(|mut chunk| {
+        // Sorting in small chunks provides rough timestamp ordering without
+        // buffering thousands of lines or creating head-of-line blocking.
+        // Clients trade latency for ordering with mergeChunkSize/mergeHoldMs.
+        let merged_stream = merge::sorted_chunks(
+            excludes::drop_excluded(futures::stream::select_all(streams), excludes).boxed(),
+            chunk_size,
+            merge_hold,
+            |a: &Result<LogEntry>, b: &Result<LogEntry>| match (a, b) {
                 // Sort by timestamp within each chunk
-                chunk.sort_by(|a, b| {
-                    match (a, b) {
-                        (Ok(entry_a), Ok(entry_b)) => entry_a.timestamp.cmp(&entry_b.timestamp),
-                        _ => std::cmp::Ordering::Equal,
-                    }
-                });
-                futures::stream::iter(chunk)
-            })
+                (Ok(entry_a), Ok(entry_b)) => entry_a.timestamp.cmp(&entry_b.timestamp),
+                _ => std::cmp::Ordering::Equal,
+            },
+        )
             // Keep guards alive for the lifetime of the stream.
             // When the stream is dropped, all guards are dropped and metrics updated.
             .map(move |item| {
                 let _guards = &guards;
                 item
-            });
-        
-        Ok(merged_stream)
+            })
+            .boxed();
+
+        // A merged stream's drop marker belongs to no single container
+        let pause_bound = state.config.log_defaults.pause_buffer_lines;
+        let merged_stream = with_filter_token(with_pause(merged_stream, pause, pause_bound, String::new(), String::new()), filter_token);
+        Ok(with_max_lifetime(merged_stream, SubscriptionKind::Log, &state.config.subscription_lifetimes))
+    }
+
+    /// Stream logs from every running container on an agent carrying a label
+    /// value (the containers of a pod or compose service), merged and tagged
+    /// with each entry's `containerId`
+    ///
+    /// The group is followed as containers come and go: an init container
+    /// that exits just stops contributing, and containers that start with
+    /// the label later are attached from their first line.
+    ///
+    /// # Example
+    /// ```graphql
+    /// subscription {
+    ///   logsByContainerGroup(
+    ///     agentId: "agent-local"
+    ///     groupLabel: "com.docker.compose.service"
+    ///     groupValue: "api"
+    ///   ) {
+    ///     containerId
+    ///     timestamp
+    ///     content
+    ///   }
+    /// }
+    /// ```
+    async fn logs_by_container_group(
+        &self,
+        ctx: &Context<'_>,
+        agent_id: String,
+        group_label: String,
+        group_value: String,
+        options: Option<LogStreamOptions>,
+    ) -> Result<impl Stream<Item = Result<LogEntry>>> {
+        let state = ctx.data::<AppState>()?;
+
+        state.metrics.subscription_started(&agent_id, SubscriptionKind::Log);
+        let metrics = state.metrics.clone();
+        let guard = Arc::new(SubscriptionGuard {
+            metrics: metrics.clone(),
+            agent_id: agent_id.clone(),
+            kind: SubscriptionKind::Log,
+        });
+        let fail = |e: ApiError| {
+            metrics.subscription_failed();
+            e.extend()
+        };
+
+        let agent_conn = state
+            .agent_pool
+            .get_agent(&agent_id)
+            .ok_or_else(|| fail(ApiError::AgentNotFound(agent_id.clone())))?;
+        if !agent_conn.is_healthy() {
+            return Err(fail(ApiError::AgentUnavailable(agent_id.clone())));
+        }
+
+        let opts = subscription_options(options, &state.config.log_defaults);
+        let (chunk_size, merge_hold) = merge_settings(&opts).map_err(fail)?;
+        let excludes = excludes::for_subscription(&state.default_excludes, &opts);
+        let pause = pause_registration(state, &opts).map_err(fail)?;
+        let filter_token = filter_token_registration(state, &opts, vec![agent_id.clone()]).map_err(fail)?;
+
+        let list_request = ContainerListRequest {
+            label_selectors: vec![LabelSelector { key: group_label.clone(), value: Some(group_value.clone()) }],
+            ..Default::default()
+        };
+        let mut client = agent_conn.client.lock().await.clone();
+        let members: Vec<String> = client
+            .list_containers(list_request.clone())
+            .await
+            .map_err(|e| fail(ApiError::from_agent(&agent_id, "Failed to list containers", e)))?
+            .containers
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        if members.is_empty() {
+            return Err(fail(ApiError::InvalidRequest(format!(
+                "No running containers with label {}={} on agent '{}'",
+                group_label, group_value, agent_id
+            ))));
+        }
+        container_stream_limit(members.len(), state.config.limits.max_container_streams).map_err(fail)?;
+
+        let request = opts.to_request(String::new());
+
+        // A member's stream error (its container removed, say) ends that
+        // member only; the group carries on
+        let member_stream = {
+            let (agent_id, metrics, guard) = (agent_id.clone(), metrics.clone(), guard.clone());
+            let inventory = state.inventory.clone();
+            move |container_id: String, stream| {
+                log_entries(stream, agent_id.clone(), &container_id, metrics.clone(), inventory.clone(), guard.clone())
+                    .take_while(move |item| {
+                        if let Err(e) = item {
+                            tracing::warn!(container_id = %container_id, "Group member's log stream failed: {:?}", e.message);
+                        }
+                        futures::future::ready(item.is_ok())
+                    })
+                    .boxed()
+            }
+        };
+
+        let mut first = Vec::new();
+        for container_id in members {
+            let request = LogStreamRequest { container_id: container_id.clone(), ..request.clone() };
+            match client.stream_logs(request).await {
+                Ok(stream) => first.push((container_id.clone(), member_stream(container_id, stream))),
+                Err(e) => tracing::warn!("Failed to open log stream for container '{}' on agent '{}': {}", container_id, agent_id, e),
+            }
+        }
+        if first.is_empty() {
+            return Err(fail(ApiError::Internal(format!(
+                "Failed to open any log streams for {}={} on agent '{}'",
+                group_label, group_value, agent_id
+            ))));
+        }
+
+        // Members joining later are read from their first line
+        let list = {
+            let agent_conn = agent_conn.clone();
+            move || {
+                let (agent_conn, list_request) = (agent_conn.clone(), list_request.clone());
+                async move {
+                    let mut client = agent_conn.client.lock().await.clone();
+                    let listed = client.list_containers(list_request).await.ok()?;
+                    Some(listed.containers.into_iter().map(|c| c.id).collect())
+                }
+                .boxed()
+            }
+        };
+        let open = move |container_id: String| {
+            let agent_conn = agent_conn.clone();
+            let member_stream = member_stream.clone();
+            let request = LogStreamRequest {
+                container_id: container_id.clone(),
+                since: None,
+                since_nanos: None,
+                tail_lines: None,
+                ..request.clone()
+            };
+            async move {
+                let mut client = agent_conn.client.lock().await.clone();
+                let stream = client.stream_logs(request).await.ok()?;
+                Some(member_stream(container_id, stream))
+            }
+            .boxed()
+        };
+        let group = group::follow_group(first, list, open, group::GROUP_POLL_INTERVAL).boxed();
+
+        let merged_stream = merge::sorted_chunks(
+            excludes::drop_excluded(group, excludes).boxed(),
+            chunk_size,
+            merge_hold,
+            |a: &Result<LogEntry>, b: &Result<LogEntry>| match (a, b) {
+                (Ok(entry_a), Ok(entry_b)) => entry_a.timestamp.cmp(&entry_b.timestamp),
+                _ => std::cmp::Ordering::Equal,
+            },
+        )
+            .boxed();
+
+        let pause_bound = state.config.log_defaults.pause_buffer_lines;
+        let merged_stream = with_filter_token(with_pause(merged_stream, pause, pause_bound, String::new(), agent_id), filter_token);
+        Ok(with_max_lifetime(merged_stream, SubscriptionKind::Log, &state.config.subscription_lifetimes))
     }
 
     /// Stream real-time health status from an agent
@@ -383,10 +771,11 @@ impl SubscriptionRoot {
         let state = ctx.data::<AppState>()?;
         
         // Track subscription metrics with RAII guard
-        state.metrics.subscription_started(&agent_id);
+        state.metrics.subscription_started(&agent_id, SubscriptionKind::Health);
         let guard = Arc::new(SubscriptionGuard {
             metrics: state.metrics.clone(),
             agent_id: agent_id.clone(),
+            kind: SubscriptionKind::Health,
         });
         
         // Get agent connection
@@ -415,16 +804,18 @@ impl SubscriptionRoot {
             .await
             .map_err(|e| {
                 state.metrics.subscription_failed();
-                ApiError::Internal(format!("Failed to open health stream: {}", e)).extend()
+                ApiError::from_agent(&agent_id, "Failed to open health stream", e).extend()
             })?;
         
         // Convert gRPC stream to GraphQL stream.
         // The guard is moved into the closure to track metrics on disconnect.
         let agent_id_clone = agent_id.clone();
+        let metrics = state.metrics.clone();
         let health_stream = grpc_stream.map(move |result| {
             let _guard = &guard;
             match result {
             Ok(response) => {
+                metrics.message_sent(grpc_message_size(&response));
                 // Convert proto health status to GraphQL AgentStatus
                 let status = match response.status {
                     1 => AgentStatus::Healthy,
@@ -448,11 +839,11 @@ impl SubscriptionRoot {
                     metadata,
                 })
             }
-            Err(e) => Err(ApiError::Internal(format!("Health stream error: {}", e)).extend()),
+            Err(e) => Err(ApiError::from_status(&agent_id_clone, "Health stream error", e).extend()),
             }
         });
         
-        Ok(health_stream)
+        Ok(with_max_lifetime(health_stream, SubscriptionKind::Health, &state.config.subscription_lifetimes))
     }
 
     /// Stream real-time resource statistics for a container
@@ -460,6 +851,9 @@ impl SubscriptionRoot {
     /// # Arguments
     /// * `container_id` - The container ID to monitor
     /// * `agent_id` - The agent ID where the container is running
+    /// * `priority` - QoS hint for agent-side admission (default: NORMAL)
+    /// * `interval_ms` - Emit at most one sample per interval, at least 1000
+    ///   (default: every Docker sample, about once a second)
     /// 
     /// # Example
     /// ```graphql
@@ -487,14 +881,17 @@ impl SubscriptionRoot {
         ctx: &Context<'_>,
         container_id: String,
         agent_id: String,
+        #[graphql(default)] priority: StreamPriority,
+        interval_ms: Option<u32>,
     ) -> Result<impl Stream<Item = Result<ContainerStats>>> {
         let state = ctx.data::<AppState>()?;
         
         // Track subscription metrics with RAII guard
-        state.metrics.subscription_started(&agent_id);
+        state.metrics.subscription_started(&agent_id, SubscriptionKind::Stats);
         let guard = Arc::new(SubscriptionGuard {
             metrics: state.metrics.clone(),
             agent_id: agent_id.clone(),
+            kind: SubscriptionKind::Stats,
         });
         
         // Get agent connection
@@ -509,10 +906,7 @@ impl SubscriptionRoot {
         // Check agent health
         if !agent_conn.is_healthy() {
             state.metrics.subscription_failed();
-            return Err(ApiError::AgentUnavailable(format!(
-                "Agent '{}' is not healthy. Try again later or check agent status.",
-                agent_id
-            )).extend());
+            return Err(ApiError::AgentUnavailable(agent_id.clone()).extend());
         }
         
         // Clone client to release lock immediately
@@ -525,6 +919,11 @@ impl SubscriptionRoot {
         let request = ContainerStatsRequest {
             container_id: container_id.clone(),
             stream: true, // Enable streaming mode
+            priority: {
+                let proto_priority: crate::agent::client::StreamPriority = priority.into();
+                proto_priority as i32
+            },
+            interval_ms: interval_ms.unwrap_or(0),
         };
         
         // Open stats stream
@@ -533,19 +932,252 @@ impl SubscriptionRoot {
             .await
             .map_err(|e| {
                 state.metrics.subscription_failed();
-                ApiError::Internal(format!("Failed to open stats stream: {}", e)).extend()
+                ApiError::from_container_agent(&agent_id, &container_id, "Failed to open stats stream", e).extend()
             })?;
         
         // Convert gRPC stream to GraphQL stream using shared helper.
         // The guard is moved into the closure to track metrics on disconnect.
+        let metrics = state.metrics.clone();
         let stats_stream = grpc_stream.map(move |result| {
             let _guard = &guard;
             match result {
-                Ok(response) => Ok(ContainerStats::from_proto(response)),
-                Err(e) => Err(ApiError::Internal(format!("Stats stream error: {}", e)).extend()),
+                Ok(response) => {
+                    metrics.message_sent(grpc_message_size(&response));
+                    Ok(ContainerStats::from_proto(response, agent_id.clone()))
+                }
+                Err(e) => Err(ApiError::from_status(&agent_id, "Stats stream error", e).extend()),
             }
         });
         
-        Ok(stats_stream)
+        Ok(with_max_lifetime(stats_stream, SubscriptionKind::Stats, &state.config.subscription_lifetimes))
+    }
+
+    /// Anomalies in a container's CPU and memory, judged against a rolling
+    /// baseline (exponentially weighted mean and deviation) learned from its
+    /// own stats rather than fixed thresholds. Reports samples more than
+    /// `sigma` (default 3) deviations away, after `warmupSamples` (default 30)
+    /// samples have been learned.
+    async fn stats_anomalies(
+        &self,
+        ctx: &Context<'_>,
+        container_id: String,
+        agent_id: String,
+        sigma: Option<f64>,
+        warmup_samples: Option<u32>,
+        interval_ms: Option<u32>,
+    ) -> Result<impl Stream<Item = Result<StatsAnomaly>>> {
+        let state = ctx.data::<AppState>()?;
+        let sigma = sigma.unwrap_or(anomaly::DEFAULT_ANOMALY_SIGMA);
+        if !(sigma.is_finite() && sigma > 0.0) {
+            return Err(ApiError::InvalidRequest(format!("sigma must be greater than 0, got {}", sigma)).extend());
+        }
+        let mut detector = anomaly::AnomalyDetector::new(sigma, warmup_samples.unwrap_or(anomaly::DEFAULT_WARMUP_SAMPLES));
+
+        state.metrics.subscription_started(&agent_id, SubscriptionKind::Stats);
+        let guard = Arc::new(SubscriptionGuard {
+            metrics: state.metrics.clone(),
+            agent_id: agent_id.clone(),
+            kind: SubscriptionKind::Stats,
+        });
+
+        let agent_conn = state
+            .agent_pool
+            .get_agent(&agent_id)
+            .ok_or_else(|| {
+                state.metrics.subscription_failed();
+                ApiError::AgentNotFound(agent_id.clone()).extend()
+            })?;
+        if !agent_conn.is_healthy() {
+            state.metrics.subscription_failed();
+            return Err(ApiError::AgentUnavailable(agent_id.clone()).extend());
+        }
+
+        let mut client = agent_conn.client.lock().await.clone();
+        let request = ContainerStatsRequest {
+            container_id: container_id.clone(),
+            stream: true,
+            priority: crate::agent::client::StreamPriority::Normal as i32,
+            interval_ms: interval_ms.unwrap_or(0),
+        };
+        let grpc_stream = client
+            .stream_container_stats(request)
+            .await
+            .map_err(|e| {
+                state.metrics.subscription_failed();
+                ApiError::from_container_agent(&agent_id, &container_id, "Failed to open stats stream", e).extend()
+            })?;
+
+        let metrics = state.metrics.clone();
+        let anomalies = grpc_stream.flat_map(move |result| {
+            let _guard = &guard;
+            let events = match result {
+                Ok(response) => {
+                    metrics.message_sent(grpc_message_size(&response));
+                    detector
+                        .check(&ContainerStats::from_proto(response, agent_id.clone()))
+                        .into_iter()
+                        .map(Ok)
+                        .collect()
+                }
+                Err(e) => vec![Err(ApiError::from_status(&agent_id, "Stats stream error", e).extend())],
+            };
+            futures::stream::iter(events)
+        });
+        Ok(with_max_lifetime(anomalies, SubscriptionKind::Stats, &state.config.subscription_lifetimes))
+    }
+
+    /// Live log throughput of a container: line and byte counts per bucket
+    /// of `bucket_secs` (1-3600), sent as each bucket closes. The agent counts
+    /// the lines without sending them. Buckets are aligned to the wall clock
+    /// (multiples of `bucket_secs` since the epoch), so the first one is partial.
+    async fn log_rate_histogram(
+        &self,
+        ctx: &Context<'_>,
+        container_id: String,
+        agent_id: String,
+        bucket_secs: u32,
+    ) -> Result<impl Stream<Item = Result<LogRateBucket>>> {
+        let state = ctx.data::<AppState>()?;
+
+        state.metrics.subscription_started(&agent_id, SubscriptionKind::Log);
+        let guard = Arc::new(SubscriptionGuard {
+            metrics: state.metrics.clone(),
+            agent_id: agent_id.clone(),
+            kind: SubscriptionKind::Log,
+        });
+
+        let agent_conn = state
+            .agent_pool
+            .get_agent(&agent_id)
+            .ok_or_else(|| {
+                state.metrics.subscription_failed();
+                ApiError::AgentNotFound(agent_id.clone()).extend()
+            })?;
+        if !agent_conn.is_healthy() {
+            state.metrics.subscription_failed();
+            return Err(ApiError::AgentUnavailable(agent_id.clone()).extend());
+        }
+
+        let mut client = agent_conn.client.lock().await.clone();
+        let grpc_stream = client
+            .stream_log_rate(LogRateRequest { container_id: container_id.clone(), bucket_secs })
+            .await
+            .map_err(|e| {
+                state.metrics.subscription_failed();
+                ApiError::from_container_agent(&agent_id, &container_id, "Failed to open log rate stream", e).extend()
+            })?;
+
+        let metrics = state.metrics.clone();
+        let buckets = grpc_stream.map(move |result| {
+            let _guard = &guard;
+            match result {
+                Ok(bucket) => {
+                    metrics.log_message_sent(&bucket);
+                    Ok(LogRateBucket::from_proto(bucket))
+                }
+                Err(e) => Err(ApiError::from_status(&agent_id, "Log rate stream error", e).extend()),
+            }
+        });
+        Ok(with_max_lifetime(buckets, SubscriptionKind::Log, &state.config.subscription_lifetimes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(metrics: &Arc<SubscriptionMetrics>, agent_id: &str, kind: SubscriptionKind) -> SubscriptionGuard {
+        metrics.subscription_started(agent_id, kind);
+        SubscriptionGuard {
+            metrics: Arc::clone(metrics),
+            agent_id: agent_id.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_configured_defaults_apply_without_options() {
+        let defaults = LogDefaultsConfig { tail: 500, timestamps: false, ..Default::default() };
+        let opts = subscription_options(None, &defaults);
+        assert_eq!(opts.tail, Some(500));
+        assert!(!opts.timestamps);
+        assert!(opts.follow);
+
+        // Options from the client win over the configured defaults
+        let requested = LogStreamOptions {
+            tail: Some(10),
+            timestamps: true,
+            ..subscription_options(None, &defaults)
+        };
+        let opts = subscription_options(Some(requested), &defaults);
+        assert_eq!(opts.tail, Some(10));
+        assert!(opts.timestamps);
+    }
+
+    #[test]
+    fn test_merge_settings() {
+        let defaults = subscription_options(None, &LogDefaultsConfig::default());
+        assert_eq!(merge_settings(&defaults).unwrap(), (10, Duration::ZERO));
+
+        let opts = LogStreamOptions { merge_chunk_size: Some(200), merge_hold_ms: Some(250), ..defaults.clone() };
+        assert_eq!(merge_settings(&opts).unwrap(), (200, Duration::from_millis(250)));
+
+        let opts = LogStreamOptions { merge_chunk_size: Some(0), ..defaults.clone() };
+        assert_eq!(merge_settings(&opts).unwrap_err().code(), "INVALID_REQUEST");
+        let opts = LogStreamOptions { merge_hold_ms: Some(60_000), ..defaults };
+        assert!(merge_settings(&opts).is_err());
+    }
+
+    #[test]
+    fn test_container_stop_behavior() {
+        let defaults = subscription_options(None, &LogDefaultsConfig::default());
+        assert!(!waits_for_restart(&defaults).unwrap());
+        let by_name = LogStreamOptions { follow_by_name: true, ..defaults.clone() };
+        assert!(waits_for_restart(&by_name).unwrap());
+
+        let complete = LogStreamOptions { on_container_stop: Some(ContainerStopBehavior::Complete), ..by_name };
+        assert!(!waits_for_restart(&complete).unwrap());
+        let wait = LogStreamOptions { on_container_stop: Some(ContainerStopBehavior::WaitForRestart), ..defaults };
+        assert_eq!(waits_for_restart(&wait).unwrap_err().code(), "INVALID_REQUEST");
+    }
+
+    #[test]
+    fn test_container_stream_limit() {
+        let limits = crate::config::LimitsConfig::default();
+        assert!(container_stream_limit(20, limits.max_container_streams).is_ok());
+        let err = container_stream_limit(21, limits.max_container_streams).unwrap_err();
+        assert_eq!(err.code(), "INVALID_REQUEST");
+
+        // Raised deliberately for a large deployment
+        let raised = crate::config::LimitsConfig { max_container_streams: 50 };
+        assert!(container_stream_limit(50, raised.max_container_streams).is_ok());
+    }
+
+    #[test]
+    fn test_guards_track_subscription_kinds() {
+        let metrics = Arc::new(SubscriptionMetrics::new());
+        let logs = vec![
+            open(&metrics, "agent-1", SubscriptionKind::Log),
+            open(&metrics, "agent-2", SubscriptionKind::Log),
+        ];
+        let stats = open(&metrics, "agent-1", SubscriptionKind::Stats);
+        let health = open(&metrics, "agent-1", SubscriptionKind::Health);
+
+        let by_kind = metrics.subscriptions_by_kind();
+        assert_eq!(by_kind["log"], 2);
+        assert_eq!(by_kind["stats"], 1);
+        assert_eq!(by_kind["health"], 1);
+        assert_eq!(metrics.active_count(), 4);
+
+        // Each guard releases its own bucket
+        drop(stats);
+        let by_kind = metrics.subscriptions_by_kind();
+        assert_eq!((by_kind["log"], by_kind["stats"], by_kind["health"]), (2, 0, 1));
+
+        drop(logs);
+        drop(health);
+        assert!(metrics.subscriptions_by_kind().values().all(|&n| n == 0));
+        assert_eq!(metrics.active_count(), 0);
+        assert!(metrics.subscriptions_by_agent().is_empty());
     }
 }
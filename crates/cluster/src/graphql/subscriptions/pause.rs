@@ -0,0 +1,227 @@
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Lines held for a paused subscription when it doesn't say
+pub const DEFAULT_PAUSE_BUFFER: usize = 1000;
+
+/// Pause switches for log subscriptions that set a `pauseToken`, driven by
+/// the `pauseLogStream` / `resumeLogStream` mutations
+#[derive(Default)]
+pub struct PauseControls {
+    switches: DashMap<String, watch::Sender<bool>>,
+}
+
+/// Keeps a token registered while its subscription is alive
+pub struct PauseRegistration {
+    controls: Arc<PauseControls>,
+    token: String,
+}
+
+impl Drop for PauseRegistration {
+    fn drop(&mut self) {
+        self.controls.switches.remove(&self.token);
+    }
+}
+
+impl PauseControls {
+    /// Claim `token` for a new subscription; `None` if it is already in use
+    pub fn register(self: &Arc<Self>, token: &str) -> Option<(PauseRegistration, watch::Receiver<bool>)> {
+        match self.switches.entry(token.to_string()) {
+            Entry::Occupied(_) => None,
+            Entry::Vacant(slot) => {
+                let (tx, rx) = watch::channel(false);
+                slot.insert(tx);
+                let registration = PauseRegistration { controls: Arc::clone(self), token: token.to_string() };
+                Some((registration, rx))
+            }
+        }
+    }
+
+    /// Pause or resume the subscription holding `token`. False if none does.
+    pub fn set_paused(&self, token: &str, paused: bool) -> bool {
+        match self.switches.get(token) {
+            Some(switch) => {
+                switch.send_replace(paused);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+struct Pausable<S, T> {
+    inner: Option<S>,
+    control: Option<watch::Receiver<bool>>,
+    held: VecDeque<T>,
+    dropped: u64,
+    /// Ready to send: the held lines, then the drop marker
+    ready: VecDeque<T>,
+}
+
+/// Hold back a stream while `control` says paused, still reading from
+/// `inner` so the agent stream stays open and in position.
+///
+/// Up to `bound` items are held; later ones are counted and dropped. On
+/// resume the held items go out first, then `marker(dropped)` if anything
+/// was dropped, then the live stream. If the stream ends while paused, what
+/// was held is sent before it ends.
+pub fn pausable<S, T, M>(inner: S, control: watch::Receiver<bool>, bound: usize, marker: M) -> impl Stream<Item = T>
+where
+    S: Stream<Item = T> + Unpin,
+    M: Fn(u64) -> T,
+{
+    let state = Pausable {
+        inner: Some(inner),
+        control: Some(control),
+        held: VecDeque::new(),
+        dropped: 0,
+        ready: VecDeque::new(),
+    };
+    stream::unfold((state, marker), move |(mut state, marker)| async move {
+        loop {
+            if let Some(item) = state.ready.pop_front() {
+                return Some((item, (state, marker)));
+            }
+            if state.inner.is_none() {
+                // Ended: release whatever was held, then finish
+                if state.held.is_empty() && state.dropped == 0 {
+                    return None;
+                }
+                state.release(&marker);
+                continue;
+            }
+            if !state.paused() && (!state.held.is_empty() || state.dropped > 0) {
+                state.release(&marker);
+                continue;
+            }
+
+            match state.next_event().await {
+                // Judged by the switch as it is now: a resume may have
+                // arrived together with this item
+                Event::Item(Some(item)) if state.paused() => {
+                    if state.held.len() < bound {
+                        state.held.push_back(item);
+                    } else {
+                        state.dropped += 1;
+                    }
+                }
+                Event::Item(Some(item)) => {
+                    state.release(&marker);
+                    state.ready.push_back(item);
+                }
+                Event::Item(None) => state.inner = None,
+                // Switch gone: never paused again
+                Event::Switch { open: false } => state.control = None,
+                Event::Switch { open: true } => {}
+            }
+        }
+    })
+}
+
+enum Event<T> {
+    Item(Option<T>),
+    Switch { open: bool },
+}
+
+impl<S: Stream<Item = T> + Unpin, T> Pausable<S, T> {
+    /// The next item from the stream or change of the pause switch
+    async fn next_event(&mut self) -> Event<T> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Event::Item(None);
+        };
+        let changed = async {
+            match self.control.as_mut() {
+                Some(control) => control.changed().await.is_ok(),
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            item = inner.next() => Event::Item(item),
+            open = changed => Event::Switch { open },
+        }
+    }
+}
+
+impl<S, T> Pausable<S, T> {
+    fn paused(&self) -> bool {
+        self.control.as_ref().is_some_and(|c| *c.borrow())
+    }
+
+    fn release<M: Fn(u64) -> T>(&mut self, marker: &M) {
+        self.ready.extend(self.held.drain(..));
+        if self.dropped > 0 {
+            self.ready.push_back(marker(self.dropped));
+            self.dropped = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq)]
+    enum Item {
+        Line(u32),
+        Dropped(u64),
+    }
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    async fn test_pause_buffers_up_to_bound_then_resumes() {
+        let controls = Arc::new(PauseControls::default());
+        let (registration, control) = controls.register("tab-1").unwrap();
+        assert!(controls.register("tab-1").is_none(), "token already in use");
+
+        let (tx, rx) = mpsc::unbounded();
+        let mut stream = Box::pin(pausable(rx, control, 3, Item::Dropped));
+
+        tx.unbounded_send(Item::Line(1)).unwrap();
+        assert_eq!(stream.next().await, Some(Item::Line(1)));
+
+        // Paused: lines keep being read, the first three are held
+        assert!(controls.set_paused("tab-1", true));
+        for n in 2..=6 {
+            tx.unbounded_send(Item::Line(n)).unwrap();
+        }
+        let nothing = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+        assert!(nothing.is_err(), "nothing is emitted while paused");
+
+        // Resumed: held lines, the drop count, then live
+        assert!(controls.set_paused("tab-1", false));
+        settle().await;
+        tx.unbounded_send(Item::Line(7)).unwrap();
+        let expected = [Item::Line(2), Item::Line(3), Item::Line(4), Item::Dropped(2), Item::Line(7)];
+        for want in expected {
+            assert_eq!(stream.next().await, Some(want));
+        }
+
+        // The token is released with the subscription
+        drop(registration);
+        assert!(!controls.set_paused("tab-1", true));
+    }
+
+    #[tokio::test]
+    async fn test_held_lines_flushed_when_stream_ends_paused() {
+        let controls = Arc::new(PauseControls::default());
+        let (_registration, control) = controls.register("tab-2").unwrap();
+        let (tx, rx) = mpsc::unbounded();
+        let mut stream = Box::pin(pausable(rx, control, 10, Item::Dropped));
+
+        controls.set_paused("tab-2", true);
+        tx.unbounded_send(Item::Line(1)).unwrap();
+        drop(tx);
+
+        assert_eq!(stream.next().await, Some(Item::Line(1)));
+        assert_eq!(stream.next().await, None);
+    }
+}
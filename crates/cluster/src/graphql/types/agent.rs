@@ -31,6 +31,7 @@ pub struct Label {
 
 /// Helper to build an AgentView from agent info (used by schema.rs)
 pub fn agent_view_from_connection(conn: &Arc<crate::agent::AgentConnection>, last_seen: chrono::DateTime<chrono::Utc>) -> AgentView {
+    let clock = conn.clock();
     AgentView {
         id: conn.info.id.clone(),
         name: conn.info.name.clone(),
@@ -42,6 +43,9 @@ pub fn agent_view_from_connection(conn: &Arc<crate::agent::AgentConnection>, las
             value: v.clone(),
         }).collect(),
         version: conn.info.version.clone(),
+        clock_offset_ms: clock.as_ref().map(|c| c.offset_ms),
+        clock_skewed: clock.as_ref().is_some_and(|c| c.skewed),
+        timezone: clock.and_then(|c| c.timezone),
     }
 }
 
@@ -55,6 +59,13 @@ pub struct AgentView {
     pub last_seen: chrono::DateTime<chrono::Utc>,
     pub labels: Vec<Label>,
     pub version: Option<String>,
+    /// Estimated agent clock minus cluster clock, in milliseconds
+    /// (null until a health check reports the agent's clock)
+    pub clock_offset_ms: Option<i64>,
+    /// True when the clock offset exceeds `clock_skew_threshold_ms`
+    pub clock_skewed: bool,
+    /// Agent timezone (TZ name or UTC offset)
+    pub timezone: Option<String>,
 }
 
 /// Agent health summary
@@ -92,3 +103,149 @@ pub struct MetadataEntry {
     pub key: String,
     pub value: String,
 }
+
+/// Agent metadata suffix and the name used for the format in `LogEntry.format`
+const DETECTED_FORMATS: [(&str, &str); 6] = [
+    ("json", "JSON"),
+    ("logfmt", "Logfmt"),
+    ("syslog", "Syslog"),
+    ("httplog", "HttpLog"),
+    ("plain", "PlainText"),
+    ("unknown", "Unknown"),
+];
+
+/// Containers detected as one log format
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject)]
+pub struct FormatCount {
+    /// Format name as in `LogEntry.format`
+    pub format: String,
+    pub containers: i32,
+}
+
+/// Log formats the agent's parser detected, one count per container it has
+/// streamed logs for
+#[derive(Debug, Clone, SimpleObject)]
+pub struct AgentFormatDistribution {
+    pub agent_id: String,
+    pub formats: Vec<FormatCount>,
+    pub total: i32,
+}
+
+/// Detected log formats cluster-wide and per agent
+#[derive(Debug, Clone, SimpleObject)]
+pub struct FormatDistribution {
+    /// Sum over the agents that answered
+    pub formats: Vec<FormatCount>,
+    pub total: i32,
+    pub agents: Vec<AgentFormatDistribution>,
+}
+
+impl AgentFormatDistribution {
+    /// Read the `format_containers_*` counts from an agent's health metadata.
+    /// Agents that don't report them count as having no detections.
+    pub fn from_metadata(agent_id: String, metadata: &std::collections::HashMap<String, String>) -> Self {
+        let formats: Vec<FormatCount> = DETECTED_FORMATS
+            .iter()
+            .map(|(key, name)| FormatCount {
+                format: name.to_string(),
+                containers: metadata
+                    .get(&format!("format_containers_{}", key))
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0),
+            })
+            .collect();
+        let total = formats.iter().map(|f| f.containers).sum();
+        Self { agent_id, formats, total }
+    }
+}
+
+impl FormatDistribution {
+    pub fn from_agents(agents: Vec<AgentFormatDistribution>) -> Self {
+        let formats: Vec<FormatCount> = DETECTED_FORMATS
+            .iter()
+            .enumerate()
+            .map(|(i, (_, name))| FormatCount {
+                format: name.to_string(),
+                containers: agents.iter().map(|a| a.formats[i].containers).sum(),
+            })
+            .collect();
+        let total = formats.iter().map(|f| f.containers).sum();
+        Self { formats, total, agents }
+    }
+}
+
+/// An agent's per-container log format cache
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ParserCacheStats {
+    pub agent_id: String,
+    /// Containers with a cached format
+    pub entries: i32,
+    /// Cached containers whose parsing was turned off after failures
+    pub disabled: i32,
+    /// Cached containers whose format is trusted and no longer re-checked
+    pub locked: i32,
+    /// Format lookups answered from the cache
+    pub hits: u64,
+    /// Format lookups that had to detect
+    pub misses: u64,
+    /// hits / (hits + misses); 0 before any lookup
+    pub hit_rate: f64,
+}
+
+impl ParserCacheStats {
+    pub fn from_proto(agent_id: String, response: crate::agent::client::ParserCacheStatsResponse) -> Self {
+        Self {
+            agent_id,
+            entries: response.entries as i32,
+            disabled: response.disabled as i32,
+            locked: response.locked as i32,
+            hits: response.hits,
+            misses: response.misses,
+            hit_rate: response.hit_rate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn metadata(counts: &[(&str, &str)]) -> HashMap<String, String> {
+        counts
+            .iter()
+            .map(|(format, n)| (format!("format_containers_{}", format), n.to_string()))
+            .chain([("total_parsed".to_string(), "1200".to_string())])
+            .collect()
+    }
+
+    fn count(formats: &[FormatCount], name: &str) -> i32 {
+        formats.iter().find(|f| f.format == name).unwrap().containers
+    }
+
+    #[test]
+    fn test_format_distribution_across_agents() {
+        let edge = AgentFormatDistribution::from_metadata(
+            "edge".to_string(),
+            &metadata(&[("json", "1"), ("httplog", "3"), ("plain", "2")]),
+        );
+        let apps = AgentFormatDistribution::from_metadata(
+            "apps".to_string(),
+            &metadata(&[("json", "5"), ("logfmt", "2"), ("plain", "1")]),
+        );
+        // An older agent that doesn't report detections
+        let legacy = AgentFormatDistribution::from_metadata("legacy".to_string(), &HashMap::new());
+
+        assert_eq!(edge.total, 6);
+        assert_eq!(count(&apps.formats, "Logfmt"), 2);
+        assert_eq!(legacy.total, 0);
+
+        let cluster = FormatDistribution::from_agents(vec![edge, apps, legacy]);
+        assert_eq!(count(&cluster.formats, "JSON"), 6);
+        assert_eq!(count(&cluster.formats, "PlainText"), 3);
+        assert_eq!(count(&cluster.formats, "HttpLog"), 3);
+        assert_eq!(count(&cluster.formats, "Syslog"), 0);
+        assert_eq!(cluster.total, 14);
+        assert_eq!(cluster.agents.len(), 3);
+    }
+}
@@ -37,6 +37,42 @@ impl From<&str> for ContainerState {
     }
 }
 
+/// Docker restart policy name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum RestartPolicyName {
+    /// Never restart (Docker's default)
+    No,
+    /// Restart only on a non-zero exit, up to the max retry count
+    OnFailure,
+    /// Always restart
+    Always,
+    /// Always restart unless explicitly stopped
+    UnlessStopped,
+}
+
+impl RestartPolicyName {
+    /// Map a policy name as reported by Docker. An empty name means the
+    /// policy was never set, which Docker treats as `no`.
+    pub fn from_docker(name: &str) -> Option<Self> {
+        match name {
+            "" | "no" => Some(Self::No),
+            "on-failure" => Some(Self::OnFailure),
+            "always" => Some(Self::Always),
+            "unless-stopped" => Some(Self::UnlessStopped),
+            _ => None,
+        }
+    }
+
+    /// Policy of an inspected container (None if the agent sent no details)
+    pub fn from_inspect(inspect: &crate::agent::client::ContainerInspectResponse) -> Option<Self> {
+        let details = inspect.details.as_ref()?;
+        match details.restart_policy.as_ref() {
+            Some(rp) => Self::from_docker(&rp.name),
+            None => Some(Self::No),
+        }
+    }
+}
+
 /// Port mapping information
 #[derive(Debug, Clone, SimpleObject)]
 pub struct PortMapping {
@@ -82,6 +118,18 @@ pub struct Container {
     
     /// Log driver (if available)
     pub log_driver: Option<String>,
+
+    /// Log driver options (e.g. max-size, max-file)
+    pub log_options: std::collections::HashMap<String, String>,
+
+    /// json-file log without a max-size
+    pub log_rotation_unbounded: bool,
+
+    /// Bytes used by the json-file log and its rotated segments
+    pub log_size_bytes: Option<u64>,
+
+    /// What the container runs (from inspect)
+    pub command: Option<ContainerCommandGql>,
     
     /// Port mappings
     pub ports: Vec<PortMapping>,
@@ -90,6 +138,50 @@ pub struct Container {
     pub state_info: Option<ContainerStateInfoGql>,
 }
 
+impl Container {
+    /// Build a container from agent-reported info
+    pub fn from_proto(info: crate::agent::client::ContainerInfo, agent_id: String) -> Self {
+        let ts = chrono::DateTime::from_timestamp(info.created_at, 0);
+        if ts.is_none() {
+            tracing::warn!(
+                container_id = %info.id,
+                created_at = info.created_at,
+                "Invalid created_at timestamp, substituting current time"
+            );
+        }
+
+        Self {
+            id: info.id,
+            agent_id,
+            name: info.name,
+            image: info.image,
+            state: ContainerState::from(info.state.as_str()),
+            status: info.status,
+            labels_map: info.labels,
+            created_at: ts.unwrap_or_else(chrono::Utc::now),
+            log_driver: info.log_driver,
+            log_options: info.log_options,
+            log_rotation_unbounded: info.log_rotation_unbounded,
+            log_size_bytes: info.log_size_bytes,
+            command: info.command.map(ContainerCommandGql::from),
+            ports: info.ports.into_iter().map(|p| PortMapping {
+                container_port: p.container_port as i32,
+                protocol: p.protocol,
+                host_ip: p.host_ip,
+                host_port: p.host_port.map(|p| p as i32),
+            }).collect(),
+            state_info: info.state_info.map(|si| ContainerStateInfoGql {
+                oom_killed: si.oom_killed,
+                pid: si.pid,
+                exit_code: si.exit_code,
+                started_at: si.started_at,
+                finished_at: si.finished_at,
+                restart_count: si.restart_count,
+            }),
+        }
+    }
+}
+
 #[Object]
 impl Container {
     /// Container ID (64-char hash)
@@ -132,6 +224,37 @@ impl Container {
         self.log_driver.as_deref()
     }
 
+    /// Log driver options such as max-size and max-file (from inspect)
+    async fn log_options(&self) -> Vec<Label> {
+        let mut options: Vec<Label> = self
+            .log_options
+            .iter()
+            .map(|(k, v)| Label {
+                key: k.clone(),
+                value: v.clone(),
+            })
+            .collect();
+        options.sort_by(|a, b| a.key.cmp(&b.key));
+        options
+    }
+
+    /// True when the container logs to json-file with no max-size, so its
+    /// log grows until the disk fills
+    async fn log_rotation_unbounded(&self) -> bool {
+        self.log_rotation_unbounded
+    }
+
+    /// Bytes on disk used by the json-file log and its rotated segments
+    /// (None for other log drivers or when the agent can't read the file)
+    async fn log_size_bytes(&self) -> Option<u64> {
+        self.log_size_bytes
+    }
+
+    /// Entrypoint, arguments and working directory
+    async fn command(&self) -> Option<&ContainerCommandGql> {
+        self.command.as_ref()
+    }
+
     /// Container labels as key-value pairs
     async fn labels(&self) -> Vec<Label> {
         self.labels_map
@@ -153,6 +276,16 @@ impl Container {
         self.state_info.as_ref()
     }
 
+    /// Image provenance from OCI annotation labels (None if the image has none)
+    async fn image_metadata(&self) -> Option<ImageMetadata> {
+        ImageMetadata::from_labels(&self.labels_map)
+    }
+
+    /// Restart policy (from inspect; shares the per-request details cache)
+    async fn restart_policy(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<RestartPolicyGql>> {
+        Ok(self.details(ctx).await?.and_then(|d| d.restart_policy))
+    }
+
     /// Get detailed information about this container.
     /// Results are cached per-request to avoid N+1 gRPC calls when multiple
     /// containers in the same query request details.
@@ -183,7 +316,8 @@ impl Container {
             .inspect_container(ContainerInspectRequest {
                 container_id: self.id.clone(),
             })
-            .await?;
+            .await
+            .map_err(|e| ApiError::from_container_agent(&self.agent_id, &self.id, "Failed to inspect container", e).extend())?;
         
         // Convert to ContainerDetails
         let result = if let Some(details) = response.details {
@@ -209,6 +343,15 @@ impl Container {
                     memory_limit_bytes: l.memory_limit_bytes,
                     cpu_limit: l.cpu_limit,
                     pids_limit: l.pids_limit,
+                    oom_score_adj: l.oom_score_adj,
+                    oom_kill_disable: l.oom_kill_disable,
+                    cpu_quota: l.cpu_quota,
+                    cpu_period: l.cpu_period,
+                    ulimits: l.ulimits.into_iter().map(|u| Ulimit {
+                        name: u.name,
+                        soft: u.soft,
+                        hard: u.hard,
+                    }).collect(),
                 }),
                 entrypoint: details.entrypoint,
                 hostname: if details.hostname.is_empty() { None } else { Some(details.hostname) },
@@ -242,6 +385,76 @@ impl Container {
     }
 }
 
+/// Image provenance from the standard OCI annotation labels
+/// (`org.opencontainers.image.*`), falling back to the older
+/// `org.label-schema.*` labels where OCI ones are missing.
+#[derive(Debug, Clone, Default, PartialEq, SimpleObject)]
+pub struct ImageMetadata {
+    /// Repository the image was built from
+    pub source: Option<String>,
+
+    /// Source control revision (e.g. git commit SHA)
+    pub revision: Option<String>,
+
+    /// Build date (RFC 3339)
+    pub created: Option<String>,
+
+    /// Image version
+    pub version: Option<String>,
+
+    /// Human-readable title
+    pub title: Option<String>,
+
+    /// Description of the software in the image
+    pub description: Option<String>,
+
+    /// Project homepage
+    pub url: Option<String>,
+
+    /// Documentation URL
+    pub documentation: Option<String>,
+
+    /// Distributing organization
+    pub vendor: Option<String>,
+
+    /// SPDX license expression
+    pub licenses: Option<String>,
+
+    /// Base image name
+    pub base_name: Option<String>,
+
+    /// Base image digest
+    pub base_digest: Option<String>,
+}
+
+impl ImageMetadata {
+    pub fn from_labels(labels: &HashMap<String, String>) -> Option<Self> {
+        let label = |oci: &str, legacy: Option<&str>| {
+            labels.get(&format!("org.opencontainers.image.{}", oci))
+                .or_else(|| legacy.and_then(|l| labels.get(&format!("org.label-schema.{}", l))))
+                .filter(|v| !v.trim().is_empty())
+                .cloned()
+        };
+
+        let metadata = Self {
+            source: label("source", Some("vcs-url")),
+            revision: label("revision", Some("vcs-ref")),
+            created: label("created", Some("build-date")),
+            version: label("version", Some("version")),
+            title: label("title", Some("name")),
+            description: label("description", Some("description")),
+            url: label("url", Some("url")),
+            documentation: label("documentation", Some("usage")),
+            vendor: label("vendor", Some("vendor")),
+            licenses: label("licenses", None),
+            base_name: label("base.name", None),
+            base_digest: label("base.digest", None),
+        };
+
+        (metadata != Self::default()).then_some(metadata)
+    }
+}
+
 /// Detailed container information
 #[derive(Debug, Clone, SimpleObject)]
 pub struct ContainerDetails {
@@ -313,12 +526,33 @@ pub struct NetworkInfo {
     pub mac_address: Option<String>,
 }
 
-/// Resource limits
+/// Resource limits (null when unset or unlimited)
 #[derive(Debug, Clone, SimpleObject)]
 pub struct ResourceLimits {
     pub memory_limit_bytes: Option<i64>,
     pub cpu_limit: Option<f64>,
     pub pids_limit: Option<i64>,
+    /// OOM score adjustment (-1000 to 1000, higher = killed first)
+    pub oom_score_adj: Option<i64>,
+    /// Whether the OOM killer is disabled
+    pub oom_kill_disable: Option<bool>,
+    /// CFS quota per period, in microseconds
+    pub cpu_quota: Option<i64>,
+    /// CFS period, in microseconds
+    pub cpu_period: Option<i64>,
+    /// Ulimits set on the container (the daemon's defaults are not listed)
+    pub ulimits: Vec<Ulimit>,
+}
+
+/// A ulimit set on a container
+#[derive(Debug, Clone, SimpleObject)]
+pub struct Ulimit {
+    /// Resource name (nofile, nproc, core, ...)
+    pub name: String,
+    /// Soft limit (null when unlimited)
+    pub soft: Option<i64>,
+    /// Hard limit (null when unlimited)
+    pub hard: Option<i64>,
 }
 
 /// Detailed container state information
@@ -338,6 +572,33 @@ pub struct ContainerStateInfoGql {
     pub restart_count: i32,
 }
 
+/// What a container runs
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ContainerCommandGql {
+    /// Entrypoint, as stored by Docker
+    pub entrypoint: Vec<String>,
+    /// Arguments (CMD), as stored by Docker
+    pub cmd: Vec<String>,
+    /// Working directory, if set
+    pub working_dir: Option<String>,
+    /// Whether the command runs through a shell ("/bin/sh -c ...")
+    pub shell_form: bool,
+    /// Readable command line (the script itself for shell form)
+    pub command_line: String,
+}
+
+impl From<crate::agent::client::ContainerCommand> for ContainerCommandGql {
+    fn from(c: crate::agent::client::ContainerCommand) -> Self {
+        Self {
+            entrypoint: c.entrypoint,
+            cmd: c.cmd,
+            working_dir: c.working_dir,
+            shell_form: c.shell_form,
+            command_line: c.command_line,
+        }
+    }
+}
+
 /// Container restart policy
 #[derive(Debug, Clone, SimpleObject)]
 pub struct RestartPolicyGql {
@@ -398,6 +659,41 @@ pub struct ContainerFilter {
     
     /// Limit number of results (must be > 0 if provided)
     pub limit: Option<i32>,
+
+    /// Skip this many matching containers before applying `limit`.
+    /// Containers from all queried agents are ordered by name, then ID and
+    /// agent, and paged together.
+    pub offset: Option<i32>,
+}
+
+/// One page of containers across the queried agents
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ContainerConnection {
+    pub items: Vec<Container>,
+    /// Matching containers on all queried agents, before paging
+    pub total_count: i32,
+    /// True when more matching containers follow this page
+    pub has_more: bool,
+}
+
+impl ContainerConnection {
+    /// Order the matching containers of every agent and cut the page the
+    /// filter asks for
+    pub fn page(mut containers: Vec<Container>, filter: Option<&ContainerFilter>) -> Self {
+        containers.sort_by(|a, b| {
+            (&a.name, &a.id, &a.agent_id).cmp(&(&b.name, &b.id, &b.agent_id))
+        });
+        let total = containers.len();
+        let offset = filter.and_then(|f| f.offset).filter(|&o| o > 0).map_or(0, |o| o as usize);
+        let limit = filter.and_then(|f| f.limit).filter(|&l| l > 0).map_or(usize::MAX, |l| l as usize);
+
+        let items: Vec<Container> = containers.into_iter().skip(offset).take(limit).collect();
+        Self {
+            has_more: offset.saturating_add(items.len()) < total,
+            total_count: i32::try_from(total).unwrap_or(i32::MAX),
+            items,
+        }
+    }
 }
 
 /// Label filter for matching key-value pairs
@@ -406,3 +702,141 @@ pub struct LabelFilter {
     pub key: String,
     pub value: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(agent_id: &str, name: &str) -> Container {
+        Container::from_proto(
+            crate::agent::client::ContainerInfo {
+                id: format!("{}-{}", name, agent_id),
+                name: name.to_string(),
+                ..Default::default()
+            },
+            agent_id.to_string(),
+        )
+    }
+
+    fn paging(offset: i32, limit: i32) -> ContainerFilter {
+        ContainerFilter {
+            state: None,
+            include_stopped: None,
+            labels: None,
+            name_pattern: None,
+            image_pattern: None,
+            limit: Some(limit),
+            offset: Some(offset),
+        }
+    }
+
+    #[test]
+    fn test_container_pages_span_agents() {
+        // Each agent's listing is sorted on its own; pages cut the merged order
+        let listings = || vec![
+            listed("a1", "api"), listed("a1", "web"),
+            listed("a2", "cache"), listed("a2", "db"), listed("a2", "worker"),
+        ];
+        let names = |page: &ContainerConnection| page.items.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+
+        let first = ContainerConnection::page(listings(), Some(&paging(0, 2)));
+        assert_eq!(names(&first), ["api", "cache"]);
+        assert_eq!((first.total_count, first.has_more), (5, true));
+
+        let second = ContainerConnection::page(listings(), Some(&paging(2, 2)));
+        assert_eq!(names(&second), ["db", "web"]);
+        assert!(second.has_more);
+
+        let last = ContainerConnection::page(listings(), Some(&paging(4, 2)));
+        assert_eq!(names(&last), ["worker"]);
+        assert_eq!((last.total_count, last.has_more), (5, false));
+
+        let all = ContainerConnection::page(listings(), None);
+        assert_eq!(all.items.len(), 5);
+        assert!(!all.has_more);
+    }
+
+    #[test]
+    fn test_restart_policy_from_docker() {
+        assert_eq!(RestartPolicyName::from_docker("no"), Some(RestartPolicyName::No));
+        assert_eq!(RestartPolicyName::from_docker(""), Some(RestartPolicyName::No));
+        assert_eq!(RestartPolicyName::from_docker("on-failure"), Some(RestartPolicyName::OnFailure));
+        assert_eq!(RestartPolicyName::from_docker("always"), Some(RestartPolicyName::Always));
+        assert_eq!(RestartPolicyName::from_docker("unless-stopped"), Some(RestartPolicyName::UnlessStopped));
+        assert_eq!(RestartPolicyName::from_docker("sometimes"), None);
+    }
+
+    fn inspected(id: &str, policy: Option<&str>) -> crate::agent::client::ContainerInspectResponse {
+        use crate::agent::client::proto;
+
+        crate::agent::client::ContainerInspectResponse {
+            info: Some(crate::agent::client::ContainerInfo {
+                id: id.to_string(),
+                ..Default::default()
+            }),
+            details: Some(proto::ContainerDetails {
+                restart_policy: policy.map(|name| proto::RestartPolicy {
+                    name: name.to_string(),
+                    max_retry_count: 0,
+                }),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_restart_policy_filter_selects_matching() {
+        let inspected = [
+            inspected("web", Some("always")),
+            inspected("worker", Some("no")),
+            inspected("cron", None),
+            inspected("db", Some("unless-stopped")),
+        ];
+
+        let misconfigured: Vec<_> = inspected.iter()
+            .filter(|r| RestartPolicyName::from_inspect(r) == Some(RestartPolicyName::No))
+            .map(|r| r.info.as_ref().unwrap().id.as_str())
+            .collect();
+        assert_eq!(misconfigured, vec!["worker", "cron"]);
+
+        let no_details = crate::agent::client::ContainerInspectResponse { info: None, details: None };
+        assert_eq!(RestartPolicyName::from_inspect(&no_details), None);
+    }
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_image_metadata_from_oci_labels() {
+        let metadata = ImageMetadata::from_labels(&labels(&[
+            ("org.opencontainers.image.source", "https://github.com/acme/payments"),
+            ("org.opencontainers.image.revision", "9f3c2a1e7b"),
+            ("org.opencontainers.image.created", "2026-03-02T10:15:00Z"),
+            ("org.opencontainers.image.version", "1.4.2"),
+            ("org.opencontainers.image.base.name", "docker.io/library/debian:12-slim"),
+            ("com.docker.compose.service", "payments"),
+        ])).unwrap();
+
+        assert_eq!(metadata.source.as_deref(), Some("https://github.com/acme/payments"));
+        assert_eq!(metadata.revision.as_deref(), Some("9f3c2a1e7b"));
+        assert_eq!(metadata.created.as_deref(), Some("2026-03-02T10:15:00Z"));
+        assert_eq!(metadata.version.as_deref(), Some("1.4.2"));
+        assert_eq!(metadata.base_name.as_deref(), Some("docker.io/library/debian:12-slim"));
+        assert_eq!(metadata.licenses, None);
+    }
+
+    #[test]
+    fn test_image_metadata_legacy_labels_and_absence() {
+        let metadata = ImageMetadata::from_labels(&labels(&[
+            ("org.label-schema.vcs-ref", "abc1234"),
+            ("org.opencontainers.image.source", "https://git.example.com/app"),
+            ("org.label-schema.vcs-url", "https://old.example.com/app"),
+        ])).unwrap();
+        assert_eq!(metadata.revision.as_deref(), Some("abc1234"));
+        assert_eq!(metadata.source.as_deref(), Some("https://git.example.com/app"), "OCI label wins");
+
+        assert_eq!(ImageMetadata::from_labels(&labels(&[("maintainer", "ops")])), None);
+        assert_eq!(ImageMetadata::from_labels(&labels(&[("org.opencontainers.image.revision", " ")])), None);
+    }
+}
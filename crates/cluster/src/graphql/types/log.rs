@@ -1,10 +1,10 @@
 // Log GraphQL types - Phase 4
 
-use async_graphql::{ComplexObject, Context, Enum, InputObject, Result, SimpleObject};
+use async_graphql::{ComplexObject, Context, Enum, InputObject, InputValueError, InputValueResult, Result, Scalar, ScalarType, SimpleObject, Value};
 use chrono::{DateTime, Utc};
 
 use crate::graphql::types::container::Container;
-use crate::agent::client::{LogLevel as ProtoLogLevel, FilterMode as ProtoFilterMode, ContainerInspectRequest};
+use crate::agent::client::{LogStreamRequest, LogLevel as ProtoLogLevel, FilterMode as ProtoFilterMode, StreamPriority as ProtoStreamPriority, LogSeverity as ProtoLogSeverity, UnleveledPolicy as ProtoUnleveledPolicy, ContainerInspectRequest};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -38,7 +38,8 @@ pub struct LogEntry {
     /// Agent ID where the container runs
     pub agent_id: String,
     
-    /// Timestamp when this log was generated
+    /// Timestamp when this log was generated (see the `timestamp` field)
+    #[graphql(skip)]
     pub timestamp: DateTime<Utc>,
     
     /// Log level (stdout or stderr)
@@ -68,6 +69,119 @@ pub struct LogEntry {
     
     /// Quick check for grouped logs
     pub is_grouped: bool,
+
+    /// Non-cryptographic hash of the normalized content (16 hex digits).
+    /// Only present when `includeHash` was requested.
+    pub content_hash: Option<String>,
+
+    /// Identical lines this entry stands for
+    /// (0 unless `collapseRepeats` or `dedupWindowMs` was requested)
+    pub repeat_count: i32,
+
+    /// Whether the parsed JSON matches the agent's log schema.
+    /// Only present when `validateSchema` was requested and the line is JSON.
+    pub schema_valid: Option<bool>,
+
+    /// Schema violations as `<json pointer>: <message>` (empty when valid)
+    pub schema_errors: Vec<String>,
+
+    /// Keepalive on a quiet stream (see `heartbeatSeconds`); has no content
+    pub heartbeat: bool,
+
+    /// First entry of a `since` stream when logs that old have rotated away;
+    /// its timestamp is the oldest line still available. Has no content.
+    pub backlog_unavailable: bool,
+
+    /// With `followByName`: the container was recreated and the stream now
+    /// follows the new one, whose ID is `containerId`. Has no content.
+    pub container_recreated: bool,
+
+    /// With `pauseToken`: lines dropped while paused because the buffer was
+    /// full. Sent on resume after the held lines. Has no content.
+    pub dropped_while_paused: i32,
+
+    /// Followed container stopped; its timestamp is when. Last entry of the
+    /// stream unless `onContainerStop` is `WAIT_FOR_RESTART`. Has no content.
+    pub container_stopped: bool,
+
+    /// With `containerStopped`: the container's exit code (null when unknown,
+    /// e.g. it was removed)
+    pub exit_code: Option<i32>,
+
+    /// With `colorByField`: 1-16 from the field's value, equal for equal
+    /// values on any container; 0 when the line doesn't have the field
+    pub color_group: Option<i32>,
+}
+
+/// How a log entry's `timestamp` is serialized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Default)]
+pub enum TimestampFormat {
+    /// RFC 3339 string with nanoseconds, e.g. `2024-05-01T12:00:00.123456789+00:00`
+    #[default]
+    Rfc3339,
+    /// Whole seconds since the Unix epoch
+    EpochSeconds,
+    /// Milliseconds since the Unix epoch
+    EpochMillis,
+    /// Nanoseconds since the Unix epoch. Beyond 2^53, so JavaScript clients
+    /// lose precision reading it as a number.
+    EpochNanos,
+}
+
+/// Log timestamp serialized per `TimestampFormat`: an RFC 3339 string or a
+/// Unix epoch integer. As input, integers are taken as seconds, milliseconds
+/// or nanoseconds by their magnitude.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogTimestamp {
+    pub at: DateTime<Utc>,
+    pub format: TimestampFormat,
+}
+
+/// Epoch integers below this are seconds (until the year 5138)
+const MAX_EPOCH_SECONDS: i64 = 100_000_000_000;
+/// Epoch integers below this (and not seconds) are milliseconds
+const MAX_EPOCH_MILLIS: i64 = 100_000_000_000_000;
+
+impl LogTimestamp {
+    fn from_epoch(value: i64) -> Option<Self> {
+        let (at, format) = if value.abs() < MAX_EPOCH_SECONDS {
+            (DateTime::from_timestamp(value, 0)?, TimestampFormat::EpochSeconds)
+        } else if value.abs() < MAX_EPOCH_MILLIS {
+            (DateTime::from_timestamp_millis(value)?, TimestampFormat::EpochMillis)
+        } else {
+            (DateTime::from_timestamp_nanos(value), TimestampFormat::EpochNanos)
+        };
+        Some(Self { at, format })
+    }
+}
+
+#[Scalar]
+impl ScalarType for LogTimestamp {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::String(s) => DateTime::parse_from_rfc3339(s)
+                .map(|at| Self { at: at.with_timezone(&Utc), format: TimestampFormat::Rfc3339 })
+                .map_err(InputValueError::custom),
+            Value::Number(n) => n
+                .as_i64()
+                .and_then(Self::from_epoch)
+                .ok_or_else(|| InputValueError::custom(format!("{} is not an epoch timestamp", n))),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self.format {
+            TimestampFormat::Rfc3339 => Value::String(self.at.to_rfc3339()),
+            TimestampFormat::EpochSeconds => Value::from(self.at.timestamp()),
+            TimestampFormat::EpochMillis => Value::from(self.at.timestamp_millis()),
+            // Outside 1677..2262 nanoseconds don't fit an i64
+            TimestampFormat::EpochNanos => match self.at.timestamp_nanos_opt() {
+                Some(nanos) => Value::from(nanos),
+                None => Value::String(self.at.to_rfc3339()),
+            },
+        }
+    }
 }
 
 /// Individual log line within a multiline group
@@ -80,6 +194,38 @@ pub struct LogLine {
 
 #[ComplexObject]
 impl LogEntry {
+    /// Timestamp when this log was generated; an RFC 3339 string unless
+    /// `format` asks for a Unix epoch integer
+    async fn timestamp(&self, #[graphql(default)] format: TimestampFormat) -> LogTimestamp {
+        LogTimestamp { at: self.timestamp, format }
+    }
+
+    /// Human-readable container name when `prefer_names` is enabled and the
+    /// name is known, otherwise the container ID
+    async fn container_name(&self, ctx: &Context<'_>) -> Result<String> {
+        use crate::state::AppState;
+
+        let state = ctx.data::<AppState>()?;
+        Ok(state.container_names.resolve(&state.agent_pool, &self.agent_id, &self.container_id).await)
+    }
+
+    /// Swarm or Compose service the container belongs to, from its labels
+    async fn service_name(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        use crate::state::AppState;
+
+        let state = ctx.data::<AppState>()?;
+        Ok(state.container_names.resolve_service(&state.agent_pool, &self.agent_id, &self.container_id).await)
+    }
+
+    /// Name of the agent (node) the container runs on when `prefer_names` is
+    /// enabled, otherwise the agent ID
+    async fn node_name(&self, ctx: &Context<'_>) -> Result<String> {
+        use crate::state::AppState;
+
+        let state = ctx.data::<AppState>()?;
+        Ok(state.container_names.node_name(&state.agent_pool, &self.agent_id))
+    }
+
     /// Container object (resolved from state).
     /// Uses a per-request cache so that thousands of log entries from the same
     /// container only trigger a single gRPC inspect call instead of N+1.
@@ -131,6 +277,7 @@ impl LogEntry {
                                 "Invalid created_at timestamp, substituting current time"
                             );
                         }
+                        state.container_names.record(&self.agent_id, &info.id, &info.name, &info.labels);
                         
                         Some(Container {
                             id: info.id,
@@ -142,6 +289,10 @@ impl LogEntry {
                             labels_map: info.labels,
                             created_at: ts.unwrap_or_else(chrono::Utc::now),
                             log_driver: info.log_driver,
+                            log_options: info.log_options,
+                            log_rotation_unbounded: info.log_rotation_unbounded,
+                            log_size_bytes: info.log_size_bytes,
+                            command: info.command.map(crate::graphql::types::container::ContainerCommandGql::from),
                             ports,
                             state_info: info.state_info.map(|si| {
                                 crate::graphql::types::container::ContainerStateInfoGql {
@@ -196,7 +347,8 @@ pub struct ContainerSource {
 /// Options for streaming or querying logs
 #[derive(Debug, Clone, InputObject)]
 pub struct LogStreamOptions {
-    /// Start time for logs (fetch logs after this timestamp)
+    /// Start time for logs, to the nanosecond: nothing earlier is sent. A
+    /// `backlogUnavailable` entry comes first when logs that old have rotated away.
     pub since: Option<DateTime<Utc>>,
     
     /// End time for logs (fetch logs before this timestamp)
@@ -219,6 +371,177 @@ pub struct LogStreamOptions {
     /// Show timestamps in the output
     #[graphql(default = true)]
     pub timestamps: bool,
+    
+    /// QoS hint: when the agent nears its stream limit, low-priority
+    /// streams are rejected first
+    #[graphql(default)]
+    pub priority: StreamPriority,
+
+    /// Attach a content hash to each entry for client-side dedup/caching
+    #[graphql(default = false)]
+    pub include_hash: bool,
+
+    /// Collapse consecutive identical lines into one entry with a repeat count
+    #[graphql(default = false)]
+    pub collapse_repeats: bool,
+
+    /// Collapse identical lines arriving within this many milliseconds of
+    /// each other, even when interleaved with other lines (max 5000). Every
+    /// line is delayed by the window.
+    pub dedup_window_ms: Option<u32>,
+
+    /// Order stdout and stderr lines by their Docker timestamps instead of
+    /// the order their frames arrived in, holding each line up to this many
+    /// milliseconds (max 1000) for an earlier one from the other stream.
+    /// Needs `timestamps`.
+    pub interleave_window_ms: Option<u32>,
+
+    /// Set `colorGroup` on each entry from this parsed field's value (e.g.
+    /// `trace_id`), so related lines across containers can share a color
+    pub color_by_field: Option<String>,
+
+    /// Only stream entries whose parsed level is at least this severity.
+    /// Composes with `filter`. Requires parsing; see `unleveledLines`.
+    pub min_level: Option<LogSeverity>,
+
+    /// With `minLevel`, what to do with entries that have no parsed level
+    /// (plain text or unknown level strings)
+    #[graphql(default)]
+    pub unleveled_lines: UnleveledPolicy,
+
+    /// Annotate JSON entries with `schemaValid`/`schemaErrors` against the
+    /// agent's configured log schema. Nothing is dropped.
+    #[graphql(default = false)]
+    pub validate_schema: bool,
+
+    /// Send a `heartbeat` entry after this many seconds without output, so a
+    /// quiet container can be told apart from a broken stream. Follow only.
+    pub heartbeat_seconds: Option<u32>,
+
+    /// `logsFromContainers`: entries sorted by timestamp together (default 10).
+    /// Larger chunks order better across containers.
+    pub merge_chunk_size: Option<u32>,
+
+    /// `logsFromContainers`: how long a chunk may wait for more entries before
+    /// it is sorted and sent (default 0, send what's ready). Bounds the added latency.
+    pub merge_hold_ms: Option<u32>,
+
+    /// `logStream` with follow: when the container is recreated under the
+    /// same name (e.g. `compose up`), reattach to the new one instead of
+    /// ending, after a `containerRecreated` entry. A restarted container is
+    /// picked up again too (see `onContainerStop`).
+    #[graphql(default = false)]
+    pub follow_by_name: bool,
+
+    /// Don't apply the cluster's default exclude patterns
+    /// (`log_defaults.exclude_patterns`), e.g. to see health-check lines
+    #[graphql(default = false)]
+    pub skip_default_excludes: bool,
+
+    /// Client-chosen ID that lets `pauseLogStream` / `resumeLogStream` hold
+    /// back this subscription without closing it. Must be unique while the
+    /// subscription is open.
+    pub pause_token: Option<String>,
+
+    /// Client-chosen ID that lets `setLogStreamFilter` replace `filter` /
+    /// `filterMode` on this subscription while it runs. The new filter
+    /// applies to lines read after the change; lines already sent are not
+    /// revisited. Must be unique while the subscription is open.
+    pub filter_token: Option<String>,
+
+    /// Type parsed field values that look like numbers, booleans or RFC 3339
+    /// timestamps (`intValue`, `floatValue`, `boolValue`, `timestampValue`).
+    /// `value` always keeps the original string.
+    #[graphql(default = false)]
+    pub infer_field_types: bool,
+
+    /// Report nested JSON as dotted fields (`user.id`, `req.headers.host`,
+    /// `tags[0]`) instead of one JSON-encoded field per top-level key.
+    /// Nesting past 8 levels and arrays over 64 items stay JSON.
+    #[graphql(default = false)]
+    pub flatten_fields: bool,
+
+    /// `logStream` with follow: what to do once the container stops, after
+    /// the `containerStopped` entry. Defaults to `WAIT_FOR_RESTART` with
+    /// `followByName` and `COMPLETE` otherwise; `WAIT_FOR_RESTART` needs
+    /// `followByName`.
+    pub on_container_stop: Option<ContainerStopBehavior>,
+}
+
+/// The GraphQL defaults: no time range, tail or filter, with timestamps
+impl Default for LogStreamOptions {
+    fn default() -> Self {
+        Self {
+            since: None,
+            until: None,
+            tail: None,
+            follow: false,
+            filter: None,
+            filter_mode: FilterMode::None,
+            timestamps: true,
+            priority: StreamPriority::Normal,
+            include_hash: false,
+            collapse_repeats: false,
+            dedup_window_ms: None,
+            interleave_window_ms: None,
+            color_by_field: None,
+            min_level: None,
+            unleveled_lines: UnleveledPolicy::Pass,
+            validate_schema: false,
+            heartbeat_seconds: None,
+            merge_chunk_size: None,
+            merge_hold_ms: None,
+            follow_by_name: false,
+            skip_default_excludes: false,
+            pause_token: None,
+            filter_token: None,
+            infer_field_types: false,
+            flatten_fields: false,
+            on_container_stop: None,
+        }
+    }
+}
+
+impl LogStreamOptions {
+    /// The agent request for one container's logs with these options.
+    /// Cluster-side options (merging, pause, excludes, follow by name) aren't sent.
+    pub fn to_request(&self, container_id: String) -> LogStreamRequest {
+        LogStreamRequest {
+            container_id,
+            since: self.since.map(|dt| dt.timestamp()),
+            until: self.until.map(|dt| dt.timestamp()),
+            tail_lines: self.tail.filter(|&t| t > 0).map(|t| t as u32),
+            follow: self.follow,
+            filter_pattern: self.filter.clone(),
+            filter_mode: ProtoFilterMode::from(self.filter_mode) as i32,
+            timestamps: self.timestamps,
+            disable_parsing: false,
+            priority: ProtoStreamPriority::from(self.priority) as i32,
+            include_hash: self.include_hash,
+            collapse_repeats: self.collapse_repeats,
+            dedup_window_ms: self.dedup_window_ms.unwrap_or(0),
+            interleave_window_ms: self.interleave_window_ms.unwrap_or(0),
+            color_by_field: self.color_by_field.clone(),
+            min_level: self.min_level.map(|level| ProtoLogSeverity::from(level) as i32).unwrap_or_default(),
+            unleveled_policy: ProtoUnleveledPolicy::from(self.unleveled_lines) as i32,
+            validate_schema: self.validate_schema,
+            heartbeat_interval_secs: self.heartbeat_seconds.unwrap_or(0),
+            since_nanos: self.since.and_then(|dt| dt.timestamp_nanos_opt()),
+            infer_field_types: self.infer_field_types,
+            flatten_fields: self.flatten_fields,
+            filter_token: self.filter_token.clone(),
+        }
+    }
+}
+
+/// What a followed log stream does when its container stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum ContainerStopBehavior {
+    /// End the stream
+    Complete,
+    /// Stay open until a container runs under the same name again, restarted
+    /// or recreated, and follow it
+    WaitForRestart,
 }
 
 /// Filter mode for log queries
@@ -233,6 +556,39 @@ pub enum FilterMode {
     Exclude,
 }
 
+/// Normalized log severity used for the `minLevel` floor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum LogSeverity {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+/// Handling of entries without a parsed level under a severity floor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Default)]
+pub enum UnleveledPolicy {
+    /// Keep them (nothing is hidden just because it couldn't be parsed)
+    #[default]
+    Pass,
+    /// Drop them
+    Drop,
+}
+
+/// Stream priority (QoS class) used for agent-side admission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Default)]
+pub enum StreamPriority {
+    /// Critical streams (e.g. alerting) - admitted up to the hard limit
+    High,
+    /// Regular interactive streams
+    #[default]
+    Normal,
+    /// Casual tails - rejected first when the agent is busy
+    Low,
+}
+
 /// Parsed structured log data
 #[derive(Debug, Clone, SimpleObject)]
 pub struct ParsedLogData {
@@ -307,6 +663,99 @@ pub struct KeyValueField {
     
     /// Field value
     pub value: String,
+
+    /// `value` as an integer, when `inferFieldTypes` recognized one
+    pub int_value: Option<i64>,
+
+    /// `value` as a decimal number, when `inferFieldTypes` recognized one
+    pub float_value: Option<f64>,
+
+    /// `value` as a boolean (`true`/`false`), when `inferFieldTypes` was set
+    pub bool_value: Option<bool>,
+
+    /// `value` as an RFC 3339 timestamp, when `inferFieldTypes` recognized one
+    pub timestamp_value: Option<DateTime<Utc>>,
+}
+
+impl KeyValueField {
+    fn from_proto(field: crate::agent::client::KeyValuePair) -> Self {
+        use crate::agent::client::typed_value::Kind;
+
+        let mut out = Self {
+            key: field.key,
+            value: field.value,
+            int_value: None,
+            float_value: None,
+            bool_value: None,
+            timestamp_value: None,
+        };
+        match field.typed.and_then(|t| t.kind) {
+            Some(Kind::IntValue(n)) => out.int_value = Some(n),
+            Some(Kind::FloatValue(f)) => out.float_value = Some(f),
+            Some(Kind::BoolValue(b)) => out.bool_value = Some(b),
+            Some(Kind::TimestampValue(ts)) => {
+                out.timestamp_value = DateTime::from_timestamp(ts.seconds, ts.nanos as u32)
+            }
+            None => {}
+        }
+        out
+    }
+}
+
+/// Lines and bytes a container logged in one `logRateHistogram` bucket
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LogRateBucket {
+    /// Start of the bucket (a multiple of the bucket size since the epoch)
+    pub start: DateTime<Utc>,
+    pub bucket_secs: i32,
+    pub lines: i64,
+    pub bytes: i64,
+    /// Lines per second over the bucket
+    pub lines_per_sec: f64,
+}
+
+impl LogRateBucket {
+    pub fn from_proto(bucket: crate::agent::client::LogRateBucket) -> Self {
+        let bucket_secs = bucket.bucket_secs.max(1);
+        Self {
+            start: DateTime::from_timestamp(bucket.bucket_start, 0).unwrap_or_default(),
+            bucket_secs: i32::try_from(bucket_secs).unwrap_or(i32::MAX),
+            lines: i64::try_from(bucket.lines).unwrap_or(i64::MAX),
+            bytes: i64::try_from(bucket.bytes).unwrap_or(i64::MAX),
+            lines_per_sec: bucket.lines as f64 / f64::from(bucket_secs),
+        }
+    }
+}
+
+/// Line sizes seen so far on one open log stream (one per container of a
+/// subscription), for tuning line size and batching settings
+#[derive(Debug, Clone, SimpleObject)]
+pub struct SubscriptionStats {
+    /// Stream ID, unique while the cluster runs
+    pub stream_id: String,
+    pub agent_id: String,
+    pub container_id: String,
+    pub lines: i64,
+    /// Longest line, in bytes
+    pub max_line_bytes: i64,
+    /// Average line length in bytes (0 before the first line)
+    pub avg_line_bytes: f64,
+    /// Lines over the agent's line size limit, passed on unparsed
+    pub oversized_lines: i64,
+}
+
+impl From<crate::metrics::LineStatsSnapshot> for SubscriptionStats {
+    fn from(stats: crate::metrics::LineStatsSnapshot) -> Self {
+        Self {
+            stream_id: stats.stream_id.to_string(),
+            agent_id: stats.agent_id,
+            container_id: stats.container_id,
+            lines: i64::try_from(stats.lines).unwrap_or(i64::MAX),
+            max_line_bytes: i64::try_from(stats.max_line_bytes).unwrap_or(i64::MAX),
+            avg_line_bytes: stats.avg_line_bytes,
+            oversized_lines: i64::try_from(stats.oversized_lines).unwrap_or(i64::MAX),
+        }
+    }
 }
 
 // Conversion functions from proto to GraphQL types
@@ -331,6 +780,38 @@ impl From<FilterMode> for ProtoFilterMode {
     }
 }
 
+impl From<LogSeverity> for ProtoLogSeverity {
+    fn from(severity: LogSeverity) -> Self {
+        match severity {
+            LogSeverity::Trace => ProtoLogSeverity::Trace,
+            LogSeverity::Debug => ProtoLogSeverity::Debug,
+            LogSeverity::Info => ProtoLogSeverity::Info,
+            LogSeverity::Warn => ProtoLogSeverity::Warn,
+            LogSeverity::Error => ProtoLogSeverity::Error,
+            LogSeverity::Fatal => ProtoLogSeverity::Fatal,
+        }
+    }
+}
+
+impl From<UnleveledPolicy> for ProtoUnleveledPolicy {
+    fn from(policy: UnleveledPolicy) -> Self {
+        match policy {
+            UnleveledPolicy::Pass => ProtoUnleveledPolicy::Pass,
+            UnleveledPolicy::Drop => ProtoUnleveledPolicy::Drop,
+        }
+    }
+}
+
+impl From<StreamPriority> for ProtoStreamPriority {
+    fn from(priority: StreamPriority) -> Self {
+        match priority {
+            StreamPriority::High => ProtoStreamPriority::High,
+            StreamPriority::Normal => ProtoStreamPriority::Normal,
+            StreamPriority::Low => ProtoStreamPriority::Low,
+        }
+    }
+}
+
 impl LogEntry {
     /// Create a LogEntry from a proto NormalizedLogEntry
     pub fn from_proto(
@@ -383,10 +864,7 @@ impl LogEntry {
                 file: e.file,
                 line: e.line,
             }),
-            fields: p.fields.into_iter().map(|f| KeyValueField {
-                key: f.key,
-                value: f.value,
-            }).collect(),
+            fields: p.fields.into_iter().map(KeyValueField::from_proto).collect(),
         });
         
         // Extract format and parse success from metadata
@@ -442,6 +920,245 @@ impl LogEntry {
             grouped_lines,
             line_count: response.line_count as i32,
             is_grouped: response.is_grouped,
+            content_hash: response.content_hash.map(|h| format!("{:016x}", h)),
+            repeat_count: i32::try_from(response.repeat_count).unwrap_or(i32::MAX),
+            schema_valid: response.schema_valid,
+            schema_errors: response.schema_errors,
+            heartbeat: response.heartbeat,
+            backlog_unavailable: response.backlog_unavailable,
+            container_recreated: false,
+            dropped_while_paused: 0,
+            container_stopped: response.container_stopped,
+            exit_code: response.exit_code,
+            color_group: response.color_group.map(|g| g as i32),
         })
     }
+
+    /// Marker sent when a `followByName` stream moves to a recreated container
+    pub fn recreated(container_id: String, agent_id: String) -> Self {
+        Self { container_recreated: true, ..Self::marker(container_id, agent_id) }
+    }
+
+    /// Marker sent on resume when a paused stream dropped lines
+    pub fn pause_drop_marker(container_id: String, agent_id: String, dropped: u64) -> Self {
+        Self {
+            dropped_while_paused: i32::try_from(dropped).unwrap_or(i32::MAX),
+            ..Self::marker(container_id, agent_id)
+        }
+    }
+
+    /// A synthetic entry with no content
+    fn marker(container_id: String, agent_id: String) -> Self {
+        Self {
+            container_id,
+            agent_id,
+            timestamp: Utc::now(),
+            level: LogLevel::Stdout,
+            content: String::new(),
+            sequence: 0,
+            parsed: None,
+            format: "Unknown".to_string(),
+            parse_success: false,
+            grouped_lines: Vec::new(),
+            line_count: 0,
+            is_grouped: false,
+            content_hash: None,
+            repeat_count: 0,
+            schema_valid: None,
+            schema_errors: Vec::new(),
+            heartbeat: false,
+            backlog_unavailable: false,
+            container_recreated: false,
+            dropped_while_paused: 0,
+            container_stopped: false,
+            exit_code: None,
+            color_group: None,
+        }
+    }
+}
+
+/// Kind of values seen for a structured log field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum FieldValueType {
+    String,
+    Int,
+    /// Decimal numbers, or a mix of integers and decimals
+    Float,
+    Bool,
+    Timestamp,
+    /// Values of more than one kind
+    Mixed,
+}
+
+impl FieldValueType {
+    fn of(field: &crate::agent::client::KeyValuePair) -> Self {
+        use crate::agent::client::typed_value::Kind;
+
+        match field.typed.as_ref().and_then(|t| t.kind.as_ref()) {
+            Some(Kind::IntValue(_)) => Self::Int,
+            Some(Kind::FloatValue(_)) => Self::Float,
+            Some(Kind::BoolValue(_)) => Self::Bool,
+            Some(Kind::TimestampValue(_)) => Self::Timestamp,
+            None => Self::String,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Self::Int, Self::Float) | (Self::Float, Self::Int) => Self::Float,
+            _ => Self::Mixed,
+        }
+    }
+}
+
+/// A structured field key seen in a container's recent log lines
+#[derive(Debug, Clone, PartialEq, SimpleObject)]
+pub struct LogFieldKey {
+    /// Field name; nested JSON keys are dotted (`user.id`, `tags[0]`)
+    pub key: String,
+    pub value_type: FieldValueType,
+    /// Sampled lines carrying the field
+    pub occurrences: i32,
+}
+
+impl LogFieldKey {
+    /// Field keys across sampled entries, most frequent first. Entries that
+    /// weren't parsed into fields (plain text) contribute nothing.
+    pub fn from_entries(entries: impl IntoIterator<Item = crate::agent::client::NormalizedLogEntry>) -> Vec<Self> {
+        let mut seen: HashMap<String, (FieldValueType, i32)> = HashMap::new();
+        for entry in entries {
+            let Some(parsed) = entry.parsed else { continue };
+            for field in &parsed.fields {
+                let value_type = FieldValueType::of(field);
+                seen.entry(field.key.clone())
+                    .and_modify(|(seen_type, count)| {
+                        *seen_type = seen_type.merge(value_type);
+                        *count += 1;
+                    })
+                    .or_insert((value_type, 1));
+            }
+        }
+
+        let mut keys: Vec<Self> = seen.into_iter()
+            .map(|(key, (value_type, occurrences))| Self { key, value_type, occurrences })
+            .collect();
+        keys.sort_by(|a, b| b.occurrences.cmp(&a.occurrences).then_with(|| a.key.cmp(&b.key)));
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::client::proto::{ParsedLog, TypedValue};
+    use crate::agent::client::typed_value::Kind;
+    use crate::agent::client::{KeyValuePair, NormalizedLogEntry};
+
+    #[test]
+    fn test_timestamp_formats_round_trip() {
+        let at = DateTime::parse_from_rfc3339("2024-05-01T12:00:00.123456789Z").unwrap().with_timezone(&Utc);
+        let cases = [
+            (TimestampFormat::Rfc3339, Value::from("2024-05-01T12:00:00.123456789+00:00"), at),
+            (TimestampFormat::EpochSeconds, Value::from(1_714_564_800), DateTime::from_timestamp(1_714_564_800, 0).unwrap()),
+            (TimestampFormat::EpochMillis, Value::from(1_714_564_800_123i64), DateTime::from_timestamp_millis(1_714_564_800_123).unwrap()),
+            (TimestampFormat::EpochNanos, Value::from(1_714_564_800_123_456_789i64), at),
+        ];
+        for (format, serialized, parsed) in cases {
+            let value = LogTimestamp { at, format }.to_value();
+            assert_eq!(value, serialized, "{:?}", format);
+            assert_eq!(LogTimestamp::parse(value).unwrap(), LogTimestamp { at: parsed, format });
+        }
+    }
+
+    #[test]
+    fn test_nanos_out_of_range_fall_back_to_rfc3339() {
+        let at = DateTime::parse_from_rfc3339("2300-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let value = LogTimestamp { at, format: TimestampFormat::EpochNanos }.to_value();
+        assert_eq!(value, Value::from("2300-01-01T00:00:00+00:00"));
+        assert!(LogTimestamp::parse(Value::Boolean(true)).is_err());
+    }
+
+    fn field(key: &str, typed: Option<Kind>) -> KeyValuePair {
+        KeyValuePair {
+            key: key.to_string(),
+            value: String::new(),
+            typed: typed.map(|kind| TypedValue { kind: Some(kind) }),
+        }
+    }
+
+    fn structured(fields: Vec<KeyValuePair>) -> NormalizedLogEntry {
+        NormalizedLogEntry {
+            parsed: Some(ParsedLog { fields, ..Default::default() }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_field_keys_from_structured_sample() {
+        // {"user":{"id":42},"duration":0.25,"cached":true,"route":"/orders"}
+        // {"user":{"id":7},"duration":3,"route":"/health"}
+        // {"user":{"id":"anon"},"route":"/"}
+        let sample = vec![
+            structured(vec![
+                field("user.id", Some(Kind::IntValue(42))),
+                field("duration", Some(Kind::FloatValue(0.25))),
+                field("cached", Some(Kind::BoolValue(true))),
+                field("route", None),
+            ]),
+            structured(vec![
+                field("user.id", Some(Kind::IntValue(7))),
+                field("duration", Some(Kind::IntValue(3))),
+                field("route", None),
+            ]),
+            structured(vec![field("user.id", None), field("route", None)]),
+            // A plain text line in between
+            NormalizedLogEntry::default(),
+        ];
+
+        let keys = LogFieldKey::from_entries(sample);
+        let expected = [
+            ("route", FieldValueType::String, 3),
+            ("user.id", FieldValueType::Mixed, 3),
+            ("duration", FieldValueType::Float, 2),
+            ("cached", FieldValueType::Bool, 1),
+        ];
+        let got: Vec<_> = keys.iter().map(|k| (k.key.as_str(), k.value_type, k.occurrences)).collect();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_unstructured_sample_has_no_field_keys() {
+        let plain = vec![NormalizedLogEntry::default(); 3];
+        assert!(LogFieldKey::from_entries(plain).is_empty());
+    }
+
+    #[test]
+    fn test_options_to_request() {
+        let since = DateTime::from_timestamp(1_700_000_000, 250).unwrap();
+        let opts = LogStreamOptions {
+            since: Some(since),
+            tail: Some(50),
+            filter: Some("timeout".to_string()),
+            filter_mode: FilterMode::Exclude,
+            priority: StreamPriority::High,
+            min_level: Some(LogSeverity::Warn),
+            dedup_window_ms: Some(200),
+            ..LogStreamOptions::default()
+        };
+        let request = opts.to_request("api".to_string());
+
+        assert_eq!(request.container_id, "api");
+        assert_eq!((request.since, request.since_nanos), (Some(1_700_000_000), Some(1_700_000_000_000_000_250)));
+        assert_eq!(request.tail_lines, Some(50));
+        assert_eq!(request.filter_pattern.as_deref(), Some("timeout"));
+        assert_eq!(request.filter_mode, ProtoFilterMode::Exclude as i32);
+        assert_eq!(request.priority, ProtoStreamPriority::High as i32);
+        assert_eq!(request.min_level, ProtoLogSeverity::Warn as i32);
+        assert_eq!(request.dedup_window_ms, 200);
+        assert!(request.timestamps);
+
+        // A tail of 0 or less means no tail
+        assert_eq!(LogStreamOptions { tail: Some(0), ..opts }.to_request(String::new()).tail_lines, None);
+    }
 }
@@ -4,4 +4,5 @@
 pub mod agent;
 pub mod container;
 pub mod log;
+pub mod search;
 pub mod stats;
@@ -0,0 +1,222 @@
+use async_graphql::SimpleObject;
+use futures::StreamExt;
+use std::future::Future;
+
+use super::log::{ContainerSource, LogEntry};
+
+/// Matches `searchAllLogs` returns when the client doesn't say
+pub const DEFAULT_SEARCH_LIMIT: i32 = 500;
+
+/// Upper bound on `searchAllLogs` limit
+pub const MAX_SEARCH_LIMIT: i32 = 5000;
+
+/// Containers one search may cover
+pub const MAX_SEARCH_SOURCES: usize = 100;
+
+/// Log lines one search reads across all its containers, split evenly
+/// between them
+pub const SEARCH_SCAN_BUDGET: u32 = 50_000;
+
+/// Containers searched at once
+const SEARCH_CONCURRENCY: usize = 8;
+
+/// Matches for a pattern across containers, in time order
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LogSearchResult {
+    /// The most recent matches, oldest first
+    pub matches: Vec<LogEntry>,
+
+    /// Matches found per container, before `limit` applied
+    pub sources: Vec<SearchSourceCount>,
+
+    /// More matches were found than `limit`; the oldest were left out
+    pub truncated: bool,
+
+    /// Containers that weren't searched (unhealthy or unknown agent,
+    /// failed request) or only partly (stream failed partway, unreadable
+    /// lines), one message each
+    pub warnings: Vec<String>,
+}
+
+/// What one container's search found
+#[derive(Debug, Default)]
+pub struct SourceMatches {
+    pub entries: Vec<LogEntry>,
+    /// Problems that didn't stop the search, such as a line that couldn't
+    /// be read; the container still counts as searched
+    pub warnings: Vec<String>,
+}
+
+/// Matches found in one container
+#[derive(Debug, Clone, SimpleObject)]
+pub struct SearchSourceCount {
+    pub container_id: String,
+    pub agent_id: String,
+    pub matches: i32,
+    /// False when the container was skipped; see `warnings`
+    pub searched: bool,
+}
+
+/// Search every source with `search`, a few at a time, and merge the
+/// matches by timestamp, keeping the `limit` most recent. A source whose
+/// search fails is reported as a warning instead of failing the whole search,
+/// as is every warning of a source that was searched.
+pub async fn search_sources<F, Fut>(sources: Vec<ContainerSource>, limit: usize, search: F) -> LogSearchResult
+where
+    F: Fn(ContainerSource) -> Fut,
+    Fut: Future<Output = Result<SourceMatches, String>>,
+{
+    let outcomes: Vec<_> = futures::stream::iter(sources)
+        .map(|source| {
+            let searched = search(source.clone());
+            async move { (source, searched.await) }
+        })
+        .buffered(SEARCH_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut matches = Vec::new();
+    let mut counts = Vec::with_capacity(outcomes.len());
+    let mut warnings = Vec::new();
+    for (source, outcome) in outcomes {
+        let (found, searched) = match outcome {
+            Ok(found) => {
+                for message in found.warnings {
+                    tracing::warn!("Log search of container {} on agent {}: {}", source.container_id, source.agent_id, message);
+                    warnings.push(format!("{} on {}: {}", source.container_id, source.agent_id, message));
+                }
+                let count = found.entries.len();
+                matches.extend(found.entries);
+                (count, true)
+            }
+            Err(message) => {
+                tracing::warn!("Log search skipped container {} on agent {}: {}", source.container_id, source.agent_id, message);
+                warnings.push(format!("{} on {}: {}", source.container_id, source.agent_id, message));
+                (0, false)
+            }
+        };
+        counts.push(SearchSourceCount {
+            container_id: source.container_id,
+            agent_id: source.agent_id,
+            matches: i32::try_from(found).unwrap_or(i32::MAX),
+            searched,
+        });
+    }
+
+    // Stable, so lines of one container with equal timestamps keep their order
+    matches.sort_by_key(|entry| entry.timestamp);
+    let truncated = matches.len() > limit;
+    if truncated {
+        matches.drain(..matches.len() - limit);
+    }
+
+    LogSearchResult { matches, sources: counts, truncated, warnings }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphql::types::log::LogLevel;
+    use chrono::{DateTime, Utc};
+    use std::collections::HashMap;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn line(source: &ContainerSource, secs: i64, content: &str) -> LogEntry {
+        LogEntry {
+            container_id: source.container_id.clone(),
+            agent_id: source.agent_id.clone(),
+            timestamp: at(secs),
+            level: LogLevel::Stdout,
+            content: content.to_string(),
+            sequence: 0,
+            parsed: None,
+            format: "PlainText".to_string(),
+            parse_success: false,
+            grouped_lines: Vec::new(),
+            line_count: 1,
+            is_grouped: false,
+            content_hash: None,
+            repeat_count: 0,
+            schema_valid: None,
+            schema_errors: Vec::new(),
+            heartbeat: false,
+            backlog_unavailable: false,
+            container_recreated: false,
+            dropped_while_paused: 0,
+            container_stopped: false,
+            exit_code: None,
+            color_group: None,
+        }
+    }
+
+    fn found(entries: Vec<LogEntry>) -> Result<SourceMatches, String> {
+        Ok(SourceMatches { entries, warnings: Vec::new() })
+    }
+
+    fn source(container_id: &str, agent_id: &str) -> ContainerSource {
+        ContainerSource { container_id: container_id.to_string(), agent_id: agent_id.to_string() }
+    }
+
+    /// Two agents with matching lines, and one that's down
+    async fn search(sources: Vec<ContainerSource>, limit: usize) -> LogSearchResult {
+        search_sources(sources, limit, |source| async move {
+            match source.agent_id.as_str() {
+                "edge" => found(vec![line(&source, 1, "timeout calling db"), line(&source, 5, "timeout calling db")]),
+                "apps" => found(vec![line(&source, 3, "db timeout"), line(&source, 4, "db timeout again")]),
+                // Stream broke after one match
+                "flaky" => Ok(SourceMatches {
+                    entries: vec![line(&source, 2, "db timeout")],
+                    warnings: vec!["search stopped early: transport error".to_string()],
+                }),
+                _ => Err("agent is unhealthy".to_string()),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_matches_merged_in_time_order_with_counts() {
+        let result = search(vec![source("proxy", "edge"), source("api", "apps"), source("batch", "down")], 100).await;
+
+        let order: Vec<(String, DateTime<Utc>)> =
+            result.matches.iter().map(|m| (m.container_id.clone(), m.timestamp)).collect();
+        assert_eq!(order, [
+            ("proxy".to_string(), at(1)),
+            ("api".to_string(), at(3)),
+            ("api".to_string(), at(4)),
+            ("proxy".to_string(), at(5)),
+        ]);
+        assert!(!result.truncated);
+
+        let counts: HashMap<_, _> = result.sources.iter().map(|s| (s.container_id.as_str(), (s.matches, s.searched))).collect();
+        assert_eq!(counts["proxy"], (2, true));
+        assert_eq!(counts["api"], (2, true));
+        assert_eq!(counts["batch"], (0, false));
+        assert_eq!(result.warnings, ["batch on down: agent is unhealthy"]);
+    }
+
+    #[tokio::test]
+    async fn test_limit_keeps_most_recent_matches() {
+        let result = search(vec![source("proxy", "edge"), source("api", "apps")], 2).await;
+
+        let times: Vec<_> = result.matches.iter().map(|m| m.timestamp).collect();
+        assert_eq!(times, [at(4), at(5)]);
+        assert!(result.truncated);
+        // Counts are what each container matched, not what was kept
+        assert_eq!(result.sources.iter().map(|s| s.matches).sum::<i32>(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_partial_search_keeps_matches_and_warns() {
+        let result = search(vec![source("proxy", "edge"), source("worker", "flaky")], 100).await;
+
+        let times: Vec<_> = result.matches.iter().map(|m| m.timestamp).collect();
+        assert_eq!(times, [at(1), at(2), at(5)]);
+        let worker = result.sources.iter().find(|s| s.container_id == "worker").unwrap();
+        assert_eq!((worker.matches, worker.searched), (1, true));
+        assert_eq!(result.warnings, ["worker on flaky: search stopped early: transport error"]);
+    }
+}
@@ -1,10 +1,15 @@
-use async_graphql::SimpleObject;
+use async_graphql::{ComplexObject, Context, Enum, Result, SimpleObject};
 
 /// Container resource statistics
 #[derive(Debug, Clone, SimpleObject)]
+#[graphql(complex)]
 pub struct ContainerStats {
     /// Container ID
     pub container_id: String,
+
+    /// Agent the stats came from (used to resolve the container name)
+    #[graphql(skip)]
+    pub agent_id: String,
     
     /// Timestamp when stats were collected (Unix timestamp)
     pub timestamp: i64,
@@ -135,6 +140,32 @@ pub struct BlockIoStats {
     pub devices: Vec<BlockIoDeviceStats>,
 }
 
+/// Metric a `statsAnomalies` baseline is learned for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum StatsMetric {
+    /// CPU percentage
+    Cpu,
+    /// Memory usage in bytes
+    Memory,
+}
+
+/// A stats sample that strayed from the container's learned baseline
+#[derive(Debug, Clone, SimpleObject)]
+pub struct StatsAnomaly {
+    pub container_id: String,
+    /// When the sample was taken (Unix timestamp)
+    pub timestamp: i64,
+    pub metric: StatsMetric,
+    /// Value in the sample
+    pub observed: f64,
+    /// Baseline mean before this sample
+    pub expected: f64,
+    /// Baseline standard deviation before this sample
+    pub stddev: f64,
+    /// `(observed - expected) / stddev`; negative for a drop
+    pub deviation: f64,
+}
+
 /// Per-device block I/O statistics
 #[derive(Debug, Clone, SimpleObject)]
 pub struct BlockIoDeviceStats {
@@ -151,16 +182,201 @@ pub struct BlockIoDeviceStats {
     pub write_bytes: i64,
 }
 
+/// Memory right-sizing profile combining configured limits with live usage
+#[derive(Debug, Clone, SimpleObject)]
+pub struct MemoryProfile {
+    /// Container ID
+    pub container_id: String,
+    
+    /// Configured memory limit (bytes, None = unlimited)
+    pub limit: Option<i64>,
+    
+    /// Current memory usage (bytes)
+    pub usage: i64,
+    
+    /// Peak memory usage recorded (bytes)
+    pub max_usage: i64,
+    
+    /// Cache memory (bytes) - reclaimable under pressure
+    pub cache: i64,
+    
+    /// RSS memory (bytes) - actual physical memory used
+    pub rss: i64,
+    
+    /// Peak usage as a percentage of the limit (None if unlimited)
+    pub peak_percentage: Option<f64>,
+    
+    /// OOM score adjustment (-1000 to 1000, higher = killed first)
+    pub oom_score_adj: Option<i64>,
+    
+    /// Whether the OOM killer is disabled
+    pub oom_kill_disabled: bool,
+    
+    /// Whether the container was last stopped by the OOM killer
+    pub oom_killed: bool,
+}
+
+impl MemoryProfile {
+    /// Build a profile from a stats snapshot and an inspect response.
+    /// The limit comes from inspect since stats report host memory when unlimited.
+    pub fn from_parts(
+        stats: &crate::agent::client::ContainerStatsResponse,
+        inspect: &crate::agent::client::ContainerInspectResponse,
+    ) -> Self {
+        let limits = inspect.details.as_ref().and_then(|d| d.limits.as_ref());
+        let limit = limits
+            .and_then(|l| l.memory_limit_bytes)
+            .filter(|&bytes| bytes > 0);
+        let memory = stats.memory_stats.as_ref();
+        let max_usage = memory.map(|m| m.max_usage as i64).unwrap_or(0);
+
+        Self {
+            container_id: stats.container_id.clone(),
+            limit,
+            usage: memory.map(|m| m.usage as i64).unwrap_or(0),
+            max_usage,
+            cache: memory.map(|m| m.cache as i64).unwrap_or(0),
+            rss: memory.map(|m| m.rss as i64).unwrap_or(0),
+            peak_percentage: limit.map(|l| (max_usage as f64 / l as f64) * 100.0),
+            oom_score_adj: limits.and_then(|l| l.oom_score_adj),
+            oom_kill_disabled: limits.and_then(|l| l.oom_kill_disable).unwrap_or(false),
+            oom_killed: inspect.info.as_ref()
+                .and_then(|i| i.state_info.as_ref())
+                .map(|si| si.oom_killed)
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Consistent debugging snapshot taken while the container was paused
+#[derive(Debug, Clone, SimpleObject)]
+pub struct FreezeSnapshot {
+    /// Container ID
+    pub container_id: String,
+
+    /// Processes in the container at the time of the snapshot
+    pub processes: Vec<ProcessInfo>,
+
+    /// Memory usage while paused
+    pub memory_stats: MemoryStats,
+
+    /// Open file descriptors across the container's processes
+    /// (None if the agent cannot read the host's /proc)
+    pub open_files: Option<i64>,
+
+    /// Most recent log lines, oldest first
+    pub recent_logs: Vec<String>,
+
+    /// When the container was paused
+    pub paused_at: chrono::DateTime<chrono::Utc>,
+
+    /// How long the container was held paused (milliseconds)
+    pub paused_ms: i64,
+}
+
+/// A container's CPU and memory limits after `updateContainerResources`
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ContainerResources {
+    /// Container ID
+    pub container_id: String,
+
+    /// CPU limit in cores (0 = unlimited)
+    pub cpus: f64,
+
+    /// Memory limit in bytes (0 = unlimited)
+    pub memory_bytes: i64,
+}
+
+impl ContainerResources {
+    pub fn from_proto(response: crate::agent::client::UpdateResourcesResponse) -> Self {
+        Self {
+            container_id: response.container_id,
+            cpus: response.cpus,
+            memory_bytes: response.memory_bytes,
+        }
+    }
+}
+
+/// A process inside a container
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ProcessInfo {
+    /// Host PID
+    pub pid: i64,
+
+    /// User the process runs as
+    pub user: String,
+
+    /// Full command line
+    pub command: String,
+}
+
+impl FreezeSnapshot {
+    pub fn from_proto(response: crate::agent::client::FreezeInspectResponse) -> Self {
+        Self {
+            processes: response.processes.into_iter().map(|p| ProcessInfo {
+                pid: p.pid as i64,
+                user: p.user,
+                command: p.command,
+            }).collect(),
+            memory_stats: MemoryStats::from_proto(response.memory_stats.as_ref()),
+            open_files: response.open_files.map(|n| n as i64),
+            recent_logs: response.recent_logs,
+            paused_at: chrono::DateTime::from_timestamp(response.paused_at, 0).unwrap_or_default(),
+            paused_ms: response.paused_ms as i64,
+            container_id: response.container_id,
+        }
+    }
+}
+
+impl MemoryStats {
+    /// Missing stats (e.g. the container stopped mid-request) read as zeros
+    pub fn from_proto(memory: Option<&crate::agent::client::proto::MemoryStats>) -> Self {
+        Self {
+            usage: memory.map(|m| m.usage as i64).unwrap_or(0),
+            max_usage: memory.map(|m| m.max_usage as i64).unwrap_or(0),
+            limit: memory.map(|m| m.limit as i64).unwrap_or(0),
+            percentage: memory.map(|m| m.percentage).unwrap_or(0.0),
+            cache: memory.map(|m| m.cache as i64).unwrap_or(0),
+            rss: memory.map(|m| m.rss as i64).unwrap_or(0),
+            swap: memory.and_then(|m| m.swap).map(|s| s as i64),
+        }
+    }
+}
+
 // ============================================================================
 // Shared conversion from proto ContainerStatsResponse → GraphQL ContainerStats
 // ============================================================================
 
+#[ComplexObject]
+impl ContainerStats {
+    /// Human-readable container name when `prefer_names` is enabled and the
+    /// name is known, otherwise the container ID
+    async fn container_name(&self, ctx: &Context<'_>) -> Result<String> {
+        let state = ctx.data::<crate::state::AppState>()?;
+        Ok(state.container_names.resolve(&state.agent_pool, &self.agent_id, &self.container_id).await)
+    }
+
+    /// Swarm or Compose service the container belongs to, from its labels
+    async fn service_name(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let state = ctx.data::<crate::state::AppState>()?;
+        Ok(state.container_names.resolve_service(&state.agent_pool, &self.agent_id, &self.container_id).await)
+    }
+
+    /// Name of the agent (node) the container runs on when `prefer_names` is
+    /// enabled, otherwise the agent ID
+    async fn node_name(&self, ctx: &Context<'_>) -> Result<String> {
+        let state = ctx.data::<crate::state::AppState>()?;
+        Ok(state.container_names.node_name(&state.agent_pool, &self.agent_id))
+    }
+}
+
 impl ContainerStats {
     /// Convert a proto ContainerStatsResponse into a GraphQL ContainerStats.
     /// This eliminates the ~60-line duplication across schema.rs and subscriptions/mod.rs.
-    pub fn from_proto(response: crate::agent::client::ContainerStatsResponse) -> Self {
+    pub fn from_proto(response: crate::agent::client::ContainerStatsResponse, agent_id: String) -> Self {
         Self {
             container_id: response.container_id,
+            agent_id,
             timestamp: response.timestamp,
             cpu_stats: CpuStats {
                 cpu_percentage: response.cpu_stats.as_ref().map(|c| c.cpu_percentage).unwrap_or(0.0),
@@ -178,15 +394,7 @@ impl ContainerStats {
                         throttled_time: t.throttled_time as i64,
                     }),
             },
-            memory_stats: MemoryStats {
-                usage: response.memory_stats.as_ref().map(|m| m.usage as i64).unwrap_or(0),
-                max_usage: response.memory_stats.as_ref().map(|m| m.max_usage as i64).unwrap_or(0),
-                limit: response.memory_stats.as_ref().map(|m| m.limit as i64).unwrap_or(0),
-                percentage: response.memory_stats.as_ref().map(|m| m.percentage).unwrap_or(0.0),
-                cache: response.memory_stats.as_ref().map(|m| m.cache as i64).unwrap_or(0),
-                rss: response.memory_stats.as_ref().map(|m| m.rss as i64).unwrap_or(0),
-                swap: response.memory_stats.as_ref().and_then(|m| m.swap).map(|s| s as i64),
-            },
+            memory_stats: MemoryStats::from_proto(response.memory_stats.as_ref()),
             network_stats: response.network_stats.iter().map(|n| NetworkStats {
                 interface_name: n.interface_name.clone(),
                 rx_bytes: n.rx_bytes as i64,
@@ -216,3 +424,82 @@ impl ContainerStats {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::client::{ContainerInspectResponse, ContainerStatsResponse};
+    use crate::agent::client::proto;
+
+    fn stats_response() -> ContainerStatsResponse {
+        ContainerStatsResponse {
+            container_id: "abc123".to_string(),
+            memory_stats: Some(proto::MemoryStats {
+                usage: 300,
+                max_usage: 512,
+                limit: 16_000_000_000,
+                percentage: 0.0,
+                cache: 100,
+                rss: 200,
+                swap: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn inspect_response(limits: Option<proto::ResourceLimits>) -> ContainerInspectResponse {
+        ContainerInspectResponse {
+            info: Some(proto::ContainerInfo {
+                id: "abc123".to_string(),
+                state_info: Some(proto::ContainerStateInfo {
+                    oom_killed: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            details: Some(proto::ContainerDetails {
+                limits,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_memory_profile_maps_stats_and_inspect() {
+        let inspect = inspect_response(Some(proto::ResourceLimits {
+            memory_limit_bytes: Some(1024),
+            oom_score_adj: Some(500),
+            oom_kill_disable: Some(false),
+            ..Default::default()
+        }));
+
+        let profile = MemoryProfile::from_parts(&stats_response(), &inspect);
+        assert_eq!(profile.container_id, "abc123");
+        assert_eq!(profile.limit, Some(1024));
+        assert_eq!(profile.usage, 300);
+        assert_eq!(profile.max_usage, 512);
+        assert_eq!(profile.cache, 100);
+        assert_eq!(profile.rss, 200);
+        assert_eq!(profile.peak_percentage, Some(50.0));
+        assert_eq!(profile.oom_score_adj, Some(500));
+        assert!(!profile.oom_kill_disabled);
+        assert!(profile.oom_killed);
+    }
+
+    #[test]
+    fn test_memory_profile_without_limit() {
+        // Docker reports memory = 0 for unlimited containers
+        let inspect = inspect_response(Some(proto::ResourceLimits {
+            memory_limit_bytes: Some(0),
+            ..Default::default()
+        }));
+
+        let profile = MemoryProfile::from_parts(&stats_response(), &inspect);
+        assert_eq!(profile.limit, None);
+        assert_eq!(profile.peak_percentage, None);
+        assert_eq!(profile.oom_score_adj, None);
+
+        let profile = MemoryProfile::from_parts(&stats_response(), &inspect_response(None));
+        assert_eq!(profile.limit, None);
+    }
+}
@@ -0,0 +1,148 @@
+use async_graphql::http::WebSocketProtocols;
+use async_graphql_axum::{GraphQLProtocol, GraphQLWebSocket};
+use axum::{
+    extract::{State, WebSocketUpgrade},
+    http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, MethodRouter},
+};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::ClusterSchema;
+
+/// Subprotocols accepted when none are configured
+pub const DEFAULT_WS_PROTOCOLS: [&str; 2] = ["graphql-transport-ws", "graphql-ws"];
+
+#[derive(Clone)]
+struct WsState {
+    schema: ClusterSchema,
+    protocols: Arc<[String]>,
+}
+
+/// `GET /ws`: GraphQL subscriptions over WebSocket.
+///
+/// The subprotocol is the client's first offer that async-graphql speaks
+/// (what `GraphQLProtocol` extracts); the upgrade is refused with 400 when
+/// that protocol isn't in `protocols`, or the client offers none.
+pub fn subscription_route<S>(schema: ClusterSchema, protocols: &[String]) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    get(ws_handler).with_state(WsState {
+        schema,
+        protocols: protocols.into(),
+    })
+}
+
+async fn ws_handler(
+    State(state): State<WsState>,
+    headers: HeaderMap,
+    protocol: Result<GraphQLProtocol, StatusCode>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let accepted = extracted_protocol(&headers).filter(|name| state.protocols.iter().any(|p| p == name));
+    let (Ok(protocol), Some(name)) = (protocol, accepted) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unsupported Sec-WebSocket-Protocol; expected one of: {}",
+                state.protocols.join(", ")
+            ),
+        )
+            .into_response();
+    };
+
+    let schema = state.schema.clone();
+    upgrade.protocols([name]).on_upgrade(move |stream| {
+        GraphQLWebSocket::new(stream, schema, protocol).serve()
+    })
+}
+
+/// Name of the protocol `GraphQLProtocol` picks from the same header; the
+/// extractor keeps it private, and it's needed to check the config and
+/// answer the handshake
+fn extracted_protocol(headers: &HeaderMap) -> Option<&'static str> {
+    headers
+        .get(SEC_WEBSOCKET_PROTOCOL)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .find_map(|p| WebSocketProtocols::from_str(p.trim()).ok())
+        .map(|p| p.sec_websocket_protocol())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClusterConfig;
+    use crate::state::AppState;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve `/ws` on an ephemeral port and return the raw handshake response
+    async fn handshake(protocols: &[&str], offered: Option<&str>) -> String {
+        let schema = super::super::build_schema(AppState::new(ClusterConfig::default()));
+        let protocols: Vec<String> = protocols.iter().map(|p| p.to_string()).collect();
+        let app = axum::Router::new().route("/ws", subscription_route(schema, &protocols));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut request = "GET /ws HTTP/1.1\r\n\
+            Host: localhost\r\n\
+            Connection: Upgrade\r\n\
+            Upgrade: websocket\r\n\
+            Sec-WebSocket-Version: 13\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n"
+            .to_string();
+        if let Some(offered) = offered {
+            request.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", offered));
+        }
+        request.push_str("\r\n");
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !response.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8_lossy(&response).to_ascii_lowercase()
+    }
+
+    #[tokio::test]
+    async fn test_uses_clients_first_offer() {
+        let response = handshake(&DEFAULT_WS_PROTOCOLS, Some("graphql-ws, graphql-transport-ws")).await;
+        assert!(response.starts_with("http/1.1 101"), "{}", response);
+        assert!(response.contains("sec-websocket-protocol: graphql-ws\r\n"), "{}", response);
+
+        // Unknown offers are passed over
+        let response = handshake(&DEFAULT_WS_PROTOCOLS, Some("mqtt, graphql-transport-ws")).await;
+        assert!(response.contains("sec-websocket-protocol: graphql-transport-ws\r\n"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_protocol_when_only_one_offered() {
+        let response = handshake(&DEFAULT_WS_PROTOCOLS, Some("graphql-ws")).await;
+        assert!(response.contains("sec-websocket-protocol: graphql-ws\r\n"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_protocol_not_configured() {
+        let response = handshake(&["graphql-transport-ws"], Some("graphql-ws")).await;
+        assert!(response.starts_with("http/1.1 400"), "{}", response);
+
+        // The client's first supported offer decides, even if a later one is allowed
+        let response = handshake(&["graphql-transport-ws"], Some("graphql-ws, graphql-transport-ws")).await;
+        assert!(response.starts_with("http/1.1 400"), "{}", response);
+
+        let response = handshake(&["graphql-transport-ws"], None).await;
+        assert!(response.starts_with("http/1.1 400"), "{}", response);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::agent::client::{ContainerInfo, ContainerListRequest};
+use dashmap::DashMap;
+use prost::Message;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Cached listings kept at most; expired ones are pruned when it fills up
+const MAX_ENTRIES: usize = 1024;
+
+/// Cluster-side cache of container listings, per agent and request.
+///
+/// Serves repeated `containers` queries for `ttl` without asking the agent.
+/// An agent's listings are dropped early on an inventory change the cluster
+/// sees (a container stopping under a log stream) or a manual refresh.
+pub struct InventoryCache {
+    ttl: Duration,
+    /// (agent_id, encoded request) → listing
+    entries: DashMap<(String, Vec<u8>), CachedListing>,
+    /// agent_id → invalidations so far, so a listing fetched across an
+    /// invalidation isn't cached
+    generations: DashMap<String, u64>,
+    /// Bumped by `invalidate_all`
+    global_generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+struct CachedListing {
+    fetched_at: Instant,
+    containers: Vec<ContainerInfo>,
+}
+
+/// Inventory cache counters for `/metrics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InventoryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub invalidations: u64,
+    pub entries: usize,
+}
+
+impl InventoryCache {
+    /// A `ttl` of zero disables caching
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: DashMap::new(),
+            generations: DashMap::new(),
+            global_generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    /// The agent's containers for `request`, from the cache while fresh,
+    /// otherwise from `fetch`. Failures aren't cached.
+    pub async fn list<F, Fut, E>(
+        &self,
+        agent_id: &str,
+        request: ContainerListRequest,
+        fetch: F,
+    ) -> Result<Vec<ContainerInfo>, E>
+    where
+        F: FnOnce(ContainerListRequest) -> Fut,
+        Fut: Future<Output = Result<Vec<ContainerInfo>, E>>,
+    {
+        if self.ttl.is_zero() {
+            return fetch(request).await;
+        }
+
+        let key = (agent_id.to_string(), request.encode_to_vec());
+        if let Some(cached) = self.entries.get(&key) {
+            if cached.fetched_at.elapsed() < self.ttl {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(cached.containers.clone());
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let generation = self.generation(agent_id);
+        let containers = fetch(request).await?;
+        if self.generation(agent_id) == generation && self.has_room() {
+            self.entries.insert(key, CachedListing {
+                fetched_at: Instant::now(),
+                containers: containers.clone(),
+            });
+        }
+        Ok(containers)
+    }
+
+    /// Drop an agent's cached listings after its inventory changed
+    pub fn invalidate(&self, agent_id: &str) {
+        *self.generations.entry(agent_id.to_string()).or_insert(0) += 1;
+        self.entries.retain(|(agent, _), _| agent != agent_id);
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(agent_id = agent_id, "Inventory cache invalidated");
+    }
+
+    /// Drop every cached listing
+    pub fn invalidate_all(&self) {
+        self.global_generation.fetch_add(1, Ordering::Relaxed);
+        self.entries.clear();
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> InventoryCacheStats {
+        InventoryCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            entries: self.entries.len(),
+        }
+    }
+
+    fn generation(&self, agent_id: &str) -> u64 {
+        let agent = self.generations.get(agent_id).map(|g| *g).unwrap_or(0);
+        self.global_generation.load(Ordering::Relaxed) + agent
+    }
+
+    fn has_room(&self) -> bool {
+        if self.entries.len() < MAX_ENTRIES {
+            return true;
+        }
+        let ttl = self.ttl;
+        self.entries.retain(|_, cached| cached.fetched_at.elapsed() < ttl);
+        self.entries.len() < MAX_ENTRIES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    fn container(id: &str) -> ContainerInfo {
+        ContainerInfo {
+            id: id.to_string(),
+            name: format!("{}-name", id),
+            ..Default::default()
+        }
+    }
+
+    async fn list(cache: &InventoryCache, agent_id: &str, calls: &AtomicUsize) -> Vec<ContainerInfo> {
+        cache
+            .list(agent_id, ContainerListRequest::default(), |_| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, ()>(vec![container("web")])
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_second_query_within_ttl_is_cached() {
+        let cache = InventoryCache::new(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+
+        assert_eq!(list(&cache, "agent-1", &calls).await[0].id, "web");
+        assert_eq!(list(&cache, "agent-1", &calls).await[0].id, "web");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().misses, 1);
+
+        // Another agent, or another filter, is a separate listing
+        list(&cache, "agent-2", &calls).await;
+        cache
+            .list("agent-1", ContainerListRequest { include_stopped: true, ..Default::default() }, |_| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, ()>(vec![])
+            })
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_inventory_event_invalidates_agent() {
+        let cache = InventoryCache::new(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+        list(&cache, "agent-1", &calls).await;
+        list(&cache, "agent-2", &calls).await;
+
+        cache.invalidate("agent-1");
+        list(&cache, "agent-1", &calls).await;
+        list(&cache, "agent-2", &calls).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.stats().invalidations, 1);
+    }
+
+    #[tokio::test]
+    async fn test_listing_fetched_across_invalidation_not_cached() {
+        let cache = InventoryCache::new(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+        cache
+            .list("agent-1", ContainerListRequest::default(), |_| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                cache.invalidate("agent-1");
+                Ok::<_, ()>(vec![container("web")])
+            })
+            .await
+            .unwrap();
+
+        list(&cache, "agent-1", &calls).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_zero_ttl_disables_cache() {
+        let cache = InventoryCache::new(Duration::ZERO);
+        let calls = AtomicUsize::new(0);
+        list(&cache, "agent-1", &calls).await;
+        list(&cache, "agent-1", &calls).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.stats().hits, 0);
+    }
+}
@@ -2,11 +2,13 @@ mod agent;
 mod config;
 mod error;
 mod graphql;
+mod inventory;
 mod metrics;
+mod names;
 mod state;
 
 use anyhow::{Context, Result};
-use async_graphql_axum::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
 use axum::{
     extract::{DefaultBodyLimit, State},
     http::{header, Method, StatusCode},
@@ -53,6 +55,11 @@ async fn main() -> Result<()> {
     config.validate()
         .context("Configuration validation failed")?;
 
+    // Agent channels take their TLS versions and cipher suites from the
+    // process-wide crypto provider
+    config.agents.tls.install()
+        .context("Failed to apply agents.tls policy")?;
+
     // Phase 2: Re-initialize tracing with config (format, level)
     // Drop the phase-1 thread-local guard so the global subscriber slot is free
     drop(_basic_tracing);
@@ -90,6 +97,9 @@ async fn main() -> Result<()> {
     info!("  - GraphQL endpoint: http://{}/graphql", addr);
     if config.graphql.enable_graphiql {
         info!("  - GraphiQL playground: http://{}/graphiql", addr);
+        if !config.graphql.enable_introspection {
+            warn!("GraphiQL is enabled but introspection is disabled; schema docs and autocomplete will not work");
+        }
     }
     info!("  - Health check: http://{}/health", addr);
     info!("  - Readiness check: http://{}/ready", addr);
@@ -146,7 +156,10 @@ fn build_router(state: RouterState) -> Router {
         // GraphQL endpoints
         .route("/graphql", post(graphql_handler).get(graphql_playground))
         .route("/graphiql", get(graphql_playground))  // Alias for playground
-        .route_service("/ws", GraphQLSubscription::new(state.schema.clone()))
+        .route("/ws", graphql::websocket::subscription_route(
+            state.schema.clone(),
+            &state.app_state.config.graphql.ws_protocols,
+        ))
         
         // Root endpoint
         .route("/", get(root_handler))
@@ -211,18 +224,37 @@ async fn metrics_handler(
 ) -> impl IntoResponse {
     let metrics = &state.app_state.metrics;
     let agent_pool = &state.app_state.agent_pool;
+    let inventory = state.app_state.inventory.stats();
     
     Json(json!({
         "subscriptions": {
             "active": metrics.active_count(),
             "total_created": metrics.total_created(),
             "failed": metrics.failed_count(),
-            "by_agent": metrics.subscriptions_by_agent()
+            "by_agent": metrics.subscriptions_by_agent(),
+            "by_type": metrics.subscriptions_by_kind()
         },
         "messages": {
             "total": metrics.total_messages(),
-            "total_bytes": metrics.total_bytes(),
-            "total_mb": (metrics.total_bytes() as f64) / (1024.0 * 1024.0)
+            "total_bytes": metrics.total_logical_bytes(),
+            "total_mb": (metrics.total_logical_bytes() as f64) / (1024.0 * 1024.0),
+            "wire_bytes": metrics.wire_bytes(),
+            "wire_mb": (metrics.wire_bytes() as f64) / (1024.0 * 1024.0)
+        },
+        "log_streams": metrics.line_stats().into_iter().map(|s| json!({
+            "stream_id": s.stream_id,
+            "agent_id": s.agent_id,
+            "container_id": s.container_id,
+            "lines": s.lines,
+            "max_line_bytes": s.max_line_bytes,
+            "avg_line_bytes": s.avg_line_bytes,
+            "oversized_lines": s.oversized_lines
+        })).collect::<Vec<_>>(),
+        "inventory_cache": {
+            "hits": inventory.hits,
+            "misses": inventory.misses,
+            "invalidations": inventory.invalidations,
+            "entries": inventory.entries
         },
         "agents": {
             "total": agent_pool.count(),
@@ -238,13 +270,13 @@ async fn metrics_handler(
 async fn readiness_handler(
     State(state): State<RouterState>,
 ) -> impl IntoResponse {
-    // Check if we have at least one healthy agent
     let total = state.app_state.agent_pool.count();
     let healthy = state.app_state.agent_pool.count_healthy();
     let unhealthy = state.app_state.agent_pool.count_unhealthy();
-    
-    // Ready if we have at least one healthy agent, or if no agents are configured
-    let ready = total == 0 || healthy > 0;
+
+    // Ready once `readiness.min_healthy_agents` are healthy, or if no agents are configured
+    let readiness = &state.app_state.config.readiness;
+    let ready = readiness.is_ready(total, healthy);
 
     let status = if ready {
         StatusCode::OK
@@ -259,7 +291,8 @@ async fn readiness_handler(
             "agents": {
                 "total": total,
                 "healthy": healthy,
-                "unhealthy": unhealthy
+                "unhealthy": unhealthy,
+                "required": readiness.required_healthy(total).ok()
             }
         })),
     )
@@ -2,6 +2,37 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use parking_lot::RwLock;
 use std::collections::HashMap;
+use std::io::Write;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use crate::agent::client::NormalizedLogEntry;
+
+/// gRPC length-prefixed message header (1-byte compressed flag + 4-byte length)
+const GRPC_FRAME_HEADER_LEN: usize = 5;
+
+/// With compression on, one log-service message in this many is gzipped
+/// again to measure the compression ratio; wire bytes are estimated from it
+const WIRE_SAMPLE_EVERY: u64 = 64;
+
+/// Kind of GraphQL subscription, for the load breakdown in `/metrics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    Log,
+    Stats,
+    Health,
+}
+
+impl SubscriptionKind {
+    pub const ALL: [SubscriptionKind; 3] = [Self::Log, Self::Stats, Self::Health];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Log => "log",
+            Self::Stats => "stats",
+            Self::Health => "health",
+        }
+    }
+}
 
 /// Subscription metrics tracker
 #[derive(Clone)]
@@ -19,34 +50,130 @@ struct SubscriptionMetricsInner {
     /// Total messages sent across all subscriptions
     total_messages_sent: AtomicU64,
     
-    /// Total bytes sent across all subscriptions
-    total_bytes_sent: AtomicU64,
+    /// Total logical bytes (encoded gRPC frames, before any transport
+    /// compression) received from agents across all subscriptions
+    total_logical_bytes: AtomicU64,
+
+    /// `enable_compression`: log-service messages arrive gzipped
+    compression: bool,
+
+    /// Logical bytes and count of messages that arrived compressed
+    compressed_logical_bytes: AtomicU64,
+    compressed_messages: AtomicU64,
+
+    /// Logical and gzipped sizes of the sampled compressed messages
+    sampled_logical_bytes: AtomicU64,
+    sampled_wire_bytes: AtomicU64,
     
     /// Active subscriptions per agent (agent_id -> count)
     subscriptions_per_agent: RwLock<HashMap<String, u64>>,
     
+    /// Active subscriptions per kind, indexed like `SubscriptionKind::ALL`
+    subscriptions_per_kind: [AtomicU64; SubscriptionKind::ALL.len()],
+    
     /// Total failed subscription attempts
     failed_subscriptions: AtomicU64,
+
+    /// Line stats of open log streams, by stream ID
+    line_stats: RwLock<HashMap<u64, StreamLines>>,
+
+    /// Next log stream ID
+    next_stream_id: AtomicU64,
+}
+
+/// Line sizes seen on one log stream, as running aggregates
+#[derive(Debug, Default)]
+struct LineStats {
+    lines: AtomicU64,
+    bytes: AtomicU64,
+    max_bytes: AtomicU64,
+    oversized: AtomicU64,
+}
+
+struct StreamLines {
+    agent_id: String,
+    container_id: String,
+    stats: Arc<LineStats>,
+}
+
+/// A log stream's line stats at one point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineStatsSnapshot {
+    pub stream_id: u64,
+    pub agent_id: String,
+    pub container_id: String,
+    pub lines: u64,
+    pub max_line_bytes: u64,
+    /// 0 before the first line
+    pub avg_line_bytes: f64,
+    /// Lines over the agent's line size limit, passed on unparsed
+    pub oversized_lines: u64,
+}
+
+/// Records one log stream's lines; its stats are dropped with it
+pub struct TrackedLines {
+    id: u64,
+    stats: Arc<LineStats>,
+    metrics: SubscriptionMetrics,
+}
+
+impl TrackedLines {
+    /// Count an entry's line. Heartbeats and markers carry no line and
+    /// aren't counted.
+    pub fn record(&self, entry: &NormalizedLogEntry) {
+        if entry.heartbeat || entry.backlog_unavailable || entry.container_stopped {
+            return;
+        }
+        let bytes = entry.raw_content.len() as u64;
+        let oversized = entry.metadata.as_ref().is_some_and(|m| m.line_too_large);
+        self.stats.lines.fetch_add(1, Ordering::Relaxed);
+        self.stats.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.stats.max_bytes.fetch_max(bytes, Ordering::Relaxed);
+        if oversized {
+            self.stats.oversized.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for TrackedLines {
+    fn drop(&mut self) {
+        self.metrics.inner.line_stats.write().remove(&self.id);
+    }
 }
 
 impl SubscriptionMetrics {
     pub fn new() -> Self {
+        Self::with_compression(false)
+    }
+
+    /// Metrics for a cluster whose agent log streams are gzip-compressed
+    /// when `compression` is set (`agents.enable_compression`)
+    pub fn with_compression(compression: bool) -> Self {
         Self {
             inner: Arc::new(SubscriptionMetricsInner {
                 active_subscriptions: AtomicU64::new(0),
                 total_subscriptions_created: AtomicU64::new(0),
                 total_messages_sent: AtomicU64::new(0),
-                total_bytes_sent: AtomicU64::new(0),
+                total_logical_bytes: AtomicU64::new(0),
+                compression,
+                compressed_logical_bytes: AtomicU64::new(0),
+                compressed_messages: AtomicU64::new(0),
+                sampled_logical_bytes: AtomicU64::new(0),
+                sampled_wire_bytes: AtomicU64::new(0),
                 subscriptions_per_agent: RwLock::new(HashMap::new()),
+                subscriptions_per_kind: Default::default(),
                 failed_subscriptions: AtomicU64::new(0),
+                line_stats: RwLock::new(HashMap::new()),
+                next_stream_id: AtomicU64::new(1),
             }),
         }
     }
     
     /// Called when a new subscription is created
-    pub fn subscription_started(&self, agent_id: &str) {
+    pub fn subscription_started(&self, agent_id: &str, kind: SubscriptionKind) {
         self.inner.active_subscriptions.fetch_add(1, Ordering::Relaxed);
         self.inner.total_subscriptions_created.fetch_add(1, Ordering::Relaxed);
+        self.kind_counter(kind).fetch_add(1, Ordering::Relaxed);
         
         let mut per_agent = self.inner.subscriptions_per_agent.write();
         *per_agent.entry(agent_id.to_string()).or_insert(0) += 1;
@@ -59,15 +186,17 @@ impl SubscriptionMetrics {
     }
     
     /// Called when a subscription ends
-    pub fn subscription_ended(&self, agent_id: &str) {
+    pub fn subscription_ended(&self, agent_id: &str, kind: SubscriptionKind) {
         // Use fetch_update for atomic check-and-decrement to prevent underflow.
         // The previous load-then-sub pattern was not atomic and could wrap to u64::MAX
         // under concurrent subscription_started/subscription_ended calls.
-        let _ = self.inner.active_subscriptions.fetch_update(
-            Ordering::Relaxed,
-            Ordering::Relaxed,
-            |current| if current > 0 { Some(current - 1) } else { None },
-        );
+        for counter in [&self.inner.active_subscriptions, self.kind_counter(kind)] {
+            let _ = counter.fetch_update(
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+                |current| if current > 0 { Some(current - 1) } else { None },
+            );
+        }
         
         let mut per_agent = self.inner.subscriptions_per_agent.write();
         
@@ -95,10 +224,62 @@ impl SubscriptionMetrics {
         self.inner.failed_subscriptions.fetch_add(1, Ordering::Relaxed);
     }
     
-    /// Called when a message is sent to a subscriber
-    pub fn message_sent(&self, bytes: usize) {
+    /// Called for each agent message a subscription receives, with its
+    /// logical size (see [`grpc_message_size`])
+    pub fn message_sent(&self, logical_bytes: usize) {
         self.inner.total_messages_sent.fetch_add(1, Ordering::Relaxed);
-        self.inner.total_bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.inner.total_logical_bytes.fetch_add(logical_bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Like [`message_sent`](Self::message_sent), for messages of the
+    /// agent's LogService, which arrive gzipped when compression is on. Every
+    /// `WIRE_SAMPLE_EVERY`th of those is compressed again to track the ratio.
+    pub fn log_message_sent<M: prost::Message>(&self, message: &M) {
+        let logical_bytes = grpc_message_size(message);
+        self.message_sent(logical_bytes);
+        if !self.inner.compression {
+            return;
+        }
+        self.inner.compressed_logical_bytes.fetch_add(logical_bytes as u64, Ordering::Relaxed);
+        if self.inner.compressed_messages.fetch_add(1, Ordering::Relaxed).is_multiple_of(WIRE_SAMPLE_EVERY) {
+            self.inner.sampled_logical_bytes.fetch_add(logical_bytes as u64, Ordering::Relaxed);
+            self.inner.sampled_wire_bytes.fetch_add(grpc_compressed_size(message) as u64, Ordering::Relaxed);
+        }
+    }
+    
+    /// Start keeping line stats for a log stream from `container_id`
+    pub fn track_lines(&self, agent_id: &str, container_id: &str) -> TrackedLines {
+        let id = self.inner.next_stream_id.fetch_add(1, Ordering::Relaxed);
+        let stats = Arc::new(LineStats::default());
+        self.inner.line_stats.write().insert(id, StreamLines {
+            agent_id: agent_id.to_string(),
+            container_id: container_id.to_string(),
+            stats: Arc::clone(&stats),
+        });
+        TrackedLines { id, stats, metrics: self.clone() }
+    }
+
+    /// Line stats of the open log streams, oldest first
+    pub fn line_stats(&self) -> Vec<LineStatsSnapshot> {
+        let mut snapshots: Vec<_> = self.inner.line_stats
+            .read()
+            .iter()
+            .map(|(id, stream)| {
+                let lines = stream.stats.lines.load(Ordering::Relaxed);
+                let bytes = stream.stats.bytes.load(Ordering::Relaxed);
+                LineStatsSnapshot {
+                    stream_id: *id,
+                    agent_id: stream.agent_id.clone(),
+                    container_id: stream.container_id.clone(),
+                    lines,
+                    max_line_bytes: stream.stats.max_bytes.load(Ordering::Relaxed),
+                    avg_line_bytes: if lines == 0 { 0.0 } else { bytes as f64 / lines as f64 },
+                    oversized_lines: stream.stats.oversized.load(Ordering::Relaxed),
+                }
+            })
+            .collect();
+        snapshots.sort_by_key(|s| s.stream_id);
+        snapshots
     }
     
     /// Get current active subscription count
@@ -116,9 +297,24 @@ impl SubscriptionMetrics {
         self.inner.total_messages_sent.load(Ordering::Relaxed)
     }
     
-    /// Get total bytes sent
-    pub fn total_bytes(&self) -> u64 {
-        self.inner.total_bytes_sent.load(Ordering::Relaxed)
+    /// Get total logical bytes received
+    pub fn total_logical_bytes(&self) -> u64 {
+        self.inner.total_logical_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Bytes that crossed agent connections: the logical size of
+    /// uncompressed messages, plus compressed ones scaled by the sampled
+    /// compression ratio. Equal to the logical total with compression off.
+    pub fn wire_bytes(&self) -> u64 {
+        let logical = self.total_logical_bytes();
+        let compressed = self.inner.compressed_logical_bytes.load(Ordering::Relaxed).min(logical);
+        let sampled_logical = self.inner.sampled_logical_bytes.load(Ordering::Relaxed);
+        let sampled_wire = self.inner.sampled_wire_bytes.load(Ordering::Relaxed);
+        if sampled_logical == 0 {
+            return logical;
+        }
+        let estimated = u128::from(compressed) * u128::from(sampled_wire) / u128::from(sampled_logical);
+        logical - compressed + u64::try_from(estimated).unwrap_or(u64::MAX)
     }
     
     /// Get failed subscription count
@@ -131,13 +327,26 @@ impl SubscriptionMetrics {
         self.inner.subscriptions_per_agent.read().clone()
     }
     
+    /// Get active subscriptions per kind ("log", "stats", "health")
+    pub fn subscriptions_by_kind(&self) -> HashMap<&'static str, u64> {
+        SubscriptionKind::ALL
+            .into_iter()
+            .map(|kind| (kind.as_str(), self.kind_counter(kind).load(Ordering::Relaxed)))
+            .collect()
+    }
+    
+    fn kind_counter(&self, kind: SubscriptionKind) -> &AtomicU64 {
+        &self.inner.subscriptions_per_kind[kind as usize]
+    }
+    
     /// Print current metrics summary
     #[allow(dead_code)]
     pub fn print_summary(&self) {
         let active = self.active_count();
         let total = self.total_created();
         let messages = self.total_messages();
-        let bytes = self.total_bytes();
+        let bytes = self.total_logical_bytes();
+        let wire_bytes = self.wire_bytes();
         let failed = self.failed_count();
         let per_agent = self.subscriptions_by_agent();
         
@@ -146,6 +355,7 @@ impl SubscriptionMetrics {
             total_created = total,
             total_messages = messages,
             total_bytes_mb = bytes / 1024 / 1024,
+            wire_bytes_mb = wire_bytes / 1024 / 1024,
             failed_subscriptions = failed,
             "Subscription metrics summary"
         );
@@ -167,3 +377,113 @@ impl Default for SubscriptionMetrics {
         Self::new()
     }
 }
+
+/// Logical size of a gRPC message: its encoded frame before transport
+/// compression. With `enable_compression` fewer bytes cross the network;
+/// tonic doesn't report those, so [`SubscriptionMetrics::wire_bytes`]
+/// estimates them from samples.
+pub fn grpc_message_size<M: prost::Message>(message: &M) -> usize {
+    GRPC_FRAME_HEADER_LEN + message.encoded_len()
+}
+
+/// Size of a gRPC frame carrying the message gzipped, the way tonic
+/// compresses it
+fn grpc_compressed_size<M: prost::Message>(message: &M) -> usize {
+    let encoded = message.encode_to_vec();
+    let mut encoder = GzEncoder::new(Vec::with_capacity(encoded.len() / 2), Compression::default());
+    match encoder.write_all(&encoded).and_then(|_| encoder.finish()) {
+        Ok(buf) => GRPC_FRAME_HEADER_LEN + buf.len(),
+        // Compressing into a Vec can't realistically fail; fall back to the raw size
+        Err(_) => GRPC_FRAME_HEADER_LEN + encoded.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::client::proto::ParseMetadata;
+    use prost::Message;
+
+    fn entry(content: &[u8]) -> NormalizedLogEntry {
+        NormalizedLogEntry {
+            container_id: "abc123".to_string(),
+            timestamp_nanos: 1_700_000_000_000_000_000,
+            sequence: 1,
+            raw_content: content.to_vec(),
+            line_count: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_message_size_is_full_message_plus_header() {
+        let msg = entry(b"hello world");
+        assert_eq!(grpc_message_size(&msg), msg.encoded_len() + GRPC_FRAME_HEADER_LEN);
+        // Structured fields are counted, not just raw content
+        assert!(grpc_message_size(&msg) > msg.raw_content.len());
+    }
+
+    #[test]
+    fn test_byte_counter_sums_logical_sizes() {
+        let metrics = SubscriptionMetrics::new();
+        let msg = entry(&b"INFO request handled path=/api/users status=200 ".repeat(20));
+
+        metrics.message_sent(grpc_message_size(&msg));
+        metrics.message_sent(grpc_message_size(&msg));
+
+        assert_eq!(metrics.total_messages(), 2);
+        assert_eq!(metrics.total_logical_bytes(), 2 * grpc_message_size(&msg) as u64);
+    }
+
+    #[test]
+    fn test_wire_bytes_below_logical_with_compression() {
+        let msg = entry(&b"INFO request handled path=/api/users status=200 ".repeat(20));
+
+        let metrics = SubscriptionMetrics::with_compression(true);
+        for _ in 0..100 {
+            metrics.log_message_sent(&msg);
+        }
+        // Stats and health streams aren't compressed
+        metrics.message_sent(1000);
+
+        let logical = 100 * grpc_message_size(&msg) as u64 + 1000;
+        assert_eq!(metrics.total_logical_bytes(), logical);
+        let wire = metrics.wire_bytes();
+        assert!(wire < logical, "wire {} not below logical {}", wire, logical);
+        // The identical messages all compress like the sampled ones
+        assert_eq!(wire, 100 * grpc_compressed_size(&msg) as u64 + 1000);
+
+        // Without compression every byte goes over the wire as is
+        let metrics = SubscriptionMetrics::new();
+        metrics.log_message_sent(&msg);
+        assert_eq!(metrics.wire_bytes(), metrics.total_logical_bytes());
+    }
+
+    #[test]
+    fn test_line_stats_follow_streamed_lines() {
+        let metrics = SubscriptionMetrics::new();
+        let tracked = metrics.track_lines("agent-1", "api");
+        assert_eq!(metrics.line_stats()[0].avg_line_bytes, 0.0);
+
+        tracked.record(&entry(&[b'a'; 100]));
+        tracked.record(&NormalizedLogEntry { heartbeat: true, ..entry(b"") });
+        tracked.record(&entry(&[b'b'; 300]));
+        // Over the agent's 1 MiB limit, passed on unparsed
+        tracked.record(&NormalizedLogEntry {
+            metadata: Some(ParseMetadata { line_too_large: true, ..Default::default() }),
+            ..entry(&vec![b'c'; 2 * 1024 * 1024])
+        });
+
+        let stats = metrics.line_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!((stats[0].agent_id.as_str(), stats[0].container_id.as_str()), ("agent-1", "api"));
+        assert_eq!(stats[0].lines, 3);
+        assert_eq!(stats[0].max_line_bytes, 2 * 1024 * 1024);
+        assert_eq!(stats[0].avg_line_bytes, (400 + 2 * 1024 * 1024) as f64 / 3.0);
+        assert_eq!(stats[0].oversized_lines, 1);
+
+        // Gone once the stream closes
+        drop(tracked);
+        assert!(metrics.line_stats().is_empty());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::agent::client::ContainerListRequest;
+use crate::agent::AgentPool;
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Minimum time between inventory refreshes for the same agent on a cache miss
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a recorded name is trusted before it is refreshed, so a renamed
+/// container shows its new name
+const NAME_TTL: Duration = Duration::from_secs(60);
+
+/// Upper bound on cached names across all agents
+const MAX_NAMES: usize = 50_000;
+
+/// Labels naming the service a container belongs to, Swarm first
+const SERVICE_LABELS: [&str; 2] = ["com.docker.swarm.service.name", "com.docker.compose.service"];
+
+struct CachedName {
+    name: String,
+    service: Option<String>,
+    recorded_at: Instant,
+}
+
+/// Cluster-side cache of container ID → name, per agent.
+///
+/// Filled from container listings the cluster already performs and, on a
+/// miss or once a name is older than its TTL, from the agent's cached
+/// inventory (a cheap in-memory read on the agent). Used to show
+/// human-readable names next to raw IDs.
+pub struct ContainerNames {
+    prefer_names: bool,
+    ttl: Duration,
+    /// (agent_id, container_id) → container name and service
+    names: DashMap<(String, String), CachedName>,
+    /// agent_id → last inventory refresh triggered by a miss
+    refreshed: DashMap<String, Instant>,
+}
+
+impl ContainerNames {
+    pub fn new(prefer_names: bool) -> Self {
+        Self::with_ttl(prefer_names, NAME_TTL)
+    }
+
+    pub fn with_ttl(prefer_names: bool, ttl: Duration) -> Self {
+        Self {
+            prefer_names,
+            ttl,
+            names: DashMap::new(),
+            refreshed: DashMap::new(),
+        }
+    }
+
+    /// Remember a container's name and, from its labels, its service
+    pub fn record(&self, agent_id: &str, container_id: &str, name: &str, labels: &HashMap<String, String>) {
+        if name.is_empty() {
+            return;
+        }
+        let key = (agent_id.to_string(), container_id.to_string());
+        if !self.names.contains_key(&key) && !self.has_room() {
+            return;
+        }
+        let service = SERVICE_LABELS
+            .iter()
+            .find_map(|label| labels.get(*label).filter(|s| !s.is_empty()).cloned());
+        self.names.insert(key, CachedName {
+            name: name.to_string(),
+            service,
+            recorded_at: Instant::now(),
+        });
+    }
+
+    /// Cached name for a container, if known. May be older than the TTL;
+    /// [`resolve`](Self::resolve) refreshes it.
+    pub fn get(&self, agent_id: &str, container_id: &str) -> Option<String> {
+        self.names
+            .get(&(agent_id.to_string(), container_id.to_string()))
+            .map(|entry| entry.name.clone())
+    }
+
+    fn is_fresh(&self, agent_id: &str, container_id: &str) -> bool {
+        self.names
+            .get(&(agent_id.to_string(), container_id.to_string()))
+            .is_some_and(|entry| entry.recorded_at.elapsed() < self.ttl)
+    }
+
+    fn has_room(&self) -> bool {
+        if self.names.len() < MAX_NAMES {
+            return true;
+        }
+        let ttl = self.ttl;
+        self.names.retain(|_, cached| cached.recorded_at.elapsed() < ttl);
+        self.names.len() < MAX_NAMES
+    }
+
+    /// The label to show for a container: its name when `prefer_names` is
+    /// enabled and the name is known, otherwise the raw ID.
+    pub fn display(&self, agent_id: &str, container_id: &str) -> String {
+        if !self.prefer_names {
+            return container_id.to_string();
+        }
+        self.get(agent_id, container_id)
+            .unwrap_or_else(|| container_id.to_string())
+    }
+
+    /// Like [`display`](Self::display), but refreshes the agent's names from
+    /// its inventory on a miss or a stale entry (rate-limited per agent).
+    pub async fn resolve(&self, pool: &AgentPool, agent_id: &str, container_id: &str) -> String {
+        if self.prefer_names && !self.is_fresh(agent_id, container_id) && self.should_refresh(agent_id) {
+            self.refresh(pool, agent_id).await;
+        }
+        self.display(agent_id, container_id)
+    }
+
+    /// The Swarm or Compose service a container belongs to, from its labels.
+    /// Refreshed like [`resolve`](Self::resolve); None when the container
+    /// carries no service label or isn't known.
+    pub async fn resolve_service(&self, pool: &AgentPool, agent_id: &str, container_id: &str) -> Option<String> {
+        if !self.is_fresh(agent_id, container_id) && self.should_refresh(agent_id) {
+            self.refresh(pool, agent_id).await;
+        }
+        self.names
+            .get(&(agent_id.to_string(), container_id.to_string()))
+            .and_then(|entry| entry.service.clone())
+    }
+
+    /// The label to show for the node (agent host) a container runs on: the
+    /// agent's configured name when `prefer_names` is enabled, otherwise its ID
+    pub fn node_name(&self, pool: &AgentPool, agent_id: &str) -> String {
+        if !self.prefer_names {
+            return agent_id.to_string();
+        }
+        pool.get_agent(agent_id)
+            .map(|agent| agent.info.name.clone())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| agent_id.to_string())
+    }
+
+    fn should_refresh(&self, agent_id: &str) -> bool {
+        let now = Instant::now();
+        let mut due = false;
+        self.refreshed
+            .entry(agent_id.to_string())
+            .and_modify(|last| {
+                if now.duration_since(*last) >= REFRESH_INTERVAL {
+                    *last = now;
+                    due = true;
+                }
+            })
+            .or_insert_with(|| {
+                due = true;
+                now
+            });
+        due
+    }
+
+    async fn refresh(&self, pool: &AgentPool, agent_id: &str) {
+        let Some(agent) = pool.get_agent(agent_id) else {
+            return;
+        };
+        let mut client = {
+            let guard = agent.client.lock().await;
+            guard.clone()
+        };
+
+        let request = ContainerListRequest {
+            include_stopped: true,
+            ..Default::default()
+        };
+        match client.list_containers(request).await {
+            Ok(response) => {
+                for container in response.containers {
+                    self.record(agent_id, &container.id, &container.name, &container.labels);
+                }
+            }
+            Err(e) => {
+                tracing::debug!("Failed to refresh container names from agent {}: {}", agent_id, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_display_prefers_resolved_name() {
+        let names = ContainerNames::new(true);
+        names.record("agent-1", "3f2a9c1b7d4e", "payments-api", &HashMap::new());
+
+        assert_eq!(names.display("agent-1", "3f2a9c1b7d4e"), "payments-api");
+        // Same ID on another agent is a different container
+        assert_eq!(names.display("agent-2", "3f2a9c1b7d4e"), "3f2a9c1b7d4e");
+    }
+
+    #[test]
+    fn test_display_falls_back_to_id() {
+        let names = ContainerNames::new(true);
+        assert_eq!(names.display("agent-1", "deadbeef"), "deadbeef");
+    }
+
+    #[test]
+    fn test_display_ids_when_disabled() {
+        let names = ContainerNames::new(false);
+        names.record("agent-1", "3f2a9c1b7d4e", "payments-api", &HashMap::new());
+        assert_eq!(names.display("agent-1", "3f2a9c1b7d4e"), "3f2a9c1b7d4e");
+    }
+
+    #[test]
+    fn test_stale_name_is_refreshed() {
+        let names = ContainerNames::with_ttl(true, Duration::ZERO);
+        names.record("agent-1", "3f2a9c1b7d4e", "payments-api", &HashMap::new());
+        // Still shown, but no longer trusted: the next resolve re-reads it
+        assert!(!names.is_fresh("agent-1", "3f2a9c1b7d4e"));
+        assert_eq!(names.display("agent-1", "3f2a9c1b7d4e"), "payments-api");
+
+        // A rename seen in a later listing replaces the old name
+        names.record("agent-1", "3f2a9c1b7d4e", "payments-api-v2", &HashMap::new());
+        assert_eq!(names.display("agent-1", "3f2a9c1b7d4e"), "payments-api-v2");
+
+        let names = ContainerNames::new(true);
+        names.record("agent-1", "3f2a9c1b7d4e", "payments-api", &HashMap::new());
+        assert!(names.is_fresh("agent-1", "3f2a9c1b7d4e"));
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let names = ContainerNames::new(true);
+        for i in 0..MAX_NAMES + 10 {
+            names.record("agent-1", &format!("c{}", i), "name", &HashMap::new());
+        }
+        assert_eq!(names.names.len(), MAX_NAMES);
+        // Known containers can still be updated when full
+        names.record("agent-1", "c0", "renamed", &HashMap::new());
+        assert_eq!(names.get("agent-1", "c0").as_deref(), Some("renamed"));
+
+        // Expired names make room
+        let names = ContainerNames::with_ttl(true, Duration::ZERO);
+        for i in 0..MAX_NAMES + 10 {
+            names.record("agent-1", &format!("c{}", i), "name", &HashMap::new());
+        }
+        assert!(names.names.len() <= MAX_NAMES);
+        assert!(names.get("agent-1", &format!("c{}", MAX_NAMES + 9)).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_service_from_labels() {
+        let pool = AgentPool::new(crate::config::ClusterConfig::default().agents);
+        let names = ContainerNames::new(true);
+        names.record("agent-1", "a", "web-1", &labels(&[("com.docker.compose.service", "web")]));
+        names.record("agent-1", "b", "api.1.xyz", &labels(&[
+            ("com.docker.compose.service", "ignored"),
+            ("com.docker.swarm.service.name", "api"),
+        ]));
+        names.record("agent-1", "c", "standalone", &HashMap::new());
+
+        assert_eq!(names.resolve_service(&pool, "agent-1", "a").await.as_deref(), Some("web"));
+        assert_eq!(names.resolve_service(&pool, "agent-1", "b").await.as_deref(), Some("api"));
+        assert_eq!(names.resolve_service(&pool, "agent-1", "c").await, None);
+    }
+
+    #[test]
+    fn test_refresh_rate_limited_per_agent() {
+        let names = ContainerNames::new(true);
+        assert!(names.should_refresh("agent-1"));
+        assert!(!names.should_refresh("agent-1"));
+        assert!(names.should_refresh("agent-2"));
+    }
+}
@@ -1,9 +1,13 @@
-use crate::config::ClusterConfig;
+use crate::config::{ClusterConfig, StartupMode};
 use crate::agent::{AgentPool, AgentRegistry};
 use crate::metrics::SubscriptionMetrics;
+use crate::graphql::subscriptions::{FilterTokens, PauseControls};
+use crate::inventory::InventoryCache;
+use crate::names::ContainerNames;
+use regex::RegexSet;
 use std::sync::Arc;
 use std::time::Duration;
-use tracing::info;
+use tracing::{info, warn};
 
 /// Shared application state (thread-safe)
 #[derive(Clone)]
@@ -11,6 +15,16 @@ pub struct AppState {
     pub config: Arc<ClusterConfig>,
     pub agent_pool: Arc<AgentPool>,
     pub metrics: Arc<SubscriptionMetrics>,
+    /// Container ID → name cache backing `containerName` fields
+    pub container_names: Arc<ContainerNames>,
+    /// Container listings backing the `containers` query
+    pub inventory: Arc<InventoryCache>,
+    /// Compiled `log_defaults.exclude_patterns`
+    pub default_excludes: Option<Arc<RegexSet>>,
+    /// Pause switches for log subscriptions with a `pauseToken`
+    pub pause_controls: Arc<PauseControls>,
+    /// Agents behind each log subscription with a `filterToken`
+    pub filter_tokens: Arc<FilterTokens>,
     /// Watch channel for shutdown signaling.
     /// Unlike broadcast, watch never loses messages — receivers always
     /// see the latest value, even if they subscribe after the send.
@@ -25,12 +39,23 @@ impl AppState {
         let agent_pool = Arc::new(AgentPool::new(config.agents.clone()));
         
         // Create metrics tracker
-        let metrics = Arc::new(SubscriptionMetrics::new());
+        let metrics = Arc::new(SubscriptionMetrics::with_compression(config.agents.enable_compression));
+
+        let container_names = Arc::new(ContainerNames::new(config.graphql.prefer_names));
+        let inventory = Arc::new(InventoryCache::new(config.inventory_cache.ttl()));
+
+        // Patterns were checked by ClusterConfig::validate
+        let default_excludes = config.log_defaults.exclude_set().ok().flatten().map(Arc::new);
 
         Self {
             config: Arc::new(config),
             agent_pool,
             metrics,
+            container_names,
+            inventory,
+            default_excludes,
+            pause_controls: Arc::new(PauseControls::default()),
+            filter_tokens: Arc::new(FilterTokens::default()),
             shutdown_tx,
         }
     }
@@ -42,6 +67,13 @@ impl AppState {
         // Initialize agent pool
         self.agent_pool.initialize().await?;
 
+        // Accept agents in reverse mode
+        if let Some(address) = &self.config.agents.reverse_listen_address {
+            let listener = tokio::net::TcpListener::bind(address).await?;
+            info!("Accepting reverse agent connections on {}", address);
+            tokio::spawn(crate::agent::reverse::accept_agents(self.agent_pool.clone(), listener));
+        }
+
         // Start health monitoring
         let registry = AgentRegistry::new(
             self.agent_pool.clone(),
@@ -53,6 +85,25 @@ impl AppState {
             registry.start_health_monitoring().await;
         });
 
+        // Reverse agents can dial in by now
+        let agents = &self.config.agents;
+        match agents.startup_mode {
+            StartupMode::Strict => {
+                let timeout = Duration::from_secs(agents.startup_timeout_secs);
+                if !self.agent_pool.wait_for_healthy(timeout).await {
+                    anyhow::bail!(
+                        "No agent became healthy within {}s (agents.startup_mode = \"strict\")",
+                        agents.startup_timeout_secs
+                    );
+                }
+            }
+            StartupMode::Lenient => {
+                if self.agent_pool.count_healthy() == 0 {
+                    warn!("No healthy agents yet; starting anyway, agents join as they become reachable");
+                }
+            }
+        }
+
         info!("✓ Application state initialized successfully");
         Ok(())
     }
@@ -62,3 +113,52 @@ impl AppState {
         let _ = self.shutdown_tx.send(true);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AgentConfig;
+    use std::collections::HashMap;
+
+    /// One static agent that can never be reached
+    fn unreachable_cluster(mode: StartupMode) -> ClusterConfig {
+        let mut config = ClusterConfig::default();
+        config.agents.startup_mode = mode;
+        config.agents.startup_timeout_secs = 1;
+        config.agents.static_agents.push(AgentConfig {
+            id: "agent-1".to_string(),
+            name: "Agent 1".to_string(),
+            address: "127.0.0.1:1".to_string(),
+            tls_cert: "/nonexistent/client.crt".to_string(),
+            tls_key: "/nonexistent/client.key".to_string(),
+            tls_ca: "/nonexistent/ca.crt".to_string(),
+            tls_domain: "localhost".to_string(),
+            tls_identity: None,
+            labels: HashMap::new(),
+            max_in_flight: None,
+            reverse: false,
+        });
+        config
+    }
+
+    #[tokio::test]
+    async fn test_strict_startup_fails_without_agents() {
+        let state = AppState::new(unreachable_cluster(StartupMode::Strict));
+        let started = std::time::Instant::now();
+        let err = state.initialize().await.unwrap_err();
+        assert!(err.to_string().contains("No agent became healthy within 1s"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(3));
+        state.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_lenient_startup_waits_for_agents() {
+        let state = AppState::new(unreachable_cluster(StartupMode::Lenient));
+        state.initialize().await.unwrap();
+        assert_eq!(state.agent_pool.count(), 0);
+
+        // Still unreachable, so still waiting to join
+        assert_eq!(state.agent_pool.connect_pending().await, 0);
+        state.shutdown();
+    }
+}
@@ -67,7 +67,7 @@ export class GraphQLError extends Error {
    * @returns True if this is a server-side error
    */
   isInternalError(): boolean {
-    return this.code === 'INTERNAL_SERVER_ERROR' || this.code === 'GRPC_ERROR';
+    return this.code === 'INTERNAL';
   }
 
   /**
@@ -75,7 +75,23 @@ export class GraphQLError extends Error {
    * @returns True if the request was malformed
    */
   isBadRequest(): boolean {
-    return this.code === 'BAD_REQUEST';
+    return this.code === 'INVALID_REQUEST';
+  }
+
+  /**
+   * Check if the agent turned the request away because it is at capacity
+   * @returns True if the agent's stream limit was reached
+   */
+  isResourceExhausted(): boolean {
+    return this.code === 'RESOURCE_EXHAUSTED';
+  }
+
+  /**
+   * Agent the error refers to, if the backend reported one
+   * @returns The `agentId` error extension
+   */
+  get agentId(): string | undefined {
+    return this.originalError?.extensions?.agentId;
   }
 
   // ============================================================================
@@ -98,12 +114,12 @@ export class GraphQLError extends Error {
         return 'Authentication required';
       case 'FORBIDDEN':
         return 'You do not have permission to access this resource';
-      case 'BAD_REQUEST':
+      case 'INVALID_REQUEST':
         return 'Invalid request. Please check your input';
-      case 'INTERNAL_SERVER_ERROR':
+      case 'RESOURCE_EXHAUSTED':
+        return 'Agent is at capacity. Please try again shortly';
+      case 'INTERNAL':
         return 'An unexpected error occurred. Please try again';
-      case 'GRPC_ERROR':
-        return 'Communication error with agent. Please retry';
       case 'WEBSOCKET_ERROR':
         return 'WebSocket connection error. Check your network connection';
       default:
@@ -121,8 +137,8 @@ export class GraphQLError extends Error {
    */
   isRetryable(): boolean {
     return this.code === 'AGENT_UNAVAILABLE' || 
-           this.code === 'GRPC_ERROR' || 
-           this.code === 'INTERNAL_SERVER_ERROR' ||
+           this.code === 'RESOURCE_EXHAUSTED' || 
+           this.code === 'INTERNAL' ||
            this.code === 'WEBSOCKET_ERROR';
   }
 }